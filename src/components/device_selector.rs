@@ -1,6 +1,6 @@
 use crate::libs::audio::{ AudioCommand, AudioContext };
 use crate::libs::device_manager::{ DeviceInfo, DeviceManager };
-use crate::libs::input_device_manager::{ InputDeviceInfo, InputDeviceManager };
+use crate::libs::input_device_manager::{ InputDeviceInfo, InputDeviceManager, InputDeviceType };
 use crate::utils::config::use_config;
 use dioxus::prelude::*;
 use lucide_dioxus::{ Headphones, Keyboard, Mouse, RefreshCw };
@@ -53,13 +53,22 @@ fn cached_input_devices() -> Vec<InputDeviceInfo> {
     input_device_cache().lock().map(|cache| cache.clone()).unwrap_or_default()
 }
 
-/// Tells the Windows input worker host to reload its enabled-device filter,
-/// so toggling a keyboard/mouse here takes effect immediately instead of on
-/// the next app start. No-op elsewhere: on Linux/macOS the listeners read
-/// the filter themselves.
+/// Tells the input listeners to reload their enabled-device filter, so
+/// toggling a keyboard/mouse here takes effect immediately instead of on the
+/// next app start.
 fn notify_input_filter_changed() {
-    #[cfg(target_os = "windows")]
-    crate::libs::input_worker_host::notify_config_changed();
+    crate::libs::device_filter::notify_config_changed();
+}
+
+/// Whether `device` belongs in the selector for `device_type`. A keyboard
+/// with a built-in touchpad is enumerated once per role, so it shows up in
+/// both selectors but only once in each.
+fn is_listed_under(device_type: DeviceType, device: &InputDeviceInfo) -> bool {
+    match device_type {
+        DeviceType::Keyboard => device.device_type == InputDeviceType::Keyboard,
+        DeviceType::Mouse => device.device_type == InputDeviceType::Mouse,
+        DeviceType::AudioOutput => false,
+    }
 }
 
 #[derive(Props, Clone, PartialEq)]
@@ -264,16 +273,14 @@ pub fn DeviceSelector(props: DeviceSelectorProps) -> Element {
                         })
                     );
 
-                    // Apply at runtime: the audio engine and ambiance
-                    // player both switch immediately, not just on next
-                    // startup.
+                    // Apply at runtime, not just on next startup. Ambiance
+                    // plays in the engine's mix, so it moves along.
                     let runtime_target = if device_id == "default" {
                         None
                     } else {
                         Some(device_id)
                     };
-                    audio_ctx.send(AudioCommand::SwitchDevice(runtime_target.clone()));
-                    crate::state::ambiance::switch_ambiance_player_device(runtime_target);
+                    audio_ctx.send(AudioCommand::SwitchDevice(runtime_target));
                 }
                 DeviceType::Keyboard => {
                     let device_id_clone = device_id.clone();
@@ -507,7 +514,7 @@ pub fn DeviceSelector(props: DeviceSelectorProps) -> Element {
                         } else {
                             div { class: "space-y-2",
                                 // Available input devices
-                                for device in input_devices().iter() {
+                                for device in input_devices().iter().filter(|device| is_listed_under(props.device_type, device)) {
                                     label { 
                                         key: "{device.id}",
                                         class: "flex items-center gap-3 p-3 rounded-lg hover:bg-base-100 cursor-pointer transition-colors",
@@ -526,7 +533,12 @@ pub fn DeviceSelector(props: DeviceSelectorProps) -> Element {
                                             {device_icon()}
                                             div { class: "flex-1 min-w-0",
                                                 div { class: "text-sm font-medium truncate", "{device.name}" }
-                                                div { class: "text-xs text-base-content/60", "{device.device_type:?}" }
+                                                div { class: "text-xs text-base-content/60",
+                                                    "{device.device_type:?}"
+                                                    if let Some(bus_type) = &device.bus_type {
+                                                        " · {bus_type}"
+                                                    }
+                                                }
                                             }
                                             div { class: "badge badge-success badge-sm", "Available" }
                                         }
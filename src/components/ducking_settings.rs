@@ -0,0 +1,99 @@
+use crate::components::effects_settings::EffectSlider;
+use crate::libs::AudioContext;
+use crate::state::config::{ Ducking, DuckingMode, MAX_DUCK_DB };
+use crate::utils::config::use_config;
+use dioxus::prelude::*;
+use std::sync::Arc;
+
+fn ms_label(ms: f32) -> String {
+    if ms >= 1000.0 { format!("{:.1} s", ms / 1000.0) } else { format!("{} ms", ms.round() as u32) }
+}
+
+/// Sidechain ducking between typing and the ambiance: which way it works,
+/// how deep, and how fast it pulls in and lets go.
+#[component]
+pub fn DuckingSettings() -> Element {
+    let (config, update_config) = use_config();
+    let audio_ctx = use_context::<Arc<AudioContext>>();
+    let ducking = use_memo(move || config().ambiance_ducking);
+
+    let set_ducking = move |ducking: Ducking| {
+        audio_ctx.set_ducking(ducking);
+        update_config(
+            Box::new(move |config| {
+                config.ambiance_ducking = ducking;
+            })
+        );
+    };
+
+    let current = ducking();
+    let off = current.mode == DuckingMode::Off;
+
+    rsx! {
+      div { class: "bg-base-200 border border-base-300 rounded-box p-6 space-y-4",
+        div { class: "space-y-1",
+          h3 { class: "text-lg font-semibold", "Ducking" }
+          p { class: "text-sm text-base-content/70",
+            "Make room for your typing, or for the loud moments of a sound"
+          }
+        }
+        div { class: "flex items-center justify-between",
+          div { class: "text-sm font-medium text-base-content", "Mode" }
+          select {
+            class: "select select-sm w-52",
+            onchange: {
+                let set_ducking = set_ducking.clone();
+                move |evt: Event<FormData>| {
+                    let value = evt.value();
+                    if let Some(mode) = DuckingMode::ALL.into_iter().find(|mode| mode.label() == value) {
+                        set_ducking(Ducking { mode, ..current });
+                    }
+                }
+            },
+            for mode in DuckingMode::ALL {
+              option { value: mode.label(), selected: mode == current.mode, "{mode.label()}" }
+            }
+          }
+        }
+        EffectSlider {
+          label: "Amount".to_string(),
+          value_label: format!("-{:.0} dB", current.amount_db),
+          min: 0.0,
+          max: MAX_DUCK_DB,
+          step: 1.0,
+          value: current.amount_db,
+          disabled: off,
+          on_change: {
+              let set_ducking = set_ducking.clone();
+              move |amount_db: f32| set_ducking(Ducking { amount_db, ..current })
+          },
+        }
+        EffectSlider {
+          label: "Attack".to_string(),
+          value_label: ms_label(current.attack_ms),
+          min: 5.0,
+          max: 500.0,
+          step: 5.0,
+          value: current.attack_ms,
+          disabled: off,
+          on_change: {
+              let set_ducking = set_ducking.clone();
+              move |attack_ms: f32| set_ducking(Ducking { attack_ms, ..current })
+          },
+        }
+        EffectSlider {
+          label: "Release".to_string(),
+          value_label: ms_label(current.release_ms),
+          min: 50.0,
+          max: 3000.0,
+          step: 50.0,
+          value: current.release_ms,
+          disabled: off,
+          on_change: {
+              let set_ducking = set_ducking.clone();
+              move |release_ms: f32| set_ducking(Ducking { release_ms, ..current })
+          },
+        }
+      }
+    }
+}
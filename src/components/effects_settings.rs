@@ -0,0 +1,189 @@
+use crate::components::ui::Toggler;
+use crate::libs::AudioContext;
+use crate::state::config::{ EffectsSettings, MAX_EQ_DB, MAX_LOWPASS_HZ, MIN_LOWPASS_HZ };
+use crate::utils::config::use_config;
+use dioxus::prelude::*;
+use std::sync::Arc;
+
+/// One labelled slider over a single effects field.
+#[component]
+pub fn EffectSlider(
+    label: String,
+    value_label: String,
+    min: f32,
+    max: f32,
+    step: f32,
+    value: f32,
+    disabled: bool,
+    on_change: EventHandler<f32>
+) -> Element {
+    rsx! {
+        div { class: "space-y-1",
+            div { class: "flex items-center justify-between",
+                div { class: "text-sm font-medium text-base-content", "{label}" }
+                span { class: "text-sm font-bold text-base-content", "{value_label}" }
+            }
+            input {
+                class: "range range-xs range-primary w-full",
+                r#type: "range",
+                min,
+                max,
+                step,
+                value,
+                disabled,
+                oninput: move |evt: Event<FormData>| {
+                    if let Ok(value) = evt.value().parse::<f32>() {
+                        on_change.call(value);
+                    }
+                },
+            }
+        }
+    }
+}
+
+fn db_label(db: f32) -> String {
+    format!("{:+.1} dB", db)
+}
+
+fn percent_label(amount: f32) -> String {
+    format!("{}%", (amount * 100.0).round() as u32)
+}
+
+/// EQ, dampening and reverb for everything the keyboard and mouse play.
+/// Every change is sent to the engine at once, so dragging a slider is heard
+/// on the next keystroke.
+#[component]
+pub fn EffectsSettingsPanel() -> Element {
+    let (config, update_config) = use_config();
+    let audio_ctx = use_context::<Arc<AudioContext>>();
+    let effects = use_memo(move || config().effects);
+
+    let set_effects = {
+        let audio_ctx = audio_ctx.clone();
+        move |effects: EffectsSettings| {
+            audio_ctx.set_effects(effects);
+            update_config(
+                Box::new(move |config| {
+                    config.effects = effects;
+                })
+            );
+        }
+    };
+
+    let current = effects();
+    let off = !current.enabled;
+    let dampening_label = if current.lowpass_hz >= MAX_LOWPASS_HZ {
+        "Off".to_string()
+    } else {
+        format!("{:.1} kHz", current.lowpass_hz / 1000.0)
+    };
+
+    rsx! {
+        div { class: "space-y-3",
+            div { class: "flex items-center justify-between",
+                div { class: "text-xs text-base-content/70",
+                    "Shape every key and click. Applies to keyboard and mouse sounds."
+                }
+                if current != EffectsSettings::default() {
+                    button {
+                        class: "btn btn-ghost btn-xs",
+                        onclick: {
+                            let set_effects = set_effects.clone();
+                            move |_| set_effects(EffectsSettings::default())
+                        },
+                        "Reset"
+                    }
+                }
+            }
+            Toggler {
+                title: "Enable effects".to_string(),
+                description: None,
+                checked: current.enabled,
+                on_change: {
+                    let set_effects = set_effects.clone();
+                    move |enabled: bool| set_effects(EffectsSettings { enabled, ..current })
+                },
+            }
+            div { class: "bg-base-100 px-4 py-3 rounded-box space-y-2",
+                EffectSlider {
+                    label: "Bass".to_string(),
+                    value_label: db_label(current.eq_low_db),
+                    min: -MAX_EQ_DB,
+                    max: MAX_EQ_DB,
+                    step: 0.5,
+                    value: current.eq_low_db,
+                    disabled: off,
+                    on_change: {
+                        let set_effects = set_effects.clone();
+                        move |eq_low_db: f32| set_effects(EffectsSettings { eq_low_db, ..current })
+                    },
+                }
+                EffectSlider {
+                    label: "Mid".to_string(),
+                    value_label: db_label(current.eq_mid_db),
+                    min: -MAX_EQ_DB,
+                    max: MAX_EQ_DB,
+                    step: 0.5,
+                    value: current.eq_mid_db,
+                    disabled: off,
+                    on_change: {
+                        let set_effects = set_effects.clone();
+                        move |eq_mid_db: f32| set_effects(EffectsSettings { eq_mid_db, ..current })
+                    },
+                }
+                EffectSlider {
+                    label: "Treble".to_string(),
+                    value_label: db_label(current.eq_high_db),
+                    min: -MAX_EQ_DB,
+                    max: MAX_EQ_DB,
+                    step: 0.5,
+                    value: current.eq_high_db,
+                    disabled: off,
+                    on_change: {
+                        let set_effects = set_effects.clone();
+                        move |eq_high_db: f32| set_effects(EffectsSettings { eq_high_db, ..current })
+                    },
+                }
+                EffectSlider {
+                    label: "Dampening".to_string(),
+                    value_label: dampening_label,
+                    min: MIN_LOWPASS_HZ,
+                    max: MAX_LOWPASS_HZ,
+                    step: 100.0,
+                    value: current.lowpass_hz,
+                    disabled: off,
+                    on_change: {
+                        let set_effects = set_effects.clone();
+                        move |lowpass_hz: f32| set_effects(EffectsSettings { lowpass_hz, ..current })
+                    },
+                }
+                EffectSlider {
+                    label: "Reverb".to_string(),
+                    value_label: percent_label(current.reverb_mix),
+                    min: 0.0,
+                    max: 1.0,
+                    step: 0.05,
+                    value: current.reverb_mix,
+                    disabled: off,
+                    on_change: {
+                        let set_effects = set_effects.clone();
+                        move |reverb_mix: f32| set_effects(EffectsSettings { reverb_mix, ..current })
+                    },
+                }
+                EffectSlider {
+                    label: "Room size".to_string(),
+                    value_label: percent_label(current.reverb_size),
+                    min: 0.0,
+                    max: 1.0,
+                    step: 0.05,
+                    value: current.reverb_size,
+                    disabled: off || current.reverb_mix == 0.0,
+                    on_change: {
+                        let set_effects = set_effects.clone();
+                        move |reverb_size: f32| set_effects(EffectsSettings { reverb_size, ..current })
+                    },
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,176 @@
+use crate::libs::hotkeys::{ self, Hotkey, HotkeyAction };
+use crate::utils::config::use_config;
+use dioxus::prelude::*;
+
+/// A combo the user pressed that another action already uses, waiting for
+/// them to confirm taking it over.
+#[derive(Clone, PartialEq)]
+struct PendingConflict {
+    action: HotkeyAction,
+    hotkey: Hotkey,
+    taken_from: HotkeyAction,
+}
+
+/// Lists every hotkey action with its combo. "Record" listens for the next
+/// combo pressed; while it does, the listeners stop matching hotkeys so the
+/// current mute combo can be rebound without muting.
+#[component]
+pub fn HotkeySettings() -> Element {
+    let (config, update_config) = use_config();
+    let bindings = use_memo(move || config().hotkeys.clone());
+
+    let mut recording = use_signal(|| None::<HotkeyAction>);
+    let mut conflict = use_signal(|| None::<PendingConflict>);
+    let mut error_message = use_signal(String::new);
+
+    // Leaving the page mid-recording must not leave hotkeys switched off.
+    use_drop(|| hotkeys::set_capturing(false));
+
+    let save = {
+        let update_config = update_config.clone();
+        move |action: HotkeyAction, hotkey: Option<Hotkey>| {
+            update_config(
+                Box::new(move |config| {
+                    match hotkey {
+                        Some(hotkey) => hotkeys::bind(&mut config.hotkeys, action, hotkey),
+                        None => {
+                            config.hotkeys.remove(&action);
+                        }
+                    }
+                })
+            );
+            hotkeys::notify_hotkeys_changed();
+        }
+    };
+
+    let mut stop_recording = move || {
+        recording.set(None);
+        hotkeys::set_capturing(false);
+    };
+
+    rsx! {
+        div { class: "space-y-2",
+            p { class: "text-xs text-base-content/60",
+                "Work from anywhere, even while the window is hidden. Each combo needs at least one of Ctrl, Alt, Shift or Meta."
+            }
+
+            div { class: "bg-base-100 px-4 py-3 rounded-box space-y-2",
+                for action in HotkeyAction::ALL {
+                    div {
+                        key: "{action.wire_name()}",
+                        class: "flex items-center justify-between gap-2",
+                        span { class: "text-sm", "{action.label()}" }
+                        div { class: "flex items-center gap-2",
+                            if recording() == Some(action) {
+                                button {
+                                    class: "btn btn-primary btn-xs",
+                                    // Focused so it receives the combo's keydown.
+                                    onmounted: move |evt: MountedEvent| async move {
+                                        let _ = evt.set_focus(true).await;
+                                    },
+                                    onkeydown: {
+                                        let save = save.clone();
+                                        move |evt: KeyboardEvent| {
+                                            evt.prevent_default();
+                                            let code = evt.code().to_string();
+                                            let modifiers = evt.modifiers();
+                                            if code == "Escape" && modifiers.is_empty() {
+                                                stop_recording();
+                                                return;
+                                            }
+                                            // Wait for the key that completes the combo.
+                                            if hotkeys::is_modifier(&code) {
+                                                return;
+                                            }
+
+                                            let hotkey = Hotkey::new(
+                                                modifiers.ctrl(),
+                                                modifiers.alt(),
+                                                modifiers.shift(),
+                                                modifiers.meta(),
+                                                &code
+                                            );
+                                            if !hotkey.has_modifier() {
+                                                error_message.set(
+                                                    format!("{} alone would block normal typing - hold Ctrl, Alt, Shift or Meta with it", hotkey.label())
+                                                );
+                                                return;
+                                            }
+
+                                            error_message.set(String::new());
+                                            stop_recording();
+                                            match hotkeys::find_conflict(&bindings(), action, &hotkey) {
+                                                Some(taken_from) => {
+                                                    conflict.set(Some(PendingConflict { action, hotkey, taken_from }));
+                                                }
+                                                None => save(action, Some(hotkey)),
+                                            }
+                                        }
+                                    },
+                                    onblur: move |_| stop_recording(),
+                                    onclick: move |_| stop_recording(),
+                                    "Press a combo… (Esc to cancel)"
+                                }
+                            } else {
+                                if let Some(hotkey) = bindings().get(&action) {
+                                    span { class: "kbd kbd-sm", "{hotkey.label()}" }
+                                } else {
+                                    span { class: "text-xs text-base-content/50", "Not set" }
+                                }
+                                button {
+                                    class: "btn btn-soft btn-xs",
+                                    onclick: move |_| {
+                                        conflict.set(None);
+                                        error_message.set(String::new());
+                                        recording.set(Some(action));
+                                        hotkeys::set_capturing(true);
+                                    },
+                                    "Record"
+                                }
+                                if bindings().contains_key(&action) {
+                                    button {
+                                        class: "btn btn-ghost btn-xs",
+                                        onclick: {
+                                            let save = save.clone();
+                                            move |_| save(action, None)
+                                        },
+                                        "Clear"
+                                    }
+                                }
+                            }
+                        }
+                    }
+                }
+            }
+
+            if let Some(pending) = conflict() {
+                div { class: "alert alert-warning mt-2",
+                    div { class: "text-sm flex-1",
+                        "{pending.hotkey.label()} is already used for {pending.taken_from.label()}."
+                    }
+                    button {
+                        class: "btn btn-warning btn-xs",
+                        onclick: {
+                            let save = save.clone();
+                            let pending = pending.clone();
+                            move |_| {
+                                conflict.set(None);
+                                save(pending.action, Some(pending.hotkey.clone()));
+                            }
+                        },
+                        "Use for {pending.action.label()}"
+                    }
+                    button {
+                        class: "btn btn-ghost btn-xs",
+                        onclick: move |_| conflict.set(None),
+                        "Cancel"
+                    }
+                }
+            }
+
+            if !error_message().is_empty() {
+                div { class: "text-xs text-error mt-2", "{error_message()}" }
+            }
+        }
+    }
+}
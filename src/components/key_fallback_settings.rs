@@ -0,0 +1,78 @@
+use crate::components::ui::Toggler;
+use crate::libs::AudioContext;
+use crate::state::config::KeyFallback;
+use crate::utils::config::use_config;
+use dioxus::prelude::*;
+use std::sync::Arc;
+
+/// What plays for a key the keyboard pack has no sound for. The steps run
+/// in the order shown; see `utils::key_fallback`.
+#[component]
+pub fn KeyFallbackSettings() -> Element {
+    let (config, update_config) = use_config();
+    let audio_ctx = use_context::<Arc<AudioContext>>();
+
+    let fallback = use_memo(move || config().key_fallback);
+    let layout = use_memo(move || config().keyboard_layout);
+
+    let set_fallback = move |fallback: KeyFallback| {
+        audio_ctx.set_key_fallback(fallback.clone());
+        update_config(
+            Box::new(move |config| {
+                config.key_fallback = fallback;
+            })
+        );
+    };
+
+    let current = fallback();
+    let default_key = current.default_key.clone().unwrap_or_default();
+
+    rsx! {
+        Toggler {
+            title: "Borrow a similar key's sound".to_string(),
+            description: Some(
+                "Keys a sound pack lacks play a key of the same kind, e.g. F13 plays F12"
+                    .to_string(),
+            ),
+            checked: current.family,
+            on_change: {
+                let set_fallback = set_fallback.clone();
+                let current = current.clone();
+                move |family: bool| set_fallback(KeyFallback { family, ..current.clone() })
+            },
+        }
+        Toggler {
+            title: "Then borrow a letter key's sound".to_string(),
+            description: None,
+            checked: current.alpha,
+            on_change: {
+                let set_fallback = set_fallback.clone();
+                let current = current.clone();
+                move |alpha: bool| set_fallback(KeyFallback { alpha, ..current.clone() })
+            },
+        }
+        div { class: "flex items-center justify-between",
+            div { class: "text-sm font-medium text-base-content", "Otherwise play" }
+            select {
+                class: "select select-sm w-40",
+                onchange: {
+                    let set_fallback = set_fallback.clone();
+                    let current = current.clone();
+                    move |evt: Event<FormData>| {
+                        let value = evt.value();
+                        let default_key = (!value.is_empty()).then_some(value);
+                        set_fallback(KeyFallback { default_key, ..current.clone() });
+                    }
+                },
+                option { value: "", selected: default_key.is_empty(), "Nothing" }
+                for key in layout().keys() {
+                    option {
+                        value: key.code,
+                        selected: key.code == default_key,
+                        "{key.code}"
+                    }
+                }
+            }
+        }
+    }
+}
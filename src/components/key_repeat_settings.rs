@@ -0,0 +1,84 @@
+use crate::libs::AudioContext;
+use crate::state::config::KeyRepeat;
+use crate::utils::config::use_config;
+use dioxus::prelude::*;
+use std::sync::Arc;
+
+/// What a held key sounds like once the OS starts auto-repeating it.
+#[component]
+pub fn KeyRepeatSettings() -> Element {
+    let (config, update_config) = use_config();
+    let audio_ctx = use_context::<Arc<AudioContext>>();
+
+    let key_repeat = use_memo(move || config().key_repeat);
+    let repeat_gain = use_memo(move || config().repeat_gain);
+
+    rsx! {
+        div { class: "flex items-center justify-between",
+            div {
+                div { class: "text-sm font-medium text-base-content", "Held keys" }
+                div { class: "text-xs text-base-content/70", "Sound for each auto-repeat while a key is held down" }
+            }
+            select {
+                class: "select select-sm w-40",
+                onchange: {
+                    let audio_ctx = audio_ctx.clone();
+                    let update_config = update_config.clone();
+                    move |evt: Event<FormData>| {
+                        let value = evt.value();
+                        if let Some(policy) = KeyRepeat::ALL
+                            .into_iter()
+                            .find(|policy| policy.label() == value)
+                        {
+                            audio_ctx.set_key_repeat(policy);
+                            update_config(
+                                Box::new(move |config| {
+                                    config.key_repeat = policy;
+                                }),
+                            );
+                        }
+                    }
+                },
+                for policy in KeyRepeat::ALL {
+                    option {
+                        value: policy.label(),
+                        selected: policy == key_repeat(),
+                        "{policy.label()}"
+                    }
+                }
+            }
+        }
+        if key_repeat() != KeyRepeat::Silent {
+            div { class: "space-y-1",
+                div { class: "flex items-center justify-between",
+                    div { class: "text-sm font-medium text-base-content", "Repeat volume" }
+                    span { class: "text-sm font-bold text-base-content",
+                        "{(repeat_gain() * 100.0).round() as u32}%"
+                    }
+                }
+                input {
+                    class: "range range-xs range-primary w-full",
+                    r#type: "range",
+                    min: 0.0,
+                    max: 1.0,
+                    step: 0.05,
+                    value: repeat_gain(),
+                    oninput: {
+                        let audio_ctx = audio_ctx.clone();
+                        let update_config = update_config.clone();
+                        move |evt: Event<FormData>| {
+                            if let Ok(gain) = evt.value().parse::<f32>() {
+                                audio_ctx.set_repeat_gain(gain);
+                                update_config(
+                                    Box::new(move |config| {
+                                        config.repeat_gain = gain;
+                                    }),
+                                );
+                            }
+                        }
+                    },
+                }
+            }
+        }
+    }
+}
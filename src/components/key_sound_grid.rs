@@ -0,0 +1,213 @@
+use crate::components::ui::Toggler;
+use crate::libs::AudioContext;
+use crate::state::config::{ KeyOverride, MAX_KEY_GAIN };
+use crate::utils::config::use_config;
+use dioxus::prelude::*;
+use std::sync::Arc;
+
+/// Short legend for a key cap, e.g. `"A"` for `"KeyA"`.
+fn cap_label(code: &str) -> String {
+    let label = match code {
+        "Escape" => "Esc",
+        "Backquote" => "`",
+        "Minus" => "-",
+        "Equal" => "=",
+        "Backspace" => "⌫",
+        "BracketLeft" => "[",
+        "BracketRight" => "]",
+        "Backslash" | "IntlBackslash" => "\\",
+        "CapsLock" => "Caps",
+        "Semicolon" => ";",
+        "Quote" => "'",
+        "Comma" => ",",
+        "Period" | "NumpadDecimal" => ".",
+        "Slash" | "NumpadDivide" => "/",
+        "ShiftLeft" | "ShiftRight" => "Shift",
+        "ControlLeft" | "ControlRight" => "Ctrl",
+        "AltLeft" | "AltRight" => "Alt",
+        "MetaLeft" | "MetaRight" => "Meta",
+        "ContextMenu" => "Menu",
+        "Space" => "",
+        "PrintScreen" => "PrtSc",
+        "ScrollLock" => "ScrLk",
+        "Insert" => "Ins",
+        "Delete" => "Del",
+        "PageUp" => "PgUp",
+        "PageDown" => "PgDn",
+        "ArrowUp" => "↑",
+        "ArrowDown" => "↓",
+        "ArrowLeft" => "←",
+        "ArrowRight" => "→",
+        "NumLock" => "Num",
+        "NumpadMultiply" => "*",
+        "NumpadSubtract" => "-",
+        "NumpadAdd" => "+",
+        "NumpadEnter" => "⏎",
+        _ => {
+            return code
+                .strip_prefix("Key")
+                .or_else(|| code.strip_prefix("Digit"))
+                .or_else(|| code.strip_prefix("Numpad"))
+                .unwrap_or(code)
+                .to_string();
+        }
+    };
+    label.to_string()
+}
+
+/// The keyboard from Settings' layout picker, one cap per key. Picking a
+/// cap opens its gain and mute for the current keyboard soundpack; caps with
+/// an override are highlighted.
+#[component]
+pub fn KeySoundGrid() -> Element {
+    let (config, update_config) = use_config();
+    let audio_ctx = use_context::<Arc<AudioContext>>();
+
+    let soundpack_id = use_memo(move || config().keyboard_soundpack.clone());
+    let layout = use_memo(move || config().keyboard_layout);
+    let overrides = use_memo(move || {
+        config().key_overrides.get(&soundpack_id()).cloned().unwrap_or_default()
+    });
+    let mut selected = use_signal(|| None::<&'static str>);
+
+    let set_override = {
+        let audio_ctx = audio_ctx.clone();
+        let update_config = update_config.clone();
+        move |code: &'static str, key_override: KeyOverride| {
+            let soundpack_id = soundpack_id();
+            audio_ctx.set_key_override(&soundpack_id, code, key_override);
+            update_config(
+                Box::new(move |config| {
+                    config.set_key_override(&soundpack_id, code, key_override);
+                })
+            );
+        }
+    };
+
+    let keys = layout().keys();
+    let width = layout().width();
+    let rows = keys
+        .iter()
+        .map(|key| key.row + 1)
+        .max()
+        .unwrap_or(1) as f32;
+    let selected_override = selected().map(|code| {
+        (code, overrides().get(code).copied().unwrap_or_default())
+    });
+
+    rsx! {
+        div { class: "space-y-3",
+            div { class: "flex items-center justify-between",
+                div { class: "text-xs text-base-content/70",
+                    "Pick a key to change its level or silence it. Saved for this soundpack only."
+                }
+                if !overrides().is_empty() {
+                    button {
+                        class: "btn btn-ghost btn-xs",
+                        onclick: {
+                            let audio_ctx = audio_ctx.clone();
+                            let update_config = update_config.clone();
+                            move |_| {
+                                let soundpack_id = soundpack_id();
+                                audio_ctx.reset_key_overrides(&soundpack_id);
+                                update_config(
+                                    Box::new(move |config| {
+                                        config.key_overrides.remove(&soundpack_id);
+                                    }),
+                                );
+                            }
+                        },
+                        "Reset all keys"
+                    }
+                }
+            }
+
+            div {
+                class: "relative w-full",
+                style: "aspect-ratio: {width} / {rows};",
+                for key in keys {
+                    button {
+                        key: "{key.code}",
+                        class: "absolute p-px",
+                        style: format!(
+                            "left: {}%; top: {}%; width: {}%; height: {}%;",
+                            key.x / width * 100.0,
+                            (key.row as f32) / rows * 100.0,
+                            key.width / width * 100.0,
+                            100.0 / rows,
+                        ),
+                        title: "{key.code}",
+                        onclick: move |_| selected.set(Some(key.code)),
+                        {
+                            let key_override = overrides().get(key.code).copied();
+                            let state_class = match key_override {
+                                Some(o) if o.muted => "bg-base-300 text-base-content/30 line-through",
+                                Some(_) => "bg-primary text-primary-content",
+                                None => "bg-base-100 text-base-content/70",
+                            };
+                            let ring_class = if selected() == Some(key.code) { "ring-2 ring-primary" } else { "" };
+                            rsx! {
+                                span {
+                                    class: "flex w-full h-full items-center justify-center rounded text-[9px] leading-none {state_class} {ring_class}",
+                                    "{cap_label(key.code)}"
+                                }
+                            }
+                        }
+                    }
+                }
+            }
+
+            if let Some((code, key_override)) = selected_override {
+                div { class: "bg-base-100 px-4 py-3 rounded-box space-y-2",
+                    div { class: "flex items-center justify-between",
+                        span { class: "kbd kbd-sm", "{code}" }
+                        if !key_override.is_default() {
+                            button {
+                                class: "btn btn-ghost btn-xs",
+                                onclick: {
+                                    let set_override = set_override.clone();
+                                    move |_| set_override(code, KeyOverride::default())
+                                },
+                                "Reset"
+                            }
+                        }
+                    }
+                    Toggler {
+                        title: "Mute this key".to_string(),
+                        description: None,
+                        checked: key_override.muted,
+                        on_change: {
+                            let set_override = set_override.clone();
+                            move |muted: bool| set_override(code, KeyOverride { muted, ..key_override })
+                        },
+                    }
+                    div { class: "space-y-1",
+                        div { class: "flex items-center justify-between",
+                            div { class: "text-sm font-medium text-base-content", "Level" }
+                            span { class: "text-sm font-bold text-base-content",
+                                "{(key_override.gain * 100.0).round() as u32}%"
+                            }
+                        }
+                        input {
+                            class: "range range-xs range-primary w-full",
+                            r#type: "range",
+                            min: 0.0,
+                            max: MAX_KEY_GAIN,
+                            step: 0.05,
+                            value: key_override.gain,
+                            disabled: key_override.muted,
+                            oninput: {
+                                let set_override = set_override.clone();
+                                move |evt: Event<FormData>| {
+                                    if let Ok(gain) = evt.value().parse::<f32>() {
+                                        set_override(code, KeyOverride { gain, ..key_override });
+                                    }
+                                }
+                            },
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
@@ -2,8 +2,15 @@ pub mod app_info;
 pub mod debug_log_viewer;
 pub mod device_selector;
 pub mod dock;
+pub mod ducking_settings;
+pub mod effects_settings;
 pub mod header;
+pub mod hotkey_settings;
+pub mod key_fallback_settings;
+pub mod key_repeat_settings;
+pub mod key_sound_grid;
 pub mod logo;
+pub mod overlap_settings;
 pub mod pages;
 pub mod soundpack_selector;
 pub mod theme_toggler;
@@ -0,0 +1,120 @@
+use crate::components::ui::Toggler;
+use crate::libs::AudioContext;
+use crate::state::config::VoiceStealing;
+use crate::utils::config::use_config;
+use dioxus::prelude::*;
+use std::sync::Arc;
+
+/// One group per line, key codes separated by spaces or commas.
+fn parse_choke_groups(text: &str) -> Vec<Vec<String>> {
+    text.lines()
+        .map(|line| {
+            line.split(|c: char| c.is_whitespace() || c == ',')
+                .filter(|code| !code.is_empty())
+                .map(str::to_string)
+                .collect::<Vec<_>>()
+        })
+        .filter(|group| !group.is_empty())
+        .collect()
+}
+
+fn format_choke_groups(groups: &[Vec<String>]) -> String {
+    groups
+        .iter()
+        .map(|group| group.join(" "))
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// How overlapping key sounds give way to each other: self-choke, the
+/// user's choke groups, and which voice is stolen past the voice limit.
+/// Long-tail packs smear during fast typing without these.
+#[component]
+pub fn OverlapSettings() -> Element {
+    let (config, update_config) = use_config();
+    let audio_ctx = use_context::<Arc<AudioContext>>();
+
+    let choke_same_key = use_memo(move || config().choke_same_key);
+    let voice_stealing = use_memo(move || config().voice_stealing);
+    let mut groups_text = use_signal(|| format_choke_groups(&config.peek().choke_groups));
+
+    rsx! {
+        Toggler {
+            title: "Cut off a key's previous sound".to_string(),
+            description: Some(
+                "A key's release ends its press sound, and pressing it again starts clean"
+                    .to_string(),
+            ),
+            checked: choke_same_key(),
+            on_change: {
+                let audio_ctx = audio_ctx.clone();
+                let update_config = update_config.clone();
+                move |new_value: bool| {
+                    audio_ctx.set_choke_same_key(new_value);
+                    update_config(
+                        Box::new(move |config| {
+                            config.choke_same_key = new_value;
+                        }),
+                    );
+                }
+            },
+        }
+        div { class: "flex items-center justify-between",
+            div { class: "text-sm font-medium text-base-content", "When too many sounds overlap, cut" }
+            select {
+                class: "select select-sm w-40",
+                onchange: {
+                    let audio_ctx = audio_ctx.clone();
+                    let update_config = update_config.clone();
+                    move |evt: Event<FormData>| {
+                        let value = evt.value();
+                        if let Some(policy) = VoiceStealing::ALL
+                            .into_iter()
+                            .find(|policy| policy.label() == value)
+                        {
+                            audio_ctx.set_voice_stealing(policy);
+                            update_config(
+                                Box::new(move |config| {
+                                    config.voice_stealing = policy;
+                                }),
+                            );
+                        }
+                    }
+                },
+                for policy in VoiceStealing::ALL {
+                    option {
+                        value: policy.label(),
+                        selected: policy == voice_stealing(),
+                        "{policy.label()}"
+                    }
+                }
+            }
+        }
+        div { class: "space-y-1",
+            div { class: "text-sm font-medium text-base-content", "Choke groups" }
+            div { class: "text-xs text-base-content/70",
+                "Keys on the same line cut each other off, e.g. "
+                span { class: "kbd kbd-xs", "ShiftLeft ShiftRight" }
+            }
+            textarea {
+                class: "textarea textarea-sm w-full font-mono",
+                rows: 3,
+                value: groups_text(),
+                oninput: move |evt: Event<FormData>| groups_text.set(evt.value()),
+                onchange: {
+                    let audio_ctx = audio_ctx.clone();
+                    let update_config = update_config.clone();
+                    move |_| {
+                        let groups = parse_choke_groups(&groups_text());
+                        audio_ctx.set_choke_groups(groups.clone());
+                        update_config(
+                            Box::new(move |config| {
+                                config.choke_groups = groups;
+                            }),
+                        );
+                    }
+                },
+            }
+        }
+    }
+}
@@ -1,3 +1,5 @@
+use crate::components::effects_settings::EffectsSettingsPanel;
+use crate::components::key_sound_grid::KeySoundGrid;
 use crate::components::theme_toggler::ThemeToggler;
 use crate::components::ui::{ Collapse, ColorPicker, PageHeader, Toggler };
 use crate::utils::config::use_config;
@@ -108,6 +110,24 @@ pub fn CustomizePage() -> Element {
           }
           LogoCollapseSection {}
           BackgroundCollapseSection {}
+          Collapse {
+            title: "Key sounds".to_string(),
+            group_name: "customize-accordion".to_string(),
+            variant: "border border-base-300 bg-base-200 text-base-content",
+            content_class: "collapse-content text-sm text-base-content/70",
+            children: rsx! {
+              KeySoundGrid {}
+            },
+          }
+          Collapse {
+            title: "Sound effects".to_string(),
+            group_name: "customize-accordion".to_string(),
+            variant: "border border-base-300 bg-base-200 text-base-content",
+            content_class: "collapse-content text-sm text-base-content/70",
+            children: rsx! {
+              EffectsSettingsPanel {}
+            },
+          }
                 // Custom CSS Section
         // div { class: "collapse collapse-arrow border border-base-300 bg-base-200 text-base-content",
         //   input { r#type: "radio", name: "customize-accordion" }
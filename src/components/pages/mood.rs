@@ -16,19 +16,13 @@ use lucide_dioxus::{
     Radio,
 };
 
+use crate::components::ducking_settings::DuckingSettings;
 use crate::components::ui::{ PageHeader };
 use crate::state::ambiance::{
     AmbiancePlayerState,
     initialize_global_ambiance_player_state,
     update_global_ambiance_player_state,
     get_global_ambiance_player_state_copy,
-    play_ambiance_sound,
-    stop_ambiance_sound,
-    pause_all_ambiance_sounds,
-    resume_all_ambiance_sounds,
-    set_ambiance_sound_volume,
-    set_global_ambiance_volume,
-    set_global_ambiance_mute,
 };
 
 // Sound Selection Panel Component
@@ -65,13 +59,6 @@ fn SoundSelectionPanel(
                 update_global_ambiance_player_state(|player| {
                     player.toggle_play_pause();
                 });
-                if let Some(current_state) = get_global_ambiance_player_state_copy() {
-                    if current_state.is_playing {
-                        let _ = resume_all_ambiance_sounds();
-                    } else {
-                        let _ = pause_all_ambiance_sounds();
-                    }
-                }
                 refresh_trigger.set(refresh_trigger() + 1);
             },
             if ambiance_player().is_playing {
@@ -86,11 +73,7 @@ fn SoundSelectionPanel(
             onclick: move |_| {
                 update_global_ambiance_player_state(|player| {
                     player.toggle_mute();
-                    player.save_config();
                 });
-                if let Some(current_state) = get_global_ambiance_player_state_copy() {
-                    let _ = set_global_ambiance_mute(current_state.is_muted);
-                }
                 refresh_trigger.set(refresh_trigger() + 1);
             },
             if ambiance_player().is_muted {
@@ -110,9 +93,7 @@ fn SoundSelectionPanel(
                 if let Ok(val) = evt.value().parse::<f32>() {
                     update_global_ambiance_player_state(|player| {
                         player.set_global_volume(val / 100.0);
-                        player.save_config();
                     });
-                    let _ = set_global_ambiance_volume(val / 100.0);
                     refresh_trigger.set(refresh_trigger() + 1);
                 }
             },
@@ -192,38 +173,10 @@ fn SoundSelectionPanel(
                     checked: ambiance_player().is_sound_active(&sound.id),
                     onchange: {
                         let sound_id = sound.id.clone();
-                        let sound_url = sound.audio_url.clone();
                         move |_| {
-                            let is_currently_active = if let Some(current_state) = get_global_ambiance_player_state_copy() {
-                                current_state.is_sound_active(&sound_id)
-                            } else {
-                                false
-                            };
                             update_global_ambiance_player_state(|player| {
                                 player.toggle_sound(sound_id.clone());
-                                player.save_config();
                             });
-                            if let Some(current_state) = get_global_ambiance_player_state_copy() {
-                                if current_state.is_sound_active(&sound_id) && !is_currently_active {
-                                    if current_state.is_playing {
-                                        let volume = current_state.get_sound_volume(&sound_id);
-                                        let global_volume = if current_state.is_muted {
-                                            0.0
-                                        } else {
-                                            current_state.global_volume
-                                        };
-                                        let _ = play_ambiance_sound(
-                                            sound_id.clone(),
-                                            sound_url.clone(),
-                                            volume * global_volume,
-                                        );
-                                    }
-                                } else if !current_state.is_sound_active(&sound_id)
-                                    && is_currently_active
-                                {
-                                    let _ = stop_ambiance_sound(&sound_id);
-                                }
-                            }
                             refresh_trigger.set(refresh_trigger() + 1);
                         }
                     },
@@ -246,9 +199,7 @@ fn SoundSelectionPanel(
                                 let volume = val / 100.0;
                                 update_global_ambiance_player_state(|player| {
                                     player.set_sound_volume(sound_id.clone(), volume);
-                                    player.save_config();
                                 });
-                                let _ = set_ambiance_sound_volume(&sound_id, volume);
                                 refresh_trigger.set(refresh_trigger() + 1);
                             }
                         }
@@ -301,6 +252,7 @@ pub fn MoodPage() -> Element {
         }
 
         SoundSelectionPanel { ambiance_player, refresh_trigger }
+        DuckingSettings {}
       }
     }
 }
@@ -1,9 +1,16 @@
 use crate::components::ui::{ Collapse, PageHeader, Toggler };
 use crate::components::device_selector::AudioOutputSelector;
+use crate::components::hotkey_settings::HotkeySettings;
+use crate::components::key_fallback_settings::KeyFallbackSettings;
+use crate::components::key_repeat_settings::KeyRepeatSettings;
+use crate::components::overlap_settings::OverlapSettings;
+use crate::libs::hotkeys::HotkeyAction;
 use crate::libs::AudioContext;
+use crate::libs::audio::engine::MAX_RANDOM_PITCH_RANGE;
 use crate::libs::theme::{ use_theme, BuiltInTheme, Theme };
 use crate::libs::tray_service::request_tray_update;
 use crate::utils::config::use_config;
+use crate::utils::keyboard_layout::KeyboardLayout;
 use crate::utils::constants::{ APP_NAME_DISPLAY, APP_NAME };
 use crate::utils::auto_updater::{ check_for_updates_simple, UpdateInfo };
 use crate::state::app::use_update_info_setter;
@@ -24,6 +31,12 @@ pub fn SettingsPage() -> Element {
     // Use computed signals that always reflect current config state
     let enable_sound = use_memo(move || config().enable_sound);
     let enable_volume_boost = use_memo(move || config().enable_volume_boost);
+    let mute_hotkey = use_memo(move || config().hotkeys.get(&HotkeyAction::ToggleSound).map(|hotkey| hotkey.label()));
+    let random_pitch_range = use_memo(move || config().random_pitch_range);
+    let auto_normalize_loudness = use_memo(move || config().auto_normalize_loudness);
+    let stereo_panning = use_memo(move || config().stereo_panning);
+    let keyboard_layout = use_memo(move || config().keyboard_layout);
+    let stereo_width = use_memo(move || config().stereo_width);
     let auto_start = use_memo(move || config().auto_start);
     let start_minimized = use_memo(move || config().start_minimized);
     let enable_telemetry = use_memo(move || config().enable_telemetry);
@@ -70,7 +83,10 @@ pub fn SettingsPage() -> Element {
                 // Volume Control
                 Toggler {
                   title: "Enable all sounds".to_string(),
-                  description: Some("You can also use Ctrl+Alt+M to toggle sound on/off".to_string()),
+                  description: Some(match mute_hotkey() {
+                      Some(combo) => format!("You can also use {} to toggle sound on/off", combo),
+                      None => "Bind a mute hotkey under Hotkeys to toggle sound from anywhere".to_string(),
+                  }),
                   checked: enable_sound(),
                   on_change: {
                       let update_config = update_config.clone();
@@ -116,6 +132,154 @@ pub fn SettingsPage() -> Element {
                       }
                   },
                 }
+                // Random pitch range
+                div { class: "space-y-1",
+                  div { class: "flex items-center justify-between",
+                    div {
+                      div { class: "text-sm font-medium text-base-content", "Random pitch range" }
+                      div { class: "text-xs text-base-content/70",
+                        "How much each keystroke may vary, for soundpacks that enable random pitch"
+                      }
+                    }
+                    span { class: "text-sm font-bold text-base-content",
+                      "±{(random_pitch_range() * 100.0).round() as u32}%"
+                    }
+                  }
+                  input {
+                    class: "range range-xs range-primary w-full",
+                    r#type: "range",
+                    min: 0.0,
+                    max: MAX_RANDOM_PITCH_RANGE,
+                    step: 0.01,
+                    value: random_pitch_range(),
+                    oninput: {
+                        let audio_ctx = audio_ctx.clone();
+                        let update_config = update_config.clone();
+                        move |evt: Event<FormData>| {
+                            if let Ok(range) = evt.value().parse::<f32>() {
+                                audio_ctx.set_random_pitch_range(range);
+                                update_config(
+                                    Box::new(move |config| {
+                                        config.random_pitch_range = range;
+                                    }),
+                                );
+                            }
+                        }
+                    },
+                  }
+                }
+                // Loudness normalization
+                Toggler {
+                  title: "Even out soundpack loudness".to_string(),
+                  description: Some(
+                      "Measure each soundpack and level it, so switching packs keeps the same volume"
+                          .to_string(),
+                  ),
+                  checked: auto_normalize_loudness(),
+                  on_change: {
+                      let audio_ctx = audio_ctx.clone();
+                      let update_config = update_config.clone();
+                      move |new_value: bool| {
+                          audio_ctx.set_auto_normalize_loudness(new_value);
+                          update_config(
+                              Box::new(move |config| {
+                                  config.auto_normalize_loudness = new_value;
+                              }),
+                          );
+                      }
+                  },
+                }
+                // Stereo panning
+                Toggler {
+                  title: "Stereo panning".to_string(),
+                  description: Some(
+                      "Place each key left or right by where it sits on your keyboard".to_string(),
+                  ),
+                  checked: stereo_panning(),
+                  on_change: {
+                      let audio_ctx = audio_ctx.clone();
+                      let update_config = update_config.clone();
+                      move |new_value: bool| {
+                          audio_ctx.set_stereo_panning(new_value);
+                          update_config(
+                              Box::new(move |config| {
+                                  config.stereo_panning = new_value;
+                              }),
+                          );
+                      }
+                  },
+                }
+                if stereo_panning() {
+                  div { class: "space-y-3 pl-4 border-l-2 border-base-300",
+                    div { class: "flex items-center justify-between",
+                      div { class: "text-sm font-medium text-base-content", "Keyboard layout" }
+                      select {
+                        class: "select select-sm w-40",
+                        onchange: {
+                            let audio_ctx = audio_ctx.clone();
+                            let update_config = update_config.clone();
+                            move |evt: Event<FormData>| {
+                                let value = evt.value();
+                                if let Some(layout) = KeyboardLayout::ALL
+                                    .into_iter()
+                                    .find(|layout| layout.label() == value)
+                                {
+                                    audio_ctx.set_keyboard_layout(layout);
+                                    update_config(
+                                        Box::new(move |config| {
+                                            config.keyboard_layout = layout;
+                                        }),
+                                    );
+                                }
+                            }
+                        },
+                        for layout in KeyboardLayout::ALL {
+                          option {
+                            value: layout.label(),
+                            selected: layout == keyboard_layout(),
+                            "{layout.label()}"
+                          }
+                        }
+                      }
+                    }
+                    div { class: "space-y-1",
+                      div { class: "flex items-center justify-between",
+                        div { class: "text-sm font-medium text-base-content", "Stereo width" }
+                        span { class: "text-sm font-bold text-base-content",
+                          "{(stereo_width() * 100.0).round() as u32}%"
+                        }
+                      }
+                      input {
+                        class: "range range-xs range-primary w-full",
+                        r#type: "range",
+                        min: 0.0,
+                        max: 1.0,
+                        step: 0.05,
+                        value: stereo_width(),
+                        oninput: {
+                            let audio_ctx = audio_ctx.clone();
+                            let update_config = update_config.clone();
+                            move |evt: Event<FormData>| {
+                                if let Ok(width) = evt.value().parse::<f32>() {
+                                    audio_ctx.set_stereo_width(width);
+                                    update_config(
+                                        Box::new(move |config| {
+                                            config.stereo_width = width;
+                                        }),
+                                    );
+                                }
+                            }
+                        },
+                      }
+                    }
+                  }
+                }
+                // Choke groups and voice stealing
+                OverlapSettings {}
+                // Auto-repeat of held keys
+                KeyRepeatSettings {}
+                // Keys the pack has no sound for
+                KeyFallbackSettings {}
                 // Auto Start
                 Toggler {
                   title: "Start with Windows".to_string(),
@@ -197,6 +361,15 @@ pub fn SettingsPage() -> Element {
               }
             },
           }
+          // Hotkeys Section
+          Collapse {
+            title: "Hotkeys".to_string(),
+            group_name: "setting-accordion".to_string(),
+            content_class: "collapse-content text-sm",
+            children: rsx! {
+              HotkeySettings {}
+            },
+          }
           // Auto-Update Section
           Collapse {
             title: "Updates".to_string(),
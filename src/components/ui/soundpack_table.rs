@@ -1,6 +1,8 @@
 use crate::state::paths;
-use crate::state::soundpack::SoundpackMetadata;
+use crate::state::soundpack::{ SoundpackMetadata, SoundpackType };
 use crate::state::{ app::use_state_trigger };
+use crate::utils::config::use_config;
+use crate::utils::key_fallback;
 use crate::utils::path::{ open_path, directory_exists };
 use dioxus::document::eval;
 use dioxus::prelude::*;
@@ -172,6 +174,41 @@ pub fn SoundpackTable(
     }
 }
 
+/// How many keys of the user's layout a keyboard pack plays natively, and
+/// how many only through the fallback chain. The full lists are in the
+/// tooltip, so an author can see exactly what to add.
+#[component]
+fn KeyCoverageCell(mapped_keys: Vec<String>) -> Element {
+    let (config, _) = use_config();
+    let config = config();
+    let coverage = key_fallback::coverage(config.keyboard_layout, &mapped_keys, &config.key_fallback);
+
+    let mut details = Vec::new();
+    if !coverage.fallback.is_empty() {
+        details.push(format!("Borrowed sound: {}", coverage.fallback.join(", ")));
+    }
+    if !coverage.silent.is_empty() {
+        details.push(format!("Silent: {}", coverage.silent.join(", ")));
+    }
+    let title = if details.is_empty() {
+        format!("Every key on the {} layout has its own sound", config.keyboard_layout.label())
+    } else {
+        details.join("\n")
+    };
+
+    rsx! {
+      td { class: "text-xs whitespace-nowrap text-right", title: "{title}",
+        span {
+          class: if coverage.silent.is_empty() { "text-base-content/70" } else { "text-warning" },
+          "{coverage.native.len()}/{coverage.total()} keys"
+        }
+        if !coverage.fallback.is_empty() {
+          span { class: "text-base-content/50", " · {coverage.fallback.len()} fallback" }
+        }
+      }
+    }
+}
+
 #[component]
 pub fn SoundpackTableRow(soundpack: SoundpackMetadata) -> Element {
     let state_trigger = use_state_trigger();
@@ -266,6 +303,14 @@ pub fn SoundpackTableRow(soundpack: SoundpackMetadata) -> Element {
             }
           }
         }
+        // Coverage
+        if soundpack.soundpack_type == SoundpackType::Keyboard {
+          if let Some(mapped_keys) = soundpack.mapped_keys.clone() {
+            KeyCoverageCell { mapped_keys }
+          } else {
+            td {}
+          }
+        }
         // Actions
         td {
           div { class: "flex items-center justify-end gap-1",
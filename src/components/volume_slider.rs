@@ -1,4 +1,5 @@
 use crate::libs::AudioContext;
+use crate::state::output::OutputState;
 use crate::utils::config::use_config;
 use dioxus::prelude::*;
 use lucide_dioxus::{ Volume2, VolumeOff };
@@ -38,6 +39,11 @@ fn VolumeSliderBase(
     // Get volume boost setting
     let enable_volume_boost = use_memo(move || config().enable_volume_boost);
 
+    // Set while the limiter holds the mix down - the sound would clip
+    // without it, so this is the cue to turn the volume back down.
+    let output_state = use_context::<Signal<OutputState>>();
+    let clipping = enable_sound() && output_state().clipping;
+
     // Calculate max volume and percentage display
     let max_volume = if enable_volume_boost() { 2.0 } else { 1.0 };
     let volume_percentage = (volume() * 100.0) as u8;
@@ -60,7 +66,9 @@ fn VolumeSliderBase(
           span {
             class: format!(
                 "font-bold ml-1 {}",
-                if enable_volume_boost() && volume() > 1.0 {
+                if clipping {
+                    "text-error"
+                } else if enable_volume_boost() && volume() > 1.0 {
                     "text-warning"
                 } else if enable_sound() {
                     "text-base-content"
@@ -68,13 +76,23 @@ fn VolumeSliderBase(
                     "text-base-content/50"
                 },
             ),
+            title: if clipping { "Too loud: the mix is being limited to stop it clipping" } else { "" },
             "{volume_percentage}%"
           }
         }
         div {
           class: format!("{} flex items-center gap-2", if !enable_volume_boost() { "col-span-8" } else { "col-span-10" }),
           input {
-            class: format!("range range-xs grow {}", if volume() > 1.0 { "range-warning" } else { "range-primary" }),
+            class: format!(
+                "range range-xs grow {}",
+                if clipping {
+                    "range-error"
+                } else if volume() > 1.0 {
+                    "range-warning"
+                } else {
+                    "range-primary"
+                },
+            ),
             r#type: "range",
             min: 0.0,
             max: max_volume,
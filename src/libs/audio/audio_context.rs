@@ -1,4 +1,5 @@
-use crate::state::config::AppConfig;
+use crate::state::config::{ AppConfig, Ducking, EffectsSettings, KeyFallback, KeyOverride, KeyRepeat, VoiceStealing };
+use crate::utils::keyboard_layout::KeyboardLayout;
 use std::sync::Mutex;
 
 use super::engine::{ engine_handle, AudioCommand, AudioEngineHandle };
@@ -38,6 +39,15 @@ pub(super) fn sync_sound_enabled_cache(enabled: bool) {
     }
 }
 
+/// Updates the cached keyboard volume without persisting or notifying the
+/// engine. The volume hotkeys step the volume on the engine thread, and the
+/// next slider read must start from that value, not the one before it.
+pub(super) fn sync_volume_cache(volume: f32) {
+    if let Some(mut guard) = AUDIO_VOLUME.get().and_then(|v| v.lock().ok()) {
+        *guard = volume;
+    }
+}
+
 /// Thin facade over the audio engine thread (see `engine.rs`). Playback,
 /// device switching and soundpack loading all live in the engine's owned
 /// state now - this struct only forwards `AudioCommand`s through the
@@ -159,6 +169,129 @@ impl AudioContext {
         self.send(AudioCommand::SetKeyboardSoundEnabled(enabled));
     }
 
+    /// Sets how far `random_pitch` packs may stray from their recorded
+    /// pitch. Not cached here like the volume: nothing on a hot path reads
+    /// it UI-side, so the config is the only copy the UI needs.
+    pub fn set_random_pitch_range(&self, range: f32) {
+        persist(|config| {
+            config.random_pitch_range = range;
+        });
+        self.send(AudioCommand::SetRandomPitchRange(range));
+    }
+
+    /// Switches between the authors' `recommended_volume` and measured
+    /// levelling. The engine keeps each loaded pack's measurement, so this
+    /// takes effect immediately without reloading either pack.
+    pub fn set_auto_normalize_loudness(&self, enabled: bool) {
+        persist(|config| {
+            config.auto_normalize_loudness = enabled;
+        });
+        self.send(AudioCommand::SetAutoNormalizeLoudness(enabled));
+    }
+
+    pub fn set_stereo_panning(&self, enabled: bool) {
+        persist(|config| {
+            config.stereo_panning = enabled;
+        });
+        self.send(AudioCommand::SetStereoPanning(enabled));
+    }
+
+    /// Picks the physical layout keys are panned by. Only positions change,
+    /// so voices already playing keep theirs.
+    pub fn set_keyboard_layout(&self, layout: KeyboardLayout) {
+        persist(|config| {
+            config.keyboard_layout = layout;
+        });
+        self.send(AudioCommand::SetKeyboardLayout(layout));
+    }
+
+    pub fn set_stereo_width(&self, width: f32) {
+        persist(|config| {
+            config.stereo_width = width;
+        });
+        self.send(AudioCommand::SetStereoWidth(width));
+    }
+
+    /// Replaces the whole effects chain; every slider on Customize sends
+    /// all of it, so the engine never sees half an update.
+    pub fn set_effects(&self, effects: EffectsSettings) {
+        persist(|config| {
+            config.effects = effects;
+        });
+        self.send(AudioCommand::SetEffects(effects));
+    }
+
+    /// Sidechain ducking between the keys and the ambiance.
+    pub fn set_ducking(&self, ducking: Ducking) {
+        persist(|config| {
+            config.ambiance_ducking = ducking;
+        });
+        self.send(AudioCommand::SetDucking(ducking));
+    }
+
+    pub fn set_voice_stealing(&self, policy: VoiceStealing) {
+        persist(|config| {
+            config.voice_stealing = policy;
+        });
+        self.send(AudioCommand::SetVoiceStealing(policy));
+    }
+
+    pub fn set_choke_same_key(&self, enabled: bool) {
+        persist(|config| {
+            config.choke_same_key = enabled;
+        });
+        self.send(AudioCommand::SetChokeSameKey(enabled));
+    }
+
+    pub fn set_choke_groups(&self, groups: Vec<Vec<String>>) {
+        persist(|config| {
+            config.choke_groups = groups.clone();
+        });
+        self.send(AudioCommand::SetChokeGroups(groups));
+    }
+
+    pub fn set_key_repeat(&self, policy: KeyRepeat) {
+        persist(|config| {
+            config.key_repeat = policy;
+        });
+        self.send(AudioCommand::SetKeyRepeat(policy));
+    }
+
+    pub fn set_repeat_gain(&self, gain: f32) {
+        persist(|config| {
+            config.repeat_gain = gain;
+        });
+        self.send(AudioCommand::SetRepeatGain(gain));
+    }
+
+    pub fn set_key_fallback(&self, fallback: KeyFallback) {
+        persist(|config| {
+            config.key_fallback = fallback.clone();
+        });
+        self.send(AudioCommand::SetKeyFallback(fallback));
+    }
+
+    pub fn set_key_override(&self, soundpack_id: &str, code: &str, key_override: KeyOverride) {
+        persist(|config| {
+            config.set_key_override(soundpack_id, code, key_override);
+        });
+        self.send_key_overrides();
+    }
+
+    pub fn reset_key_overrides(&self, soundpack_id: &str) {
+        persist(|config| {
+            config.key_overrides.remove(soundpack_id);
+        });
+        self.send_key_overrides();
+    }
+
+    /// The engine gets every pack's overrides, not just the current pack's,
+    /// so a later pack switch needs no round trip through config.
+    fn send_key_overrides(&self) {
+        let overrides = crate::state::config_writer::current().key_overrides;
+        self.send(AudioCommand::SetKeyOverrides(overrides));
+    }
+
     pub fn set_mouse_sound_enabled(&self, enabled: bool) {
         if let Some(global) = ENABLE_MOUSE_SOUND.get() {
             *global.lock().unwrap() = enabled;
@@ -0,0 +1,210 @@
+//! Speaker layouts and the matrices that move audio between them.
+//!
+//! Two places need this. Packs recorded with more than two channels are
+//! folded down to stereo once, when they are decoded, since the mixer pans
+//! and mixes in stereo. And the mixer's stereo output is spread over however
+//! many channels the device has - rodio's own conversion keeps only the left
+//! channel on a mono device, so a key panned right would vanish there.
+//!
+//! The matrices follow the usual ITU-style folding: a missing centre is
+//! split into the front pair at -3 dB, missing surrounds fold into the
+//! nearest side at -3 dB, and LFE is dropped when the target has none. A
+//! target channel fed by more than unity in total is scaled back to unity,
+//! so a downmix can never push full-scale input past full scale.
+
+use symphonia::core::audio::Channels;
+
+const MINUS_3DB: f32 = std::f32::consts::FRAC_1_SQRT_2;
+
+/// Where a channel plays from.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub(super) enum Speaker {
+    FrontLeft,
+    FrontRight,
+    FrontCentre,
+    Lfe,
+    BackLeft,
+    BackRight,
+    BackCentre,
+    SideLeft,
+    SideRight,
+    /// A position nothing here maps; it is dropped on conversion.
+    Other,
+}
+
+use Speaker::*;
+
+/// The conventional layout for `channels` channels, in the WAVE order cpal
+/// reports devices in. Mono is a lone centre.
+pub(super) fn layout(channels: u16) -> Vec<Speaker> {
+    let known: &[Speaker] = match channels {
+        0 => &[],
+        1 => &[FrontCentre],
+        2 => &[FrontLeft, FrontRight],
+        3 => &[FrontLeft, FrontRight, FrontCentre],
+        4 => &[FrontLeft, FrontRight, BackLeft, BackRight],
+        5 => &[FrontLeft, FrontRight, FrontCentre, BackLeft, BackRight],
+        6 => &[FrontLeft, FrontRight, FrontCentre, Lfe, BackLeft, BackRight],
+        7 => &[FrontLeft, FrontRight, FrontCentre, Lfe, BackCentre, SideLeft, SideRight],
+        _ => &[FrontLeft, FrontRight, FrontCentre, Lfe, BackLeft, BackRight, SideLeft, SideRight],
+    };
+    let mut speakers = known.to_vec();
+    speakers.resize(channels as usize, Other);
+    speakers
+}
+
+/// The layout a decoder reported, in the order symphonia lays its planes
+/// out (ascending bit order). A lone channel is mono whatever its bit says.
+pub(super) fn layout_from_mask(mask: Channels) -> Vec<Speaker> {
+    if mask.count() == 1 {
+        return vec![FrontCentre];
+    }
+    mask.iter()
+        .map(|channel| {
+            match channel {
+                Channels::FRONT_LEFT => FrontLeft,
+                Channels::FRONT_RIGHT => FrontRight,
+                Channels::FRONT_CENTRE => FrontCentre,
+                Channels::LFE1 => Lfe,
+                Channels::REAR_LEFT => BackLeft,
+                Channels::REAR_RIGHT => BackRight,
+                Channels::REAR_CENTRE => BackCentre,
+                Channels::SIDE_LEFT => SideLeft,
+                Channels::SIDE_RIGHT => SideRight,
+                _ => Other,
+            }
+        })
+        .collect()
+}
+
+/// Where `speaker` goes when the target has no such speaker: the first
+/// option whose speakers all exist in the target wins.
+fn fold_options(speaker: Speaker) -> &'static [&'static [(Speaker, f32)]] {
+    match speaker {
+        FrontLeft => &[&[(FrontCentre, MINUS_3DB)]],
+        FrontRight => &[&[(FrontCentre, MINUS_3DB)]],
+        FrontCentre => &[&[(FrontLeft, MINUS_3DB), (FrontRight, MINUS_3DB)]],
+        BackLeft => &[&[(SideLeft, 1.0)], &[(FrontLeft, MINUS_3DB)], &[(FrontCentre, 0.5)]],
+        BackRight => &[&[(SideRight, 1.0)], &[(FrontRight, MINUS_3DB)], &[(FrontCentre, 0.5)]],
+        SideLeft => &[&[(BackLeft, 1.0)], &[(FrontLeft, MINUS_3DB)], &[(FrontCentre, 0.5)]],
+        SideRight => &[&[(BackRight, 1.0)], &[(FrontRight, MINUS_3DB)], &[(FrontCentre, 0.5)]],
+        BackCentre =>
+            &[
+                &[(BackLeft, MINUS_3DB), (BackRight, MINUS_3DB)],
+                &[(SideLeft, MINUS_3DB), (SideRight, MINUS_3DB)],
+                &[(FrontLeft, MINUS_3DB), (FrontRight, MINUS_3DB)],
+                &[(FrontCentre, 0.5)],
+            ],
+        Lfe | Other => &[],
+    }
+}
+
+/// `matrix[out][in]`: how much of input channel `in` feeds output `out`.
+pub(super) fn mix_matrix(from: &[Speaker], to: &[Speaker]) -> Vec<Vec<f32>> {
+    let mut matrix = vec![vec![0.0f32; from.len()]; to.len()];
+    for (input, &speaker) in from.iter().enumerate() {
+        if speaker == Other {
+            continue;
+        }
+        if let Some(output) = to.iter().position(|&s| s == speaker) {
+            matrix[output][input] += 1.0;
+            continue;
+        }
+        let Some(option) = fold_options(speaker)
+            .iter()
+            .find(|option| option.iter().all(|(target, _)| to.contains(target))) else {
+            continue;
+        };
+        for &(target, gain) in option.iter() {
+            let output = to.iter().position(|&s| s == target).expect("checked above");
+            matrix[output][input] += gain;
+        }
+    }
+
+    for row in matrix.iter_mut() {
+        let total: f32 = row.iter().sum();
+        if total > 1.0 {
+            row.iter_mut().for_each(|gain| {
+                *gain /= total;
+            });
+        }
+    }
+    matrix
+}
+
+/// Converts interleaved `samples` from one layout to another.
+pub(super) fn remix(samples: &[f32], from: &[Speaker], to: &[Speaker]) -> Vec<f32> {
+    if from == to || from.is_empty() {
+        return samples.to_vec();
+    }
+    let matrix = mix_matrix(from, to);
+    let mut out = Vec::with_capacity((samples.len() / from.len()) * to.len());
+    for frame in samples.chunks_exact(from.len()) {
+        for row in &matrix {
+            out.push(
+                row
+                    .iter()
+                    .zip(frame)
+                    .map(|(gain, sample)| gain * sample)
+                    .sum()
+            );
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn stereo_reaches_a_mono_device_as_the_average_of_both_sides() {
+        assert_eq!(remix(&[1.0, 0.0, 0.0, 1.0], &layout(2), &layout(1)), vec![0.5, 0.5]);
+    }
+
+    #[test]
+    fn stereo_plays_from_the_front_pair_of_a_surround_device() {
+        for channels in [4, 6, 8] {
+            let out = remix(&[0.25, -0.5], &layout(2), &layout(channels));
+            let mut expected = vec![0.0; channels as usize];
+            expected[0] = 0.25;
+            expected[1] = -0.5;
+            assert_eq!(out, expected, "{} channels", channels);
+        }
+    }
+
+    #[test]
+    fn a_surround_downmix_keeps_centre_and_surrounds_but_not_lfe() {
+        let matrix = mix_matrix(&layout(6), &layout(2));
+        let [left, right] = [&matrix[0], &matrix[1]];
+        assert!(left[2] > 0.0 && right[2] > 0.0, "centre reaches both sides");
+        assert!(left[4] > 0.0 && right[4] == 0.0, "back left stays left");
+        assert_eq!(left[3], 0.0, "LFE is dropped");
+        for row in &matrix {
+            let total: f32 = row.iter().sum();
+            assert!(total <= 1.0 + 1e-6, "full-scale input cannot clip: {}", total);
+        }
+    }
+
+    #[test]
+    fn seven_one_folds_side_and_back_pairs_onto_their_own_sides() {
+        let out = remix(&[0.0, 0.0, 0.0, 0.0, 0.0, 0.0, 1.0, 0.0], &layout(8), &layout(2));
+        assert!(out[0] > 0.0);
+        assert_eq!(out[1], 0.0);
+    }
+
+    #[test]
+    fn mono_is_a_centre_channel_that_splits_evenly_into_stereo() {
+        let out = remix(&[1.0], &layout(1), &layout(2));
+        assert_eq!(out[0], out[1]);
+        let out = remix(&[1.0], &layout(1), &layout(6));
+        assert_eq!(out, vec![0.0, 0.0, 1.0, 0.0, 0.0, 0.0], "a 5.1 device has a centre of its own");
+    }
+
+    #[test]
+    fn decoder_masks_map_to_their_speakers() {
+        let mask = Channels::FRONT_LEFT | Channels::FRONT_RIGHT | Channels::LFE1;
+        assert_eq!(layout_from_mask(mask), vec![FrontLeft, FrontRight, Lfe]);
+        assert_eq!(layout_from_mask(Channels::FRONT_LEFT), vec![FrontCentre]);
+    }
+}
@@ -0,0 +1,166 @@
+//! Sidechain ducking between the keystroke buses and the ambiance bus.
+//!
+//! With `DuckingMode::Ambiance` every keyboard voice the mixer starts is a
+//! cue, so a burst of typing turns the ambiance down and it swells back once
+//! the typing stops. `DuckingMode::Keys` works the other way round: the cue
+//! is the ambiance bus itself playing above `LOUD_AMBIANCE`, such as a clap
+//! of thunder, and the keyboard and mouse give way to it.
+//!
+//! A cue holds the duck for `HOLD_MS`, long enough to bridge the gaps
+//! between keystrokes in a burst and the zero crossings of a loud wave.
+//! While held, an envelope rises toward 1 over `Ducking::attack_ms`; after,
+//! it falls back over `release_ms`. The ducked bus plays at
+//! `1 - envelope * depth`, where full depth is a cut of `amount_db`.
+
+use super::mixer::Bus;
+use crate::state::config::{ Ducking, DuckingMode, MAX_DUCK_DB };
+
+/// Ambiance peak level, after its bus gain, that counts as loud.
+const LOUD_AMBIANCE: f32 = 0.3;
+
+const HOLD_MS: f64 = 120.0;
+
+/// Below this the envelope snaps to zero, so an idle ducker leaves its bus
+/// at exactly its own gain.
+const ENVELOPE_SNAP: f32 = 1e-5;
+
+pub(super) struct Ducker {
+    settings: Ducking,
+    rate: u32,
+    /// Fractions of the distance to the target the envelope moves per frame.
+    attack: f32,
+    release: f32,
+    /// Share of the bus's level taken away at full envelope.
+    depth: f32,
+    hold_frames: u32,
+    /// Frames the current cue still holds the duck.
+    held: u32,
+    envelope: f32,
+}
+
+fn coefficient(ms: f32, rate: u32) -> f32 {
+    let frames = ((ms.max(0.0) as f64) / 1000.0) * (rate as f64);
+    (1.0 - (-1.0 / frames.max(1.0)).exp()) as f32
+}
+
+impl Ducker {
+    pub(super) fn new(rate: u32) -> Self {
+        let rate = rate.max(1);
+        let mut ducker = Self {
+            settings: Ducking::default(),
+            rate,
+            attack: 1.0,
+            release: 1.0,
+            depth: 0.0,
+            hold_frames: ((HOLD_MS / 1000.0) * (rate as f64)) as u32,
+            held: 0,
+            envelope: 0.0,
+        };
+        ducker.set(Ducking::default());
+        ducker
+    }
+
+    pub(super) fn set(&mut self, settings: Ducking) {
+        self.settings = settings;
+        self.attack = coefficient(settings.attack_ms, self.rate);
+        self.release = coefficient(settings.release_ms, self.rate);
+        let cut_db = settings.amount_db.clamp(0.0, MAX_DUCK_DB);
+        self.depth = 1.0 - 10f32.powf(-cut_db / 20.0);
+        if settings.mode == DuckingMode::Off {
+            self.held = 0;
+            self.envelope = 0.0;
+        }
+    }
+
+    /// Whether `bus` is the one being ducked.
+    pub(super) fn ducks(&self, bus: Bus) -> bool {
+        match self.settings.mode {
+            DuckingMode::Off => false,
+            DuckingMode::Ambiance => bus == Bus::Ambiance,
+            DuckingMode::Keys => matches!(bus, Bus::Keyboard | Bus::Mouse),
+        }
+    }
+
+    /// A keyboard voice started.
+    pub(super) fn key_pressed(&mut self) {
+        if self.settings.mode == DuckingMode::Ambiance {
+            self.held = self.hold_frames;
+        }
+    }
+
+    /// Advances one frame, given the ambiance bus's peak in the previous
+    /// one, and returns the gain for the ducked bus.
+    pub(super) fn process(&mut self, ambiance_level: f32) -> f32 {
+        if self.settings.mode == DuckingMode::Keys && ambiance_level > LOUD_AMBIANCE {
+            self.held = self.hold_frames;
+        }
+
+        if self.held > 0 {
+            self.held -= 1;
+            self.envelope += (1.0 - self.envelope) * self.attack;
+        } else if self.envelope > 0.0 {
+            self.envelope -= self.envelope * self.release;
+            if self.envelope < ENVELOPE_SNAP {
+                self.envelope = 0.0;
+            }
+        }
+        1.0 - self.envelope * self.depth
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const RATE: u32 = 1000;
+
+    fn ducker(mode: DuckingMode) -> Ducker {
+        let mut ducker = Ducker::new(RATE);
+        ducker.set(Ducking { mode, amount_db: 20.0, attack_ms: 10.0, release_ms: 100.0 });
+        ducker
+    }
+
+    #[test]
+    fn typing_ducks_the_ambiance_by_the_amount_then_lets_it_back() {
+        let mut ducker = ducker(DuckingMode::Ambiance);
+        assert!(ducker.ducks(Bus::Ambiance));
+        assert!(!ducker.ducks(Bus::Keyboard));
+
+        ducker.key_pressed();
+        let gains: Vec<f32> = (0..100).map(|_| ducker.process(0.0)).collect();
+        assert!(gains[0] > 0.8, "the attack is a ramp, not a jump");
+        assert!((gains[99] - 0.1).abs() < 1e-3, "20 dB down once pulled in, got {}", gains[99]);
+
+        let released = (0..2000).map(|_| ducker.process(0.0)).last().unwrap();
+        assert_eq!(released, 1.0, "exactly back to unity");
+    }
+
+    #[test]
+    fn loud_ambiance_ducks_the_keys_only_while_it_is_loud() {
+        let mut ducker = ducker(DuckingMode::Keys);
+        assert!(ducker.ducks(Bus::Keyboard) && ducker.ducks(Bus::Mouse));
+        assert!(!ducker.ducks(Bus::Ambiance));
+
+        ducker.key_pressed();
+        assert_eq!(ducker.process(0.1), 1.0, "keystrokes and quiet ambiance are no cue");
+
+        let loud = (0..100).map(|_| ducker.process(0.8)).last().unwrap();
+        assert!(loud < 0.2);
+        let after = (0..2000).map(|_| ducker.process(0.1)).last().unwrap();
+        assert_eq!(after, 1.0);
+    }
+
+    #[test]
+    fn turning_ducking_off_releases_at_once() {
+        let mut ducker = ducker(DuckingMode::Ambiance);
+        ducker.key_pressed();
+        (0..50).for_each(|_| {
+            ducker.process(0.0);
+        });
+
+        ducker.set(Ducking { mode: DuckingMode::Off, ..Ducking::default() });
+        ducker.key_pressed();
+        assert!(!ducker.ducks(Bus::Ambiance));
+        assert_eq!(ducker.process(1.0), 1.0);
+    }
+}
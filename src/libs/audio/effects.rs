@@ -0,0 +1,439 @@
+//! The optional effects chain on the mixer's stereo output: a three-band EQ,
+//! a low-pass "dampening" filter and a small room reverb, in that order.
+//!
+//! It runs inside `Mixer` on the audio thread, once per output frame, after
+//! both buses are summed - so it shapes keyboard and mouse alike, live and in
+//! `offline_render`. Everything it needs is allocated in `EffectChain::new`;
+//! `set` only recomputes coefficients, so moving a slider never allocates on
+//! the audio thread.
+//!
+//! The filters are the usual RBJ cookbook biquads. The reverb is a small
+//! Freeverb: four damped combs into two allpasses per side, with the right
+//! side's delays offset a little for width.
+
+use crate::state::config::{ EffectsSettings, MAX_EQ_DB, MAX_LOWPASS_HZ, MIN_LOWPASS_HZ };
+
+const LOW_SHELF_HZ: f64 = 200.0;
+const MID_PEAK_HZ: f64 = 1_000.0;
+const HIGH_SHELF_HZ: f64 = 4_000.0;
+const BUTTERWORTH_Q: f64 = std::f64::consts::FRAC_1_SQRT_2;
+const MID_PEAK_Q: f64 = 0.7;
+
+/// Freeverb's delay lengths, in samples at 44.1 kHz.
+const COMB_TUNING: [usize; 4] = [1116, 1188, 1277, 1356];
+const ALLPASS_TUNING: [usize; 2] = [556, 441];
+const STEREO_SPREAD: usize = 23;
+const REVERB_INPUT_GAIN: f32 = 0.015;
+const REVERB_WET_SCALE: f32 = 3.0;
+const REVERB_DAMPING: f32 = 0.3;
+
+/// Biquad coefficients, normalised so `a0` is 1.
+#[derive(Clone, Copy)]
+struct Coefficients {
+    b0: f32,
+    b1: f32,
+    b2: f32,
+    a1: f32,
+    a2: f32,
+}
+
+impl Coefficients {
+    fn from_raw([b0, b1, b2, a0, a1, a2]: [f64; 6]) -> Self {
+        Self {
+            b0: (b0 / a0) as f32,
+            b1: (b1 / a0) as f32,
+            b2: (b2 / a0) as f32,
+            a1: (a1 / a0) as f32,
+            a2: (a2 / a0) as f32,
+        }
+    }
+
+    /// `(cos w0, alpha)` for a filter at `hz`, kept below Nyquist so a low
+    /// device rate cannot make the filter unstable.
+    fn angle(hz: f64, rate: f64, q: f64) -> (f64, f64) {
+        let w0 = (2.0 * std::f64::consts::PI * hz.min(rate * 0.45)) / rate;
+        (w0.cos(), w0.sin() / (2.0 * q))
+    }
+
+    fn low_shelf(hz: f64, db: f64, rate: f64) -> Self {
+        let a = (10f64).powf(db / 40.0);
+        let (cos, alpha) = Self::angle(hz, rate, BUTTERWORTH_Q);
+        let k = 2.0 * a.sqrt() * alpha;
+        Self::from_raw([
+            a * (a + 1.0 - (a - 1.0) * cos + k),
+            2.0 * a * (a - 1.0 - (a + 1.0) * cos),
+            a * (a + 1.0 - (a - 1.0) * cos - k),
+            a + 1.0 + (a - 1.0) * cos + k,
+            -2.0 * (a - 1.0 + (a + 1.0) * cos),
+            a + 1.0 + (a - 1.0) * cos - k,
+        ])
+    }
+
+    fn high_shelf(hz: f64, db: f64, rate: f64) -> Self {
+        let a = (10f64).powf(db / 40.0);
+        let (cos, alpha) = Self::angle(hz, rate, BUTTERWORTH_Q);
+        let k = 2.0 * a.sqrt() * alpha;
+        Self::from_raw([
+            a * (a + 1.0 + (a - 1.0) * cos + k),
+            -2.0 * a * (a - 1.0 + (a + 1.0) * cos),
+            a * (a + 1.0 + (a - 1.0) * cos - k),
+            a + 1.0 - (a - 1.0) * cos + k,
+            2.0 * (a - 1.0 - (a + 1.0) * cos),
+            a + 1.0 - (a - 1.0) * cos - k,
+        ])
+    }
+
+    fn peak(hz: f64, db: f64, q: f64, rate: f64) -> Self {
+        let a = (10f64).powf(db / 40.0);
+        let (cos, alpha) = Self::angle(hz, rate, q);
+        Self::from_raw([
+            1.0 + alpha * a,
+            -2.0 * cos,
+            1.0 - alpha * a,
+            1.0 + alpha / a,
+            -2.0 * cos,
+            1.0 - alpha / a,
+        ])
+    }
+
+    fn low_pass(hz: f64, rate: f64) -> Self {
+        let (cos, alpha) = Self::angle(hz, rate, BUTTERWORTH_Q);
+        Self::from_raw([
+            (1.0 - cos) / 2.0,
+            1.0 - cos,
+            (1.0 - cos) / 2.0,
+            1.0 + alpha,
+            -2.0 * cos,
+            1.0 - alpha,
+        ])
+    }
+}
+
+/// One biquad on both channels (transposed direct form II).
+struct Biquad {
+    coefficients: Coefficients,
+    state: [[f32; 2]; 2],
+}
+
+impl Biquad {
+    fn new(coefficients: Coefficients) -> Self {
+        Self { coefficients, state: [[0.0; 2]; 2] }
+    }
+
+    fn process(&mut self, frame: &mut [f32; 2]) {
+        let Coefficients { b0, b1, b2, a1, a2 } = self.coefficients;
+        for (sample, [z1, z2]) in frame.iter_mut().zip(self.state.iter_mut()) {
+            let x = *sample;
+            let y = b0 * x + *z1;
+            *z1 = b1 * x - a1 * y + *z2;
+            *z2 = b2 * x - a2 * y;
+            *sample = y;
+        }
+    }
+
+    fn reset(&mut self) {
+        self.state = [[0.0; 2]; 2];
+    }
+}
+
+/// Keeps a decaying feedback loop out of denormals, which are slow enough
+/// on some CPUs to starve the audio thread.
+fn flush_denormal(value: f32) -> f32 {
+    if value.abs() < 1e-20 { 0.0 } else { value }
+}
+
+struct Comb {
+    buffer: Vec<f32>,
+    index: usize,
+    filter_store: f32,
+}
+
+impl Comb {
+    fn new(length: usize) -> Self {
+        Self { buffer: vec![0.0; length.max(1)], index: 0, filter_store: 0.0 }
+    }
+
+    fn process(&mut self, input: f32, feedback: f32) -> f32 {
+        let output = self.buffer[self.index];
+        self.filter_store = flush_denormal(output * (1.0 - REVERB_DAMPING) + self.filter_store * REVERB_DAMPING);
+        self.buffer[self.index] = input + self.filter_store * feedback;
+        self.index = (self.index + 1) % self.buffer.len();
+        output
+    }
+
+    fn clear(&mut self) {
+        self.buffer.fill(0.0);
+        self.filter_store = 0.0;
+    }
+}
+
+struct Allpass {
+    buffer: Vec<f32>,
+    index: usize,
+}
+
+impl Allpass {
+    fn new(length: usize) -> Self {
+        Self { buffer: vec![0.0; length.max(1)], index: 0 }
+    }
+
+    fn process(&mut self, input: f32) -> f32 {
+        let buffered = self.buffer[self.index];
+        self.buffer[self.index] = flush_denormal(input + buffered * 0.5);
+        self.index = (self.index + 1) % self.buffer.len();
+        buffered - input
+    }
+
+    fn clear(&mut self) {
+        self.buffer.fill(0.0);
+    }
+}
+
+struct Reverb {
+    combs: [Vec<Comb>; 2],
+    allpasses: [Vec<Allpass>; 2],
+    feedback: f32,
+}
+
+impl Reverb {
+    fn new(rate: u32) -> Self {
+        let scale = |samples: usize| ((samples as f64) * (rate as f64) / 44_100.0) as usize;
+        let side = |spread: usize| {
+            (
+                COMB_TUNING.iter()
+                    .map(|&length| Comb::new(scale(length + spread)))
+                    .collect::<Vec<_>>(),
+                ALLPASS_TUNING.iter()
+                    .map(|&length| Allpass::new(scale(length + spread)))
+                    .collect::<Vec<_>>(),
+            )
+        };
+        let (left_combs, left_allpasses) = side(0);
+        let (right_combs, right_allpasses) = side(STEREO_SPREAD);
+        Self {
+            combs: [left_combs, right_combs],
+            allpasses: [left_allpasses, right_allpasses],
+            feedback: 0.0,
+        }
+    }
+
+    fn set_size(&mut self, size: f32) {
+        let size = if size.is_finite() { size.clamp(0.0, 1.0) } else { 0.5 };
+        self.feedback = 0.7 + 0.28 * size;
+    }
+
+    /// The wet signal for one frame of dry input.
+    fn process(&mut self, frame: &[f32; 2]) -> [f32; 2] {
+        let input = (frame[0] + frame[1]) * REVERB_INPUT_GAIN;
+        let mut wet = [0.0; 2];
+        for (side, out) in wet.iter_mut().enumerate() {
+            let mut sum: f32 = self.combs[side]
+                .iter_mut()
+                .map(|comb| comb.process(input, self.feedback))
+                .sum();
+            for allpass in self.allpasses[side].iter_mut() {
+                sum = allpass.process(sum);
+            }
+            *out = sum * REVERB_WET_SCALE;
+        }
+        wet
+    }
+
+    fn clear(&mut self) {
+        self.combs.iter_mut().flatten().for_each(Comb::clear);
+        self.allpasses.iter_mut().flatten().for_each(Allpass::clear);
+    }
+}
+
+/// The chain itself, owned by the mixer.
+pub(super) struct EffectChain {
+    rate: f64,
+    enabled: bool,
+    /// Only the bands whose gain is not 0 dB run.
+    eq: [Option<Biquad>; 3],
+    lowpass: Option<Biquad>,
+    reverb: Reverb,
+    reverb_mix: f32,
+}
+
+impl EffectChain {
+    /// A disabled chain for output at `rate`. Allocates the reverb's delay
+    /// lines, so it belongs in `Mixer::new`, never on the audio thread.
+    pub(super) fn new(rate: u32) -> Self {
+        Self {
+            rate: rate.max(1) as f64,
+            enabled: false,
+            eq: [None, None, None],
+            lowpass: None,
+            reverb: Reverb::new(rate.max(1)),
+            reverb_mix: 0.0,
+        }
+    }
+
+    pub(super) fn set(&mut self, settings: EffectsSettings) {
+        let rate = self.rate;
+        let db = |gain: f32| if gain.is_finite() { gain.clamp(-MAX_EQ_DB, MAX_EQ_DB) as f64 } else { 0.0 };
+        let band = |gain: f64, design: &dyn Fn(f64) -> Coefficients, current: &mut Option<Biquad>| {
+            if gain == 0.0 {
+                *current = None;
+            } else if let Some(biquad) = current {
+                biquad.coefficients = design(gain);
+            } else {
+                *current = Some(Biquad::new(design(gain)));
+            }
+        };
+        let [low, mid, high] = &mut self.eq;
+        band(db(settings.eq_low_db), &(|g| Coefficients::low_shelf(LOW_SHELF_HZ, g, rate)), low);
+        band(db(settings.eq_mid_db), &(|g| Coefficients::peak(MID_PEAK_HZ, g, MID_PEAK_Q, rate)), mid);
+        band(db(settings.eq_high_db), &(|g| Coefficients::high_shelf(HIGH_SHELF_HZ, g, rate)), high);
+
+        let cutoff = if settings.lowpass_hz.is_finite() { settings.lowpass_hz } else { MAX_LOWPASS_HZ };
+        let cutoff = cutoff.clamp(MIN_LOWPASS_HZ, MAX_LOWPASS_HZ);
+        band(
+            if cutoff >= MAX_LOWPASS_HZ { 0.0 } else { cutoff as f64 },
+            &(|hz| Coefficients::low_pass(hz, rate)),
+            &mut self.lowpass
+        );
+
+        let reverb_mix = if settings.reverb_mix.is_finite() { settings.reverb_mix.clamp(0.0, 1.0) } else { 0.0 };
+        let reverb_was_on = self.enabled && self.reverb_mix > 0.0;
+        if reverb_was_on && !(settings.enabled && reverb_mix > 0.0) {
+            // Otherwise the old tail would play out when it is next turned on.
+            self.reverb.clear();
+        }
+        self.reverb_mix = reverb_mix;
+        self.reverb.set_size(settings.reverb_size);
+
+        if self.enabled && !settings.enabled {
+            self.eq.iter_mut().flatten().for_each(Biquad::reset);
+            self.lowpass.iter_mut().for_each(Biquad::reset);
+        }
+        self.enabled = settings.enabled;
+    }
+
+    /// Runs one stereo frame through the chain in place.
+    pub(super) fn process(&mut self, frame: &mut [f32; 2]) {
+        if !self.enabled {
+            return;
+        }
+        for biquad in self.eq.iter_mut().flatten() {
+            biquad.process(frame);
+        }
+        if let Some(lowpass) = &mut self.lowpass {
+            lowpass.process(frame);
+        }
+        if self.reverb_mix > 0.0 {
+            let wet = self.reverb.process(frame);
+            frame[0] += wet[0] * self.reverb_mix;
+            frame[1] += wet[1] * self.reverb_mix;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const RATE: u32 = 48_000;
+
+    fn chain(settings: EffectsSettings) -> EffectChain {
+        let mut chain = EffectChain::new(RATE);
+        chain.set(EffectsSettings { enabled: true, ..settings });
+        chain
+    }
+
+    /// RMS of a `hz` sine at amplitude 0.5 after the chain, measured over
+    /// the second half of 0.2 s so the filters have settled.
+    fn sine_rms(chain: &mut EffectChain, hz: f32) -> f32 {
+        let frames = (RATE / 5) as usize;
+        let mut sum = 0.0;
+        for n in 0..frames {
+            let x = 0.5 * ((2.0 * std::f32::consts::PI * hz * (n as f32)) / (RATE as f32)).sin();
+            let mut frame = [x, x];
+            chain.process(&mut frame);
+            if n >= frames / 2 {
+                sum += frame[0] * frame[0];
+            }
+        }
+        (sum / ((frames - frames / 2) as f32)).sqrt()
+    }
+
+    const DRY_RMS: f32 = 0.5 * std::f32::consts::FRAC_1_SQRT_2;
+
+    #[test]
+    fn a_disabled_or_neutral_chain_leaves_the_mix_untouched() {
+        let loud = EffectsSettings { eq_low_db: 12.0, reverb_mix: 1.0, ..Default::default() };
+        for mut chain in [chain(EffectsSettings::default()), EffectChain::new(RATE), {
+            let mut off = chain(loud);
+            off.set(EffectsSettings { enabled: false, ..loud });
+            off
+        }] {
+            let mut frame = [0.25, -0.5];
+            chain.process(&mut frame);
+            assert_eq!(frame, [0.25, -0.5]);
+        }
+    }
+
+    #[test]
+    fn the_low_pass_takes_the_edge_off_without_touching_the_body() {
+        let mut filtered = chain(EffectsSettings { lowpass_hz: 1_000.0, ..Default::default() });
+        assert!(sine_rms(&mut filtered, 8_000.0) < DRY_RMS * 0.05, "8 kHz is cut by well over 20 dB");
+        let mut filtered = chain(EffectsSettings { lowpass_hz: 1_000.0, ..Default::default() });
+        assert!((sine_rms(&mut filtered, 100.0) - DRY_RMS).abs() < DRY_RMS * 0.02);
+    }
+
+    #[test]
+    fn each_eq_band_moves_only_its_own_range() {
+        // +12 dB is close to four times the amplitude.
+        let mut bass = chain(EffectsSettings { eq_low_db: 12.0, ..Default::default() });
+        let boosted = sine_rms(&mut bass, 40.0) / DRY_RMS;
+        assert!((3.5..4.2).contains(&boosted), "low shelf gain {}", boosted);
+        let mut bass = chain(EffectsSettings { eq_low_db: 12.0, ..Default::default() });
+        assert!((sine_rms(&mut bass, 12_000.0) / DRY_RMS - 1.0).abs() < 0.05, "treble untouched");
+
+        let mut treble = chain(EffectsSettings { eq_high_db: -12.0, ..Default::default() });
+        let cut = sine_rms(&mut treble, 15_000.0) / DRY_RMS;
+        assert!((0.2..0.3).contains(&cut), "high shelf gain {}", cut);
+
+        let mut mid = chain(EffectsSettings { eq_mid_db: 6.0, ..Default::default() });
+        let peak = sine_rms(&mut mid, 1_000.0) / DRY_RMS;
+        assert!((1.9..2.1).contains(&peak), "mid peak gain {}", peak);
+    }
+
+    #[test]
+    fn reverb_rings_on_after_the_sound_and_dies_away() {
+        let mut room = chain(EffectsSettings { reverb_mix: 1.0, reverb_size: 0.5, ..Default::default() });
+        let mut frame = [1.0, 1.0];
+        room.process(&mut frame);
+
+        let mut tail = Vec::new();
+        for _ in 0..RATE * 4 {
+            let mut frame = [0.0, 0.0];
+            room.process(&mut frame);
+            tail.push(frame);
+        }
+        let energy = |frames: &[[f32; 2]]| frames.iter().map(|[l, r]| l * l + r * r).sum::<f32>();
+        let early = energy(&tail[..(RATE as usize) / 10]);
+        let late = energy(&tail[(RATE as usize) * 3..]);
+        assert!(early > 0.0, "the impulse leaves a tail");
+        assert!(late < early * 1e-3, "the tail decays: {} -> {}", early, late);
+        assert!(tail.iter().flatten().all(|s| s.is_finite()));
+        assert!(tail.iter().any(|[l, r]| l != r), "left and right tails differ");
+    }
+
+    #[test]
+    fn turning_reverb_off_drops_its_tail() {
+        let settings = EffectsSettings { reverb_mix: 1.0, ..Default::default() };
+        let mut room = chain(settings);
+        let mut frame = [1.0, 1.0];
+        room.process(&mut frame);
+        room.set(EffectsSettings { reverb_mix: 0.0, ..settings });
+        room.set(settings);
+
+        for _ in 0..RATE / 10 {
+            let mut frame = [0.0, 0.0];
+            room.process(&mut frame);
+            assert_eq!(frame, [0.0, 0.0]);
+        }
+    }
+}
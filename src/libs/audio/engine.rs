@@ -1,26 +1,96 @@
-use crossbeam_channel::{ unbounded, Receiver, Sender };
-use rodio::buffer::SamplesBuffer;
-use rodio::{ OutputStream, OutputStreamHandle, Sink };
+use crossbeam_channel::{ bounded, unbounded, Receiver, Sender };
+use rand::rngs::StdRng;
+use rand::{ Rng, SeedableRng };
 use std::collections::HashMap;
 use std::sync::{ Arc, OnceLock };
-use std::time::Duration;
 
-use crate::libs::device_manager::DeviceManager;
-
-const FADE_IN_MS: f32 = 2.0;
-const FADE_OUT_MS: f32 = 5.0;
-const EVICT_RAMP_MS: u64 = 10;
-const MAX_VOICES: usize = 32;
+use crate::libs::hotkeys::HotkeyAction;
+use crate::state::config::{
+    AppConfig,
+    Ducking,
+    EffectsSettings,
+    KeyFallback,
+    KeyOverride,
+    KeyOverrides,
+    KeyRepeat,
+    VoiceStealing,
+    MAX_KEY_GAIN,
+};
+use crate::state::soundpack::{ SoundpackOptions, VariationMode };
+use crate::utils::keyboard_layout::KeyboardLayout;
+use super::loudness::{ self, Loudness };
+use super::mixer::{ key_id, Bus, Choke, Mixer, MixerHandle, Stream, StreamSource, Voice, MIX_CHANNELS };
+use super::output::{ AudioOutput, RodioOutput };
+use super::pack_loader::{ LoadedPack, PackKind, PackLoader };
+
+/// Mixer rate when the device's own rate could not be probed. rodio converts
+/// from whatever the mixer produces, so this only has to be plausible.
+const FALLBACK_MIX_RATE: u32 = 44_100;
+
+/// Upper bound for `AppConfig::random_pitch_range`. Past roughly a quarter
+/// the variation stops sounding like the same switch and starts sounding like
+/// a different pack, so a hand-edited config is clamped to this.
+pub const MAX_RANDOM_PITCH_RANGE: f32 = 0.25;
+
+/// How far one press of the volume hotkeys moves the keyboard volume.
+const VOLUME_HOTKEY_STEP: f32 = 0.1;
 
 /// (samples, channels, sample_rate) for a decoded/resampled audio buffer.
-type DecodedAudio = (Arc<Vec<f32>>, u16, u32);
+pub(super) type DecodedAudio = (Arc<Vec<f32>>, u16, u32);
+
+/// A key's timing sets in ms: its `timing`, then each of its `variations`.
+/// A set is `[down]` or `[down, up]`.
+pub(super) type KeyTimings = Vec<Vec<[f32; 2]>>;
+
+/// Decoded audio for one loaded pack, keyed by the `audio_file` it came from.
+/// A "single" pack has one buffer that every key slices into; a "multi" pack
+/// has one per distinct file, decoded once and shared by every key naming it.
+#[derive(Default)]
+pub(super) struct PackBuffers {
+    /// Resampled to the device rate - what voices are cut from.
+    pub(super) buffers: HashMap<String, DecodedAudio>,
+    /// The same buffers at each file's native rate, so a device switch can
+    /// re-resample without re-reading from disk.
+    pub(super) originals: HashMap<String, DecodedAudio>,
+    /// Keys that play from their own file ("multi" packs).
+    pub(super) key_files: HashMap<String, String>,
+    /// The pack's top-level `audio_file`, for every key not in `key_files`.
+    pub(super) default_file: Option<String>,
+}
+
+impl PackBuffers {
+    /// The buffer `code`'s timings index into.
+    pub(super) fn buffer_for(&self, code: &str) -> Option<&DecodedAudio> {
+        self.key_files
+            .get(code)
+            .or(self.default_file.as_ref())
+            .and_then(|file| self.buffers.get(file))
+    }
+
+    pub(super) fn resample_to(&mut self, target_rate: Option<u32>) {
+        self.buffers = self.originals
+            .iter()
+            .map(|(file, (samples, channels, rate))| {
+                (file.clone(), resample_if_needed(samples, *channels, *rate, target_rate))
+            })
+            .collect();
+    }
+}
+
+/// A stream the engine keeps playing, remembered so a device switch can
+/// reopen it on the new mixer.
+struct StreamEntry {
+    bus: Bus,
+    path: String,
+    gain: f32,
+}
 
 /// Commands the engine thread accepts. Every audio-affecting operation goes
 /// through this channel so the thread that owns `OutputStream` never has to
 /// share it across threads (rodio's `OutputStream` is not `Send`).
 ///
 /// Keyboard/mouse key events are NOT a variant here: the engine reads raw
-/// `"KeyA"` / `"UP:KeyA"` strings directly off the same crossbeam receivers
+/// `"KeyA"` / `"UP:KeyA"` / `"REPEAT:KeyA"` strings directly off the same crossbeam receivers
 /// the input listeners (rdev/device_query/evdev) send to, via `select!` in
 /// `run_engine` - see `spawn_engine`. Routing them through `AudioCommand`
 /// would add an extra hop with no benefit.
@@ -30,6 +100,45 @@ pub enum AudioCommand {
     SetSoundEnabled(bool),
     SetKeyboardSoundEnabled(bool),
     SetMouseSoundEnabled(bool),
+    SetRandomPitchRange(f32),
+    SetAutoNormalizeLoudness(bool),
+    SetStereoPanning(bool),
+    SetKeyboardLayout(KeyboardLayout),
+    SetStereoWidth(f32),
+    /// Every pack's per-key overrides (`AppConfig::key_overrides`).
+    SetKeyOverrides(HashMap<String, KeyOverrides>),
+    SetEffects(EffectsSettings),
+    SetVoiceStealing(VoiceStealing),
+    SetChokeSameKey(bool),
+    /// The user's choke groups (`AppConfig::choke_groups`).
+    SetChokeGroups(Vec<Vec<String>>),
+    SetKeyRepeat(KeyRepeat),
+    SetRepeatGain(f32),
+    SetKeyFallback(KeyFallback),
+    /// Keyboard pack ids in selector order (`SoundpackCache::keyboard_pack_order`),
+    /// which the next/previous-pack hotkeys step through. Sent whenever the
+    /// soundpacks are scanned, so the engine never reads the cache itself.
+    SetKeyboardPackOrder(Vec<String>),
+    /// The user's level for `bus`. On the keystroke buses this is the same
+    /// as `SetVolume` / `SetMouseVolume`: the pack gain still applies on top.
+    SetBusGain(Bus, f32),
+    SetBusMuted(Bus, bool),
+    /// Holds every stream on `bus` where it is, e.g. ambiance on pause.
+    SetBusPaused(Bus, bool),
+    SetDucking(Ducking),
+    /// Loops the audio file at `path` on `bus`, replacing whatever was
+    /// playing as `id`. Ambiance and music both play through this.
+    PlayStream {
+        id: String,
+        bus: Bus,
+        path: String,
+        gain: f32,
+    },
+    StopStream(String),
+    SetStreamGain {
+        id: String,
+        gain: f32,
+    },
     LoadKeyboardPack {
         soundpack_id: String,
         update_cache_on_error: bool,
@@ -51,6 +160,11 @@ pub enum UiEvent {
         is_keyboard: bool,
         result: Result<String, String>,
     },
+    /// A hotkey whose action lives on the UI side (ambiance, the window).
+    Hotkey(HotkeyAction),
+    /// The output limiter started (`true`) or stopped holding the mix down:
+    /// without it, what is playing would clip.
+    Clipping(bool),
 }
 
 /// Cheap, `Clone + Send` handle to the audio engine thread. UI code and input
@@ -77,7 +191,7 @@ static UI_EVENT_RX: OnceLock<Receiver<UiEvent>> = OnceLock::new();
 /// plain OS thread rather than inside the webview/Dioxus runtime.
 ///
 /// `keyboard_rx`/`mouse_rx`/`hotkey_rx` are the sole consumers of the input
-/// channels - the engine reads raw `"KeyA"` / `"UP:KeyA"` strings directly
+/// channels - the engine reads raw `"KeyA"` / `"UP:KeyA"` / `"REPEAT:KeyA"` strings directly
 /// from the input listeners (rdev, device_query, evdev, raw-input worker)
 /// via `select!`, the same wire format those listeners already produce,
 /// instead of the UI polling and forwarding them.
@@ -116,137 +230,380 @@ pub fn ui_event_receiver() -> &'static Receiver<UiEvent> {
 /// Arc<Mutex<...>> path used pre-Phase-3). Fields are `pub(super)` so
 /// `soundpack_loader.rs` (a sibling module) can update them after decoding.
 pub(super) struct EngineState {
-    stream: OutputStream,
-    pub(super) stream_handle: OutputStreamHandle,
-    device_manager: DeviceManager,
-    current_device_id: Option<String>,
+    /// The device side - a rodio stream in the app, a capture buffer in
+    /// tests. Holds the only `Mixer`; the engine talks to it via `mixer`.
+    output: Box<dyn AudioOutput>,
     pub(super) device_rate: Option<u32>,
 
-    pub(super) keyboard_samples: Option<DecodedAudio>,
-    pub(super) keyboard_samples_original: Option<DecodedAudio>,
-    pub(super) mouse_samples: Option<DecodedAudio>,
-    pub(super) mouse_samples_original: Option<DecodedAudio>,
-    pub(super) key_map: HashMap<String, Vec<[f32; 2]>>,
-    pub(super) mouse_map: HashMap<String, Vec<[f32; 2]>>,
+    pub(super) keyboard_audio: PackBuffers,
+    pub(super) mouse_audio: PackBuffers,
+    pub(super) key_map: HashMap<String, KeyTimings>,
+    pub(super) mouse_map: HashMap<String, KeyTimings>,
+    /// The keyboard pack's per-key `repeat` segments, in ms.
+    pub(super) key_repeats: HashMap<String, (f32, f32)>,
+    /// The timing set each key's last keydown played, so its keyup plays
+    /// from the same one and round-robin knows what comes next. Cleared
+    /// whenever the matching map is replaced.
+    pub(super) key_variation: HashMap<String, usize>,
+    pub(super) mouse_variation: HashMap<String, usize>,
+    /// Id of the loaded keyboard pack, which picks its `key_overrides`.
+    pub(super) keyboard_pack_id: String,
+    /// `options` from the loaded packs' config.json, kept so per-pack flags
+    /// like `random_pitch` apply to every voice without re-reading the file.
+    pub(super) keyboard_options: SoundpackOptions,
+    pub(super) mouse_options: SoundpackOptions,
+    /// Measured when a pack is decoded and kept so toggling
+    /// `auto_normalize_loudness` can recompute the gain without a reload.
+    pub(super) keyboard_loudness: Option<Loudness>,
+    pub(super) mouse_loudness: Option<Loudness>,
+    /// Per-pack gain from `loudness::pack_gain`, multiplied into every voice
+    /// on top of the user's volume. Refreshed by `refresh_pack_gains`.
+    keyboard_gain: f32,
+    mouse_gain: f32,
+    /// The user's levels for the stream buses, see `bus_gain`.
+    ambiance_volume: f32,
+    music_volume: f32,
+    bus_muted: [bool; Bus::ALL.len()],
+    bus_paused: [bool; Bus::ALL.len()],
+    ducking: Ducking,
+    /// Every stream started and not yet stopped, keyed by its id.
+    streams: HashMap<String, StreamEntry>,
 
     key_pressed: HashMap<String, bool>,
     mouse_pressed: HashMap<String, bool>,
-    pub(super) key_sinks: Vec<Sink>,
-    pub(super) mouse_sinks: Vec<Sink>,
+    /// The one mixer `Source` playing on `output` - every voice goes here.
+    pub(super) mixer: MixerHandle,
+    /// Limiter reports from whichever mixer is current, for `UiEvent::Clipping`.
+    limit_tx: Sender<bool>,
+    limit_rx: Receiver<bool>,
 
     volume: f32,
     mouse_volume: f32,
     sound_enabled: bool,
     keyboard_sound_enabled: bool,
     mouse_sound_enabled: bool,
-}
-
-/// Opens a stream for `device_id` (`None` = system default). Does NOT fall
-/// back silently - callers decide what to do on `Err` (see `switch_device`,
-/// which keeps the previous device on failure, vs `EngineState::new`, which
-/// falls back to default since there's no previous device to keep).
-fn open_stream(
-    device_manager: &DeviceManager,
-    device_id: Option<&str>
-) -> Result<(OutputStream, OutputStreamHandle, Option<String>), String> {
-    match device_id {
-        Some(id) => {
-            match device_manager.get_output_device_by_id(id) {
-                Ok(Some(device)) => {
-                    rodio::OutputStream
-                        ::try_from_device(&device)
-                        .map(|(stream, handle)| (stream, handle, Some(id.to_string())))
-                        .map_err(|e| format!("Failed to open stream for device {}: {}", id, e))
-                }
-                Ok(None) => Err(format!("Device {} not found", id)),
-                Err(e) => Err(format!("Error accessing device {}: {}", id, e)),
-            }
-        }
-        None => {
-            rodio::OutputStream
-                ::try_default()
-                .map(|(stream, handle)| (stream, handle, None))
-                .map_err(|e| format!("Failed to open default audio output stream: {}", e))
-        }
-    }
+    random_pitch_range: f32,
+    auto_normalize_loudness: bool,
+    stereo_panning: bool,
+    keyboard_layout: KeyboardLayout,
+    stereo_width: f32,
+    /// Every key's pan on `keyboard_layout`, already narrowed by
+    /// `stereo_width`, so a keystroke is one lookup. Rebuilt by
+    /// `refresh_key_pans` whenever either changes.
+    key_pans: HashMap<&'static str, f32>,
+    key_overrides: HashMap<String, KeyOverrides>,
+    /// Kept so a device switch can hand them to the new mixer.
+    effects: EffectsSettings,
+    voice_stealing: VoiceStealing,
+    choke_same_key: bool,
+    choke_groups: Vec<Vec<String>>,
+    key_repeat: KeyRepeat,
+    repeat_gain: f32,
+    key_fallback: KeyFallback,
+    /// See `AudioCommand::SetKeyboardPackOrder`.
+    keyboard_pack_order: Vec<String>,
+    /// Draws pitch jitter and random variations. Seeded from the OS, except
+    /// by `offline_render`, which seeds it so a render repeats exactly.
+    rng: StdRng,
+    /// Decodes requested packs off this thread; see `pack_loader`.
+    loader: PackLoader,
 }
 
 impl EngineState {
     fn new() -> Self {
-        let device_manager = DeviceManager::new();
         let config = crate::state::config_writer::current();
+        let output = RodioOutput::open(config.selected_audio_device.as_deref());
+        Self::with_output(Box::new(output), &config)
+    }
 
-        let (stream, stream_handle, opened_device_id) = open_stream(
-            &device_manager,
-            config.selected_audio_device.as_deref()
-        ).unwrap_or_else(|e| {
-            crate::always_eprint!("❌ [AudioEngine] {} - falling back to default", e);
-            open_stream(&device_manager, None).expect("Failed to open default audio output stream")
-        });
-        let current_device_id = opened_device_id.or(config.selected_audio_device.clone());
-        let device_rate = device_manager.get_current_output_sample_rate();
-
-        Self {
-            stream,
-            stream_handle,
-            device_manager,
-            current_device_id,
+    /// Builds the engine around any output. `new` passes the real device;
+    /// tests and `offline_render` pass a `CaptureOutput` and need no sound
+    /// card.
+    pub(super) fn with_output(mut output: Box<dyn AudioOutput>, config: &AppConfig) -> Self {
+        let device_rate = output.sample_rate();
+        let (limit_tx, limit_rx) = bounded(16);
+        let mixer = start_mixer(output.as_mut(), device_rate, &limit_tx);
+
+        let mut state = Self {
+            output,
             device_rate,
-            keyboard_samples: None,
-            keyboard_samples_original: None,
-            mouse_samples: None,
-            mouse_samples_original: None,
+            keyboard_audio: PackBuffers::default(),
+            mouse_audio: PackBuffers::default(),
             key_map: HashMap::new(),
             mouse_map: HashMap::new(),
+            key_repeats: HashMap::new(),
+            key_variation: HashMap::new(),
+            mouse_variation: HashMap::new(),
+            keyboard_pack_id: String::new(),
+            keyboard_options: SoundpackOptions::default(),
+            mouse_options: SoundpackOptions::default(),
+            keyboard_loudness: None,
+            mouse_loudness: None,
+            keyboard_gain: 1.0,
+            mouse_gain: 1.0,
+            ambiance_volume: config.ambiance_global_volume,
+            music_volume: 1.0,
+            bus_muted: Bus::ALL.map(|bus| bus == Bus::Ambiance && config.ambiance_is_muted),
+            bus_paused: [false; Bus::ALL.len()],
+            ducking: config.ambiance_ducking,
+            streams: HashMap::new(),
             key_pressed: HashMap::new(),
             mouse_pressed: HashMap::new(),
-            key_sinks: Vec::new(),
-            mouse_sinks: Vec::new(),
+            mixer,
+            limit_tx,
+            limit_rx,
             volume: config.volume,
             mouse_volume: config.mouse_volume,
             sound_enabled: config.enable_sound,
             keyboard_sound_enabled: config.enable_keyboard_sound,
             mouse_sound_enabled: config.enable_mouse_sound,
+            random_pitch_range: config.random_pitch_range,
+            auto_normalize_loudness: config.auto_normalize_loudness,
+            stereo_panning: config.stereo_panning,
+            keyboard_layout: config.keyboard_layout,
+            stereo_width: config.stereo_width,
+            key_pans: HashMap::new(),
+            key_overrides: config.key_overrides.clone(),
+            effects: config.effects,
+            voice_stealing: config.voice_stealing,
+            choke_same_key: config.choke_same_key,
+            choke_groups: config.choke_groups.clone(),
+            key_repeat: config.key_repeat,
+            repeat_gain: config.repeat_gain,
+            key_fallback: config.key_fallback.clone(),
+            keyboard_pack_order: Vec::new(),
+            rng: StdRng::from_os_rng(),
+            loader: PackLoader::new(),
+        };
+        state.refresh_pack_gains();
+        state.refresh_key_pans();
+        state.restore_mixer();
+        state
+    }
+
+    /// Makes every random choice from here on follow from `seed`.
+    pub(super) fn seed_rng(&mut self, seed: u64) {
+        self.rng = StdRng::seed_from_u64(seed);
+    }
+
+    fn refresh_key_pans(&mut self) {
+        let width = self.stereo_width.clamp(0.0, 1.0);
+        self.key_pans = self.keyboard_layout
+            .pans()
+            .into_iter()
+            .map(|(code, pan)| (code, pan * width))
+            .collect();
+    }
+
+    /// The gain `bus` plays at: the user's level for it, times the pack gain
+    /// on the keystroke buses.
+    fn bus_gain(&self, bus: Bus) -> f32 {
+        match bus {
+            Bus::Keyboard => self.volume * self.keyboard_gain,
+            Bus::Mouse => self.mouse_volume * self.mouse_gain,
+            Bus::Ambiance => self.ambiance_volume,
+            Bus::Music => self.music_volume,
         }
     }
 
-    fn handle_key_event(&mut self, code: &str, down: bool) {
+    fn set_bus_volume(&mut self, bus: Bus, volume: f32) {
+        match bus {
+            Bus::Keyboard => {
+                self.volume = volume;
+            }
+            Bus::Mouse => {
+                self.mouse_volume = volume;
+            }
+            Bus::Ambiance => {
+                self.ambiance_volume = volume;
+            }
+            Bus::Music => {
+                self.music_volume = volume;
+            }
+        }
+        self.mixer.set_bus_gain(bus, self.bus_gain(bus));
+    }
+
+    /// Gives a freshly started mixer everything but its voices: every bus's
+    /// level, mute and pause, the effects, stealing and ducking settings,
+    /// and the streams that were playing on the mixer it replaces.
+    fn restore_mixer(&mut self) {
+        for bus in Bus::ALL {
+            self.mixer.set_bus_gain(bus, self.bus_gain(bus));
+            self.mixer.set_bus_muted(bus, self.bus_muted[bus.index()]);
+            self.mixer.set_bus_paused(bus, self.bus_paused[bus.index()]);
+        }
+        self.mixer.set_effects(self.effects);
+        self.mixer.set_voice_stealing(self.voice_stealing);
+        self.mixer.set_ducking(self.ducking);
+        for (id, entry) in &self.streams {
+            if let Err(e) = self.start_stream(id, entry) {
+                crate::always_eprint!("❌ [AudioEngine] Failed to resume stream {}: {}", id, e);
+            }
+        }
+    }
+
+    /// Opens `entry`'s file and hands it to the mixer as stream `id`.
+    fn start_stream(&self, id: &str, entry: &StreamEntry) -> Result<(), String> {
+        let source = looping_source(&entry.path, self.device_rate.unwrap_or(FALLBACK_MIX_RATE))?;
+        self.mixer.play_stream(Stream { id: key_id(id), bus: entry.bus, gain: entry.gain, source });
+        Ok(())
+    }
+
+    fn play_stream(&mut self, id: String, entry: StreamEntry) {
+        match self.start_stream(&id, &entry) {
+            Ok(()) => {
+                crate::always_print!("🎵 [AudioEngine] Started stream {} on the {:?} bus", id, entry.bus);
+                self.streams.insert(id, entry);
+            }
+            Err(e) => crate::always_eprint!("❌ [AudioEngine] Failed to play stream {}: {}", id, e),
+        }
+    }
+
+    /// Recomputes both pack gains from the stored options and measurements,
+    /// and moves the voices already playing along with them. Called after a
+    /// pack load and when the user flips `auto_normalize_loudness`.
+    pub(super) fn refresh_pack_gains(&mut self) {
+        self.keyboard_gain = loudness::pack_gain(
+            self.keyboard_options.recommended_volume,
+            self.keyboard_loudness,
+            self.auto_normalize_loudness
+        );
+        self.mouse_gain = loudness::pack_gain(
+            self.mouse_options.recommended_volume,
+            self.mouse_loudness,
+            self.auto_normalize_loudness
+        );
+        self.mixer.set_bus_gain(Bus::Keyboard, self.bus_gain(Bus::Keyboard));
+        self.mixer.set_bus_gain(Bus::Mouse, self.bus_gain(Bus::Mouse));
+    }
+
+    /// Hands a pack load to the background loader. The current pack keeps
+    /// playing until the loaded one is installed by `handle_loaded_pack`.
+    fn request_pack(&mut self, kind: PackKind, soundpack_id: &str, update_cache_on_error: bool) {
+        crate::always_print!("📦 [Engine] Loading {:?} soundpack in the background: {}", kind, soundpack_id);
+        let rate = self.device_rate;
+        self.loader.request(kind, soundpack_id, rate, update_cache_on_error);
+    }
+
+    /// The loaded keyboard pack's override for `code`, or the default.
+    fn key_override(&self, code: &str) -> KeyOverride {
+        self.key_overrides
+            .get(&self.keyboard_pack_id)
+            .and_then(|overrides| overrides.get(code))
+            .copied()
+            .unwrap_or_default()
+    }
+
+    pub(super) fn handle_key_event(&mut self, code: &str, down: bool) {
         if !should_play(self.sound_enabled, self.keyboard_sound_enabled) {
             return;
         }
         if !debounce_press(&mut self.key_pressed, code, down) {
             return;
         }
-        if let Some((start, end)) = lookup_timing(&self.key_map, code, down) {
-            play_segment(
-                &self.stream_handle,
-                &self.keyboard_samples,
-                code,
-                start,
-                end,
-                self.volume,
-                &mut self.key_sinks
-            );
+        // Checked after the debounce, so a key muted while held still has
+        // its release recorded and sounds again on the next press.
+        let key_override = self.key_override(code);
+        if key_override.muted {
+            return;
         }
+        let Some(source) = sound_source(&self.key_map, &self.key_fallback, code) else {
+            return;
+        };
+        let timing = lookup_timing(
+            &self.key_map,
+            &mut self.key_variation,
+            &mut self.rng,
+            self.keyboard_options.variation_mode,
+            source,
+            down
+        );
+        let Some(timing) = timing else {
+            return;
+        };
+        let Some(voice) = segment_voice(Bus::Keyboard, self.keyboard_audio.buffer_for(source), code, timing) else {
+            return;
+        };
+        self.mixer.play(Voice {
+            speed: random_pitch_ratio(self.keyboard_options.random_pitch, self.random_pitch_range, &mut self.rng),
+            pan: key_pan(self.stereo_panning, &self.key_pans, code),
+            gain: key_gain(key_override.gain),
+            choke: voice_choke(&self.keyboard_options, self.choke_same_key, &self.choke_groups, code),
+            ..voice
+        });
     }
 
-    fn handle_mouse_event(&mut self, code: &str, down: bool) {
+    /// An OS auto-repeat of a held key, played as `key_repeat` says. A
+    /// repeat of a key whose press was never seen is ignored, like a keyup
+    /// without its keydown.
+    pub(super) fn handle_key_repeat(&mut self, code: &str) {
+        if !should_play(self.sound_enabled, self.keyboard_sound_enabled) {
+            return;
+        }
+        if !self.key_pressed.get(code).copied().unwrap_or(false) {
+            return;
+        }
+        let key_override = self.key_override(code);
+        if key_override.muted {
+            return;
+        }
+        let Some(source) = sound_source(&self.key_map, &self.key_fallback, code) else {
+            return;
+        };
+        let segment = match self.key_repeat {
+            KeyRepeat::Silent => {
+                return;
+            }
+            KeyRepeat::Segment => self.key_repeats.get(source).copied(),
+            KeyRepeat::Replay => None,
+        };
+        let (timing, gain) = match segment {
+            Some(segment) => (segment, 1.0),
+            None => {
+                let Some(press) = last_press_timing(&self.key_map, &self.key_variation, source) else {
+                    return;
+                };
+                (press, repeat_gain(self.repeat_gain))
+            }
+        };
+        let Some(voice) = segment_voice(Bus::Keyboard, self.keyboard_audio.buffer_for(source), code, timing) else {
+            return;
+        };
+        self.mixer.play(Voice {
+            speed: random_pitch_ratio(self.keyboard_options.random_pitch, self.random_pitch_range, &mut self.rng),
+            pan: key_pan(self.stereo_panning, &self.key_pans, code),
+            gain: key_gain(key_override.gain) * gain,
+            choke: voice_choke(&self.keyboard_options, self.choke_same_key, &self.choke_groups, code),
+            ..voice
+        });
+    }
+
+    pub(super) fn handle_mouse_event(&mut self, code: &str, down: bool) {
         if !should_play(self.sound_enabled, self.mouse_sound_enabled) {
             return;
         }
         if !debounce_press(&mut self.mouse_pressed, code, down) {
             return;
         }
-        if let Some((start, end)) = lookup_timing(&self.mouse_map, code, down) {
-            play_segment(
-                &self.stream_handle,
-                &self.mouse_samples,
-                code,
-                start,
-                end,
-                self.mouse_volume,
-                &mut self.mouse_sinks
-            );
+        let timing = lookup_timing(
+            &self.mouse_map,
+            &mut self.mouse_variation,
+            &mut self.rng,
+            self.mouse_options.variation_mode,
+            code,
+            down
+        );
+        let Some(timing) = timing else {
+            return;
+        };
+        // Mouse buttons have no place on the keyboard layout, so stay centred.
+        if let Some(voice) = segment_voice(Bus::Mouse, self.mouse_audio.buffer_for(code), code, timing) {
+            self.mixer.play(Voice {
+                speed: random_pitch_ratio(self.mouse_options.random_pitch, self.random_pitch_range, &mut self.rng),
+                choke: voice_choke(&self.mouse_options, self.choke_same_key, &self.choke_groups, code),
+                ..voice
+            });
         }
     }
 
@@ -254,35 +611,18 @@ impl EngineState {
         // On Err, `self` is left untouched entirely - the previous device
         // keeps playing, matching the "keep current sound, report error"
         // requirement (Phase 3 success criteria).
-        let (new_stream, new_handle, opened_device_id) = open_stream(
-            &self.device_manager,
-            device_id.as_deref()
-        )?;
+        let label = self.output.switch_device(device_id)?;
 
         // Re-resample cached original samples to the new device's rate so
         // in-flight soundpacks keep playing correctly without a reload.
-        let new_rate = self.device_manager.get_current_output_sample_rate();
-        if let Some((orig_samples, channels, orig_rate)) = &self.keyboard_samples_original {
-            self.keyboard_samples = Some(
-                resample_if_needed(orig_samples, *channels, *orig_rate, new_rate)
-            );
-        }
-        if let Some((orig_samples, channels, orig_rate)) = &self.mouse_samples_original {
-            self.mouse_samples = Some(
-                resample_if_needed(orig_samples, *channels, *orig_rate, new_rate)
-            );
-        }
+        let new_rate = self.output.sample_rate();
+        self.keyboard_audio.resample_to(new_rate);
+        self.mouse_audio.resample_to(new_rate);
 
-        // Drop old voices/stream only after the new one is confirmed open,
-        // so a failed switch leaves the previous device still playing.
-        self.key_sinks.clear();
-        self.mouse_sinks.clear();
-        self.stream = new_stream;
-        self.stream_handle = new_handle;
+        self.mixer = start_mixer(self.output.as_mut(), new_rate, &self.limit_tx);
         self.device_rate = new_rate;
-        self.current_device_id = opened_device_id.clone().or(device_id);
-
-        let label = self.current_device_id.clone().unwrap_or_else(|| "System Default".to_string());
+        self.refresh_pack_gains();
+        self.restore_mixer();
         Ok(label)
     }
 }
@@ -310,129 +650,200 @@ fn debounce_press(pressed: &mut HashMap<String, bool>, code: &str, down: bool) -
 }
 
 /// Looks up the `[start, end]` (ms) pair for a keydown/keyup event from a
-/// soundpack's timing map. Mirrors the pre-Phase-3 `play_key_event_sound`
-/// logic in `sound_manager.rs`.
-fn lookup_timing(map: &HashMap<String, Vec<[f32; 2]>>, code: &str, down: bool) -> Option<(f32, f32)> {
-    match map.get(code) {
-        Some(arr) if arr.len() == 2 => {
-            let idx = if down { 0 } else { 1 };
-            Some((arr[idx][0], arr[idx][1]))
-        }
-        Some(arr) if arr.len() == 1 => {
-            if !down {
-                return None; // keydown-only mapping, ignore keyup
-            }
-            Some((arr[0][0], arr[0][1]))
-        }
-        _ => None,
-    }
-}
-
-fn play_segment(
-    stream_handle: &OutputStreamHandle,
-    samples: &Option<DecodedAudio>,
+/// soundpack's timing map. A keydown picks one of the key's timing sets and
+/// records it in `last_played`; the keyup then plays from that same set, so
+/// a press and its release always come from the same recording.
+fn lookup_timing(
+    map: &HashMap<String, KeyTimings>,
+    last_played: &mut HashMap<String, usize>,
+    rng: &mut impl Rng,
+    mode: VariationMode,
     code: &str,
-    start_ms: f32,
-    end_ms: f32,
-    volume: f32,
-    sinks: &mut Vec<Sink>
-) {
-    let Some((samples_arc, channels, sample_rate)) = samples else {
-        return;
+    down: bool
+) -> Option<(f32, f32)> {
+    let sets = map.get(code)?;
+    let index = if down {
+        let index = pick_variation(mode, sets.len(), last_played.get(code).copied(), rng);
+        last_played.insert(code.to_string(), index);
+        index
+    } else {
+        last_played.get(code).copied().unwrap_or(0)
     };
-    let samples: &Vec<f32> = samples_arc.as_ref();
-    let channels = *channels;
-    let sample_rate = *sample_rate;
 
-    let duration = end_ms - start_ms;
-    if start_ms < 0.0 || duration <= 0.0 {
-        return;
+    match sets.get(index)?.as_slice() {
+        [down_segment, up_segment] => {
+            let segment = if down { down_segment } else { up_segment };
+            Some((segment[0], segment[1]))
+        }
+        // keydown-only mapping, ignore keyup
+        [down_segment] if down => Some((down_segment[0], down_segment[1])),
+        _ => None,
     }
+}
 
-    let start_sample = ((start_ms / 1000.0) * (sample_rate as f32) * (channels as f32)) as usize;
-    let end_sample = ((end_ms / 1000.0) * (sample_rate as f32) * (channels as f32)) as usize;
-    let end_sample = end_sample.min(samples.len());
-    if start_sample >= end_sample {
-        crate::always_eprint!(
-            "❌ [AudioEngine] Invalid sample range for '{}': {}..{} (max {})",
-            code,
-            start_sample,
-            end_sample,
-            samples.len()
-        );
-        return;
+/// The key whose sound `code` plays: itself when the pack maps it, else
+/// whatever the fallback chain picks. Panning, chokes and per-key overrides
+/// still go by `code`.
+fn sound_source<'a>(
+    map: &HashMap<String, KeyTimings>,
+    fallback: &'a KeyFallback,
+    code: &'a str
+) -> Option<&'a str> {
+    if map.contains_key(code) {
+        return Some(code);
     }
+    crate::utils::key_fallback::fallback_for(code, |key| map.contains_key(key), fallback)
+}
 
-    let mut segment_samples = samples[start_sample..end_sample].to_vec();
-    apply_fade(&mut segment_samples, channels, sample_rate);
-    let segment = SamplesBuffer::new(channels, sample_rate, segment_samples);
+/// The keydown segment of the timing set `code` last pressed with, so a
+/// replayed repeat sounds like the press it continues.
+fn last_press_timing(
+    map: &HashMap<String, KeyTimings>,
+    last_played: &HashMap<String, usize>,
+    code: &str
+) -> Option<(f32, f32)> {
+    let sets = map.get(code)?;
+    let set = sets.get(last_played.get(code).copied().unwrap_or(0))?;
+    set.first().map(|segment| (segment[0], segment[1]))
+}
 
-    if let Ok(sink) = Sink::try_new(stream_handle) {
-        sink.set_volume(volume);
-        sink.append(segment);
+/// Which of a key's `count` timing sets its next keydown plays, given the
+/// one it played last. Random never repeats the previous pick: two identical
+/// presses in a row is exactly the machine-gun sound variations are there
+/// to break up.
+fn pick_variation(mode: VariationMode, count: usize, last: Option<usize>, rng: &mut impl Rng) -> usize {
+    if count <= 1 {
+        return 0;
+    }
+    match (mode, last) {
+        (VariationMode::RoundRobin, Some(last)) => (last + 1) % count,
+        (VariationMode::RoundRobin, None) => 0,
+        (VariationMode::Random, Some(last)) if last < count => {
+            // Draw from the other `count - 1` sets and step over `last`.
+            let pick = rng.random_range(0..count - 1);
+            if pick >= last { pick + 1 } else { pick }
+        }
+        (VariationMode::Random, _) => rng.random_range(0..count),
+    }
+}
 
-        manage_active_sinks(sinks, MAX_VOICES);
-        sinks.push(sink);
+/// Playback-rate multiplier for one voice. `1.0` unless the pack opted in
+/// via `options.random_pitch`; otherwise a uniform pick within
+/// `1.0 +/- range`, so repeated presses of one key stop sounding identical.
+///
+/// Rate rather than a pitch shift proper: it moves pitch and length together,
+/// which is what a slightly different keystroke actually sounds like, and it
+/// costs nothing beyond rodio's `speed` adapter.
+fn random_pitch_ratio(random_pitch: bool, range: f32, rng: &mut impl Rng) -> f32 {
+    let range = range.clamp(0.0, MAX_RANDOM_PITCH_RANGE);
+    if !random_pitch || range <= 0.0 {
+        return 1.0;
     }
+    1.0 + rng.random_range(-range..=range)
 }
 
-/// Applies a linear fade-in/fade-out to interleaved PCM samples in place.
-/// Operates per-frame (one frame = `channels` consecutive samples) so all
-/// channels in a frame share the same gain and stay in phase.
-fn apply_fade(samples: &mut [f32], channels: u16, sample_rate: u32) {
-    let channels = channels.max(1) as usize;
-    let frame_count = samples.len() / channels;
-    if frame_count == 0 {
-        return;
+/// Stereo position for a keystroke on `code`, from `pans` (see
+/// `EngineState::key_pans`; width 0.0 = everything centred, 1.0 = the
+/// outermost keys hard left and right). Keys the layout lacks, and
+/// everything with panning off, play centred.
+fn key_pan(enabled: bool, pans: &HashMap<&'static str, f32>, code: &str) -> f32 {
+    if !enabled {
+        return 0.0;
     }
+    pans.get(code).copied().unwrap_or(0.0)
+}
 
-    let mut fade_in_frames = ((FADE_IN_MS / 1000.0) * (sample_rate as f32)) as usize;
-    let mut fade_out_frames = ((FADE_OUT_MS / 1000.0) * (sample_rate as f32)) as usize;
+/// Starts a fresh mixer on `output`. It runs at the device rate when known,
+/// so the already-resampled buffers are read one-to-one.
+fn start_mixer(output: &mut dyn AudioOutput, device_rate: Option<u32>, limit_tx: &Sender<bool>) -> MixerHandle {
+    // Bus gains start at zero and are set by `refresh_pack_gains`, which
+    // every caller runs before the first keystroke can reach the mixer.
+    let channels = output.channels().unwrap_or(2);
+    let (mixer, handle) = Mixer::new(device_rate.unwrap_or(FALLBACK_MIX_RATE), channels, 0.0, 0.0);
+    output.play(mixer.reporting_limits(limit_tx.clone()));
+    handle
+}
 
-    let half = frame_count / 2;
-    if fade_in_frames > half {
-        fade_in_frames = half;
-    }
-    if fade_out_frames > half {
-        fade_out_frames = half;
+/// `path` decoded and looped forever, as stereo at `rate` for the mixer.
+fn looping_source(path: &str, rate: u32) -> Result<StreamSource, String> {
+    use rodio::Source;
+
+    let file = std::fs::File
+        ::open(path)
+        .map_err(|e| format!("Failed to open audio file {}: {}", path, e))?;
+    let decoder = rodio::Decoder
+        ::new(std::io::BufReader::new(file))
+        .map_err(|e| format!("Failed to decode audio {}: {}", path, e))?;
+    Ok(Box::new(rodio::source::UniformSourceIterator::<_, f32>::new(decoder.repeat_infinite(), MIX_CHANNELS, rate)))
+}
+
+/// A voice for the `(start_ms, end_ms)` slice of `samples`, centred and at
+/// its recorded pitch and level; callers fill in the rest. Only an `Arc`
+/// clone and two offsets travel to the audio thread.
+fn segment_voice(
+    bus: Bus,
+    samples: Option<&DecodedAudio>,
+    code: &str,
+    (start_ms, end_ms): (f32, f32)
+) -> Option<Voice> {
+    let (buffer, channels, sample_rate) = samples?;
+
+    if start_ms < 0.0 || end_ms <= start_ms {
+        return None;
     }
 
-    for frame in 0..fade_in_frames {
-        let gain = (frame as f32) / (fade_in_frames as f32);
-        let base = frame * channels;
-        for c in 0..channels {
-            samples[base + c] *= gain;
-        }
+    let frames = buffer.len() / ((*channels).max(1) as usize);
+    let start_frame = ((start_ms / 1000.0) * (*sample_rate as f32)) as usize;
+    let end_frame = (((end_ms / 1000.0) * (*sample_rate as f32)) as usize).min(frames);
+    if start_frame >= end_frame {
+        crate::always_eprint!(
+            "❌ [AudioEngine] Invalid sample range for '{}': {}..{} (max {})",
+            code,
+            start_frame,
+            end_frame,
+            frames
+        );
+        return None;
     }
 
-    for frame in 0..fade_out_frames {
-        let gain = (frame as f32) / (fade_out_frames as f32);
-        let frame_idx = frame_count - 1 - frame;
-        let base = frame_idx * channels;
-        for c in 0..channels {
-            samples[base + c] *= gain;
-        }
+    Some(Voice {
+        buffer: buffer.clone(),
+        channels: *channels,
+        sample_rate: *sample_rate,
+        start_frame,
+        end_frame,
+        speed: 1.0,
+        pan: 0.0,
+        gain: 1.0,
+        bus,
+        key: key_id(code),
+        choke: None,
+    })
+}
+
+/// What a new voice for `code` cuts off. Pack groups are numbered before the
+/// user's, so a key listed in both keeps its pack's group.
+fn voice_choke(options: &SoundpackOptions, same_key: bool, user_groups: &[Vec<String>], code: &str) -> Option<Choke> {
+    let group = options.choke_groups
+        .iter()
+        .chain(user_groups)
+        .position(|group| group.iter().any(|member| member == code));
+    match group {
+        Some(group) => Some(Choke::Group(group as u32)),
+        None if options.self_choke || same_key => Some(Choke::SameKey),
+        None => None,
     }
 }
 
-/// Removes finished sinks, then evicts the oldest voice (ramped down to
-/// avoid a click) if the pool is still at or above `max_voices`.
-fn manage_active_sinks(sinks: &mut Vec<Sink>, max_voices: usize) {
-    sinks.retain(|s| !s.empty());
+/// `AppConfig::repeat_gain` kept within 0..=1; not a number means half.
+fn repeat_gain(gain: f32) -> f32 {
+    if gain.is_finite() { gain.clamp(0.0, 1.0) } else { 0.5 }
+}
 
-    if sinks.len() >= max_voices {
-        let old_sink = sinks.remove(0);
-        std::thread::spawn(move || {
-            const STEPS: u32 = 10;
-            let starting_volume = old_sink.volume();
-            for step in 1..=STEPS {
-                let gain = starting_volume * (1.0 - (step as f32) / (STEPS as f32));
-                old_sink.set_volume(gain.max(0.0));
-                std::thread::sleep(Duration::from_millis(EVICT_RAMP_MS / (STEPS as u64)));
-            }
-            old_sink.stop();
-        });
-    }
+/// A per-key override's gain, kept within 0..=`MAX_KEY_GAIN`; a
+/// hand-edited value that is not a number plays the key unchanged.
+fn key_gain(gain: f32) -> f32 {
+    if gain.is_finite() { gain.clamp(0.0, MAX_KEY_GAIN) } else { 1.0 }
 }
 
 fn resample_if_needed(
@@ -455,20 +866,104 @@ fn resample_if_needed(
     }
 }
 
-/// Parses the `"KeyA"` / `"UP:KeyA"` wire format the input listeners
-/// (rdev, device_query, evdev) already send, same as the pre-Phase-3 UI
+/// What an input event did to its key or button.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(super) enum KeyAction {
+    Down,
+    Up,
+    /// The OS auto-repeating a held key; never a real press.
+    Repeat,
+}
+
+/// Parses the `"KeyA"` / `"UP:KeyA"` / `"REPEAT:KeyA"` wire format the input
+/// listeners (rdev, device_query, evdev) send, same as the pre-Phase-3 UI
 /// polling loops in `ui.rs` did.
-fn parse_input_event(raw: &str) -> Option<(String, bool)> {
+pub(super) fn parse_input_event(raw: &str) -> Option<(String, KeyAction)> {
     if let Some(code) = raw.strip_prefix("UP:") {
-        Some((code.to_string(), false))
+        Some((code.to_string(), KeyAction::Up))
+    } else if let Some(code) = raw.strip_prefix("REPEAT:") {
+        Some((code.to_string(), KeyAction::Repeat))
     } else if !raw.is_empty() {
-        Some((raw.to_string(), true))
+        Some((raw.to_string(), KeyAction::Down))
     } else {
         None
     }
 }
 
-/// Handles the Ctrl+Alt+M hotkey: flips `enable_sound`, persists it, and
+/// Runs one hotkey action. Actions on state the engine owns are handled
+/// here; the rest are handed to the UI poll loop as `UiEvent::Hotkey`.
+fn handle_hotkey(state: &mut EngineState, event_tx: &Sender<UiEvent>, action: HotkeyAction) {
+    match action {
+        HotkeyAction::ToggleSound => {
+            // Adopt the persisted value rather than negating the cached one,
+            // so the engine can't drift out of sync with config if the two
+            // ever disagree.
+            state.sound_enabled = handle_toggle_sound();
+        }
+        HotkeyAction::VolumeUp | HotkeyAction::VolumeDown => {
+            let step = if action == HotkeyAction::VolumeUp {
+                VOLUME_HOTKEY_STEP
+            } else {
+                -VOLUME_HOTKEY_STEP
+            };
+            state.set_bus_volume(Bus::Keyboard, handle_volume_step(step));
+        }
+        HotkeyAction::NextKeyboardPack | HotkeyAction::PreviousKeyboardPack => {
+            handle_cycle_keyboard_pack(state, action == HotkeyAction::NextKeyboardPack);
+        }
+        HotkeyAction::ToggleAmbiance | HotkeyAction::ShowWindow => {
+            let _ = event_tx.send(UiEvent::Hotkey(action));
+        }
+    }
+}
+
+/// `volume` moved by `step`, kept within 0..=`max` and rounded to whole
+/// percent so repeated steps land back on the values the slider shows.
+fn step_volume(volume: f32, step: f32, max: f32) -> f32 {
+    (((volume + step) * 100.0).round() / 100.0).clamp(0.0, max)
+}
+
+/// Steps the keyboard volume in config and returns the new value. Like
+/// `handle_toggle_sound`, the step is applied to the authority's value.
+fn handle_volume_step(step: f32) -> f32 {
+    let mut volume = 0.0;
+    crate::state::config_writer::apply(|config| {
+        let max = if config.enable_volume_boost { 2.0 } else { 1.0 };
+        config.volume = step_volume(config.volume, step, max);
+        volume = config.volume;
+    });
+    super::audio_context::sync_volume_cache(volume);
+    volume
+}
+
+/// The pack after (or before) `current` in `packs`, wrapping around. A
+/// `current` that is not in the list starts from the first pack.
+fn adjacent_pack<'a>(packs: &'a [String], current: &str, forward: bool) -> Option<&'a String> {
+    let Some(index) = packs.iter().position(|id| id == current) else {
+        return packs.first();
+    };
+    let next = if forward { index + 1 } else { index + packs.len() - 1 };
+    packs.get(next % packs.len())
+}
+
+/// Switches to the next or previous keyboard pack, in the order the
+/// soundpack selector lists them (most recently modified first).
+fn handle_cycle_keyboard_pack(state: &mut EngineState, forward: bool) {
+    let current = crate::state::config_writer::current().keyboard_soundpack;
+    let Some(next) = adjacent_pack(&state.keyboard_pack_order, &current, forward).cloned() else {
+        return;
+    };
+    if next == current {
+        return;
+    }
+
+    crate::state::config_writer::apply(|config| {
+        config.keyboard_soundpack = next.clone();
+    });
+    state.request_pack(PackKind::Keyboard, &next, false);
+}
+
+/// Handles the mute hotkey: flips `enable_sound`, persists it, and
 /// returns the new value so the caller can move the engine's own cached flag
 /// in lockstep.
 ///
@@ -498,20 +993,24 @@ fn handle_toggle_sound() -> bool {
     enabled
 }
 
+/// Installs a pack the loader finished and tells the UI. A load that was
+/// superseded after it finished is dropped: the newer one is on its way and
+/// its `PackLoaded` is the one the UI should see.
+fn handle_loaded_pack(state: &mut EngineState, event_tx: &Sender<UiEvent>, loaded: LoadedPack) {
+    if loaded.token.is_cancelled() {
+        return;
+    }
+    let kind = loaded.kind;
+    let result = loaded.result.map(|prepared| {
+        super::soundpack_loader::install_pack(state, kind, prepared)
+    });
+    let _ = event_tx.send(UiEvent::PackLoaded { is_keyboard: kind == PackKind::Keyboard, result });
+}
+
 fn handle_command(state: &mut EngineState, event_tx: &Sender<UiEvent>, command: AudioCommand) {
     match command {
-        AudioCommand::SetVolume(v) => {
-            state.volume = v;
-            for sink in &state.key_sinks {
-                sink.set_volume(v);
-            }
-        }
-        AudioCommand::SetMouseVolume(v) => {
-            state.mouse_volume = v;
-            for sink in &state.mouse_sinks {
-                sink.set_volume(v);
-            }
-        }
+        AudioCommand::SetVolume(v) => state.set_bus_volume(Bus::Keyboard, v),
+        AudioCommand::SetMouseVolume(v) => state.set_bus_volume(Bus::Mouse, v),
         AudioCommand::SetSoundEnabled(enabled) => {
             state.sound_enabled = enabled;
         }
@@ -521,29 +1020,84 @@ fn handle_command(state: &mut EngineState, event_tx: &Sender<UiEvent>, command:
         AudioCommand::SetMouseSoundEnabled(enabled) => {
             state.mouse_sound_enabled = enabled;
         }
+        AudioCommand::SetRandomPitchRange(range) => {
+            state.random_pitch_range = range;
+        }
+        AudioCommand::SetAutoNormalizeLoudness(enabled) => {
+            state.auto_normalize_loudness = enabled;
+            state.refresh_pack_gains();
+        }
+        AudioCommand::SetStereoPanning(enabled) => {
+            state.stereo_panning = enabled;
+        }
+        AudioCommand::SetKeyboardLayout(layout) => {
+            state.keyboard_layout = layout;
+            state.refresh_key_pans();
+        }
+        AudioCommand::SetStereoWidth(width) => {
+            state.stereo_width = width;
+            state.refresh_key_pans();
+        }
+        AudioCommand::SetKeyOverrides(overrides) => {
+            state.key_overrides = overrides;
+        }
+        AudioCommand::SetEffects(effects) => {
+            state.effects = effects;
+            state.mixer.set_effects(effects);
+        }
+        AudioCommand::SetVoiceStealing(policy) => {
+            state.voice_stealing = policy;
+            state.mixer.set_voice_stealing(policy);
+        }
+        AudioCommand::SetChokeSameKey(enabled) => {
+            state.choke_same_key = enabled;
+        }
+        AudioCommand::SetChokeGroups(groups) => {
+            state.choke_groups = groups;
+        }
+        AudioCommand::SetKeyRepeat(policy) => {
+            state.key_repeat = policy;
+        }
+        AudioCommand::SetRepeatGain(gain) => {
+            state.repeat_gain = gain;
+        }
+        AudioCommand::SetKeyFallback(fallback) => {
+            state.key_fallback = fallback;
+        }
+        AudioCommand::SetKeyboardPackOrder(order) => {
+            state.keyboard_pack_order = order;
+        }
+        AudioCommand::SetBusGain(bus, gain) => state.set_bus_volume(bus, gain),
+        AudioCommand::SetBusMuted(bus, muted) => {
+            state.bus_muted[bus.index()] = muted;
+            state.mixer.set_bus_muted(bus, muted);
+        }
+        AudioCommand::SetBusPaused(bus, paused) => {
+            state.bus_paused[bus.index()] = paused;
+            state.mixer.set_bus_paused(bus, paused);
+        }
+        AudioCommand::SetDucking(ducking) => {
+            state.ducking = ducking;
+            state.mixer.set_ducking(ducking);
+        }
+        AudioCommand::PlayStream { id, bus, path, gain } => {
+            state.play_stream(id, StreamEntry { bus, path, gain });
+        }
+        AudioCommand::StopStream(id) => {
+            state.streams.remove(&id);
+            state.mixer.stop_stream(key_id(&id));
+        }
+        AudioCommand::SetStreamGain { id, gain } => {
+            if let Some(entry) = state.streams.get_mut(&id) {
+                entry.gain = gain;
+                state.mixer.set_stream_gain(key_id(&id), gain);
+            }
+        }
         AudioCommand::LoadKeyboardPack { soundpack_id, update_cache_on_error } => {
-            let result = crate::libs::trace::time(
-                crate::libs::trace::Point::PackLoad,
-                &soundpack_id,
-                || super::soundpack_loader::load_keyboard_pack_into_engine(
-                    state,
-                    &soundpack_id,
-                    update_cache_on_error
-                )
-            );
-            let _ = event_tx.send(UiEvent::PackLoaded { is_keyboard: true, result });
+            state.request_pack(PackKind::Keyboard, &soundpack_id, update_cache_on_error);
         }
         AudioCommand::LoadMousePack { soundpack_id, update_cache_on_error } => {
-            let result = crate::libs::trace::time(
-                crate::libs::trace::Point::PackLoad,
-                &soundpack_id,
-                || super::soundpack_loader::load_mouse_pack_into_engine(
-                    state,
-                    &soundpack_id,
-                    update_cache_on_error
-                )
-            );
-            let _ = event_tx.send(UiEvent::PackLoaded { is_keyboard: false, result });
+            state.request_pack(PackKind::Mouse, &soundpack_id, update_cache_on_error);
         }
         AudioCommand::SwitchDevice(device_id) => {
             // User-initiated switch: on failure, keep the previous device
@@ -569,24 +1123,17 @@ fn run_engine(
     hotkey_rx: Receiver<String>
 ) {
     let mut state = EngineState::new();
+    let loaded_rx = state.loader.completed();
+    let limit_rx = state.limit_rx.clone();
 
-    // Load the configured soundpacks once at startup.
+    // Load the configured soundpacks once at startup. Keys typed before they
+    // arrive are simply silent, as they were while the load ran inline.
     let config = crate::state::config_writer::current();
     if !config.keyboard_soundpack.is_empty() {
-        let result = super::soundpack_loader::load_keyboard_pack_into_engine(
-            &mut state,
-            &config.keyboard_soundpack,
-            false
-        );
-        let _ = event_tx.send(UiEvent::PackLoaded { is_keyboard: true, result });
+        state.request_pack(PackKind::Keyboard, &config.keyboard_soundpack, false);
     }
     if !config.mouse_soundpack.is_empty() {
-        let result = super::soundpack_loader::load_mouse_pack_into_engine(
-            &mut state,
-            &config.mouse_soundpack,
-            false
-        );
-        let _ = event_tx.send(UiEvent::PackLoaded { is_keyboard: false, result });
+        state.request_pack(PackKind::Mouse, &config.mouse_soundpack, false);
     }
 
     // This loop is purely event-driven: every arm below is a channel receive,
@@ -614,30 +1161,48 @@ fn run_engine(
             }
             recv(keyboard_rx) -> msg => {
                 if let Ok(raw) = msg {
-                    if let Some((code, down)) = parse_input_event(&raw) {
-                        crate::libs::trace::record(crate::libs::trace::Point::EngineDequeue, &code, 0.0);
-                        crate::libs::trace::time(crate::libs::trace::Point::PlayedSound, &code, || {
-                            state.handle_key_event(&code, down);
-                        });
-                        crate::libs::trace::record(crate::libs::trace::Point::UiEventSent, &code, 0.0);
-                        let _ = event_tx.send(if down { UiEvent::KeyDown(code) } else { UiEvent::KeyUp(code) });
+                    match parse_input_event(&raw) {
+                        // Repeats sound but are not presses: the UI's
+                        // pressed-key state and counters never see them.
+                        Some((code, KeyAction::Repeat)) => {
+                            state.handle_key_repeat(&code);
+                        }
+                        Some((code, action)) => {
+                            let down = action == KeyAction::Down;
+                            crate::libs::trace::record(crate::libs::trace::Point::EngineDequeue, &code, 0.0);
+                            crate::libs::trace::time(crate::libs::trace::Point::PlayedSound, &code, || {
+                                state.handle_key_event(&code, down);
+                            });
+                            crate::libs::trace::record(crate::libs::trace::Point::UiEventSent, &code, 0.0);
+                            let _ = event_tx.send(if down { UiEvent::KeyDown(code) } else { UiEvent::KeyUp(code) });
+                        }
+                        None => {}
                     }
                 }
             }
             recv(mouse_rx) -> msg => {
                 if let Ok(raw) = msg {
-                    if let Some((code, down)) = parse_input_event(&raw) {
-                        state.handle_mouse_event(&code, down);
+                    // Buttons have no auto-repeat; a stray repeat is dropped.
+                    if let Some((code, action @ (KeyAction::Down | KeyAction::Up))) = parse_input_event(&raw) {
+                        state.handle_mouse_event(&code, action == KeyAction::Down);
                     }
                 }
             }
+            recv(loaded_rx) -> msg => {
+                if let Ok(loaded) = msg {
+                    handle_loaded_pack(&mut state, &event_tx, loaded);
+                }
+            }
+            recv(limit_rx) -> msg => {
+                if let Ok(limiting) = msg {
+                    let _ = event_tx.send(UiEvent::Clipping(limiting));
+                }
+            }
             recv(hotkey_rx) -> msg => {
                 if let Ok(command) = msg {
-                    if command == "TOGGLE_SOUND" {
-                        // Adopt the persisted value rather than negating the
-                        // cached one, so the engine can't drift out of sync
-                        // with config if the two ever disagree.
-                        state.sound_enabled = handle_toggle_sound();
+                    match HotkeyAction::from_wire_name(&command) {
+                        Some(action) => handle_hotkey(&mut state, &event_tx, action),
+                        None => crate::always_eprint!("⚠️ [AudioEngine] Unknown hotkey action: {}", command),
                     }
                 }
             }
@@ -648,6 +1213,9 @@ fn run_engine(
 #[cfg(test)]
 mod tests {
     use super::*;
+    use super::super::output::{ CaptureHandle, CaptureOutput };
+    use super::super::soundpack_loader::{ install_pack, PreparedPack };
+    use super::super::test_support;
 
     /// The engine's own source, so the assertions below are checked against
     /// what actually ships rather than a description of it.
@@ -710,6 +1278,17 @@ mod tests {
         assert!(runtime_source().contains("UiEvent::DeviceSwitched"));
     }
 
+    #[test]
+    fn keystrokes_go_through_the_mixer_not_per_voice_sinks_or_threads() {
+        // Every keystroke used to build a `Sink` and copy its segment, and
+        // every eviction spawned a thread to ramp the old voice down. Both
+        // now happen inside the one mixer `Source`; the engine thread itself
+        // is the only thread this file may spawn.
+        assert!(!runtime_source().contains("Sink::try_new"));
+        assert!(!runtime_source().contains(".to_vec()"), "voices must not copy their segment");
+        assert_eq!(runtime_source().matches("std::thread::spawn").count(), 1);
+    }
+
     #[test]
     fn global_mute_silences_both_input_types() {
         // The global flag wins regardless of the per-type flag - this is what
@@ -726,42 +1305,554 @@ mod tests {
         assert!(should_play(true, true), "nothing muted must play");
     }
 
+    #[test]
+    fn packs_without_random_pitch_always_play_at_recorded_pitch() {
+        // The option is opt-in per pack: a user-wide range must never bend
+        // a pack whose author did not ask for it.
+        for _ in 0..100 {
+            assert_eq!(random_pitch_ratio(false, 0.1, &mut rand::rng()), 1.0);
+        }
+        assert_eq!(random_pitch_ratio(true, 0.0, &mut rand::rng()), 1.0, "a zero range disables the effect");
+    }
+
+    #[test]
+    fn random_pitch_stays_within_the_configured_range() {
+        for _ in 0..1000 {
+            let ratio = random_pitch_ratio(true, 0.05, &mut rand::rng());
+            assert!((0.95..=1.05).contains(&ratio), "ratio {ratio} escaped +/-5%");
+        }
+    }
+
+    #[test]
+    fn an_oversized_random_pitch_range_is_clamped() {
+        // A hand-edited config must not be able to push playback to a rate
+        // that no longer sounds like the pack (or to zero/negative speed).
+        for _ in 0..1000 {
+            let ratio = random_pitch_ratio(true, 5.0, &mut rand::rng());
+            assert!(
+                (1.0 - MAX_RANDOM_PITCH_RANGE..=1.0 + MAX_RANDOM_PITCH_RANGE).contains(&ratio),
+                "ratio {ratio} escaped the clamp"
+            );
+        }
+    }
+
+    /// A key with three timing sets, each a distinct down/up pair, so the
+    /// returned start times say which set played.
+    fn varied_key() -> HashMap<String, KeyTimings> {
+        HashMap::from([
+            (
+                "KeyA".to_string(),
+                vec![
+                    vec![[0.0, 10.0], [10.0, 20.0]],
+                    vec![[100.0, 110.0], [110.0, 120.0]],
+                    vec![[200.0, 210.0], [210.0, 220.0]]
+                ],
+            ),
+        ])
+    }
+
+    #[test]
+    fn round_robin_walks_every_variation_in_order() {
+        let map = varied_key();
+        let mut last = HashMap::new();
+        let starts: Vec<f32> = (0..4)
+            .map(|_| lookup_timing(&map, &mut last, &mut rand::rng(), VariationMode::RoundRobin, "KeyA", true).unwrap().0)
+            .collect();
+        assert_eq!(starts, vec![0.0, 100.0, 200.0, 0.0]);
+    }
+
+    #[test]
+    fn random_variations_never_repeat_back_to_back() {
+        let map = varied_key();
+        let mut last = HashMap::new();
+        let mut previous = None;
+        let mut seen = std::collections::HashSet::new();
+        for _ in 0..300 {
+            let (start, _) = lookup_timing(&map, &mut last, &mut rand::rng(), VariationMode::Random, "KeyA", true).unwrap();
+            assert_ne!(Some(start), previous, "the same variation played twice in a row");
+            previous = Some(start);
+            seen.insert(start as u32);
+        }
+        assert_eq!(seen.len(), 3, "every variation gets played");
+    }
+
+    #[test]
+    fn a_keyup_plays_from_the_set_its_keydown_picked() {
+        let map = varied_key();
+        let mut last = HashMap::new();
+        for mode in [VariationMode::RoundRobin, VariationMode::Random] {
+            for _ in 0..20 {
+                let (down, _) = lookup_timing(&map, &mut last, &mut rand::rng(), mode, "KeyA", true).unwrap();
+                let (up, _) = lookup_timing(&map, &mut last, &mut rand::rng(), mode, "KeyA", false).unwrap();
+                assert_eq!(up, down + 10.0, "keyup came from a different recording");
+            }
+        }
+    }
+
+    #[test]
+    fn a_key_without_variations_plays_as_before() {
+        let map = HashMap::from([
+            ("KeyA".to_string(), vec![vec![[0.0, 10.0], [10.0, 20.0]]]),
+            ("Space".to_string(), vec![vec![[50.0, 90.0]]]),
+        ]);
+        let mut last = HashMap::new();
+        let mode = VariationMode::Random;
+
+        assert_eq!(lookup_timing(&map, &mut last, &mut rand::rng(), mode, "KeyA", true), Some((0.0, 10.0)));
+        assert_eq!(lookup_timing(&map, &mut last, &mut rand::rng(), mode, "KeyA", true), Some((0.0, 10.0)));
+        assert_eq!(lookup_timing(&map, &mut last, &mut rand::rng(), mode, "KeyA", false), Some((10.0, 20.0)));
+        assert_eq!(lookup_timing(&map, &mut last, &mut rand::rng(), mode, "Space", true), Some((50.0, 90.0)));
+        assert_eq!(lookup_timing(&map, &mut last, &mut rand::rng(), mode, "Space", false), None, "keydown-only");
+        assert_eq!(lookup_timing(&map, &mut last, &mut rand::rng(), mode, "KeyZ", true), None);
+    }
+
+    /// An engine on the capture output, with a one-key keyboard pack and a
+    /// one-button mouse pack: each plays 100 ms of a constant 0.5 on press.
+    /// Everything runs at 1 kHz, so one frame is one millisecond.
+    fn capture_engine() -> (EngineState, CaptureHandle) {
+        let (output, capture) = CaptureOutput::new(1000);
+        let mut state = EngineState::with_output(Box::new(output), &AppConfig::default());
+
+        for (kind, id, code) in [
+            (PackKind::Keyboard, "keyboard/test", "KeyA"),
+            (PackKind::Mouse, "mouse/test", "MouseLeft"),
+        ] {
+            let pack = test_support::prepared_pack(id, vec![0.5; 200], 1000, &[(code, vec![vec![[0.0, 100.0]]])]);
+            install_pack(&mut state, kind, pack);
+        }
+        (state, capture)
+    }
+
+    /// Left-channel sample of output frame `frame`.
+    fn left(samples: &[f32], frame: usize) -> f32 {
+        samples[frame * 2]
+    }
+
+    #[test]
+    fn a_keystroke_renders_its_segment_at_the_configured_volume() {
+        let (mut state, capture) = capture_engine();
+        state.handle_key_event("KeyA", true);
+        let out = capture.render(150);
+
+        assert_eq!(left(&out, 0), 0.0, "the segment fades in from silence");
+        assert_eq!(left(&out, 50), 0.5);
+        assert_eq!(out[101], 0.5, "mono reaches both channels");
+        assert!(out[200..].iter().all(|s| *s == 0.0), "nothing past the 100 ms segment");
+    }
+
+    #[test]
+    fn volume_commands_scale_what_is_rendered() {
+        let (mut state, capture) = capture_engine();
+        let (event_tx, _event_rx) = unbounded();
+
+        handle_command(&mut state, &event_tx, AudioCommand::SetVolume(0.5));
+        handle_command(&mut state, &event_tx, AudioCommand::SetMouseVolume(0.0));
+        state.handle_key_event("KeyA", true);
+        state.handle_mouse_event("MouseLeft", true);
+
+        assert_eq!(left(&capture.render(60), 50), 0.25, "only the keyboard voice, at half volume");
+    }
+
+    #[test]
+    fn stereo_panning_places_a_key_by_its_position() {
+        let (mut state, capture) = capture_engine();
+        let (event_tx, _event_rx) = unbounded();
+
+        state.handle_key_event("KeyA", true);
+        // Rendered to the end, so the panned presses below do not overlap it.
+        let centred = capture.render(150);
+        assert_eq!(centred[2 * 50], centred[2 * 50 + 1], "off by default");
+        state.handle_key_event("KeyA", false);
+
+        handle_command(&mut state, &event_tx, AudioCommand::SetStereoPanning(true));
+        handle_command(&mut state, &event_tx, AudioCommand::SetStereoWidth(1.0));
+        state.handle_key_event("KeyA", true);
+        state.handle_mouse_event("MouseLeft", true);
+        let panned = capture.render(150);
+
+        // KeyA sits left of centre; the mouse voice adds 0.5 to both sides.
+        assert_eq!(left(&panned, 50), 1.0, "the near channel keeps full level");
+        assert!(panned[2 * 50 + 1] < 1.0, "the far channel is quieter");
+    }
+
+    #[test]
+    fn key_overrides_apply_only_to_their_pack() {
+        let (mut state, capture) = capture_engine();
+        let (event_tx, _event_rx) = unbounded();
+        state.keyboard_pack_id = "keyboard/loud".to_string();
+
+        let mut config = AppConfig::default();
+        config.set_key_override("keyboard/loud", "KeyA", KeyOverride { gain: 0.5, muted: false });
+        config.set_key_override("keyboard/other", "KeyA", KeyOverride { gain: 1.0, muted: true });
+        handle_command(&mut state, &event_tx, AudioCommand::SetKeyOverrides(config.key_overrides.clone()));
+
+        state.handle_key_event("KeyA", true);
+        assert_eq!(left(&capture.render(60), 50), 0.25, "half the usual level");
+        state.handle_key_event("KeyA", false);
+        capture.render(100);
+
+        state.keyboard_pack_id = "keyboard/other".to_string();
+        state.handle_key_event("KeyA", true);
+        assert!(capture.render(60).iter().all(|s| *s == 0.0), "muted in the other pack");
+    }
+
+    #[test]
+    fn a_key_muted_while_held_sounds_again_on_its_next_press() {
+        let (mut state, capture) = capture_engine();
+        let (event_tx, _event_rx) = unbounded();
+
+        state.handle_key_event("KeyA", true);
+        let mut config = AppConfig::default();
+        config.set_key_override("", "KeyA", KeyOverride { gain: 1.0, muted: true });
+        handle_command(&mut state, &event_tx, AudioCommand::SetKeyOverrides(config.key_overrides));
+        state.handle_key_event("KeyA", false);
+        capture.render(200);
+
+        handle_command(&mut state, &event_tx, AudioCommand::SetKeyOverrides(HashMap::new()));
+        state.handle_key_event("KeyA", true);
+        assert_eq!(left(&capture.render(60), 50), 0.5, "the release was still recorded");
+    }
+
+    #[test]
+    fn an_unusable_key_gain_plays_the_key_unchanged() {
+        assert_eq!(key_gain(f32::NAN), 1.0);
+        assert_eq!(key_gain(5.0), MAX_KEY_GAIN);
+        assert_eq!(key_gain(-1.0), 0.0);
+    }
+
     #[test]
     fn set_sound_enabled_command_moves_engine_state() {
         // Regression guard for the mute bug: the UI writing config alone left
-        // the engine's cached flag untouched and sound kept playing. These
-        // asserts pin the command -> state edge that the UI must drive.
-        //
-        // Applies the same assignments `handle_command` performs; the full
-        // `handle_command` needs an `EngineState`, which needs a real audio
-        // device and so cannot be built in a headless test.
-        let mut sound_enabled = true;
-        let mut keyboard_sound_enabled = true;
-        let mut mouse_sound_enabled = true;
-
+        // the engine's cached flag untouched and sound kept playing. This
+        // drives the real `handle_command` and listens to the output, so the
+        // command -> silence edge the UI relies on is pinned end to end.
         for command in [
             AudioCommand::SetSoundEnabled(false),
             AudioCommand::SetKeyboardSoundEnabled(false),
             AudioCommand::SetMouseSoundEnabled(false),
         ] {
-            match command {
-                AudioCommand::SetSoundEnabled(v) => {
-                    sound_enabled = v;
-                }
-                AudioCommand::SetKeyboardSoundEnabled(v) => {
-                    keyboard_sound_enabled = v;
-                }
-                AudioCommand::SetMouseSoundEnabled(v) => {
-                    mouse_sound_enabled = v;
-                }
-                _ => unreachable!("only mute commands are exercised here"),
+            let (mut state, capture) = capture_engine();
+            let (event_tx, _event_rx) = unbounded();
+            let keyboard_muted = !matches!(command, AudioCommand::SetMouseSoundEnabled(_));
+            let mouse_muted = !matches!(command, AudioCommand::SetKeyboardSoundEnabled(_));
+
+            handle_command(&mut state, &event_tx, command);
+            state.handle_key_event("KeyA", true);
+            let keyboard_out = capture.render(100);
+            state.handle_mouse_event("MouseLeft", true);
+            let mouse_out = capture.render(100);
+
+            assert_eq!(keyboard_out.iter().all(|s| *s == 0.0), keyboard_muted);
+            assert_eq!(mouse_out.iter().all(|s| *s == 0.0), mouse_muted);
+        }
+    }
+
+    #[test]
+    fn a_device_switch_keeps_keys_playing_on_the_new_output() {
+        let (mut state, capture) = capture_engine();
+        let (event_tx, event_rx) = unbounded();
+
+        handle_command(&mut state, &event_tx, AudioCommand::SwitchDevice(Some("other".to_string())));
+        assert!(
+            matches!(event_rx.try_recv(), Ok(UiEvent::DeviceSwitched(Ok(label))) if label == "other")
+        );
+
+        state.handle_key_event("KeyA", true);
+        assert_eq!(left(&capture.render(60), 50), 0.5, "the new mixer has the pack's gain");
+    }
+
+    #[test]
+    fn the_effects_chain_shapes_the_output_and_follows_a_device_switch() {
+        let (mut state, capture) = capture_engine();
+        let (event_tx, _event_rx) = unbounded();
+
+        // The test pack is a constant level, which a bass cut all but removes.
+        let bass_cut = EffectsSettings { enabled: true, eq_low_db: -12.0, ..Default::default() };
+        handle_command(&mut state, &event_tx, AudioCommand::SetEffects(bass_cut));
+        state.handle_key_event("KeyA", true);
+        assert!(left(&capture.render(100), 90).abs() < 0.2);
+        state.handle_key_event("KeyA", false);
+
+        handle_command(&mut state, &event_tx, AudioCommand::SwitchDevice(Some("other".to_string())));
+        state.handle_key_event("KeyA", true);
+        assert!(left(&capture.render(100), 90).abs() < 0.2, "the new mixer keeps the chain");
+        state.handle_key_event("KeyA", false);
+
+        handle_command(&mut state, &event_tx, AudioCommand::SetEffects(EffectsSettings { enabled: false, ..bass_cut }));
+        state.handle_key_event("KeyA", true);
+        assert_eq!(left(&capture.render(100), 90), 0.5, "disabled is the dry sound");
+    }
+
+    #[test]
+    fn streams_play_in_the_same_mix_and_follow_a_device_switch() {
+        let dir = test_support::ScratchDir::new("engine-stream");
+        let path = dir.join("loop.wav");
+        let spec = hound::WavSpec {
+            channels: 1,
+            sample_rate: 1000,
+            bits_per_sample: 16,
+            sample_format: hound::SampleFormat::Int,
+        };
+        let mut writer = hound::WavWriter::create(&path, spec).expect("wav");
+        for _ in 0..500 {
+            writer.write_sample(i16::MAX / 2).expect("sample");
+        }
+        writer.finalize().expect("finalize");
+
+        let (mut state, capture) = capture_engine();
+        let (event_tx, _event_rx) = unbounded();
+        let id = "ambiance:rain".to_string();
+        let switch = || AudioCommand::SwitchDevice(Some("other".to_string()));
+
+        handle_command(&mut state, &event_tx, AudioCommand::PlayStream {
+            id: id.clone(),
+            bus: Bus::Ambiance,
+            path: path.to_string_lossy().into_owned(),
+            gain: 1.0,
+        });
+        handle_command(&mut state, &event_tx, AudioCommand::SetBusGain(Bus::Ambiance, 0.5));
+        assert!((left(&capture.render(200), 199) - 0.25).abs() < 1e-3);
+
+        handle_command(&mut state, &event_tx, switch());
+        assert!(
+            (left(&capture.render(200), 199) - 0.25).abs() < 1e-3,
+            "reopened on the new mixer at the same level"
+        );
+
+        handle_command(&mut state, &event_tx, AudioCommand::SetBusMuted(Bus::Ambiance, true));
+        assert_eq!(left(&capture.render(200), 199), 0.0);
+        state.handle_key_event("KeyA", true);
+        assert_eq!(left(&capture.render(60), 50), 0.5, "muting ambiance leaves the keys alone");
+
+        handle_command(&mut state, &event_tx, AudioCommand::StopStream(id));
+        handle_command(&mut state, &event_tx, AudioCommand::SetBusMuted(Bus::Ambiance, false));
+        handle_command(&mut state, &event_tx, switch());
+        assert!(capture.render(200).iter().all(|s| *s == 0.0), "a stopped stream stays stopped");
+
+    }
+
+    #[test]
+    fn a_self_choking_pack_ends_the_keydown_tail_on_keyup() {
+        for self_choke in [false, true] {
+            let (mut state, capture) = capture_engine();
+            state.key_map.insert("KeyA".to_string(), vec![vec![[0.0, 100.0], [100.0, 200.0]]]);
+            state.keyboard_options.self_choke = self_choke;
+
+            state.handle_key_event("KeyA", true);
+            capture.render(20);
+            state.handle_key_event("KeyA", false);
+            let out = capture.render(40);
+
+            let expected = if self_choke { 0.5 } else { 1.0 };
+            assert_eq!(left(&out, 30), expected, "self_choke: {}", self_choke);
+        }
+    }
+
+    #[test]
+    fn pack_choke_groups_come_before_the_users() {
+        let options = SoundpackOptions {
+            choke_groups: vec![vec!["Space".to_string(), "Enter".to_string()]],
+            ..Default::default()
+        };
+        let user = vec![vec!["Enter".to_string(), "KeyA".to_string()]];
+
+        assert_eq!(voice_choke(&options, false, &user, "Enter"), Some(Choke::Group(0)));
+        assert_eq!(voice_choke(&options, false, &user, "KeyA"), Some(Choke::Group(1)));
+        assert_eq!(voice_choke(&options, false, &user, "KeyB"), None);
+        assert_eq!(voice_choke(&options, true, &user, "KeyB"), Some(Choke::SameKey));
+    }
+
+    #[test]
+    fn repeats_are_told_apart_from_presses_on_the_wire() {
+        assert_eq!(parse_input_event("KeyA"), Some(("KeyA".to_string(), KeyAction::Down)));
+        assert_eq!(parse_input_event("UP:KeyA"), Some(("KeyA".to_string(), KeyAction::Up)));
+        assert_eq!(parse_input_event("REPEAT:KeyA"), Some(("KeyA".to_string(), KeyAction::Repeat)));
+        assert_eq!(parse_input_event(""), None);
+    }
+
+    #[test]
+    fn each_repeat_policy_plays_its_own_sound() {
+        // (policy, pack has a repeat segment, level a repeat plays at)
+        for (policy, has_segment, expected) in [
+            (KeyRepeat::Silent, true, 0.0),
+            (KeyRepeat::Replay, true, 0.25),
+            (KeyRepeat::Segment, true, 0.5),
+            (KeyRepeat::Segment, false, 0.25),
+        ] {
+            let (mut state, capture) = capture_engine();
+            let (event_tx, _event_rx) = unbounded();
+            handle_command(&mut state, &event_tx, AudioCommand::SetKeyRepeat(policy));
+            handle_command(&mut state, &event_tx, AudioCommand::SetRepeatGain(0.5));
+            if has_segment {
+                state.key_repeats.insert("KeyA".to_string(), (100.0, 200.0));
             }
+
+            state.handle_key_event("KeyA", true);
+            capture.render(150);
+            state.handle_key_repeat("KeyA");
+            let out = capture.render(60);
+
+            assert_eq!(left(&out, 50), expected, "{:?}, segment: {}", policy, has_segment);
         }
+    }
+
+    #[test]
+    fn a_repeat_is_not_a_press() {
+        let (mut state, capture) = capture_engine();
+        let (event_tx, _event_rx) = unbounded();
+        handle_command(&mut state, &event_tx, AudioCommand::SetKeyRepeat(KeyRepeat::Replay));
+
+        state.handle_key_repeat("KeyA");
+        assert!(capture.render(60).iter().all(|s| *s == 0.0), "no repeat without a held key");
+
+        // Repeats leave the held state alone, so the release still sounds
+        // and the next press is not swallowed by the debounce.
+        state.handle_key_event("KeyA", true);
+        state.handle_key_repeat("KeyA");
+        state.handle_key_event("KeyA", false);
+        capture.render(150);
+        state.handle_key_event("KeyA", true);
+        assert_eq!(left(&capture.render(60), 50), 0.5);
+    }
+
+    #[test]
+    fn a_key_missing_from_the_pack_borrows_a_mapped_keys_sound() {
+        let (mut state, capture) = capture_engine();
+        let (event_tx, _event_rx) = unbounded();
+
+        state.handle_key_event("KeyZ", true);
+        assert_eq!(left(&capture.render(60), 50), 0.5, "a letter falls back to KeyA");
+        state.handle_key_event("KeyZ", false);
+        capture.render(100);
+
+        let off = KeyFallback { family: false, alpha: false, default_key: None };
+        handle_command(&mut state, &event_tx, AudioCommand::SetKeyFallback(off));
+        state.handle_key_event("F13", true);
+        assert!(capture.render(60).iter().all(|s| *s == 0.0), "no chain, no sound");
+    }
+
+    #[test]
+    fn boosted_keys_that_would_clip_are_limited_and_reported() {
+        let (mut state, capture) = capture_engine();
+        let (event_tx, _event_rx) = unbounded();
+
+        handle_command(&mut state, &event_tx, AudioCommand::SetVolume(2.0));
+        handle_command(&mut state, &event_tx, AudioCommand::SetMouseVolume(2.0));
+        state.handle_key_event("KeyA", true);
+        state.handle_mouse_event("MouseLeft", true);
+        let out = capture.render(60);
+
+        assert_eq!(left(&out, 50), 1.0, "2.0 held at full scale");
+        assert_eq!(state.limit_rx.try_recv(), Ok(true));
+    }
+
+    /// A keyboard pack at `rate` whose KeyA plays 100 ms of `level`,
+    /// as the loader would hand it over.
+    fn prepared_pack(level: f32, rate: u32, device_rate: Option<u32>) -> PreparedPack {
+        let samples = vec![level; (rate as usize) / 5];
+        let mut pack = test_support::prepared_pack("keyboard/new", samples, rate, &[("KeyA", vec![vec![[0.0, 100.0]]])]);
+        pack.name = "New".to_string();
+        pack.device_rate = device_rate;
+        pack
+    }
+
+    #[test]
+    fn the_old_pack_plays_until_a_finished_load_is_swapped_in() {
+        let (mut state, capture) = capture_engine();
+        let (event_tx, event_rx) = unbounded();
+        let token = state.loader.supersede(PackKind::Keyboard);
+
+        // Requested, still decoding: keys keep the old pack.
+        state.handle_key_event("KeyA", true);
+        assert_eq!(left(&capture.render(60), 50), 0.5);
+        state.handle_key_event("KeyA", false);
+        capture.render(100);
+
+        let loaded = LoadedPack {
+            kind: PackKind::Keyboard,
+            token,
+            result: Ok(prepared_pack(0.25, 1000, Some(1000))),
+        };
+        handle_loaded_pack(&mut state, &event_tx, loaded);
+        assert!(
+            matches!(event_rx.try_recv(), Ok(UiEvent::PackLoaded { is_keyboard: true, result: Ok(name) }) if name == "New")
+        );
+        assert_eq!(state.keyboard_pack_id, "keyboard/new");
+
+        state.handle_key_event("KeyA", true);
+        assert_eq!(left(&capture.render(60), 50), 0.25, "the new pack plays from the next key");
+    }
+
+    #[test]
+    fn a_load_superseded_after_it_finished_is_dropped() {
+        let (mut state, capture) = capture_engine();
+        let (event_tx, event_rx) = unbounded();
+        let stale = state.loader.supersede(PackKind::Keyboard);
+        state.loader.supersede(PackKind::Keyboard);
+
+        let loaded = LoadedPack {
+            kind: PackKind::Keyboard,
+            token: stale,
+            result: Ok(prepared_pack(0.25, 1000, Some(1000))),
+        };
+        handle_loaded_pack(&mut state, &event_tx, loaded);
+
+        assert!(event_rx.try_recv().is_err(), "the UI only hears about the newest load");
+        state.handle_key_event("KeyA", true);
+        assert_eq!(left(&capture.render(60), 50), 0.5, "the old pack is still installed");
+    }
+
+    #[test]
+    fn a_pack_decoded_for_another_device_rate_is_resampled_on_install() {
+        let (mut state, _capture) = capture_engine();
+        let (event_tx, _event_rx) = unbounded();
+        let token = state.loader.supersede(PackKind::Keyboard);
+
+        // Decoded for a 2 kHz device that was switched away from meanwhile.
+        let loaded = LoadedPack {
+            kind: PackKind::Keyboard,
+            token,
+            result: Ok(prepared_pack(0.25, 2000, Some(2000))),
+        };
+        handle_loaded_pack(&mut state, &event_tx, loaded);
+
+        let (_, _, rate) = state.keyboard_audio.buffer_for("KeyA").expect("installed");
+        assert_eq!(*rate, 1000);
+    }
+
+    #[test]
+    fn volume_hotkeys_step_in_whole_percent_within_range() {
+        assert_eq!(step_volume(0.7, 0.1, 1.0), 0.8);
+        assert_eq!(step_volume(0.95, 0.1, 1.0), 1.0, "capped without boost");
+        assert_eq!(step_volume(1.0, 0.1, 2.0), 1.1, "boost raises the cap");
+        assert_eq!(step_volume(0.05, -0.1, 1.0), 0.0);
+
+        let mut volume = 0.5;
+        for _ in 0..3 {
+            volume = step_volume(volume, 0.1, 1.0);
+        }
+        assert_eq!(volume, 0.8, "repeated steps do not drift");
+    }
+
+    #[test]
+    fn pack_hotkeys_cycle_through_the_list_and_wrap() {
+        let packs: Vec<String> = ["keyboard/a", "keyboard/b", "keyboard/c"]
+            .iter()
+            .map(|id| id.to_string())
+            .collect();
+        assert_eq!(adjacent_pack(&packs, "keyboard/a", true).unwrap(), "keyboard/b");
+        assert_eq!(adjacent_pack(&packs, "keyboard/c", true).unwrap(), "keyboard/a");
+        assert_eq!(adjacent_pack(&packs, "keyboard/a", false).unwrap(), "keyboard/c");
+        assert_eq!(adjacent_pack(&packs, "keyboard/gone", false).unwrap(), "keyboard/a");
+        assert!(adjacent_pack(&[], "keyboard/a", true).is_none());
+    }
+
+    #[test]
+    fn ui_side_hotkeys_are_forwarded_to_the_ui() {
+        let (mut state, _capture) = capture_engine();
+        let (event_tx, event_rx) = unbounded();
 
-        assert!(!sound_enabled);
-        assert!(!keyboard_sound_enabled);
-        assert!(!mouse_sound_enabled);
-        assert!(!should_play(sound_enabled, keyboard_sound_enabled));
-        assert!(!should_play(sound_enabled, mouse_sound_enabled));
+        handle_hotkey(&mut state, &event_tx, HotkeyAction::ShowWindow);
+        assert!(matches!(event_rx.try_recv(), Ok(UiEvent::Hotkey(HotkeyAction::ShowWindow))));
     }
 }
@@ -0,0 +1,318 @@
+//! Peak limiter on the mixer's final stereo frame.
+//!
+//! With `enable_volume_boost` the keyboard bus runs at up to twice unity, and
+//! a burst of overlapping voices sums well past full scale; the device then
+//! clips it hard. The limiter holds the mix at `CEILING` instead. Frames are
+//! held back by `LOOKAHEAD_MS`, so the gain sees a peak coming and ramps down
+//! to meet it over that window rather than stepping down on the peak's own
+//! sample, which would click; it then eases back over `RELEASE_MS`. Both
+//! channels share one gain so the stereo image does not shift while it works.
+//!
+//! Only frames within the look-ahead of an overload, or in its release, are
+//! turned down. A mix that never goes over comes out bit for bit as it went
+//! in, so the limiter can stay on all the time.
+//!
+//! The look-ahead is only worth its latency while something can push the mix
+//! past full scale, so the mixer turns it on only while a bus gain is above
+//! unity. Without it, frames go straight through and an overload is met on
+//! its own frame.
+//!
+//! `process` also reports when limiting starts and stops, for the clip
+//! indicator on the volume slider. The stop is held back by `HOLD_MS` so a
+//! run of fast keystrokes reads as one saturated stretch, not a flicker.
+
+/// Highest sample level let through: full scale, where the device clips.
+const CEILING: f32 = 1.0;
+
+/// How far ahead the gain sees, and so how long its ramp down onto a peak
+/// takes. Also the latency the limiter adds to the mix.
+const LOOKAHEAD_MS: f64 = 2.0;
+const RELEASE_MS: f64 = 80.0;
+const HOLD_MS: f64 = 300.0;
+
+/// How close to unity the released gain has to get before it snaps back to
+/// exactly 1.0. An exponential approach alone stalls a hair below unity in
+/// `f32`, which would leave every later frame very slightly quieter.
+const UNITY_SNAP: f32 = 1e-4;
+
+pub(super) struct Limiter {
+    /// Frames waiting to go out, each with the most gain it can take
+    /// without going over. Filled with silence up front, so it never
+    /// grows on the audio thread.
+    delay: Vec<([f32; 2], f32)>,
+    /// Index of the oldest frame in `delay`, the next to go out.
+    next: usize,
+    /// Whether frames go through `delay` at all.
+    lookahead: bool,
+    /// What `set_lookahead` last asked for. Turning the look-ahead off waits
+    /// until `delay` holds only silence, so dropping it skips nothing.
+    wants_lookahead: bool,
+    /// The last frame taken in, with its headroom.
+    last_in: ([f32; 2], f32),
+    /// Silent frames taken in since the last one that was not.
+    silent_frames: usize,
+    gain: f32,
+    /// Fraction of the distance back to unity the gain moves per frame.
+    release: f32,
+    hold_frames: u32,
+    /// Frames since the gain was last below unity, while `limiting`.
+    frames_at_unity: u32,
+    limiting: bool,
+}
+
+impl Limiter {
+    pub(super) fn new(rate: u32, lookahead: bool) -> Self {
+        let frames_per_ms = (rate.max(1) as f64) / 1000.0;
+        Self {
+            delay: vec![([0.0; 2], 1.0); lookahead_frames(rate)],
+            next: 0,
+            lookahead,
+            wants_lookahead: lookahead,
+            last_in: ([0.0; 2], 1.0),
+            silent_frames: 0,
+            gain: 1.0,
+            release: (1.0 - (-1.0 / (RELEASE_MS * frames_per_ms)).exp()) as f32,
+            hold_frames: (HOLD_MS * frames_per_ms) as u32,
+            frames_at_unity: 0,
+            limiting: false,
+        }
+    }
+
+    /// Turns the look-ahead on at once, or off once the frames it holds
+    /// have gone quiet.
+    pub(super) fn set_lookahead(&mut self, lookahead: bool) {
+        self.wants_lookahead = lookahead;
+        if lookahead && !self.lookahead {
+            // Holding the last frame out over the new delay keeps the
+            // waveform continuous where the latency grows
+            self.delay.fill(self.last_in);
+            self.next = 0;
+            self.lookahead = true;
+        }
+    }
+
+    /// Takes one frame in and writes the frame from `LOOKAHEAD_MS` ago,
+    /// limited, back in its place; without the look-ahead, the same frame.
+    /// Returns `Some(true)` when limiting starts and `Some(false)` once it
+    /// has been idle for `HOLD_MS`.
+    pub(super) fn process(&mut self, frame: &mut [f32; 2]) -> Option<bool> {
+        if self.lookahead && !self.wants_lookahead && self.silent_frames >= self.delay.len() {
+            self.lookahead = false;
+        }
+
+        let incoming = (*frame, headroom(frame));
+        self.last_in = incoming;
+        self.silent_frames = if *frame == [0.0; 2] { self.silent_frames.saturating_add(1) } else { 0 };
+
+        let (out, out_headroom) = if self.lookahead {
+            let out = std::mem::replace(&mut self.delay[self.next], incoming);
+            self.next = (self.next + 1) % self.delay.len();
+            out
+        } else {
+            incoming
+        };
+
+        // The steepest straight ramp that still has the gain under every
+        // waiting frame's headroom by the time that frame goes out: the one
+        // leaving now is due this frame, the newest `delay.len()` from now.
+        let mut step = self.gain - out_headroom;
+        let mut lowest = out_headroom;
+        if self.lookahead {
+            for waiting in 0..self.delay.len() {
+                let (_, headroom) = self.delay[(self.next + waiting) % self.delay.len()];
+                step = step.max((self.gain - headroom) / ((waiting + 2) as f32));
+                lowest = lowest.min(headroom);
+            }
+        }
+        if step > 0.0 {
+            self.gain -= step;
+        } else if self.gain < 1.0 {
+            self.gain += (1.0 - self.gain) * self.release;
+            if 1.0 - self.gain < UNITY_SNAP {
+                self.gain = 1.0;
+            }
+            // Easing back up must not undo a ramp already under way
+            self.gain = self.gain.min(lowest);
+        }
+        // Rounding in the ramp can land a hair short of the headroom
+        self.gain = self.gain.min(out_headroom);
+
+        *frame = out;
+        if self.gain < 1.0 {
+            frame[0] *= self.gain;
+            frame[1] *= self.gain;
+        }
+
+        if self.gain < 1.0 {
+            self.frames_at_unity = 0;
+            if !self.limiting {
+                self.limiting = true;
+                return Some(true);
+            }
+        } else if self.limiting {
+            self.frames_at_unity += 1;
+            if self.frames_at_unity >= self.hold_frames {
+                self.limiting = false;
+                return Some(false);
+            }
+        }
+        None
+    }
+}
+
+/// How many frames late the limiter puts the mix out at `rate` while its
+/// look-ahead is on.
+pub(super) fn lookahead_frames(rate: u32) -> usize {
+    let frames_per_ms = (rate.max(1) as f64) / 1000.0;
+    ((LOOKAHEAD_MS * frames_per_ms).round() as usize).max(1)
+}
+
+/// The most gain `frame` can take and stay at or under `CEILING`, capped at
+/// unity since the limiter never turns anything up.
+fn headroom(frame: &[f32; 2]) -> f32 {
+    let peak = frame[0].abs().max(frame[1].abs());
+    if peak > CEILING { CEILING / peak } else { 1.0 }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const RATE: u32 = 1000;
+
+    /// Runs `frames` through, then enough silence to flush the look-ahead,
+    /// and returns what came out from the first of `frames` on.
+    fn run(limiter: &mut Limiter, frames: &[[f32; 2]]) -> Vec<[f32; 2]> {
+        let flush = vec![[0.0; 2]; limiter.delay.len()];
+        let mut out: Vec<[f32; 2]> = frames.iter().chain(&flush).map(|frame| {
+            let mut frame = *frame;
+            limiter.process(&mut frame);
+            frame
+        }).collect();
+        out.drain(..flush.len());
+        out
+    }
+
+    #[test]
+    fn a_mix_within_full_scale_passes_untouched() {
+        let mut limiter = Limiter::new(RATE, true);
+        let frames = [[0.5, -0.5], [1.0, -1.0], [0.0, 0.999]];
+        assert_eq!(run(&mut limiter, &frames), frames, "late, but bit for bit");
+        assert!(!limiter.limiting);
+    }
+
+    #[test]
+    fn an_overload_is_held_at_the_ceiling_on_both_channels_alike() {
+        let mut limiter = Limiter::new(RATE, true);
+        assert_eq!(limiter.process(&mut [2.0, 1.0]), Some(true), "reported as it is seen coming");
+        for _ in 0..limiter.delay.len() - 1 {
+            limiter.process(&mut [0.0, 0.0]);
+        }
+        let mut frame = [0.0, 0.0];
+        limiter.process(&mut frame);
+        assert!((frame[0] - 1.0).abs() < 1e-6 && (frame[1] - 0.5).abs() < 1e-6, "{:?}", frame);
+        assert!(frame[0] <= CEILING, "scaled together, so the balance holds");
+
+        for frame in run(&mut limiter, &[[1.6, -1.8]; 50]) {
+            assert!(frame.iter().all(|s| s.abs() <= CEILING), "{:?}", frame);
+        }
+    }
+
+    #[test]
+    fn a_transient_is_met_by_a_ramp_not_a_step() {
+        let rate = 48_000;
+        let mut limiter = Limiter::new(rate, true);
+        let lookahead = lookahead_frames(rate);
+        // A steady mix, then a hit four times over full scale
+        let input: Vec<[f32; 2]> = std::iter::repeat_n([0.8, 0.8], 200)
+            .chain(std::iter::repeat_n([4.0, 4.0], 200))
+            .collect();
+        let out = run(&mut limiter, &input);
+
+        let gains: Vec<f32> = out.iter().zip(&input).map(|(out, input)| out[0] / input[0]).collect();
+        assert!(out.iter().all(|frame| frame[0] <= CEILING + 1e-6));
+        assert_eq!(gains[200 - lookahead - 1], 1.0, "untouched until the hit is in view");
+        assert!((gains[200] - 0.25).abs() < 1e-6, "at the ceiling when the hit arrives");
+        let biggest_step = gains.windows(2).map(|pair| (pair[0] - pair[1]).abs()).fold(0.0, f32::max);
+        assert!(
+            biggest_step <= (0.75 / (lookahead as f32)) * 1.01,
+            "spread over the look-ahead, not taken in one frame: {}",
+            biggest_step
+        );
+    }
+
+    #[test]
+    fn the_gain_recovers_to_exactly_unity_and_reports_it_after_the_hold() {
+        let mut limiter = Limiter::new(RATE, true);
+        limiter.process(&mut [4.0, 4.0]);
+
+        let mut reports = Vec::new();
+        let mut frames = 0;
+        while frames < 5 * RATE {
+            if let Some(limiting) = limiter.process(&mut [0.0, 0.0]) {
+                reports.push((frames, limiting));
+            }
+            frames += 1;
+        }
+        assert_eq!(reports.len(), 1);
+        let (at, limiting) = reports[0];
+        assert!(!limiting);
+        assert!(at as f64 >= HOLD_MS, "not before the hold has passed");
+
+        assert_eq!(run(&mut limiter, &[[0.5, 0.5]]), [[0.5, 0.5]], "back to transparent");
+    }
+
+    #[test]
+    fn without_the_look_ahead_frames_go_straight_through() {
+        let mut limiter = Limiter::new(RATE, false);
+        let mut frame = [0.5, -0.5];
+        assert_eq!(limiter.process(&mut frame), None);
+        assert_eq!(frame, [0.5, -0.5], "no delay, and untouched");
+
+        let mut frame = [2.0, 1.0];
+        assert_eq!(limiter.process(&mut frame), Some(true));
+        assert_eq!(frame, [1.0, 0.5], "met on its own frame");
+    }
+
+    #[test]
+    fn the_look_ahead_starts_at_once_and_stops_only_once_it_holds_silence() {
+        let mut limiter = Limiter::new(RATE, false);
+        let lookahead = lookahead_frames(RATE);
+        limiter.process(&mut [0.25, 0.25]);
+
+        limiter.set_lookahead(true);
+        let out = run(&mut limiter, &[[0.5, 0.5]]);
+        assert_eq!(out, [[0.5, 0.5]]);
+        let mut frame = [0.75, 0.75];
+        limiter.process(&mut frame);
+        assert_eq!(frame, [0.0, 0.0], "delayed behind the flush");
+
+        limiter.set_lookahead(false);
+        for _ in 0..lookahead - 1 {
+            limiter.process(&mut [0.0, 0.0]);
+        }
+        let mut frame = [0.0, 0.0];
+        limiter.process(&mut frame);
+        assert_eq!(frame, [0.75, 0.75], "what it held still comes out");
+
+        let mut frame = [0.5, 0.5];
+        limiter.process(&mut frame);
+        assert_eq!(frame, [0.5, 0.5], "straight through once the delay is silent");
+    }
+
+    #[test]
+    fn turning_the_look_ahead_on_repeats_the_last_frame_rather_than_jumping() {
+        let mut limiter = Limiter::new(RATE, false);
+        let lookahead = lookahead_frames(RATE);
+        limiter.process(&mut [0.25, 0.25]);
+        limiter.set_lookahead(true);
+
+        let out: Vec<[f32; 2]> = (0..=lookahead).map(|_| {
+            let mut frame = [0.5, 0.5];
+            limiter.process(&mut frame);
+            frame
+        }).collect();
+        assert!(out[..lookahead].iter().all(|frame| *frame == [0.25, 0.25]), "{:?}", out);
+        assert_eq!(out[lookahead], [0.5, 0.5]);
+    }
+}
@@ -0,0 +1,218 @@
+//! Per-pack loudness levelling.
+//!
+//! Packs are recorded at wildly different levels - `cherrymx-blue-abs` is
+//! several times louder than `eg-oreo` - so without a per-pack gain the user's
+//! volume slider means something different for every pack they switch to.
+//! Two sources for that gain: the author's `options.recommended_volume`, which
+//! is always honoured, and (when the user opts in) a measurement taken here
+//! when the pack is decoded.
+
+/// RMS the automatic mode aims every pack at, roughly -20 dBFS. Picked near
+/// the level of the bundled packs so that turning the mode on moves a typical
+/// pack very little and only the outliers noticeably.
+const TARGET_RMS: f32 = 0.1;
+
+/// A boosted pack's loudest segment is not pushed past this, so levelling a
+/// quiet pack up never makes it clip on its own.
+const PEAK_CEILING: f32 = 0.98;
+
+/// Bounds for any per-pack gain. Keeps a near-silent or corrupt pack from
+/// being amplified into noise, and a bad `recommended_volume` from muting one.
+const MIN_PACK_GAIN: f32 = 0.25;
+const MAX_PACK_GAIN: f32 = 4.0;
+
+/// Peak and RMS of the audio a pack actually plays.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Loudness {
+    pub peak: f32,
+    pub rms: f32,
+}
+
+/// Measures the segments a pack's key map points at, `[start_ms, end_ms]`
+/// each. Measuring the whole file instead would count the silence between
+/// samples, so a pack that spaces its keys out would read as quieter than one
+/// that packs them tightly even if every keystroke is equally loud.
+///
+/// Falls back to the whole buffer when no segment lands inside it. Returns
+/// `None` for a buffer with no signal at all.
+pub fn measure(
+    samples: &[f32],
+    channels: u16,
+    sample_rate: u32,
+    segments: impl IntoIterator<Item = [f32; 2]>
+) -> Option<Loudness> {
+    let channels = channels.max(1) as usize;
+    let samples_per_ms = ((sample_rate as f32) * (channels as f32)) / 1000.0;
+
+    let mut peak = 0.0f32;
+    let mut sum_squares = 0.0f64;
+    let mut count = 0usize;
+    let mut accumulate = |range: &[f32]| {
+        for &sample in range {
+            peak = peak.max(sample.abs());
+            sum_squares += (sample as f64) * (sample as f64);
+        }
+        count += range.len();
+        count
+    };
+
+    let mut measured = 0;
+    for [start_ms, end_ms] in segments {
+        if start_ms < 0.0 || end_ms <= start_ms {
+            continue;
+        }
+        // Align to whole frames so every channel is weighted equally.
+        let start = (((start_ms * samples_per_ms) as usize) / channels) * channels;
+        let end = ((((end_ms * samples_per_ms) as usize) / channels) * channels).min(samples.len());
+        if start < end {
+            measured = accumulate(&samples[start..end]);
+        }
+    }
+
+    if measured == 0 {
+        accumulate(samples);
+    }
+
+    if count == 0 || peak <= 0.0 {
+        return None;
+    }
+
+    Some(Loudness {
+        peak,
+        rms: (sum_squares / (count as f64)).sqrt() as f32,
+    })
+}
+
+/// Folds the measurements of a multi-file pack into one. Each file counts
+/// once regardless of length, so a long ambience-style file cannot drown out
+/// the short keystroke files that make up most of what the user hears.
+pub fn combine(parts: impl IntoIterator<Item = Loudness>) -> Option<Loudness> {
+    let mut peak = 0.0f32;
+    let mut sum_squares = 0.0f32;
+    let mut count = 0usize;
+    for part in parts {
+        peak = peak.max(part.peak);
+        sum_squares += part.rms * part.rms;
+        count += 1;
+    }
+
+    if count == 0 {
+        return None;
+    }
+
+    Some(Loudness {
+        peak,
+        rms: (sum_squares / (count as f32)).sqrt(),
+    })
+}
+
+/// The gain a pack plays at, before the user's own volume.
+///
+/// With `auto_normalize` off this is the author's `recommended_volume`. With
+/// it on, the measurement replaces it - the hint exists to level packs by ear,
+/// and applying both would correct the same difference twice. A pack that
+/// could not be measured keeps its recommended volume either way.
+pub fn pack_gain(recommended_volume: f32, measured: Option<Loudness>, auto_normalize: bool) -> f32 {
+    let recommended = if recommended_volume.is_finite() && recommended_volume > 0.0 {
+        recommended_volume.clamp(MIN_PACK_GAIN, MAX_PACK_GAIN)
+    } else {
+        1.0
+    };
+
+    match measured {
+        Some(loudness) if auto_normalize && loudness.rms > 0.0 => {
+            let gain = (TARGET_RMS / loudness.rms).min(PEAK_CEILING / loudness.peak);
+            gain.clamp(MIN_PACK_GAIN, MAX_PACK_GAIN)
+        }
+        _ => recommended,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// One second of a mono sine at `amplitude`, 1 kHz sample rate so
+    /// millisecond offsets map straight onto sample indices.
+    fn sine(amplitude: f32) -> Vec<f32> {
+        (0..1000)
+            .map(|i| amplitude * ((i as f32) * 0.1).sin())
+            .collect()
+    }
+
+    #[test]
+    fn measurement_ignores_the_silence_between_segments() {
+        // A loud 100 ms keystroke followed by 900 ms of silence. Over the
+        // whole file it would read ten times quieter than it plays.
+        let mut samples = sine(0.5);
+        for s in &mut samples[100..] {
+            *s = 0.0;
+        }
+
+        let segment = measure(&samples, 1, 1000, [[0.0, 100.0]]).unwrap();
+        let whole = measure(&samples, 1, 1000, []).unwrap();
+
+        assert!(segment.rms > whole.rms * 2.0, "segment {:?} vs whole {:?}", segment, whole);
+        assert!((segment.peak - 0.5).abs() < 0.01);
+    }
+
+    #[test]
+    fn silence_cannot_be_measured() {
+        assert_eq!(measure(&[0.0; 64], 2, 1000, [[0.0, 10.0]]), None);
+        assert_eq!(measure(&[], 2, 1000, []), None);
+    }
+
+    #[test]
+    fn a_multi_file_pack_takes_the_loudest_peak_and_the_mean_power() {
+        let combined = combine([
+            Loudness { peak: 0.5, rms: 0.3 },
+            Loudness { peak: 0.9, rms: 0.4 },
+        ]).unwrap();
+
+        assert_eq!(combined.peak, 0.9);
+        assert!((combined.rms - 0.125f32.sqrt()).abs() < 1e-6);
+        assert_eq!(combine([]), None);
+    }
+
+    #[test]
+    fn recommended_volume_scales_the_pack_when_auto_is_off() {
+        let measured = measure(&sine(0.9), 1, 1000, []);
+        assert_eq!(pack_gain(0.5, measured, false), 0.5);
+        assert_eq!(pack_gain(1.0, None, false), 1.0);
+    }
+
+    #[test]
+    fn a_nonsense_recommended_volume_falls_back_to_unity_or_a_bound() {
+        assert_eq!(pack_gain(0.0, None, false), 1.0, "zero would silently mute the pack");
+        assert_eq!(pack_gain(f32::NAN, None, false), 1.0);
+        assert_eq!(pack_gain(100.0, None, false), MAX_PACK_GAIN);
+    }
+
+    #[test]
+    fn auto_mode_brings_loud_and_quiet_packs_together() {
+        let loud = measure(&sine(0.4), 1, 1000, []);
+        let quiet = measure(&sine(0.1), 1, 1000, []);
+
+        let loud_level = loud.unwrap().rms * pack_gain(1.0, loud, true);
+        let quiet_level = quiet.unwrap().rms * pack_gain(1.0, quiet, true);
+
+        assert!(
+            (loud_level - quiet_level).abs() < 0.01,
+            "levelled rms should match: {} vs {}",
+            loud_level,
+            quiet_level
+        );
+    }
+
+    #[test]
+    fn auto_mode_never_boosts_a_pack_into_clipping() {
+        // Spiky material: a single full-scale click in near silence has a
+        // tiny RMS, so the RMS target alone would ask for a huge boost.
+        let mut samples = vec![0.001f32; 1000];
+        samples[500] = 0.9;
+        let measured = measure(&samples, 1, 1000, []).unwrap();
+
+        let gain = pack_gain(1.0, Some(measured), true);
+        assert!(measured.peak * gain <= PEAK_CEILING + 1e-6, "gain {} clips the peak", gain);
+    }
+}
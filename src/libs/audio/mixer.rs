@@ -0,0 +1,1049 @@
+use crossbeam_channel::{ bounded, unbounded, Receiver, Sender };
+use rodio::Source;
+use std::sync::Arc;
+use std::time::Duration;
+
+use super::channel_mix;
+use super::ducking::Ducker;
+use super::effects::EffectChain;
+use super::limiter::Limiter;
+use crate::state::config::{ Ducking, EffectsSettings, VoiceStealing };
+
+/// Sounding voices the mixer keeps before it starts stealing the oldest.
+pub const MAX_VOICES: usize = 32;
+
+/// Slots in the voice table. Twice `MAX_VOICES` so a stolen voice can finish
+/// its ramp-down in a slot of its own while its replacement starts at once.
+const VOICE_SLOTS: usize = MAX_VOICES * 2;
+
+const FADE_IN_MS: f64 = 2.0;
+const FADE_OUT_MS: f64 = 5.0;
+const STEAL_RAMP_MS: f64 = 10.0;
+/// A choked voice ramps out faster than a stolen one: it is being replaced
+/// by a sound of the same kind, and a longer overlap is the smear chokes are
+/// there to stop.
+const CHOKE_RAMP_MS: f64 = 3.0;
+/// How fast a voice's tracked level falls between peaks, for
+/// `VoiceStealing::Quietest`.
+const LEVEL_DECAY_MS: f64 = 10.0;
+
+/// Slots for streams: ambiance loops and music, not keystrokes.
+const STREAM_SLOTS: usize = 16;
+
+/// How long a stream takes to follow a change of its level, and to fade in,
+/// out, or to a pause. Long enough that a slider drag never zippers.
+const STREAM_RAMP_MS: f64 = 20.0;
+
+/// Voices are panned and summed in stereo. Each finished frame is then
+/// spread over the device's own channels by `Mixer::output_matrix`.
+pub(super) const MIX_CHANNELS: u16 = 2;
+
+/// What a new voice cuts off when it starts, see `Voice::choke`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Choke {
+    /// Earlier voices of the same key.
+    SameKey,
+    /// Earlier voices in the same choke group.
+    Group(u32),
+}
+
+/// A named group in the one output mix, with its own gain, mute and pause.
+/// Keystrokes play on `Keyboard` and `Mouse`, so the two keep separate
+/// volumes and a pack reload only silences its own voices; `Ambiance` and
+/// `Music` carry streams.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Bus {
+    Keyboard,
+    Mouse,
+    Ambiance,
+    Music,
+}
+
+impl Bus {
+    pub const ALL: [Bus; 4] = [Bus::Keyboard, Bus::Mouse, Bus::Ambiance, Bus::Music];
+
+    pub(super) fn index(self) -> usize {
+        match self {
+            Bus::Keyboard => 0,
+            Bus::Mouse => 1,
+            Bus::Ambiance => 2,
+            Bus::Music => 3,
+        }
+    }
+}
+
+/// One keystroke's worth of audio: a frame range within a shared decoded
+/// buffer. Building one clones an `Arc`, never the samples.
+pub struct Voice {
+    pub buffer: Arc<Vec<f32>>,
+    pub channels: u16,
+    pub sample_rate: u32,
+    pub start_frame: usize,
+    pub end_frame: usize,
+    /// Playback-rate multiplier, see `random_pitch_ratio`.
+    pub speed: f32,
+    /// Stereo position, -1.0 (left) to 1.0 (right); see `pan_gains`.
+    pub pan: f32,
+    /// Level of this voice alone, on top of its bus gain - a per-key
+    /// override, 1.0 for everything else.
+    pub gain: f32,
+    pub bus: Bus,
+    /// Identifies the key or button that played it, for chokes and
+    /// `VoiceStealing::SameKeyFirst`; see `key_id`.
+    pub key: u64,
+    /// Earlier voices on the same bus this one ramps out on start.
+    pub choke: Option<Choke>,
+}
+
+/// Interleaved stereo at the mixer's rate, for as long as the stream lasts.
+pub type StreamSource = Box<dyn Iterator<Item = f32> + Send>;
+
+/// A long-running sound on a bus of its own, such as an ambiance loop.
+/// Unlike a `Voice` it is not cut from a shared buffer: the source decodes
+/// as it plays, and its level can change while it does.
+pub struct Stream {
+    /// Replaces, stops and re-levels the stream; see `key_id`.
+    pub id: u64,
+    pub bus: Bus,
+    /// Level of this stream alone, on top of its bus gain.
+    pub gain: f32,
+    pub source: StreamSource,
+}
+
+/// A stable id for key `code`, to match voices of the same key on the audio
+/// thread without comparing strings there.
+pub fn key_id(code: &str) -> u64 {
+    use std::hash::{ Hash, Hasher };
+    let mut hasher = std::collections::hash_map::DefaultHasher::new();
+    code.hash(&mut hasher);
+    hasher.finish()
+}
+
+/// Left/right gains for a voice at `pan`. A balance law rather than constant
+/// power: the near channel stays at full level and the far one falls away
+/// along a quarter cosine. A centred voice therefore plays exactly as it does
+/// with panning off, and no position is ever boosted toward clipping.
+fn pan_gains(pan: f32) -> [f32; MIX_CHANNELS as usize] {
+    let pan = if pan.is_finite() { pan.clamp(-1.0, 1.0) } else { 0.0 };
+    let far = (pan.abs() * std::f32::consts::FRAC_PI_2).cos();
+    if pan < 0.0 { [1.0, far] } else { [far, 1.0] }
+}
+
+/// Everything but keystrokes. These go over their own unbounded queue, so a
+/// mute, a pack switch's `StopBus` or a stream start is never lost to a
+/// queue full of voices.
+enum MixerCommand {
+    SetBusGain(Bus, f32),
+    StopBus(Bus),
+    SetEffects(EffectsSettings),
+    SetVoiceStealing(VoiceStealing),
+    SetBusMuted(Bus, bool),
+    SetBusPaused(Bus, bool),
+    PlayStream(Stream),
+    StopStream(u64),
+    SetStreamGain(u64, f32),
+    SetDucking(Ducking),
+}
+
+/// Engine-side handle to the mixer running on the output stream. Sends are
+/// non-blocking: the audio thread drains both queues once per frame,
+/// control commands first.
+#[derive(Clone)]
+pub struct MixerHandle {
+    voice_tx: Sender<Voice>,
+    tx: Sender<MixerCommand>,
+}
+
+impl MixerHandle {
+    /// Queues a voice. If the queue is full the audio thread has stalled,
+    /// and dropping this keystroke is better than blocking the engine on it.
+    pub fn play(&self, voice: Voice) {
+        let _ = self.voice_tx.try_send(voice);
+    }
+
+    pub fn set_bus_gain(&self, bus: Bus, gain: f32) {
+        let _ = self.tx.send(MixerCommand::SetBusGain(bus, gain));
+    }
+
+    /// Ramps every voice on `bus` out, e.g. before its pack is replaced.
+    pub fn stop_bus(&self, bus: Bus) {
+        let _ = self.tx.send(MixerCommand::StopBus(bus));
+    }
+
+    pub fn set_effects(&self, settings: EffectsSettings) {
+        let _ = self.tx.send(MixerCommand::SetEffects(settings));
+    }
+
+    pub fn set_voice_stealing(&self, policy: VoiceStealing) {
+        let _ = self.tx.send(MixerCommand::SetVoiceStealing(policy));
+    }
+
+    pub fn set_bus_muted(&self, bus: Bus, muted: bool) {
+        let _ = self.tx.send(MixerCommand::SetBusMuted(bus, muted));
+    }
+
+    /// Fades the streams on `bus` out and holds them where they are, until
+    /// the bus is unpaused.
+    pub fn set_bus_paused(&self, bus: Bus, paused: bool) {
+        let _ = self.tx.send(MixerCommand::SetBusPaused(bus, paused));
+    }
+
+    /// Starts `stream`, fading out any stream already playing under its id.
+    pub fn play_stream(&self, stream: Stream) {
+        let _ = self.tx.send(MixerCommand::PlayStream(stream));
+    }
+
+    pub fn stop_stream(&self, id: u64) {
+        let _ = self.tx.send(MixerCommand::StopStream(id));
+    }
+
+    pub fn set_stream_gain(&self, id: u64, gain: f32) {
+        let _ = self.tx.send(MixerCommand::SetStreamGain(id, gain));
+    }
+
+    pub fn set_ducking(&self, settings: Ducking) {
+        let _ = self.tx.send(MixerCommand::SetDucking(settings));
+    }
+}
+
+struct ActiveVoice {
+    voice: Voice,
+    /// Read position in source frames; fractional when the rate differs.
+    position: f64,
+    /// Source frames advanced per output frame.
+    step: f64,
+    fade_in_frames: f64,
+    fade_out_frames: f64,
+    /// Start order, so the oldest voice can be found for stealing.
+    serial: u64,
+    /// Output frames left in a steal/stop ramp, and its total length.
+    release: Option<(u32, u32)>,
+    /// `pan_gains` times the voice's own gain, worked out once at start.
+    channel_gains: [f32; MIX_CHANNELS as usize],
+    /// Peak of what the voice last contributed, falling off by `level_decay`
+    /// per frame between peaks.
+    level: f32,
+    level_decay: f32,
+}
+
+impl ActiveVoice {
+    fn new(voice: Voice, output_rate: u32, serial: u64) -> Self {
+        let rate = voice.sample_rate.max(1) as f64;
+        let length = voice.end_frame.saturating_sub(voice.start_frame) as f64;
+        // A fade never takes more than half of a very short segment, so a
+        // click-length sample still reaches full level in its middle.
+        let fade_in_frames = ((FADE_IN_MS / 1000.0) * rate).min(length / 2.0);
+        let fade_out_frames = ((FADE_OUT_MS / 1000.0) * rate).min(length / 2.0);
+        let step = (rate / (output_rate.max(1) as f64)) * (voice.speed.max(0.01) as f64);
+        let level_frames = (LEVEL_DECAY_MS / 1000.0) * (output_rate.max(1) as f64);
+
+        Self {
+            position: voice.start_frame as f64,
+            channel_gains: pan_gains(voice.pan).map(|g| g * voice.gain),
+            voice,
+            step,
+            fade_in_frames,
+            fade_out_frames,
+            serial,
+            release: None,
+            level: 0.0,
+            level_decay: (-1.0 / level_frames.max(1.0)).exp() as f32,
+        }
+    }
+
+    fn sounding(&self) -> bool {
+        self.release.is_none()
+    }
+
+    /// Whether the voice has yet to play past its fade-in. Until then
+    /// `level` says nothing about how loud it is - a voice queued in the
+    /// same drain has not rendered at all.
+    fn settling(&self) -> bool {
+        self.position - (self.voice.start_frame as f64) <= self.fade_in_frames
+    }
+
+    fn release(&mut self, frames: u32) {
+        if self.release.is_none() {
+            self.release = Some((frames, frames.max(1)));
+        }
+    }
+
+    /// Mixes one output frame into `out`. Returns `false` once the voice is
+    /// done and its slot can be reused.
+    fn mix_into(&mut self, out: &mut [f32; MIX_CHANNELS as usize], bus_gain: f32) -> bool {
+        let end = self.voice.end_frame.min(self.voice.buffer.len() / (self.voice.channels.max(1) as usize));
+        if self.position >= (end as f64) {
+            return false;
+        }
+
+        let mut gain = 1.0f64;
+        let played = self.position - (self.voice.start_frame as f64);
+        let remaining = (end as f64) - self.position;
+        if self.fade_in_frames > 0.0 && played < self.fade_in_frames {
+            gain = gain.min(played / self.fade_in_frames);
+        }
+        if self.fade_out_frames > 0.0 && remaining < self.fade_out_frames {
+            gain = gain.min(remaining / self.fade_out_frames);
+        }
+        if let Some((left, total)) = &mut self.release {
+            if *left == 0 {
+                return false;
+            }
+            gain *= (*left as f64) / (*total as f64);
+            *left -= 1;
+        }
+        let gain = (gain as f32) * bus_gain;
+
+        let channels = self.voice.channels.max(1) as usize;
+        let frame = self.position as usize;
+        let next = (frame + 1).min(end - 1);
+        let frac = (self.position - (frame as f64)) as f32;
+        let samples = self.voice.buffer.as_slice();
+        let mut peak = 0.0f32;
+        for (c, slot) in out.iter_mut().enumerate() {
+            let source_channel = c.min(channels - 1);
+            let a = samples[frame * channels + source_channel];
+            let b = samples[next * channels + source_channel];
+            let sample = (a + (b - a) * frac) * gain * self.channel_gains[c];
+            *slot += sample;
+            peak = peak.max(sample.abs());
+        }
+        self.level = peak.max(self.level * self.level_decay);
+
+        self.position += self.step;
+        true
+    }
+}
+
+struct ActiveStream {
+    stream: Stream,
+    /// The level the stream plays at right now, easing toward its target by
+    /// `ramp` of the distance each frame.
+    level: f32,
+    ramp: f32,
+    stopping: bool,
+}
+
+impl ActiveStream {
+    fn new(stream: Stream, output_rate: u32) -> Self {
+        let frames = (STREAM_RAMP_MS / 1000.0) * (output_rate.max(1) as f64);
+        Self {
+            stream,
+            level: 0.0,
+            ramp: (1.0 - (-1.0 / frames.max(1.0)).exp()) as f32,
+            stopping: false,
+        }
+    }
+
+    /// Mixes one output frame into `out` at `bus_gain`, or holds still at
+    /// silence if `paused`, and returns the peak it added. `None` once the
+    /// stream has ended or faded out after a stop, and its slot can be
+    /// reused.
+    fn mix_into(&mut self, out: &mut [f32; MIX_CHANNELS as usize], bus_gain: f32, paused: bool) -> Option<f32> {
+        let target = if self.stopping || paused { 0.0 } else { self.stream.gain * bus_gain };
+        self.level += (target - self.level) * self.ramp;
+        if (target - self.level).abs() < 1e-4 {
+            self.level = target;
+        }
+        if self.level == 0.0 && target == 0.0 {
+            // Faded all the way out: a stop is done, a pause keeps its place.
+            return (!self.stopping).then_some(0.0);
+        }
+
+        let (left, right) = (self.stream.source.next()?, self.stream.source.next()?);
+        let (left, right) = (left * self.level, right * self.level);
+        out[0] += left;
+        out[1] += right;
+        Some(left.abs().max(right.abs()))
+    }
+}
+
+/// The single long-lived `Source` every keystroke is mixed into. Runs on
+/// rodio's output thread, so after construction it never allocates, locks or
+/// spawns: voices arrive over a bounded channel, live in a fixed table, and
+/// read straight out of the shared decoded buffers by offset. Control
+/// commands are rare enough that their unbounded queue's occasional block
+/// free here does not count against that.
+///
+/// A voice whose buffer outlives its pack is the one exception - the final
+/// `Arc` drop then frees the old pack's samples here. That only happens once
+/// per pack switch, never per keystroke.
+///
+/// Streams are the other: they decode on this thread as they play, and a
+/// stopped one is dropped here. Both are rare and bounded - a handful of
+/// ambiance loops read a few frames of file at a time - and the app has a
+/// single device to keep in step rather than one stream per kind of sound.
+pub struct Mixer {
+    voice_rx: Receiver<Voice>,
+    rx: Receiver<MixerCommand>,
+    voices: Vec<Option<ActiveVoice>>,
+    streams: Vec<Option<ActiveStream>>,
+    bus_gains: [f32; Bus::ALL.len()],
+    bus_muted: [bool; Bus::ALL.len()],
+    bus_paused: [bool; Bus::ALL.len()],
+    stealing: VoiceStealing,
+    output_rate: u32,
+    next_serial: u64,
+    frame: [f32; MIX_CHANNELS as usize],
+    /// EQ, dampening and reverb on the summed stereo mix, before it is
+    /// spread over the device's channels.
+    effects: EffectChain,
+    /// Turns the ambiance down under typing, or the keys under loud ambiance.
+    ducker: Ducker,
+    /// Peak the ambiance bus added to the last frame, the ducker's cue.
+    ambiance_level: f32,
+    /// Last stage before the device: keeps the mix from clipping.
+    limiter: Limiter,
+    /// Where limiter start/stop is reported, if anyone listens.
+    limit_tx: Option<Sender<bool>>,
+    /// One row per device channel: how much of the stereo mix it plays. A
+    /// stereo device gets the mix as is; a mono one the average of both
+    /// sides; a surround one the mix on its front pair, so a centred mono
+    /// pack sounds from in front rather than from every speaker.
+    output_matrix: Vec<[f32; MIX_CHANNELS as usize]>,
+    channel: usize,
+}
+
+impl Mixer {
+    /// A mixer with every bus unmuted; the stream buses start at full gain.
+    pub fn new(
+        output_rate: u32,
+        output_channels: u16,
+        keyboard_gain: f32,
+        mouse_gain: f32
+    ) -> (Self, MixerHandle) {
+        let (voice_tx, voice_rx) = bounded(VOICE_SLOTS * 4);
+        let (tx, rx) = unbounded();
+        let mut voices = Vec::with_capacity(VOICE_SLOTS);
+        voices.resize_with(VOICE_SLOTS, || None);
+        let mut streams = Vec::with_capacity(STREAM_SLOTS);
+        streams.resize_with(STREAM_SLOTS, || None);
+        let output_layout = channel_mix::layout(output_channels.max(1));
+        let output_matrix = channel_mix
+            ::mix_matrix(&channel_mix::layout(MIX_CHANNELS), &output_layout)
+            .into_iter()
+            .map(|row| [row[0], row[1]])
+            .collect();
+
+        let mixer = Self {
+            voice_rx,
+            rx,
+            voices,
+            streams,
+            bus_gains: [keyboard_gain, mouse_gain, 1.0, 1.0],
+            bus_muted: [false; Bus::ALL.len()],
+            bus_paused: [false; Bus::ALL.len()],
+            stealing: VoiceStealing::default(),
+            output_rate: output_rate.max(1),
+            next_serial: 0,
+            frame: [0.0; MIX_CHANNELS as usize],
+            effects: EffectChain::new(output_rate.max(1)),
+            ducker: Ducker::new(output_rate.max(1)),
+            ambiance_level: 0.0,
+            limiter: Limiter::new(output_rate.max(1), keyboard_gain > 1.0 || mouse_gain > 1.0),
+            limit_tx: None,
+            output_matrix,
+            channel: usize::MAX,
+        };
+        (mixer, MixerHandle { voice_tx, tx })
+    }
+
+    /// Sends `true` on `tx` when the limiter starts holding the mix down and
+    /// `false` once it lets go. Sends never block: a report that does not fit
+    /// is dropped.
+    pub fn reporting_limits(mut self, tx: Sender<bool>) -> Self {
+        self.limit_tx = Some(tx);
+        self
+    }
+
+    fn ramp_frames(&self, ms: f64) -> u32 {
+        ((ms / 1000.0) * (self.output_rate as f64)) as u32
+    }
+
+    fn handle(&mut self, command: MixerCommand) {
+        match command {
+            MixerCommand::SetBusGain(bus, gain) => {
+                self.bus_gains[bus.index()] = gain;
+                // Nothing can go over without a gain above unity somewhere,
+                // so the limiter's look-ahead latency is only paid then
+                self.limiter.set_lookahead(self.bus_gains.iter().any(|gain| *gain > 1.0));
+            }
+            MixerCommand::StopBus(bus) => {
+                let ramp = self.ramp_frames(STEAL_RAMP_MS);
+                for active in self.voices.iter_mut().flatten() {
+                    if active.voice.bus == bus {
+                        active.release(ramp);
+                    }
+                }
+            }
+            MixerCommand::SetEffects(settings) => self.effects.set(settings),
+            MixerCommand::SetVoiceStealing(policy) => {
+                self.stealing = policy;
+            }
+            MixerCommand::SetBusMuted(bus, muted) => {
+                self.bus_muted[bus.index()] = muted;
+            }
+            MixerCommand::SetBusPaused(bus, paused) => {
+                self.bus_paused[bus.index()] = paused;
+            }
+            MixerCommand::PlayStream(stream) => self.start_stream(stream),
+            MixerCommand::StopStream(id) => {
+                for active in self.streams.iter_mut().flatten() {
+                    if active.stream.id == id {
+                        active.stopping = true;
+                    }
+                }
+            }
+            MixerCommand::SetDucking(settings) => self.ducker.set(settings),
+            MixerCommand::SetStreamGain(id, gain) => {
+                for active in self.streams.iter_mut().flatten() {
+                    if active.stream.id == id && !active.stopping {
+                        active.stream.gain = gain;
+                    }
+                }
+            }
+        }
+    }
+
+    fn start_stream(&mut self, stream: Stream) {
+        for active in self.streams.iter_mut().flatten() {
+            if active.stream.id == stream.id {
+                active.stopping = true;
+            }
+        }
+        // With every slot taken, a stream still fading out gives way early.
+        // Past that the new stream is dropped: the app never starts anywhere
+        // near `STREAM_SLOTS` at once.
+        let slot = self.streams
+            .iter()
+            .position(|s| s.is_none())
+            .or_else(|| self.streams.iter().position(|s| s.as_ref().is_some_and(|s| s.stopping)));
+        if let Some(slot) = slot {
+            self.streams[slot] = Some(ActiveStream::new(stream, self.output_rate));
+        }
+    }
+
+    /// What a bus plays at after its gain and mute.
+    fn bus_level(&self, bus: Bus) -> f32 {
+        if self.bus_muted[bus.index()] { 0.0 } else { self.bus_gains[bus.index()] }
+    }
+
+    fn start(&mut self, voice: Voice) {
+        if voice.bus == Bus::Keyboard {
+            self.ducker.key_pressed();
+        }
+        if let Some(choke) = voice.choke {
+            let ramp = self.ramp_frames(CHOKE_RAMP_MS);
+            for active in self.voices.iter_mut().flatten() {
+                let choked = match choke {
+                    Choke::SameKey => active.voice.key == voice.key,
+                    Choke::Group(group) => active.voice.choke == Some(Choke::Group(group)),
+                };
+                if choked && active.voice.bus == voice.bus {
+                    active.release(ramp);
+                }
+            }
+        }
+
+        // Past the voice limit a sounding voice ramps out rather than being
+        // cut, which would click. `stealing` picks which one.
+        let sounding = self.voices
+            .iter()
+            .flatten()
+            .filter(|v| v.sounding())
+            .count();
+        if sounding >= MAX_VOICES {
+            let ramp = self.ramp_frames(STEAL_RAMP_MS);
+            let candidates = self.voices.iter_mut().flatten().filter(|v| v.sounding());
+            let victim = match self.stealing {
+                VoiceStealing::Oldest => candidates.min_by_key(|v| v.serial),
+                // A settling voice would always look quietest, and stealing it
+                // would cut the keystroke that just started.
+                VoiceStealing::Quietest =>
+                    candidates.min_by(|a, b| {
+                        a.settling()
+                            .cmp(&b.settling())
+                            .then(a.level.total_cmp(&b.level))
+                            .then(a.serial.cmp(&b.serial))
+                    }),
+                VoiceStealing::SameKeyFirst =>
+                    candidates.min_by_key(|v| (v.voice.key != voice.key || v.voice.bus != voice.bus, v.serial)),
+            };
+            if let Some(victim) = victim {
+                victim.release(ramp);
+            }
+        }
+
+        // Normally a free slot exists. If every slot is still ramping, the
+        // ramp closest to silence is cut short instead.
+        let slot = match self.voices.iter().position(|v| v.is_none()) {
+            Some(free) => free,
+            None =>
+                self.voices
+                    .iter()
+                    .enumerate()
+                    .min_by_key(|(_, v)| v.as_ref().and_then(|v| v.release).map_or(u32::MAX, |(left, _)| left))
+                    .map(|(i, _)| i)
+                    .unwrap_or(0),
+        };
+
+        let serial = self.next_serial;
+        self.next_serial += 1;
+        self.voices[slot] = Some(ActiveVoice::new(voice, self.output_rate, serial));
+    }
+
+    fn render_frame(&mut self) {
+        // Control first: a `StopBus` sent before a pack's first keystroke
+        // must not catch that keystroke in the same drain.
+        while let Ok(command) = self.rx.try_recv() {
+            self.handle(command);
+        }
+        while let Ok(voice) = self.voice_rx.try_recv() {
+            self.start(voice);
+        }
+
+        self.frame = [0.0; MIX_CHANNELS as usize];
+        let duck = self.ducker.process(self.ambiance_level);
+        let levels = Bus::ALL.map(|bus| {
+            let level = self.bus_level(bus);
+            if self.ducker.ducks(bus) { level * duck } else { level }
+        });
+        for slot in self.voices.iter_mut() {
+            if let Some(active) = slot {
+                let bus_gain = levels[active.voice.bus.index()];
+                if !active.mix_into(&mut self.frame, bus_gain) {
+                    *slot = None;
+                }
+            }
+        }
+        self.effects.process(&mut self.frame);
+        // Streams join after the effects chain, which is there to shape the
+        // keystrokes, and before the limiter, which guards the whole mix.
+        self.ambiance_level = 0.0;
+        for slot in self.streams.iter_mut() {
+            if let Some(active) = slot {
+                let bus = active.stream.bus;
+                match active.mix_into(&mut self.frame, levels[bus.index()], self.bus_paused[bus.index()]) {
+                    Some(peak) if bus == Bus::Ambiance => {
+                        self.ambiance_level += peak;
+                    }
+                    Some(_) => {}
+                    None => {
+                        *slot = None;
+                    }
+                }
+            }
+        }
+        let limiting = self.limiter.process(&mut self.frame);
+        if let (Some(limiting), Some(tx)) = (limiting, &self.limit_tx) {
+            let _ = tx.try_send(limiting);
+        }
+    }
+
+    #[cfg(test)]
+    fn active_voices(&self) -> usize {
+        self.voices.iter().flatten().count()
+    }
+
+    #[cfg(test)]
+    fn active_streams(&self) -> usize {
+        self.streams.iter().flatten().count()
+    }
+}
+
+impl Iterator for Mixer {
+    type Item = f32;
+
+    fn next(&mut self) -> Option<f32> {
+        if self.channel >= self.output_matrix.len() {
+            self.render_frame();
+            self.channel = 0;
+        }
+        let [left, right] = self.output_matrix[self.channel];
+        let sample = left * self.frame[0] + right * self.frame[1];
+        self.channel += 1;
+        // Never ends: silence between keystrokes is still output.
+        Some(sample)
+    }
+}
+
+impl Source for Mixer {
+    fn current_frame_len(&self) -> Option<usize> {
+        None
+    }
+
+    fn channels(&self) -> u16 {
+        self.output_matrix.len() as u16
+    }
+
+    fn sample_rate(&self) -> u32 {
+        self.output_rate
+    }
+
+    fn total_duration(&self) -> Option<Duration> {
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn voice(buffer: &Arc<Vec<f32>>, frames: usize, bus: Bus) -> Voice {
+        Voice {
+            buffer: buffer.clone(),
+            channels: 1,
+            sample_rate: 1000,
+            start_frame: 0,
+            end_frame: frames,
+            speed: 1.0,
+            pan: 0.0,
+            gain: 1.0,
+            bus,
+            key: 0,
+            choke: None,
+        }
+    }
+
+    fn pull_frames(mixer: &mut Mixer, frames: usize) -> Vec<f32> {
+        mixer.take(frames * 2).collect::<Vec<_>>()
+    }
+
+    #[test]
+    fn a_voice_plays_its_segment_once_then_frees_its_slot() {
+        let (mut mixer, handle) = Mixer::new(1000, 2, 1.0, 1.0);
+        let buffer = Arc::new(vec![0.5; 100]);
+        handle.play(voice(&buffer, 100, Bus::Keyboard));
+
+        let out = pull_frames(&mut mixer, 200);
+        // Mono is copied to both output channels.
+        assert_eq!(out[100], out[101]);
+        assert!((out[100] - 0.5).abs() < 1e-6, "full level mid-segment, got {}", out[100]);
+        assert!(out[0].abs() < 1e-6, "the segment fades in from silence");
+        assert!(out[200..].iter().all(|s| *s == 0.0), "nothing plays past the segment");
+        assert_eq!(mixer.active_voices(), 0);
+    }
+
+    #[test]
+    fn voices_read_the_shared_buffer_without_copying_it() {
+        let (mut mixer, handle) = Mixer::new(1000, 2, 1.0, 1.0);
+        let buffer = Arc::new(vec![0.25; 100]);
+        handle.play(voice(&buffer, 100, Bus::Keyboard));
+        handle.play(voice(&buffer, 100, Bus::Keyboard));
+        pull_frames(&mut mixer, 1);
+
+        assert_eq!(Arc::strong_count(&buffer), 3, "each voice holds the same allocation");
+        pull_frames(&mut mixer, 200);
+        assert_eq!(Arc::strong_count(&buffer), 1, "finished voices let go of it");
+    }
+
+    #[test]
+    fn control_commands_get_through_a_full_voice_queue() {
+        let (mut mixer, handle) = Mixer::new(1000, 2, 1.0, 1.0);
+        let buffer = Arc::new(vec![0.5; 100]);
+        // More keystrokes than the queue holds, then a mute
+        for _ in 0..=VOICE_SLOTS * 4 {
+            handle.play(voice(&buffer, 100, Bus::Keyboard));
+        }
+        handle.set_bus_muted(Bus::Keyboard, true);
+
+        assert!(pull_frames(&mut mixer, 50).iter().all(|s| *s == 0.0), "the mute was not dropped");
+    }
+
+    #[test]
+    fn a_boosted_pile_up_is_limited_instead_of_clipping() {
+        let (tx, rx) = bounded(4);
+        let (mixer, handle) = Mixer::new(1000, 2, 2.0, 1.0);
+        let mut mixer = mixer.reporting_limits(tx);
+        let buffer = Arc::new(vec![0.5; 100]);
+        for _ in 0..3 {
+            handle.play(voice(&buffer, 100, Bus::Keyboard));
+        }
+
+        let out = pull_frames(&mut mixer, 100);
+        assert!(out.iter().all(|s| s.abs() <= 1.0), "three voices at double volume would reach 3.0");
+        assert!((out[100] - 1.0).abs() < 1e-6, "held at full scale");
+        assert_eq!(rx.try_recv(), Ok(true));
+    }
+
+    #[test]
+    fn the_limiter_look_ahead_is_only_paid_while_a_bus_is_boosted() {
+        let (mut mixer, handle) = Mixer::new(1000, 2, 1.0, 1.0);
+        let buffer = Arc::new(vec![0.5; 100]);
+        handle.play(voice(&buffer, 100, Bus::Keyboard));
+        let unity = pull_frames(&mut mixer, 200);
+
+        handle.set_bus_gain(Bus::Keyboard, 2.0);
+        handle.play(voice(&buffer, 100, Bus::Keyboard));
+        let boosted = pull_frames(&mut mixer, 200);
+
+        let last_sound = |out: &[f32]| out.iter().rposition(|s| *s != 0.0).unwrap() / 2;
+        let lookahead = super::super::limiter::lookahead_frames(1000);
+        assert_eq!(last_sound(&boosted), last_sound(&unity) + lookahead);
+    }
+
+    #[test]
+    fn overlapping_voices_sum() {
+        let (mut mixer, handle) = Mixer::new(1000, 2, 1.0, 1.0);
+        let buffer = Arc::new(vec![0.25; 100]);
+        handle.play(voice(&buffer, 100, Bus::Keyboard));
+        handle.play(voice(&buffer, 100, Bus::Mouse));
+
+        let out = pull_frames(&mut mixer, 50);
+        assert!((out[98] - 0.5).abs() < 1e-6);
+    }
+
+    #[test]
+    fn bus_gain_scales_only_its_own_voices() {
+        let (mut mixer, handle) = Mixer::new(1000, 2, 1.0, 1.0);
+        let buffer = Arc::new(vec![0.5; 100]);
+        handle.set_bus_gain(Bus::Mouse, 0.0);
+        handle.play(voice(&buffer, 100, Bus::Keyboard));
+        handle.play(voice(&buffer, 100, Bus::Mouse));
+
+        let out = pull_frames(&mut mixer, 50);
+        assert!((out[98] - 0.5).abs() < 1e-6, "muted mouse must not add to the keyboard voice");
+    }
+
+    fn stream(id: u64, bus: Bus, source: impl Iterator<Item = f32> + Send + 'static) -> Stream {
+        Stream { id, bus, gain: 1.0, source: Box::new(source) }
+    }
+
+    #[test]
+    fn a_stream_fades_in_and_follows_its_bus_gain_and_mute() {
+        let (mut mixer, handle) = Mixer::new(1000, 2, 1.0, 1.0);
+        handle.play_stream(stream(1, Bus::Ambiance, std::iter::repeat(0.5)));
+
+        let out = pull_frames(&mut mixer, 200);
+        assert!(out[0] < 0.05, "starts from near silence, got {}", out[0]);
+        assert!((out[2 * 199] - 0.5).abs() < 1e-3);
+
+        handle.set_bus_gain(Bus::Ambiance, 0.5);
+        handle.set_bus_gain(Bus::Keyboard, 0.0);
+        assert!((pull_frames(&mut mixer, 200)[2 * 199] - 0.25).abs() < 1e-3, "only its own bus gain applies");
+
+        handle.set_bus_muted(Bus::Ambiance, true);
+        assert_eq!(pull_frames(&mut mixer, 200)[2 * 199], 0.0);
+    }
+
+    #[test]
+    fn a_paused_bus_holds_its_streams_in_place() {
+        let (mut mixer, handle) = Mixer::new(1000, 2, 1.0, 1.0);
+        // Long enough for a fade out and back in, and for nowhere near as
+        // long as the pause below.
+        handle.play_stream(stream(1, Bus::Music, std::iter::repeat_n(0.5, 2 * 600)));
+        pull_frames(&mut mixer, 100);
+
+        handle.set_bus_paused(Bus::Music, true);
+        let paused = pull_frames(&mut mixer, 2000);
+        assert!(paused[2 * 300..].iter().all(|s| *s == 0.0));
+        assert_eq!(mixer.active_streams(), 1);
+
+        handle.set_bus_paused(Bus::Music, false);
+        assert!(pull_frames(&mut mixer, 100)[2 * 99] > 0.4, "picks up where it left off");
+    }
+
+    #[test]
+    fn a_stopped_stream_fades_out_then_frees_its_slot() {
+        let (mut mixer, handle) = Mixer::new(1000, 2, 1.0, 1.0);
+        handle.play_stream(stream(1, Bus::Ambiance, std::iter::repeat(0.5)));
+        handle.play_stream(stream(2, Bus::Ambiance, std::iter::repeat(0.25)));
+        pull_frames(&mut mixer, 200);
+
+        handle.stop_stream(1);
+        let out = pull_frames(&mut mixer, 300);
+        assert!(out[0] > 0.7, "no cut: the stopped stream is still fading");
+        assert!((out[2 * 299] - 0.25).abs() < 1e-3);
+        assert_eq!(mixer.active_streams(), 1);
+
+        handle.play_stream(stream(2, Bus::Ambiance, std::iter::repeat(0.5)));
+        pull_frames(&mut mixer, 300);
+        assert_eq!(mixer.active_streams(), 1, "replaying an id replaces its stream");
+    }
+
+    #[test]
+    fn typing_ducks_a_stream_on_the_ambiance_bus() {
+        let (mut mixer, handle) = Mixer::new(1000, 2, 0.0, 1.0);
+        handle.set_ducking(Ducking {
+            mode: crate::state::config::DuckingMode::Ambiance,
+            amount_db: 20.0,
+            attack_ms: 10.0,
+            release_ms: 100.0,
+        });
+        handle.play_stream(stream(1, Bus::Ambiance, std::iter::repeat(0.5)));
+        assert!((pull_frames(&mut mixer, 200)[2 * 199] - 0.5).abs() < 1e-3);
+
+        // A silent keystroke is still a keystroke.
+        let buffer = Arc::new(vec![0.5; 100]);
+        handle.play(voice(&buffer, 100, Bus::Keyboard));
+        // Read just before the keystroke's hold runs out.
+        let ducked = pull_frames(&mut mixer, 115)[2 * 114];
+        assert!((ducked - 0.05).abs() < 5e-3, "20 dB down, got {}", ducked);
+        assert!((pull_frames(&mut mixer, 2000)[2 * 1999] - 0.5).abs() < 1e-3, "and back up after");
+    }
+
+    #[test]
+    fn past_the_voice_limit_the_oldest_ramps_out_instead_of_cutting() {
+        let (mut mixer, handle) = Mixer::new(1000, 2, 1.0, 1.0);
+        let buffer = Arc::new(vec![0.01; 1000]);
+        for _ in 0..MAX_VOICES {
+            handle.play(voice(&buffer, 1000, Bus::Keyboard));
+        }
+        pull_frames(&mut mixer, 1);
+        handle.play(voice(&buffer, 1000, Bus::Keyboard));
+        pull_frames(&mut mixer, 1);
+
+        assert_eq!(mixer.active_voices(), MAX_VOICES + 1, "the stolen voice is still ramping");
+        pull_frames(&mut mixer, 20);
+        assert_eq!(mixer.active_voices(), MAX_VOICES, "and gone once the ramp is over");
+    }
+
+    /// Keys of the voices currently ramping out.
+    fn released_keys(mixer: &Mixer) -> Vec<u64> {
+        let mut keys: Vec<u64> = mixer.voices
+            .iter()
+            .flatten()
+            .filter(|v| !v.sounding())
+            .map(|v| v.voice.key)
+            .collect();
+        keys.sort();
+        keys
+    }
+
+    /// `MAX_VOICES` voices with keys 0, 1, 2 ..., then one more for `key`.
+    fn overflow(policy: VoiceStealing, levels: impl Fn(u64) -> f32, key: u64) -> Mixer {
+        let (mut mixer, handle) = Mixer::new(1000, 2, 1.0, 1.0);
+        handle.set_voice_stealing(policy);
+        for k in 0..MAX_VOICES as u64 {
+            let buffer = Arc::new(vec![levels(k); 1000]);
+            handle.play(Voice { key: k, ..voice(&buffer, 1000, Bus::Keyboard) });
+        }
+        pull_frames(&mut mixer, 5);
+        let buffer = Arc::new(vec![0.01; 1000]);
+        handle.play(Voice { key, ..voice(&buffer, 1000, Bus::Keyboard) });
+        pull_frames(&mut mixer, 1);
+        mixer
+    }
+
+    #[test]
+    fn each_stealing_policy_picks_its_own_victim() {
+        let quiet_seven = |k: u64| if k == 7 { 0.001 } else { 0.01 };
+        assert_eq!(released_keys(&overflow(VoiceStealing::Oldest, quiet_seven, 5)), vec![0]);
+        assert_eq!(released_keys(&overflow(VoiceStealing::Quietest, quiet_seven, 5)), vec![7]);
+        assert_eq!(released_keys(&overflow(VoiceStealing::SameKeyFirst, quiet_seven, 5)), vec![5]);
+        assert_eq!(
+            released_keys(&overflow(VoiceStealing::SameKeyFirst, quiet_seven, 99)),
+            vec![0],
+            "no earlier voice of the key: the oldest goes"
+        );
+    }
+
+    #[test]
+    fn quietest_never_steals_a_voice_that_has_not_played_yet() {
+        let (mut mixer, handle) = Mixer::new(1000, 2, 1.0, 1.0);
+        handle.set_voice_stealing(VoiceStealing::Quietest);
+        let level = |k: u64| match k {
+            7 => 0.001,
+            8 => 0.002,
+            _ => 0.01,
+        };
+        for k in 0..MAX_VOICES as u64 {
+            let buffer = Arc::new(vec![level(k); 1000]);
+            handle.play(Voice { key: k, ..voice(&buffer, 1000, Bus::Keyboard) });
+        }
+        pull_frames(&mut mixer, 5);
+
+        // Two keystrokes past the limit, drained together
+        let buffer = Arc::new(vec![0.01; 1000]);
+        handle.play(Voice { key: 100, ..voice(&buffer, 1000, Bus::Keyboard) });
+        handle.play(Voice { key: 101, ..voice(&buffer, 1000, Bus::Keyboard) });
+        pull_frames(&mut mixer, 1);
+
+        assert_eq!(released_keys(&mixer), vec![7, 8], "the quietest settled voices go");
+    }
+
+    #[test]
+    fn a_self_choking_key_cuts_only_its_own_earlier_sound() {
+        let (mut mixer, handle) = Mixer::new(1000, 2, 1.0, 1.0);
+        let buffer = Arc::new(vec![0.1; 1000]);
+        let key = |key: u64, bus: Bus| Voice { key, choke: Some(Choke::SameKey), ..voice(&buffer, 1000, bus) };
+        handle.play(key(1, Bus::Keyboard));
+        handle.play(key(2, Bus::Keyboard));
+        handle.play(key(1, Bus::Mouse));
+        pull_frames(&mut mixer, 5);
+
+        handle.play(key(1, Bus::Keyboard));
+        pull_frames(&mut mixer, 1);
+        assert_eq!(released_keys(&mixer), vec![1]);
+        pull_frames(&mut mixer, 5);
+        assert_eq!(mixer.active_voices(), 4 - 1, "gone after a short ramp");
+    }
+
+    #[test]
+    fn a_choke_group_cuts_its_members_and_nothing_else() {
+        let (mut mixer, handle) = Mixer::new(1000, 2, 1.0, 1.0);
+        let buffer = Arc::new(vec![0.1; 1000]);
+        let member = |key: u64, choke: Option<Choke>| Voice { key, choke, ..voice(&buffer, 1000, Bus::Keyboard) };
+        handle.play(member(1, Some(Choke::Group(0))));
+        handle.play(member(2, Some(Choke::Group(1))));
+        handle.play(member(3, None));
+        pull_frames(&mut mixer, 5);
+
+        handle.play(member(4, Some(Choke::Group(0))));
+        pull_frames(&mut mixer, 1);
+        assert_eq!(released_keys(&mixer), vec![1]);
+    }
+
+    #[test]
+    fn a_panned_voice_keeps_its_near_channel_and_fades_the_far_one() {
+        assert_eq!(pan_gains(0.0), [1.0, 1.0], "centre is untouched");
+        assert_eq!(pan_gains(-1.0)[0], 1.0);
+        assert!(pan_gains(-1.0)[1].abs() < 1e-6, "hard left silences the right");
+        assert!((pan_gains(0.5)[0] - std::f32::consts::FRAC_1_SQRT_2).abs() < 1e-6);
+        assert_eq!(pan_gains(f32::NAN), [1.0, 1.0]);
+
+        let (mut mixer, handle) = Mixer::new(1000, 2, 1.0, 1.0);
+        let buffer = Arc::new(vec![0.5; 100]);
+        handle.play(Voice { pan: 1.0, ..voice(&buffer, 100, Bus::Keyboard) });
+
+        let out = pull_frames(&mut mixer, 50);
+        assert!(out[2 * 40].abs() < 1e-6, "left is silent");
+        assert!((out[2 * 40 + 1] - 0.5).abs() < 1e-6, "right at full level");
+    }
+
+    #[test]
+    fn a_different_source_rate_is_stepped_not_played_at_the_wrong_speed() {
+        // 100 frames at 1 kHz last 100 ms, i.e. 200 frames at 2 kHz.
+        let (mut mixer, handle) = Mixer::new(2000, 2, 1.0, 1.0);
+        let buffer = Arc::new(vec![0.5; 100]);
+        handle.play(voice(&buffer, 100, Bus::Keyboard));
+
+        let out = pull_frames(&mut mixer, 300);
+        assert!(out[2 * 190].abs() > 0.0, "still playing near the end of 100 ms");
+        assert!(out[2 * 200..].iter().all(|s| *s == 0.0));
+    }
+
+    #[test]
+    fn a_mono_pack_plays_from_the_front_pair_of_a_surround_device() {
+        for channels in [6, 8] {
+            let (mut mixer, handle) = Mixer::new(1000, channels, 1.0, 1.0);
+            let buffer = Arc::new(vec![0.5; 100]);
+            handle.play(voice(&buffer, 100, Bus::Keyboard));
+
+            let out: Vec<f32> = mixer.by_ref().take(60 * (channels as usize)).collect();
+            let frame = &out[50 * (channels as usize)..51 * (channels as usize)];
+            assert_eq!(&frame[..2], &[0.5, 0.5], "{} channels", channels);
+            assert!(frame[2..].iter().all(|s| *s == 0.0), "centre, LFE and surrounds stay quiet");
+        }
+    }
+
+    #[test]
+    fn a_voice_panned_right_still_sounds_on_a_mono_device() {
+        let (mut mixer, handle) = Mixer::new(1000, 1, 1.0, 1.0);
+        let buffer = Arc::new(vec![0.5; 100]);
+        handle.play(Voice { pan: 1.0, ..voice(&buffer, 100, Bus::Keyboard) });
+
+        let out: Vec<f32> = mixer.by_ref().take(60).collect();
+        assert!(out[50] > 0.2, "the right channel must not be dropped: {}", out[50]);
+    }
+}
@@ -2,7 +2,20 @@ mod audio_context;
 pub mod soundpack_loader;
 pub mod resampler;
 pub mod engine;
+mod channel_mix;
+mod ducking;
+mod effects;
+mod limiter;
+mod loudness;
+mod mixer;
+mod output;
+mod pack_loader;
+mod pcm_cache;
+pub mod offline_render;
+#[cfg(test)]
+pub(crate) mod test_support;
 
 pub use audio_context::AudioContext;
 pub use soundpack_loader::{ load_keyboard_soundpack, load_mouse_soundpack };
 pub use engine::{ spawn_engine, ui_event_receiver, AudioCommand, UiEvent };
+pub use mixer::Bus;
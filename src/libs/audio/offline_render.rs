@@ -0,0 +1,361 @@
+//! `mechvibes-dx render <soundpack> <script> <out.wav>`: plays a timed list of
+//! key events through a soundpack with no output device and writes the result
+//! to a WAV file - for pack demos, and for checking how a pack sounds in CI.
+//!
+//! The script is one event per line, `<time_ms> <event>`, where the event is
+//! the same `"KeyA"` / `"UP:KeyA"` / `"REPEAT:KeyA"` wire format the input
+//! listeners send.
+//! Blank lines and lines starting with `#` are ignored:
+//!
+//! ```text
+//! # press and release A, then Space
+//! 0    KeyA
+//! 90   UP:KeyA
+//! 200  Space
+//! 280  UP:Space
+//! ```
+//!
+//! Events go through the real engine - the same timing lookup, fades and
+//! voice limit as live typing - on a `CaptureOutput` instead of a device.
+//! `--effects` runs them through the EQ, dampening and reverb saved on
+//! Customize as well, so a chain can be judged on a repeatable render.
+//!
+//! A render is deterministic: pitch jitter is off unless `--random-pitch` is
+//! given, and it and a pack's random variations are drawn from an RNG seeded
+//! by `--seed` (0 by default), so the same script renders the same WAV.
+//!
+//! A render leaves the app's state alone: the pack is decoded afresh rather
+//! than through the PCM cache, its metadata is not recorded, and `--effects`
+//! only reads config.json. On Windows the
+//! binary is a GUI-subsystem app, so `main` attaches render mode to the
+//! console of the shell that started it.
+
+use crate::state::config::AppConfig;
+use super::engine::{ parse_input_event, EngineState, KeyAction };
+use super::output::{ CaptureHandle, CaptureOutput };
+
+/// First argument that switches the binary into render mode.
+pub const RENDER_ARG: &str = "render";
+
+const USAGE: &str =
+    "usage: mechvibes-dx render <soundpack_id> <script.txt> <output.wav> [--rate <hz>] [--random-pitch] [--seed <n>] [--effects]";
+
+const DEFAULT_RATE: u32 = 48_000;
+
+/// Audio rendered past the last event, so its sound can ring out.
+const TAIL_MS: f64 = 1000.0;
+
+struct RenderOptions {
+    soundpack_id: String,
+    script_path: String,
+    output_path: String,
+    sample_rate: u32,
+    random_pitch: bool,
+    seed: u64,
+    effects: bool,
+}
+
+fn parse_args(args: &[String]) -> Result<RenderOptions, String> {
+    let mut positional = Vec::new();
+    let mut sample_rate = DEFAULT_RATE;
+    let mut random_pitch = false;
+    let mut seed = 0;
+    let mut effects = false;
+
+    let mut iter = args.iter();
+    while let Some(arg) = iter.next() {
+        match arg.as_str() {
+            "--rate" => {
+                sample_rate = iter
+                    .next()
+                    .and_then(|v| v.parse().ok())
+                    .filter(|rate| *rate > 0)
+                    .ok_or_else(|| format!("--rate needs a sample rate in Hz\n{}", USAGE))?;
+            }
+            "--random-pitch" => {
+                random_pitch = true;
+            }
+            "--seed" => {
+                seed = iter
+                    .next()
+                    .and_then(|v| v.parse().ok())
+                    .ok_or_else(|| format!("--seed needs a whole number\n{}", USAGE))?;
+            }
+            "--effects" => {
+                effects = true;
+            }
+            flag if flag.starts_with("--") => {
+                return Err(format!("Unknown option {}\n{}", flag, USAGE));
+            }
+            value => positional.push(value.to_string()),
+        }
+    }
+
+    let [soundpack_id, script_path, output_path]: [String; 3] = positional
+        .try_into()
+        .map_err(|_| USAGE.to_string())?;
+
+    Ok(RenderOptions { soundpack_id, script_path, output_path, sample_rate, random_pitch, seed, effects })
+}
+
+/// Parses a render script into `(time_ms, event)` pairs, in time order.
+/// Events at the same time keep their order in the file.
+fn parse_script(script: &str) -> Result<Vec<(f64, String)>, String> {
+    let mut events = Vec::new();
+    for (index, line) in script.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+
+        let mut parts = line.split_whitespace();
+        let (Some(time), Some(event), None) = (parts.next(), parts.next(), parts.next()) else {
+            return Err(format!("Line {}: expected `<time_ms> <event>`, got `{}`", index + 1, line));
+        };
+        let time: f64 = time
+            .parse()
+            .ok()
+            .filter(|t: &f64| t.is_finite() && *t >= 0.0)
+            .ok_or_else(|| format!("Line {}: `{}` is not a time in milliseconds", index + 1, time))?;
+
+        events.push((time, event.to_string()));
+    }
+
+    events.sort_by(|a, b| a.0.total_cmp(&b.0));
+    Ok(events)
+}
+
+/// Feeds `events` to `state` at their times and returns everything the
+/// mixer produced, interleaved, through `TAIL_MS` past the last event.
+fn render(
+    state: &mut EngineState,
+    capture: &CaptureHandle,
+    sample_rate: u32,
+    is_mouse: bool,
+    events: &[(f64, String)]
+) -> Vec<f32> {
+    let frame_at = |ms: f64| ((ms / 1000.0) * (sample_rate as f64)).round() as usize;
+
+    let mut samples = Vec::new();
+    let mut rendered = 0;
+    for (time, raw) in events {
+        let target = frame_at(*time);
+        if target > rendered {
+            samples.extend(capture.render(target - rendered));
+            rendered = target;
+        }
+
+        match parse_input_event(raw) {
+            Some((code, KeyAction::Repeat)) if !is_mouse => state.handle_key_repeat(&code),
+            Some((_, KeyAction::Repeat)) | None => {}
+            Some((code, action)) => {
+                let down = action == KeyAction::Down;
+                if is_mouse {
+                    state.handle_mouse_event(&code, down);
+                } else {
+                    state.handle_key_event(&code, down);
+                }
+            }
+        }
+    }
+
+    let last = events.last().map_or(0.0, |(time, _)| *time);
+    samples.extend(capture.render(frame_at(last + TAIL_MS) - rendered));
+    samples
+}
+
+/// Writes interleaved samples as 32-bit float WAV, so a render can be
+/// compared sample-for-sample against an earlier one.
+fn write_wav(path: &str, samples: &[f32], channels: u16, sample_rate: u32) -> Result<(), String> {
+    let spec = hound::WavSpec {
+        channels,
+        sample_rate,
+        bits_per_sample: 32,
+        sample_format: hound::SampleFormat::Float,
+    };
+    let mut writer = hound::WavWriter
+        ::create(path, spec)
+        .map_err(|e| format!("Failed to create {}: {}", path, e))?;
+    for &sample in samples {
+        writer.write_sample(sample).map_err(|e| format!("Failed to write {}: {}", path, e))?;
+    }
+    writer.finalize().map_err(|e| format!("Failed to finish {}: {}", path, e))
+}
+
+/// The effects saved on Customize, read straight from config.json.
+/// `AppConfig::load` would migrate and rewrite the file, and a render must
+/// leave the app's state exactly as it found it.
+fn saved_effects() -> Result<crate::state::config::EffectsSettings, String> {
+    let path = crate::state::paths::data::config_json();
+    let contents = std::fs
+        ::read_to_string(&path)
+        .map_err(|e| format!("--effects could not read {}: {}", path.display(), e))?;
+    crate::state::config
+        ::parse_lenient(&contents)
+        .map(|config| config.effects)
+        .map_err(|e| format!("--effects could not parse {}: {}", path.display(), e))
+}
+
+/// Entry point for `mechvibes-dx render ...`; `args` are the arguments after
+/// `render` itself.
+pub fn run(args: &[String]) -> Result<(), String> {
+    let options = parse_args(args)?;
+    let script = std::fs
+        ::read_to_string(&options.script_path)
+        .map_err(|e| format!("Failed to read script {}: {}", options.script_path, e))?;
+    let events = parse_script(&script)?;
+
+    // Defaults rather than the user's config, so a render sounds the same on
+    // every machine regardless of their volume sliders.
+    let mut config = AppConfig::default();
+    if !options.random_pitch {
+        config.random_pitch_range = 0.0;
+    }
+    if options.effects {
+        config.effects = saved_effects()?;
+    }
+
+    let (output, capture) = CaptureOutput::new(options.sample_rate);
+    let mut state = EngineState::with_output(Box::new(output), &config);
+    state.seed_rng(options.seed);
+
+    let is_mouse = options.soundpack_id.starts_with("mouse/") || options.soundpack_id.starts_with("mouse\\");
+    if is_mouse {
+        super::soundpack_loader::load_mouse_pack_into_engine(&mut state, &options.soundpack_id)?;
+    } else {
+        super::soundpack_loader::load_keyboard_pack_into_engine(&mut state, &options.soundpack_id)?;
+    }
+
+    let samples = render(&mut state, &capture, options.sample_rate, is_mouse, &events);
+    write_wav(&options.output_path, &samples, 2, options.sample_rate)?;
+
+    crate::always_print!(
+        "✅ Rendered {} events ({:.1}s) to {}",
+        events.len(),
+        (samples.len() as f64) / 2.0 / (options.sample_rate as f64),
+        options.output_path
+    );
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use super::super::pack_loader::PackKind;
+    use super::super::soundpack_loader::install_pack;
+    use super::super::test_support::{ prepared_pack, ScratchDir };
+
+    fn args(list: &[&str]) -> Vec<String> {
+        list.iter().map(|s| s.to_string()).collect()
+    }
+
+    #[test]
+    fn scripts_are_parsed_in_time_order_skipping_comments() {
+        let events = parse_script("# demo\n\n100 UP:KeyA\n0 KeyA\n100 KeyB\n").unwrap();
+        assert_eq!(
+            events,
+            vec![
+                (0.0, "KeyA".to_string()),
+                (100.0, "UP:KeyA".to_string()),
+                (100.0, "KeyB".to_string())
+            ]
+        );
+    }
+
+    #[test]
+    fn a_malformed_script_line_is_reported_with_its_number() {
+        let err = parse_script("0 KeyA\nsoon KeyB\n").unwrap_err();
+        assert!(err.starts_with("Line 2:"), "{}", err);
+        assert!(parse_script("0 KeyA extra").is_err());
+        assert!(parse_script("-5 KeyA").is_err());
+    }
+
+    #[test]
+    fn arguments_need_three_paths_and_accept_options_anywhere() {
+        let options = parse_args(&args(&["--rate", "44100", "keyboard/x", "s.txt", "o.wav"])).unwrap();
+        assert_eq!(options.soundpack_id, "keyboard/x");
+        assert_eq!(options.sample_rate, 44100);
+        assert!(!options.random_pitch, "off unless asked for, so renders repeat");
+        assert_eq!(options.seed, 0);
+        assert!(!options.effects);
+
+        let options = parse_args(&args(&["keyboard/x", "--effects", "s.txt", "o.wav", "--random-pitch", "--seed", "7"])).unwrap();
+        assert!(options.effects);
+        assert!(options.random_pitch);
+        assert_eq!(options.seed, 7);
+
+        assert!(parse_args(&args(&["keyboard/x", "s.txt"])).is_err());
+        assert!(parse_args(&args(&["keyboard/x", "s.txt", "o.wav", "--rate", "0"])).is_err());
+        assert!(parse_args(&args(&["keyboard/x", "s.txt", "o.wav", "--loud"])).is_err());
+        assert!(parse_args(&args(&["keyboard/x", "s.txt", "o.wav", "--seed", "-1"])).is_err());
+    }
+
+    #[test]
+    fn events_sound_at_their_scripted_time() {
+        let (output, capture) = CaptureOutput::new(1000);
+        let mut state = EngineState::with_output(Box::new(output), &AppConfig::default());
+        let pack = prepared_pack("keyboard/test", vec![0.5; 100], 1000, &[("KeyA", vec![vec![[0.0, 50.0]]])]);
+        install_pack(&mut state, PackKind::Keyboard, pack);
+
+        let events = parse_script("300 KeyA").unwrap();
+        let out = render(&mut state, &capture, 1000, false, &events);
+
+        assert_eq!(out.len(), 2 * 1300, "runs through the tail after the last event");
+        assert!(out[..2 * 300].iter().all(|s| *s == 0.0), "silent before the keystroke");
+        assert_eq!(out[2 * 325], 0.5, "playing 25 ms in");
+        assert!(out[2 * 350..].iter().all(|s| *s == 0.0), "done after its 50 ms segment");
+    }
+
+    #[test]
+    fn effects_in_the_config_reach_the_rendered_output() {
+        let mut config = AppConfig::default();
+        config.effects.enabled = true;
+        config.effects.reverb_mix = 1.0;
+        let (output, capture) = CaptureOutput::new(8000);
+        let mut state = EngineState::with_output(Box::new(output), &config);
+        let pack = prepared_pack("keyboard/test", vec![0.5; 800], 8000, &[("KeyA", vec![vec![[0.0, 50.0]]])]);
+        install_pack(&mut state, PackKind::Keyboard, pack);
+
+        let events = parse_script("0 KeyA").unwrap();
+        let out = render(&mut state, &capture, 8000, false, &events);
+
+        assert!(out[2 * 800..].iter().any(|s| *s != 0.0), "the reverb rings on after the 50 ms segment");
+    }
+
+    #[test]
+    fn the_same_seed_renders_the_same_samples() {
+        let render_with_seed = |seed: u64| {
+            let (output, capture) = CaptureOutput::new(1000);
+            let mut state = EngineState::with_output(Box::new(output), &AppConfig::default());
+            // A ramp, so which variation plays and at what pitch shows in the output
+            let ramp: Vec<f32> = (0..300).map(|i| (i as f32) / 300.0).collect();
+            let variations = vec![vec![[0.0, 50.0]], vec![[100.0, 150.0]], vec![[200.0, 250.0]]];
+            let mut pack = prepared_pack("keyboard/test", ramp, 1000, &[("KeyA", variations)]);
+            pack.options.random_pitch = true;
+            pack.options.variation_mode = crate::state::soundpack::VariationMode::Random;
+            install_pack(&mut state, PackKind::Keyboard, pack);
+            state.seed_rng(seed);
+
+            let events = parse_script("0 KeyA\n60 UP:KeyA\n100 KeyA\n160 UP:KeyA\n200 KeyA").unwrap();
+            render(&mut state, &capture, 1000, false, &events)
+        };
+
+        assert_eq!(render_with_seed(3), render_with_seed(3));
+        assert_ne!(render_with_seed(3), render_with_seed(4), "the seed is what varies it");
+    }
+
+    #[test]
+    fn the_wav_holds_the_rendered_samples_exactly() {
+        let dir = ScratchDir::new("render");
+        let path = dir.join("out.wav");
+        let path = path.to_str().unwrap();
+        let samples = vec![0.0, 0.25, -0.5, 0.125];
+
+        write_wav(path, &samples, 2, 48_000).unwrap();
+        let mut reader = hound::WavReader::open(path).unwrap();
+        assert_eq!(reader.spec().channels, 2);
+        assert_eq!(reader.spec().sample_rate, 48_000);
+        let read: Vec<f32> = reader.samples::<f32>().map(|s| s.unwrap()).collect();
+        assert_eq!(read, samples);
+    }
+}
@@ -0,0 +1,185 @@
+use rodio::{ OutputStream, OutputStreamHandle };
+
+use crate::libs::device_manager::DeviceManager;
+use super::mixer::Mixer;
+
+/// Where the engine's mixer ends up. The engine only ever hands a `Mixer`
+/// over and asks for the rate to run it at, so everything that needs a sound
+/// card lives behind this trait and the rest of `EngineState` can be built
+/// and driven without one.
+pub(super) trait AudioOutput {
+    /// The rate the mixer should produce, if the output knows it. `None`
+    /// means unknown - see `load_audio_file` for why that is not guessed.
+    fn sample_rate(&self) -> Option<u32>;
+
+    /// The device's channel count, which the mixer renders straight into.
+    /// `None` means unknown, and the mixer stays stereo.
+    fn channels(&self) -> Option<u16>;
+
+    /// Starts playing `mixer`, replacing whatever mixer was playing before.
+    fn play(&mut self, mixer: Mixer);
+
+    /// Moves to `device_id` (`None` = system default) and returns a label for
+    /// it. On `Err` the current output must keep playing untouched.
+    fn switch_device(&mut self, device_id: Option<String>) -> Result<String, String>;
+}
+
+/// The real output: a rodio stream on a cpal device.
+pub(super) struct RodioOutput {
+    stream: OutputStream,
+    stream_handle: OutputStreamHandle,
+    device_manager: DeviceManager,
+    current_device_id: Option<String>,
+    sample_rate: Option<u32>,
+    channels: Option<u16>,
+}
+
+/// Opens a stream for `device_id` (`None` = system default). Does NOT fall
+/// back silently - callers decide what to do on `Err` (see `switch_device`,
+/// which keeps the previous device on failure, vs `RodioOutput::open`, which
+/// falls back to default since there's no previous device to keep).
+fn open_stream(
+    device_manager: &DeviceManager,
+    device_id: Option<&str>
+) -> Result<(OutputStream, OutputStreamHandle, Option<String>), String> {
+    match device_id {
+        Some(id) => {
+            match device_manager.get_output_device_by_id(id) {
+                Ok(Some(device)) => {
+                    rodio::OutputStream
+                        ::try_from_device(&device)
+                        .map(|(stream, handle)| (stream, handle, Some(id.to_string())))
+                        .map_err(|e| format!("Failed to open stream for device {}: {}", id, e))
+                }
+                Ok(None) => Err(format!("Device {} not found", id)),
+                Err(e) => Err(format!("Error accessing device {}: {}", id, e)),
+            }
+        }
+        None => {
+            rodio::OutputStream
+                ::try_default()
+                .map(|(stream, handle)| (stream, handle, None))
+                .map_err(|e| format!("Failed to open default audio output stream: {}", e))
+        }
+    }
+}
+
+impl RodioOutput {
+    /// Opens the configured device, falling back to the system default.
+    /// Panics only if there is no usable output device at all.
+    pub(super) fn open(device_id: Option<&str>) -> Self {
+        let device_manager = DeviceManager::new();
+        let (stream, stream_handle, opened_device_id) = open_stream(
+            &device_manager,
+            device_id
+        ).unwrap_or_else(|e| {
+            crate::always_eprint!("❌ [AudioEngine] {} - falling back to default", e);
+            open_stream(&device_manager, None).expect("Failed to open default audio output stream")
+        });
+        let format = device_manager.get_current_output_format();
+
+        Self {
+            stream,
+            stream_handle,
+            device_manager,
+            current_device_id: opened_device_id.or(device_id.map(str::to_string)),
+            sample_rate: format.map(|(rate, _)| rate),
+            channels: format.map(|(_, channels)| channels),
+        }
+    }
+}
+
+impl AudioOutput for RodioOutput {
+    fn sample_rate(&self) -> Option<u32> {
+        self.sample_rate
+    }
+
+    fn channels(&self) -> Option<u16> {
+        self.channels
+    }
+
+    fn play(&mut self, mixer: Mixer) {
+        if let Err(e) = self.stream_handle.play_raw(mixer) {
+            crate::always_eprint!("❌ [AudioEngine] Failed to start mixer: {}", e);
+        }
+    }
+
+    fn switch_device(&mut self, device_id: Option<String>) -> Result<String, String> {
+        let (new_stream, new_handle, opened_device_id) = open_stream(
+            &self.device_manager,
+            device_id.as_deref()
+        )?;
+
+        // Dropping the old stream takes its mixer, and every voice in it,
+        // along - only once the new one is confirmed open.
+        self.stream = new_stream;
+        self.stream_handle = new_handle;
+        let format = self.device_manager.get_current_output_format();
+        self.sample_rate = format.map(|(rate, _)| rate);
+        self.channels = format.map(|(_, channels)| channels);
+        self.current_device_id = opened_device_id.or(device_id);
+
+        Ok(self.current_device_id.clone().unwrap_or_else(|| "System Default".to_string()))
+    }
+}
+
+/// An output with no device behind it: the mixer only runs when samples are
+/// pulled through the paired `CaptureHandle`. Tests use it to assert on
+/// exactly what a keystroke produces; `offline_render` to write it to a file.
+pub(super) struct CaptureOutput {
+    sample_rate: u32,
+    channels: u16,
+    mixer: std::sync::Arc<std::sync::Mutex<Option<Mixer>>>,
+}
+
+#[derive(Clone)]
+pub(super) struct CaptureHandle {
+    mixer: std::sync::Arc<std::sync::Mutex<Option<Mixer>>>,
+}
+
+impl CaptureOutput {
+    /// A stereo capture.
+    pub(super) fn new(sample_rate: u32) -> (Self, CaptureHandle) {
+        Self::with_channels(sample_rate, 2)
+    }
+
+    pub(super) fn with_channels(sample_rate: u32, channels: u16) -> (Self, CaptureHandle) {
+        let mixer = std::sync::Arc::new(std::sync::Mutex::new(None));
+        (CaptureOutput { sample_rate, channels, mixer: mixer.clone() }, CaptureHandle { mixer })
+    }
+}
+
+impl CaptureHandle {
+    /// Runs the current mixer for `frames` frames and returns the
+    /// interleaved output. Silence if nothing has been started yet.
+    pub(super) fn render(&self, frames: usize) -> Vec<f32> {
+        use rodio::Source;
+
+        let mut slot = self.mixer.lock().unwrap();
+        match slot.as_mut() {
+            Some(mixer) => {
+                let samples = frames * (mixer.channels() as usize);
+                mixer.by_ref().take(samples).collect()
+            }
+            None => Vec::new(),
+        }
+    }
+}
+
+impl AudioOutput for CaptureOutput {
+    fn sample_rate(&self) -> Option<u32> {
+        Some(self.sample_rate)
+    }
+
+    fn channels(&self) -> Option<u16> {
+        Some(self.channels)
+    }
+
+    fn play(&mut self, mixer: Mixer) {
+        *self.mixer.lock().unwrap() = Some(mixer);
+    }
+
+    fn switch_device(&mut self, device_id: Option<String>) -> Result<String, String> {
+        Ok(device_id.unwrap_or_else(|| "Capture".to_string()))
+    }
+}
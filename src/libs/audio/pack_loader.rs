@@ -0,0 +1,241 @@
+//! Background soundpack loading.
+//!
+//! Decoding and resampling a pack takes anywhere from tens of milliseconds to
+//! a few seconds, and used to run inline on the engine thread - every
+//! keystroke typed during a load waited for it. Loads now run on one loader
+//! thread: the engine hands it a request, keeps playing the old pack, and
+//! swaps the new one in when the finished `LoadedPack` arrives on
+//! `PackLoader::completed`.
+//!
+//! Each request supersedes every earlier one of the same kind. Scrolling
+//! through the selector queues a load per pack passed over; the loader skips
+//! the ones already superseded, abandons the one in progress at its next
+//! cancellation check, and the engine drops any result that still slips
+//! through, so only the last pack picked is ever installed.
+
+use crossbeam_channel::{ unbounded, Receiver, Sender };
+use std::sync::atomic::{ AtomicU64, Ordering };
+use std::sync::Arc;
+
+use super::soundpack_loader::PreparedPack;
+
+/// Which of the engine's two pack slots a load fills.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub(super) enum PackKind {
+    Keyboard,
+    Mouse,
+}
+
+impl PackKind {
+    fn index(self) -> usize {
+        match self {
+            PackKind::Keyboard => 0,
+            PackKind::Mouse => 1,
+        }
+    }
+}
+
+/// Identifies one load request. It is cancelled as soon as a newer request
+/// for the same kind is made.
+#[derive(Clone, Debug)]
+pub(super) struct LoadToken {
+    generation: u64,
+    latest: Arc<AtomicU64>,
+}
+
+impl LoadToken {
+    /// A token nothing can supersede, for loads that run to completion
+    /// inline (`offline_render`).
+    pub(super) fn detached() -> Self {
+        Self { generation: 0, latest: Arc::new(AtomicU64::new(0)) }
+    }
+
+    pub(super) fn is_cancelled(&self) -> bool {
+        self.latest.load(Ordering::Acquire) != self.generation
+    }
+}
+
+/// One queued load.
+pub(super) struct LoadRequest {
+    pub(super) kind: PackKind,
+    pub(super) soundpack_id: String,
+    /// The engine's rate when the load was requested. The engine re-resamples
+    /// on install if its device changed in the meantime.
+    pub(super) device_rate: Option<u32>,
+    pub(super) update_cache_on_error: bool,
+    pub(super) token: LoadToken,
+}
+
+/// A finished load, sent back to the engine to install.
+pub(super) struct LoadedPack {
+    pub(super) kind: PackKind,
+    pub(super) token: LoadToken,
+    pub(super) result: Result<PreparedPack, String>,
+}
+
+/// The engine's side of the loader thread. The thread is started by the
+/// first request, so an engine that never loads in the background (tests,
+/// `offline_render`) never starts one.
+pub(super) struct PackLoader {
+    requests: Option<Sender<LoadRequest>>,
+    done_tx: Sender<LoadedPack>,
+    done_rx: Receiver<LoadedPack>,
+    latest: [Arc<AtomicU64>; 2],
+}
+
+impl PackLoader {
+    pub(super) fn new() -> Self {
+        let (done_tx, done_rx) = unbounded();
+        Self {
+            requests: None,
+            done_tx,
+            done_rx,
+            latest: [Arc::new(AtomicU64::new(0)), Arc::new(AtomicU64::new(0))],
+        }
+    }
+
+    /// Where finished loads arrive, for the engine's `select!`.
+    pub(super) fn completed(&self) -> Receiver<LoadedPack> {
+        self.done_rx.clone()
+    }
+
+    /// Returns a token for a new `kind` load, cancelling every earlier one.
+    pub(super) fn supersede(&self, kind: PackKind) -> LoadToken {
+        let latest = &self.latest[kind.index()];
+        let generation = latest.fetch_add(1, Ordering::AcqRel) + 1;
+        LoadToken { generation, latest: latest.clone() }
+    }
+
+    /// Queues a load of `soundpack_id`, superseding any earlier `kind` load.
+    pub(super) fn request(
+        &mut self,
+        kind: PackKind,
+        soundpack_id: &str,
+        device_rate: Option<u32>,
+        update_cache_on_error: bool
+    ) {
+        let request = LoadRequest {
+            kind,
+            soundpack_id: soundpack_id.to_string(),
+            device_rate,
+            update_cache_on_error,
+            token: self.supersede(kind),
+        };
+        let done_tx = self.done_tx.clone();
+        let requests = self.requests.get_or_insert_with(|| {
+            let (tx, rx) = unbounded();
+            std::thread::Builder
+                ::new()
+                .name("soundpack-loader".to_string())
+                .spawn(move || run_loader(rx, done_tx, prepare))
+                .expect("failed to spawn soundpack loader thread");
+            tx
+        });
+        // The loader only exits once this sender is dropped.
+        let _ = requests.send(request);
+    }
+}
+
+fn prepare(request: &LoadRequest) -> Result<PreparedPack, String> {
+    crate::libs::trace::time(crate::libs::trace::Point::PackLoad, &request.soundpack_id, || {
+        super::soundpack_loader::prepare_pack(
+            request.kind,
+            &request.soundpack_id,
+            request.device_rate,
+            &request.token,
+            request.update_cache_on_error,
+            true
+        )
+    })
+}
+
+/// The loader thread: runs each request that is still current and sends
+/// back its result. Requests superseded while queued are skipped unread.
+fn run_loader(
+    requests: Receiver<LoadRequest>,
+    done_tx: Sender<LoadedPack>,
+    prepare: impl Fn(&LoadRequest) -> Result<PreparedPack, String>
+) {
+    for request in requests {
+        if request.token.is_cancelled() {
+            crate::always_print!("⏭️ Skipped superseded soundpack load: {}", request.soundpack_id);
+            continue;
+        }
+        let result = prepare(&request);
+        if request.token.is_cancelled() {
+            crate::always_print!("⏭️ Abandoned superseded soundpack load: {}", request.soundpack_id);
+            continue;
+        }
+        let loaded = LoadedPack { kind: request.kind, token: request.token, result };
+        if done_tx.send(loaded).is_err() {
+            break;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Mutex;
+
+    fn request(loader: &PackLoader, kind: PackKind, soundpack_id: &str) -> LoadRequest {
+        LoadRequest {
+            kind,
+            soundpack_id: soundpack_id.to_string(),
+            device_rate: None,
+            update_cache_on_error: false,
+            token: loader.supersede(kind),
+        }
+    }
+
+    #[test]
+    fn a_newer_request_cancels_only_earlier_ones_of_its_kind() {
+        let loader = PackLoader::new();
+        let first = loader.supersede(PackKind::Keyboard);
+        let mouse = loader.supersede(PackKind::Mouse);
+        assert!(!first.is_cancelled());
+
+        let second = loader.supersede(PackKind::Keyboard);
+        assert!(first.is_cancelled());
+        assert!(!second.is_cancelled());
+        assert!(!mouse.is_cancelled(), "a keyboard load must not cancel the mouse one");
+        assert!(!LoadToken::detached().is_cancelled());
+    }
+
+    #[test]
+    fn the_loader_skips_requests_superseded_while_queued() {
+        let loader = PackLoader::new();
+        let (tx, rx) = unbounded();
+        for id in ["keyboard/a", "keyboard/b", "keyboard/c"] {
+            tx.send(request(&loader, PackKind::Keyboard, id)).unwrap();
+        }
+        tx.send(request(&loader, PackKind::Mouse, "mouse/a")).unwrap();
+        drop(tx);
+
+        let prepared = Mutex::new(Vec::new());
+        run_loader(rx, loader.done_tx.clone(), |request| {
+            prepared.lock().unwrap().push(request.soundpack_id.clone());
+            Err("not a real pack".to_string())
+        });
+
+        assert_eq!(*prepared.lock().unwrap(), vec!["keyboard/c", "mouse/a"]);
+        let kinds: Vec<PackKind> = loader.completed().try_iter().map(|loaded| loaded.kind).collect();
+        assert_eq!(kinds, vec![PackKind::Keyboard, PackKind::Mouse]);
+    }
+
+    #[test]
+    fn a_load_superseded_mid_decode_is_not_sent_back() {
+        let loader = PackLoader::new();
+        let (tx, rx) = unbounded();
+        tx.send(request(&loader, PackKind::Keyboard, "keyboard/slow")).unwrap();
+        drop(tx);
+
+        run_loader(rx, loader.done_tx.clone(), |_| {
+            // The user picks another pack while this one decodes.
+            loader.supersede(PackKind::Keyboard);
+            Err("cancelled".to_string())
+        });
+
+        assert!(loader.completed().try_recv().is_err());
+    }
+}
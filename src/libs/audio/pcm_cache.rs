@@ -0,0 +1,277 @@
+//! On-disk cache of decoded soundpack audio.
+//!
+//! Decoding a pack's OGG/MP3 and resampling it to the device rate is most of
+//! what a pack load costs, and it produces the same samples every time for
+//! the same file at the same rate. Each decoded file is kept here as one
+//! bincode entry holding the original and the resampled PCM, keyed by the
+//! file's path, modification time and size plus the rate it was resampled
+//! to. Editing a pack's audio changes its mtime and so misses the cache
+//! rather than playing stale sound.
+//!
+//! The directory is capped at `MAX_CACHE_BYTES`. A hit touches the entry's
+//! own mtime, which makes the file times an LRU order without an index to
+//! keep in sync; going over the cap removes the oldest entries first.
+//!
+//! Every failure here is a miss - a broken or missing cache only ever costs
+//! the decode it was meant to save.
+
+use serde::{ Deserialize, Serialize };
+use std::path::{ Path, PathBuf };
+use std::time::{ SystemTime, UNIX_EPOCH };
+
+/// Largest the cache directory grows before least recently used entries are
+/// evicted. A typical pack is a few MB of PCM, so this keeps dozens.
+pub(super) const MAX_CACHE_BYTES: u64 = 256 * 1024 * 1024;
+
+const ENTRY_EXTENSION: &str = "pcm";
+
+/// Bumped whenever an entry's layout changes, so entries written by an
+/// older build miss instead of failing to decode.
+const FORMAT_VERSION: u32 = 2;
+
+/// (samples, channels, sample_rate), as `soundpack_loader` decodes them.
+type Pcm = (Vec<f32>, u16, u32);
+
+/// Identifies one decode. Stored in the entry as well as hashed into its
+/// file name, so a hash collision reads as a miss.
+#[derive(Serialize, Deserialize, PartialEq, Debug)]
+struct CacheKey {
+    version: u32,
+    source: String,
+    modified_secs: u64,
+    modified_nanos: u32,
+    len: u64,
+    target_rate: Option<u32>,
+}
+
+impl CacheKey {
+    fn new(source: &Path, target_rate: Option<u32>) -> Option<Self> {
+        let metadata = std::fs::metadata(source).ok()?;
+        let modified = metadata.modified().ok()?.duration_since(UNIX_EPOCH).ok()?;
+        let source = std::fs::canonicalize(source).unwrap_or_else(|_| source.to_path_buf());
+        Some(Self {
+            version: FORMAT_VERSION,
+            source: source.to_string_lossy().into_owned(),
+            modified_secs: modified.as_secs(),
+            modified_nanos: modified.subsec_nanos(),
+            len: metadata.len(),
+            target_rate,
+        })
+    }
+}
+
+#[derive(Deserialize)]
+struct CacheEntry {
+    key: CacheKey,
+    original: Pcm,
+    /// `None` when no resampling was needed and `original` plays as is.
+    resampled: Option<Pcm>,
+}
+
+/// `CacheEntry` by reference, so storing does not copy the samples.
+#[derive(Serialize)]
+struct CacheEntryRef<'a> {
+    key: &'a CacheKey,
+    original: &'a Pcm,
+    resampled: Option<&'a Pcm>,
+}
+
+pub(super) struct PcmCache {
+    dir: PathBuf,
+    max_bytes: u64,
+}
+
+impl PcmCache {
+    /// The cache in the app data dir.
+    pub(super) fn app() -> Self {
+        Self::new(crate::state::paths::data::pcm_cache_dir(), MAX_CACHE_BYTES)
+    }
+
+    pub(super) fn new(dir: PathBuf, max_bytes: u64) -> Self {
+        Self { dir, max_bytes }
+    }
+
+    fn entry_path(&self, key: &CacheKey) -> Option<PathBuf> {
+        use sha2::{ Digest, Sha256 };
+
+        let encoded = bincode::serialize(key).ok()?;
+        let digest = Sha256::digest(&encoded);
+        let name: String = digest[..16]
+            .iter()
+            .map(|byte| format!("{:02x}", byte))
+            .collect();
+        Some(self.dir.join(name).with_extension(ENTRY_EXTENSION))
+    }
+
+    /// `(original, resampled)` for `source` at `target_rate`, if it was
+    /// decoded before and has not changed since.
+    pub(super) fn get(&self, source: &Path, target_rate: Option<u32>) -> Option<(Pcm, Pcm)> {
+        let key = CacheKey::new(source, target_rate)?;
+        let path = self.entry_path(&key)?;
+        let bytes = std::fs::read(&path).ok()?;
+        let entry: CacheEntry = bincode::deserialize(&bytes).ok()?;
+        if entry.key != key {
+            return None;
+        }
+
+        // Mark it recently used; eviction goes by this time.
+        if let Ok(file) = std::fs::File::options().write(true).open(&path) {
+            let _ = file.set_modified(SystemTime::now());
+        }
+
+        let resampled = entry.resampled.unwrap_or_else(|| entry.original.clone());
+        Some((entry.original, resampled))
+    }
+
+    /// Stores a decode of `source`, then evicts down to the size cap.
+    pub(super) fn put(&self, source: &Path, target_rate: Option<u32>, original: &Pcm, resampled: &Pcm) {
+        let Some(key) = CacheKey::new(source, target_rate) else {
+            return;
+        };
+        let Some(path) = self.entry_path(&key) else {
+            return;
+        };
+        let entry = CacheEntryRef {
+            key: &key,
+            original,
+            resampled: (resampled.2 != original.2).then_some(resampled),
+        };
+        let bytes = match bincode::serialize(&entry) {
+            Ok(bytes) => bytes,
+            Err(e) => {
+                crate::always_eprint!("⚠️ Could not encode decoded audio for the cache: {}", e);
+                return;
+            }
+        };
+
+        // Written aside and renamed into place, so a crash mid-write cannot
+        // leave a truncated entry under the real name.
+        let partial = path.with_extension("partial");
+        let written = std::fs
+            ::create_dir_all(&self.dir)
+            .and_then(|_| std::fs::write(&partial, &bytes))
+            .and_then(|_| std::fs::rename(&partial, &path));
+        if let Err(e) = written {
+            crate::always_eprint!("⚠️ Could not write the decoded audio cache: {}", e);
+            let _ = std::fs::remove_file(&partial);
+            return;
+        }
+
+        self.evict();
+    }
+
+    /// Removes least recently used entries until the cache fits its cap.
+    fn evict(&self) {
+        let Ok(entries) = std::fs::read_dir(&self.dir) else {
+            return;
+        };
+        let mut files: Vec<(SystemTime, u64, PathBuf)> = entries
+            .flatten()
+            .filter(|entry| entry.path().extension().is_some_and(|ext| ext == ENTRY_EXTENSION))
+            .filter_map(|entry| {
+                let metadata = entry.metadata().ok()?;
+                Some((metadata.modified().unwrap_or(UNIX_EPOCH), metadata.len(), entry.path()))
+            })
+            .collect();
+
+        let mut total: u64 = files
+            .iter()
+            .map(|(_, len, _)| len)
+            .sum();
+        if total <= self.max_bytes {
+            return;
+        }
+
+        files.sort_by_key(|(modified, _, _)| *modified);
+        for (_, len, path) in files {
+            if total <= self.max_bytes {
+                break;
+            }
+            if std::fs::remove_file(&path).is_ok() {
+                total -= len;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use super::super::test_support::ScratchDir;
+    use std::time::Duration;
+
+    fn source(dir: &Path, name: &str) -> PathBuf {
+        let path = dir.join(name);
+        std::fs::write(&path, name).expect("source");
+        path
+    }
+
+    fn pcm(level: f32, rate: u32) -> Pcm {
+        (vec![level; 1000], 2, rate)
+    }
+
+    fn set_age(cache: &PcmCache, source: &Path, rate: Option<u32>, secs_ago: u64) {
+        let key = CacheKey::new(source, rate).unwrap();
+        let file = std::fs::File::options().write(true).open(cache.entry_path(&key).unwrap()).unwrap();
+        file.set_modified(SystemTime::now() - Duration::from_secs(secs_ago)).unwrap();
+    }
+
+    #[test]
+    fn a_stored_decode_comes_back_for_the_same_file_and_rate() {
+        let dir = ScratchDir::new("pcm-cache-hit");
+        let cache = PcmCache::new(dir.join("cache"), MAX_CACHE_BYTES);
+        let file = source(&dir, "a.ogg");
+
+        cache.put(&file, Some(48_000), &pcm(0.5, 44_100), &pcm(0.25, 48_000));
+        let (original, resampled) = cache.get(&file, Some(48_000)).expect("hit");
+        assert_eq!(original, pcm(0.5, 44_100));
+        assert_eq!(resampled, pcm(0.25, 48_000));
+
+        assert!(cache.get(&file, Some(44_100)).is_none(), "another device rate misses");
+    }
+
+    #[test]
+    fn an_unresampled_decode_is_stored_once_and_returned_twice() {
+        let dir = ScratchDir::new("pcm-cache-native");
+        let cache = PcmCache::new(dir.join("cache"), MAX_CACHE_BYTES);
+        let file = source(&dir, "a.ogg");
+
+        cache.put(&file, None, &pcm(0.5, 44_100), &pcm(0.5, 44_100));
+        let (original, resampled) = cache.get(&file, None).expect("hit");
+        assert_eq!(original, resampled);
+    }
+
+    #[test]
+    fn an_edited_source_file_misses() {
+        let dir = ScratchDir::new("pcm-cache-edited");
+        let cache = PcmCache::new(dir.join("cache"), MAX_CACHE_BYTES);
+        let file = source(&dir, "a.ogg");
+
+        cache.put(&file, None, &pcm(0.5, 44_100), &pcm(0.5, 44_100));
+        std::fs::write(&file, "re-exported with new audio").unwrap();
+
+        assert!(cache.get(&file, None).is_none());
+    }
+
+    #[test]
+    fn going_over_the_cap_evicts_the_least_recently_used_entry() {
+        let dir = ScratchDir::new("pcm-cache-evict");
+        let mut cache = PcmCache::new(dir.join("cache"), MAX_CACHE_BYTES);
+        let [a, b, c] = ["a.ogg", "b.ogg", "c.ogg"].map(|name| source(&dir, name));
+
+        cache.put(&a, None, &pcm(0.1, 44_100), &pcm(0.1, 44_100));
+        cache.put(&b, None, &pcm(0.2, 44_100), &pcm(0.2, 44_100));
+        set_age(&cache, &a, None, 20);
+        set_age(&cache, &b, None, 10);
+        // `a` is the older write, but using it makes `b` the one to go.
+        assert!(cache.get(&a, None).is_some());
+
+        let entry_len = std::fs::read_dir(&cache.dir).unwrap().next().unwrap().unwrap().metadata().unwrap().len();
+        cache.max_bytes = entry_len * 2 + entry_len / 2;
+        cache.put(&c, None, &pcm(0.3, 44_100), &pcm(0.3, 44_100));
+
+        assert!(cache.get(&a, None).is_some());
+        assert!(cache.get(&b, None).is_none(), "least recently used");
+        assert!(cache.get(&c, None).is_some());
+    }
+}
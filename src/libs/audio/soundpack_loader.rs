@@ -1,10 +1,16 @@
 use crate::state::paths;
 use crate::state::soundpack::SoundPack;
-use crate::state::soundpack::{ SoundpackCache, SoundpackMetadata };
+use crate::state::soundpack::{ SoundpackCache, SoundpackMetadata, SoundpackOptions, SoundpackType };
 use std::sync::Arc;
 
 use super::audio_context::AudioContext;
-use super::engine::{ AudioCommand, EngineState };
+use super::engine::{ AudioCommand, EngineState, KeyTimings, PackBuffers };
+use super::pack_loader::{ LoadToken, PackKind };
+use super::pcm_cache::PcmCache;
+use super::channel_mix;
+use symphonia::core::audio::{ AudioBuffer, AudioBufferRef, Signal };
+use symphonia::core::conv::IntoSample;
+use symphonia::core::sample::Sample;
 
 /// Determine soundpack type based on the soundpack path
 fn determine_soundpack_type(soundpack_id: &str) -> crate::state::soundpack::SoundpackType {
@@ -83,10 +89,14 @@ pub fn load_mouse_soundpack_with_cache_control(
     Ok(())
 }
 
+/// Error for a load abandoned because a newer one replaced it. The engine
+/// never shows it: superseded results are dropped before they reach the UI.
+const LOAD_SUPERSEDED: &str = "Load superseded by a newer request";
+
 /// (samples, channels, sample_rate) for a decoded/resampled audio buffer.
 type DecodedAudio = (Vec<f32>, u16, u32);
 
-/// Loads and decodes a soundpack's audio file, then resamples it to
+/// Loads and decodes one of a soundpack's audio files, then resamples it to
 /// `device_rate` if given. Returns `(original, resampled)`; `original` keeps
 /// the file's native rate so a later device switch can re-resample without
 /// re-reading from disk.
@@ -98,24 +108,46 @@ type DecodedAudio = (Vec<f32>, u16, u32);
 /// failed at startup) means skip resampling and keep the file's native rate,
 /// same as pre-resample baseline behavior - guessing a rate would risk
 /// resampling twice (once here, once again by rodio realtime).
+///
+/// With a `cache`, a file decoded before at the same rate is read back from
+/// it instead, and a fresh decode is stored for next time.
 fn load_audio_file(
     soundpack_path: &str,
-    soundpack: &SoundPack,
-    device_rate: Option<u32>
+    audio_file: &str,
+    device_rate: Option<u32>,
+    token: &LoadToken,
+    cache: Option<&PcmCache>
 ) -> Result<(DecodedAudio, DecodedAudio), String> {
-    let sound_file_path = soundpack.audio_file
-        .as_ref()
-        .map(|src| format!("{}/{}", soundpack_path, src.trim_start_matches("./")))
-        .ok_or_else(|| "No audio_file field in soundpack config".to_string())?;
+    let sound_file_path = format!("{}/{}", soundpack_path, audio_file.trim_start_matches("./"));
+    let source = std::path::Path::new(&sound_file_path);
 
-    if !std::path::Path::new(&sound_file_path).exists() {
+    if !source.exists() {
         return Err(format!("Sound file not found: {}", sound_file_path));
     }
 
+    if let Some(cached) = cache.and_then(|cache| cache.get(source, device_rate)) {
+        return Ok(cached);
+    }
+    let decoded = decode_audio_file(&sound_file_path, device_rate, token)?;
+    if let Some(cache) = cache {
+        cache.put(source, device_rate, &decoded.0, &decoded.1);
+    }
+    Ok(decoded)
+}
+
+/// The uncached half of `load_audio_file`: decode, then resample.
+fn decode_audio_file(
+    sound_file_path: &str,
+    device_rate: Option<u32>,
+    token: &LoadToken
+) -> Result<(DecodedAudio, DecodedAudio), String> {
     // Use Symphonia for audio loading instead of Rodio
-    let (samples, channels, file_rate) = load_audio_with_symphonia(&sound_file_path).map_err(
+    let (samples, channels, file_rate) = load_audio_with_symphonia(sound_file_path, token).map_err(
         |e| format!("Failed to load audio: {}", e)
     )?;
+    if token.is_cancelled() {
+        return Err(LOAD_SUPERSEDED.to_string());
+    }
 
     match device_rate {
         Some(device_rate) if device_rate != file_rate => {
@@ -146,9 +178,91 @@ fn load_audio_file(
     }
 }
 
-/// Load audio file using Symphonia for consistent duration detection
-fn load_audio_with_symphonia(file_path: &str) -> Result<(Vec<f32>, u16, u32), String> {
-    use symphonia::core::audio::{ AudioBufferRef, Signal };
+/// Decodes every audio file a pack's definitions refer to - the top-level
+/// `audio_file`, plus each key's own `audio_file` in a "multi" pack - each
+/// exactly once. Multi packs play as-is this way; they used to be rewritten
+/// into a single file on disk first, which dropped every key that did not use
+/// the most common file.
+///
+/// A multi-pack key with an empty `timing` (or an empty variation) plays its
+/// whole file, so that set in `key_mappings` is filled in here once the
+/// file's length is known.
+fn load_pack_buffers(
+    soundpack_path: &str,
+    soundpack: &SoundPack,
+    key_mappings: &mut std::collections::HashMap<String, KeyTimings>,
+    device_rate: Option<u32>,
+    token: &LoadToken,
+    cache: Option<&PcmCache>
+) -> Result<PackBuffers, String> {
+    let mut pack = PackBuffers {
+        default_file: soundpack.audio_file.clone(),
+        ..Default::default()
+    };
+
+    if soundpack.definition_method == "multi" {
+        for (key, key_def) in &soundpack.definitions {
+            if let Some(audio_file) = &key_def.audio_file {
+                pack.key_files.insert(key.clone(), audio_file.clone());
+            }
+        }
+    }
+
+    let files: std::collections::HashSet<&String> = pack.key_files
+        .values()
+        .chain(pack.default_file.iter())
+        .collect();
+    if files.is_empty() {
+        return Err("No audio_file field in soundpack config".to_string());
+    }
+
+    for file in files {
+        let ((orig_samples, orig_channels, orig_rate), (samples, channels, rate)) =
+            load_audio_file(soundpack_path, file, device_rate, token, cache)?;
+        pack.originals.insert(file.clone(), (Arc::new(orig_samples), orig_channels, orig_rate));
+        pack.buffers.insert(file.clone(), (Arc::new(samples), channels, rate));
+    }
+
+    for (key, file) in &pack.key_files {
+        let (Some(sets), Some((samples, channels, rate))) = (
+            key_mappings.get_mut(key),
+            pack.originals.get(file),
+        ) else {
+            continue;
+        };
+        let frames = samples.len() / (*channels).max(1) as usize;
+        let whole_file = [0.0, ((frames as f32) * 1000.0) / (*rate as f32)];
+        for set in sets.iter_mut().filter(|set| set.is_empty()) {
+            set.push(whole_file);
+        }
+    }
+
+    Ok(pack)
+}
+
+/// Loudness of everything a pack plays, measured per source file over the
+/// segments mapped into it (see `loudness::measure`).
+fn measure_pack_loudness(
+    pack: &PackBuffers,
+    key_map: &std::collections::HashMap<String, KeyTimings>
+) -> Option<super::loudness::Loudness> {
+    let measured = pack.originals.iter().filter_map(|(file, (samples, channels, rate))| {
+        let segments = key_map
+            .iter()
+            .filter(|(key, _)| pack.key_files.get(*key).or(pack.default_file.as_ref()) == Some(file))
+            .flat_map(|(_, sets)| sets.iter().flatten().copied());
+        super::loudness::measure(samples, *channels, *rate, segments)
+    });
+    super::loudness::combine(measured)
+}
+
+/// Load audio file using Symphonia for consistent duration detection.
+/// Stops between packets once `token` is cancelled, so a superseded load of
+/// a long file does not hold up the next one.
+fn load_audio_with_symphonia(
+    file_path: &str,
+    token: &LoadToken
+) -> Result<(Vec<f32>, u16, u32), String> {
     use symphonia::core::codecs::{ DecoderOptions, CODEC_TYPE_NULL };
     use symphonia::core::formats::FormatOptions;
     use symphonia::core::io::MediaSourceStream;
@@ -204,10 +318,13 @@ fn load_audio_with_symphonia(file_path: &str) -> Result<(Vec<f32>, u16, u32), St
     let track_id = track.id;
     let mut samples = Vec::new();
     let mut sample_rate = 44100u32;
-    let mut channels = 2u16;
+    let mut layout = Vec::new();
 
     // Decode audio packets
     loop {
+        if token.is_cancelled() {
+            return Err(LOAD_SUPERSEDED.to_string());
+        }
         let packet = match format.next_packet() {
             Ok(packet) => packet,
             Err(_) => {
@@ -221,219 +338,15 @@ fn load_audio_with_symphonia(file_path: &str) -> Result<(Vec<f32>, u16, u32), St
 
         match decoder.decode(&packet) {
             Ok(decoded) => {
-                if samples.is_empty() {
+                if layout.is_empty() {
                     // Get format info from first decoded buffer
                     sample_rate = decoded.spec().rate;
-                    channels = decoded.spec().channels.count() as u16;
-                } // Convert audio buffer to f32 samples
-                match decoded {
-                    AudioBufferRef::F32(buf) => {
-                        if channels == 1 {
-                            // Mono audio
-                            for &sample in buf.chan(0) {
-                                samples.push(sample);
-                            }
-                        } else {
-                            // Stereo audio - interleave samples correctly
-                            let left_chan = buf.chan(0);
-                            let right_chan = if buf.spec().channels.count() > 1 {
-                                buf.chan(1)
-                            } else {
-                                buf.chan(0)
-                            };
-                            for (left, right) in left_chan.iter().zip(right_chan.iter()) {
-                                samples.push(*left);
-                                samples.push(*right);
-                            }
-                        }
-                    }
-                    AudioBufferRef::S32(buf) => {
-                        if channels == 1 {
-                            for &sample in buf.chan(0) {
-                                samples.push((sample as f32) / (i32::MAX as f32));
-                            }
-                        } else {
-                            let left_chan = buf.chan(0);
-                            let right_chan = if buf.spec().channels.count() > 1 {
-                                buf.chan(1)
-                            } else {
-                                buf.chan(0)
-                            };
-                            for (left, right) in left_chan.iter().zip(right_chan.iter()) {
-                                samples.push((*left as f32) / (i32::MAX as f32));
-                                samples.push((*right as f32) / (i32::MAX as f32));
-                            }
-                        }
-                    }
-                    AudioBufferRef::S16(buf) => {
-                        if channels == 1 {
-                            for &sample in buf.chan(0) {
-                                samples.push((sample as f32) / (i16::MAX as f32));
-                            }
-                        } else {
-                            let left_chan = buf.chan(0);
-                            let right_chan = if buf.spec().channels.count() > 1 {
-                                buf.chan(1)
-                            } else {
-                                buf.chan(0)
-                            };
-                            for (left, right) in left_chan.iter().zip(right_chan.iter()) {
-                                samples.push((*left as f32) / (i16::MAX as f32));
-                                samples.push((*right as f32) / (i16::MAX as f32));
-                            }
-                        }
-                    }
-                    AudioBufferRef::U32(buf) => {
-                        if channels == 1 {
-                            for &sample in buf.chan(0) {
-                                samples.push(
-                                    ((sample as f32) - (u32::MAX as f32) / 2.0) /
-                                        ((u32::MAX as f32) / 2.0)
-                                );
-                            }
-                        } else {
-                            let left_chan = buf.chan(0);
-                            let right_chan = if buf.spec().channels.count() > 1 {
-                                buf.chan(1)
-                            } else {
-                                buf.chan(0)
-                            };
-                            for (left, right) in left_chan.iter().zip(right_chan.iter()) {
-                                samples.push(
-                                    ((*left as f32) - (u32::MAX as f32) / 2.0) /
-                                        ((u32::MAX as f32) / 2.0)
-                                );
-                                samples.push(
-                                    ((*right as f32) - (u32::MAX as f32) / 2.0) /
-                                        ((u32::MAX as f32) / 2.0)
-                                );
-                            }
-                        }
-                    }
-                    AudioBufferRef::U16(buf) => {
-                        if channels == 1 {
-                            for &sample in buf.chan(0) {
-                                samples.push(
-                                    ((sample as f32) - (u16::MAX as f32) / 2.0) /
-                                        ((u16::MAX as f32) / 2.0)
-                                );
-                            }
-                        } else {
-                            let left_chan = buf.chan(0);
-                            let right_chan = if buf.spec().channels.count() > 1 {
-                                buf.chan(1)
-                            } else {
-                                buf.chan(0)
-                            };
-                            for (left, right) in left_chan.iter().zip(right_chan.iter()) {
-                                samples.push(
-                                    ((*left as f32) - (u16::MAX as f32) / 2.0) /
-                                        ((u16::MAX as f32) / 2.0)
-                                );
-                                samples.push(
-                                    ((*right as f32) - (u16::MAX as f32) / 2.0) /
-                                        ((u16::MAX as f32) / 2.0)
-                                );
-                            }
-                        }
-                    }
-                    AudioBufferRef::U8(buf) => {
-                        if channels == 1 {
-                            for &sample in buf.chan(0) {
-                                samples.push(((sample as f32) - 128.0) / 128.0);
-                            }
-                        } else {
-                            let left_chan = buf.chan(0);
-                            let right_chan = if buf.spec().channels.count() > 1 {
-                                buf.chan(1)
-                            } else {
-                                buf.chan(0)
-                            };
-                            for (left, right) in left_chan.iter().zip(right_chan.iter()) {
-                                samples.push(((*left as f32) - 128.0) / 128.0);
-                                samples.push(((*right as f32) - 128.0) / 128.0);
-                            }
-                        }
-                    }
-                    AudioBufferRef::S8(buf) => {
-                        if channels == 1 {
-                            for &sample in buf.chan(0) {
-                                samples.push((sample as f32) / (i8::MAX as f32));
-                            }
-                        } else {
-                            let left_chan = buf.chan(0);
-                            let right_chan = if buf.spec().channels.count() > 1 {
-                                buf.chan(1)
-                            } else {
-                                buf.chan(0)
-                            };
-                            for (left, right) in left_chan.iter().zip(right_chan.iter()) {
-                                samples.push((*left as f32) / (i8::MAX as f32));
-                                samples.push((*right as f32) / (i8::MAX as f32));
-                            }
-                        }
-                    }
-                    AudioBufferRef::F64(buf) => {
-                        if channels == 1 {
-                            for &sample in buf.chan(0) {
-                                samples.push(sample as f32);
-                            }
-                        } else {
-                            let left_chan = buf.chan(0);
-                            let right_chan = if buf.spec().channels.count() > 1 {
-                                buf.chan(1)
-                            } else {
-                                buf.chan(0)
-                            };
-                            for (left, right) in left_chan.iter().zip(right_chan.iter()) {
-                                samples.push(*left as f32);
-                                samples.push(*right as f32);
-                            }
-                        }
-                    }
-                    AudioBufferRef::U24(buf) => {
-                        if channels == 1 {
-                            for &sample in buf.chan(0) {
-                                let sample_f32 = ((sample.inner() as f32) - 8388608.0) / 8388608.0; // 2^23
-                                samples.push(sample_f32);
-                            }
-                        } else {
-                            let left_chan = buf.chan(0);
-                            let right_chan = if buf.spec().channels.count() > 1 {
-                                buf.chan(1)
-                            } else {
-                                buf.chan(0)
-                            };
-                            for (left, right) in left_chan.iter().zip(right_chan.iter()) {
-                                let left_f32 = ((left.inner() as f32) - 8388608.0) / 8388608.0;
-                                let right_f32 = ((right.inner() as f32) - 8388608.0) / 8388608.0;
-                                samples.push(left_f32);
-                                samples.push(right_f32);
-                            }
-                        }
-                    }
-                    AudioBufferRef::S24(buf) => {
-                        if channels == 1 {
-                            for &sample in buf.chan(0) {
-                                let sample_f32 = (sample.inner() as f32) / 8388607.0; // 2^23 - 1
-                                samples.push(sample_f32);
-                            }
-                        } else {
-                            let left_chan = buf.chan(0);
-                            let right_chan = if buf.spec().channels.count() > 1 {
-                                buf.chan(1)
-                            } else {
-                                buf.chan(0)
-                            };
-                            for (left, right) in left_chan.iter().zip(right_chan.iter()) {
-                                let left_f32 = (left.inner() as f32) / 8388607.0;
-                                let right_f32 = (right.inner() as f32) / 8388607.0;
-                                samples.push(left_f32);
-                                samples.push(right_f32);
-                            }
-                        }
-                    }
+                    layout = channel_mix::layout_from_mask(decoded.spec().channels);
+                } else if decoded.spec().channels.count() != layout.len() {
+                    crate::always_print!("⚠️ [DEBUG] Skipping packet with a different channel count");
+                    continue;
                 }
+                interleave(&decoded, &mut samples);
             }
             Err(e) => {
                 crate::always_print!("⚠️ [DEBUG] Decode error (continuing): {}", e);
@@ -446,7 +359,41 @@ fn load_audio_with_symphonia(file_path: &str) -> Result<(Vec<f32>, u16, u32), St
         return Err("No audio data decoded".to_string());
     }
 
-    Ok((samples, channels, sample_rate))
+    // The mixer pans and mixes in stereo, so a wider recording is folded
+    // down once here rather than on every keystroke.
+    if layout.len() > 2 {
+        let stereo = channel_mix::layout(2);
+        samples = channel_mix::remix(&samples, &layout, &stereo);
+        layout = stereo;
+    }
+
+    Ok((samples, layout.len() as u16, sample_rate))
+}
+
+/// Appends one decoded packet to `out` as interleaved f32, keeping every
+/// channel. Each sample format converts through symphonia's `IntoSample`,
+/// so unsigned formats are centred and every format reaches full scale.
+fn interleave(decoded: &AudioBufferRef, out: &mut Vec<f32>) {
+    fn interleave_planes<S: Sample + IntoSample<f32>>(buf: &AudioBuffer<S>, out: &mut Vec<f32>) {
+        let planes: Vec<&[S]> = (0..buf.spec().channels.count()).map(|c| buf.chan(c)).collect();
+        out.reserve(buf.frames() * planes.len());
+        for frame in 0..buf.frames() {
+            out.extend(planes.iter().map(|plane| plane[frame].into_sample()));
+        }
+    }
+
+    match decoded {
+        AudioBufferRef::U8(buf) => interleave_planes(buf, out),
+        AudioBufferRef::U16(buf) => interleave_planes(buf, out),
+        AudioBufferRef::U24(buf) => interleave_planes(buf, out),
+        AudioBufferRef::U32(buf) => interleave_planes(buf, out),
+        AudioBufferRef::S8(buf) => interleave_planes(buf, out),
+        AudioBufferRef::S16(buf) => interleave_planes(buf, out),
+        AudioBufferRef::S24(buf) => interleave_planes(buf, out),
+        AudioBufferRef::S32(buf) => interleave_planes(buf, out),
+        AudioBufferRef::F32(buf) => interleave_planes(buf, out),
+        AudioBufferRef::F64(buf) => interleave_planes(buf, out),
+    }
 }
 
 fn create_soundpack_metadata(
@@ -517,43 +464,43 @@ fn create_soundpack_metadata(
         can_be_converted: false,
         // Error tracking - None since we successfully created metadata
         last_error: None,
+        mapped_keys: Some({
+            let mut keys: Vec<String> = soundpack.definitions.keys().cloned().collect();
+            keys.sort();
+            keys
+        }),
     })
 }
 
-fn create_key_mappings(
-    soundpack: &SoundPack,
-    _samples: &[f32]
-) -> std::collections::HashMap<String, Vec<(f64, f64)>> {
+fn create_key_mappings(soundpack: &SoundPack) -> std::collections::HashMap<String, KeyTimings> {
     let mut key_mappings = std::collections::HashMap::new(); // For keyboard soundpacks, use the definitions field for keyboard mappings
     // For mouse soundpacks, return empty key mappings
     if soundpack.soundpack_type == crate::state::soundpack::SoundpackType::Keyboard {
         for (key, key_def) in &soundpack.definitions {
-            // Convert KeyDefinition timing to Vec<(f64, f64)>
-            let converted_mappings: Vec<(f64, f64)> = key_def.timing
-                .iter()
-                .map(|pair| (pair[0] as f64, pair[1] as f64))
-                .collect();
-            key_mappings.insert(key.clone(), converted_mappings);
+            key_mappings.insert(key.clone(), key_def.timing_sets().cloned().collect());
         }
     }
 
     key_mappings
 }
 
-fn create_mouse_mappings(
-    soundpack: &SoundPack,
-    _samples: &[f32]
-) -> std::collections::HashMap<String, Vec<(f64, f64)>> {
+/// Each keyboard key's `repeat` segment, for the keys that have one.
+fn create_repeat_mappings(soundpack: &SoundPack) -> std::collections::HashMap<String, (f32, f32)> {
+    if soundpack.soundpack_type != crate::state::soundpack::SoundpackType::Keyboard {
+        return std::collections::HashMap::new();
+    }
+    soundpack.definitions
+        .iter()
+        .filter_map(|(key, key_def)| key_def.repeat.map(|[start, end]| (key.clone(), (start, end))))
+        .collect()
+}
+
+fn create_mouse_mappings(soundpack: &SoundPack) -> std::collections::HashMap<String, KeyTimings> {
     let mut mouse_mappings = std::collections::HashMap::new(); // For mouse soundpacks, use the definitions field directly
     if soundpack.soundpack_type == crate::state::soundpack::SoundpackType::Mouse {
         // This is a mouse soundpack, use definitions field for mouse mappings
         for (button, key_def) in &soundpack.definitions {
-            // Convert KeyDefinition timing to Vec<(f64, f64)>
-            let converted_mappings: Vec<(f64, f64)> = key_def.timing
-                .iter()
-                .map(|pair| (pair[0] as f64, pair[1] as f64))
-                .collect();
-            mouse_mappings.insert(button.clone(), converted_mappings);
+            mouse_mappings.insert(button.clone(), key_def.timing_sets().cloned().collect());
         }
     } else {
         // This is a keyboard soundpack, create default mouse mappings from keyboard sounds
@@ -576,11 +523,7 @@ fn create_mouse_mappings(
         ];
         for (mouse_button, keyboard_key) in &fallback_mappings {
             if let Some(key_def) = soundpack.definitions.get(*keyboard_key) {
-                let converted_mappings: Vec<(f64, f64)> = key_def.timing
-                    .iter()
-                    .map(|pair| (pair[0] as f64, pair[1] as f64))
-                    .collect();
-                mouse_mappings.insert(mouse_button.to_string(), converted_mappings);
+                mouse_mappings.insert(mouse_button.to_string(), key_def.timing_sets().cloned().collect());
             }
         }
     }
@@ -588,23 +531,45 @@ fn create_mouse_mappings(
     mouse_mappings
 }
 
-/// Loads a keyboard soundpack directly into engine-owned state (Phase 3).
-/// Mirrors `load_keyboard_soundpack_optimized` but writes to `EngineState`
-/// fields instead of an `&AudioContext`, since the engine thread owns its
-/// data as plain fields rather than `Arc<Mutex<...>>`.
-pub(super) fn load_keyboard_pack_into_engine(
-    state: &mut EngineState,
+/// A soundpack decoded and mapped off the engine thread, ready for
+/// `install_pack` to swap in.
+pub(super) struct PreparedPack {
+    pub(super) soundpack_id: String,
+    pub(super) name: String,
+    pub(super) audio: PackBuffers,
+    pub(super) map: std::collections::HashMap<String, KeyTimings>,
+    /// Keys' `repeat` segments; always empty for a mouse pack.
+    pub(super) repeats: std::collections::HashMap<String, (f32, f32)>,
+    pub(super) loudness: Option<super::loudness::Loudness>,
+    pub(super) options: SoundpackOptions,
+    /// The rate `audio.buffers` were resampled to.
+    pub(super) device_rate: Option<u32>,
+}
+
+/// Reads, decodes and resamples a soundpack without touching the engine, so
+/// it can run on the loader thread while the previous pack keeps playing.
+/// Gives up early once `token` is cancelled; a cancelled load is not recorded
+/// as a loading error, since nothing is wrong with the pack.
+///
+/// With `app_caches`, decoded audio goes through the app's PCM cache and the
+/// pack's metadata is recorded in the soundpack cache. Without, nothing but
+/// the pack itself is read and nothing at all is written.
+pub(super) fn prepare_pack(
+    kind: PackKind,
     soundpack_id: &str,
-    update_cache_on_error: bool
-) -> Result<String, String> {
+    device_rate: Option<u32>,
+    token: &LoadToken,
+    update_cache_on_error: bool,
+    app_caches: bool
+) -> Result<PreparedPack, String> {
     if soundpack_id.is_empty() {
         return Err("empty soundpack ID".to_string());
     }
 
-    match load_keyboard_pack_into_engine_inner(state, soundpack_id) {
-        Ok(name) => Ok(name),
+    match prepare_pack_inner(kind, soundpack_id, device_rate, token, app_caches) {
+        Ok(prepared) => Ok(prepared),
         Err(e) => {
-            if update_cache_on_error {
+            if update_cache_on_error && !token.is_cancelled() {
                 capture_soundpack_loading_error(soundpack_id, &e);
             }
             Err(e)
@@ -612,10 +577,13 @@ pub(super) fn load_keyboard_pack_into_engine(
     }
 }
 
-fn load_keyboard_pack_into_engine_inner(
-    state: &mut EngineState,
-    soundpack_id: &str
-) -> Result<String, String> {
+fn prepare_pack_inner(
+    kind: PackKind,
+    soundpack_id: &str,
+    device_rate: Option<u32>,
+    token: &LoadToken,
+    app_caches: bool
+) -> Result<PreparedPack, String> {
     let soundpack_path = paths::soundpacks::soundpack_dir(soundpack_id);
     let config_path = paths::soundpacks::config_json(soundpack_id);
     let config_content = std::fs
@@ -626,94 +594,115 @@ fn load_keyboard_pack_into_engine_inner(
         .map_err(|e| format!("Failed to parse V2 soundpack config: {}", e))?;
     soundpack.soundpack_type = determine_soundpack_type(soundpack_id);
 
-    if soundpack.soundpack_type != crate::state::soundpack::SoundpackType::Keyboard {
-        return Err("This is a mouse soundpack, not a keyboard soundpack".to_string());
-    }
-
-    let (original, resampled) = load_audio_file(&soundpack_path, &soundpack, state.device_rate)?;
-    let key_mappings = create_key_mappings(&soundpack, &resampled.0);
+    let mut map = match kind {
+        PackKind::Keyboard => {
+            if soundpack.soundpack_type != SoundpackType::Keyboard {
+                return Err("This is a mouse soundpack, not a keyboard soundpack".to_string());
+            }
+            create_key_mappings(&soundpack)
+        }
+        PackKind::Mouse => {
+            if soundpack.soundpack_type != SoundpackType::Mouse {
+                return Err("This is a keyboard soundpack, not a mouse soundpack".to_string());
+            }
+            create_mouse_mappings(&soundpack)
+        }
+    };
+    let pcm_cache = app_caches.then(PcmCache::app);
+    let audio = load_pack_buffers(&soundpack_path, &soundpack, &mut map, device_rate, token, pcm_cache.as_ref())?;
+    // Measured on the decoded originals: timings are in ms, so the result does
+    // not depend on the device rate and survives a device switch unchanged.
+    let loudness = measure_pack_loudness(&audio, &map);
 
-    let (audio_samples, channels, sample_rate) = resampled;
-    state.keyboard_samples = Some((Arc::new(audio_samples), channels, sample_rate));
-    let (orig_samples, orig_channels, orig_rate) = original;
-    state.keyboard_samples_original = Some((Arc::new(orig_samples), orig_channels, orig_rate));
+    let repeats = create_repeat_mappings(&soundpack);
 
-    state.key_map.clear();
-    for (key, mappings) in key_mappings {
-        let converted: Vec<[f32; 2]> = mappings
-            .into_iter()
-            .map(|(start, end)| [start as f32, end as f32])
-            .collect();
-        state.key_map.insert(key, converted);
+    if app_caches {
+        update_soundpack_cache(&soundpack_path, &soundpack, soundpack_id);
     }
-    state.key_sinks.clear();
-
-    update_soundpack_cache(&soundpack_path, &soundpack, soundpack_id);
-    crate::always_print!("✅ [Engine] Loaded keyboard soundpack: {}", soundpack.name);
-    Ok(soundpack.name)
+    Ok(PreparedPack {
+        soundpack_id: soundpack_id.to_string(),
+        name: soundpack.name,
+        audio,
+        map,
+        repeats,
+        loudness,
+        options: soundpack.options,
+        device_rate,
+    })
 }
 
-/// Loads a mouse soundpack directly into engine-owned state (Phase 3).
-/// See `load_keyboard_pack_into_engine` for the rationale.
-pub(super) fn load_mouse_pack_into_engine(
-    state: &mut EngineState,
-    soundpack_id: &str,
-    update_cache_on_error: bool
-) -> Result<String, String> {
-    if soundpack_id.is_empty() {
-        return Err("empty soundpack ID".to_string());
+/// Swaps a prepared pack into the engine in one step and returns its name.
+/// Voices of the old pack are stopped here rather than when the load
+/// started, so it plays right up to the switch.
+pub(super) fn install_pack(state: &mut EngineState, kind: PackKind, prepared: PreparedPack) -> String {
+    let mut audio = prepared.audio;
+    // The device changed while the pack was decoding.
+    if prepared.device_rate != state.device_rate {
+        audio.resample_to(state.device_rate);
     }
 
-    match load_mouse_pack_into_engine_inner(state, soundpack_id) {
-        Ok(name) => Ok(name),
-        Err(e) => {
-            if update_cache_on_error {
-                capture_soundpack_loading_error(soundpack_id, &e);
-            }
-            Err(e)
+    match kind {
+        PackKind::Keyboard => {
+            state.key_map = prepared.map;
+            state.key_repeats = prepared.repeats;
+            state.key_variation.clear();
+            state.keyboard_pack_id = prepared.soundpack_id;
+            state.keyboard_loudness = prepared.loudness;
+            state.keyboard_audio = audio;
+            state.keyboard_options = prepared.options;
+            state.refresh_pack_gains();
+            state.mixer.stop_bus(super::mixer::Bus::Keyboard);
+            crate::always_print!("✅ [Engine] Loaded keyboard soundpack: {}", prepared.name);
+        }
+        PackKind::Mouse => {
+            state.mouse_map = prepared.map;
+            state.mouse_variation.clear();
+            state.mouse_loudness = prepared.loudness;
+            state.mouse_audio = audio;
+            state.mouse_options = prepared.options;
+            state.refresh_pack_gains();
+            state.mixer.stop_bus(super::mixer::Bus::Mouse);
+            crate::always_print!("✅ [Engine] Loaded mouse soundpack: {}", prepared.name);
         }
     }
+    prepared.name
 }
 
-fn load_mouse_pack_into_engine_inner(
+/// Loads a keyboard soundpack into the engine inline, for callers that need
+/// it in place before they continue (`offline_render`). The live engine goes
+/// through `PackLoader` instead. Leaves the app's caches alone, so a one-off
+/// render neither reads nor changes anything the app keeps.
+pub(super) fn load_keyboard_pack_into_engine(
     state: &mut EngineState,
     soundpack_id: &str
 ) -> Result<String, String> {
-    let soundpack_path = paths::soundpacks::soundpack_dir(soundpack_id);
-    let config_path = paths::soundpacks::config_json(soundpack_id);
-    let config_content = std::fs
-        ::read_to_string(&config_path)
-        .map_err(|e| format!("Failed to read config: {}", e))?;
-    let mut soundpack: SoundPack = serde_json
-        ::from_str(&config_content)
-        .map_err(|e| format!("Failed to parse config: {}", e))?;
-    soundpack.soundpack_type = determine_soundpack_type(soundpack_id);
-
-    if soundpack.soundpack_type != crate::state::soundpack::SoundpackType::Mouse {
-        return Err("This is a keyboard soundpack, not a mouse soundpack".to_string());
-    }
-
-    let (original, resampled) = load_audio_file(&soundpack_path, &soundpack, state.device_rate)?;
-    let mouse_mappings = create_mouse_mappings(&soundpack, &resampled.0);
-
-    let (audio_samples, channels, sample_rate) = resampled;
-    state.mouse_samples = Some((Arc::new(audio_samples), channels, sample_rate));
-    let (orig_samples, orig_channels, orig_rate) = original;
-    state.mouse_samples_original = Some((Arc::new(orig_samples), orig_channels, orig_rate));
-
-    state.mouse_map.clear();
-    for (button, mappings) in mouse_mappings {
-        let converted: Vec<[f32; 2]> = mappings
-            .into_iter()
-            .map(|(start, end)| [start as f32, end as f32])
-            .collect();
-        state.mouse_map.insert(button, converted);
-    }
-    state.mouse_sinks.clear();
+    let rate = state.device_rate;
+    let prepared = prepare_pack(
+        PackKind::Keyboard,
+        soundpack_id,
+        rate,
+        &LoadToken::detached(),
+        false,
+        false
+    )?;
+    Ok(install_pack(state, PackKind::Keyboard, prepared))
+}
 
-    update_soundpack_cache(&soundpack_path, &soundpack, soundpack_id);
-    crate::always_print!("✅ [Engine] Loaded mouse soundpack: {}", soundpack.name);
-    Ok(soundpack.name)
+/// Mouse counterpart of `load_keyboard_pack_into_engine`.
+pub(super) fn load_mouse_pack_into_engine(
+    state: &mut EngineState,
+    soundpack_id: &str
+) -> Result<String, String> {
+    let rate = state.device_rate;
+    let prepared = prepare_pack(
+        PackKind::Mouse,
+        soundpack_id,
+        rate,
+        &LoadToken::detached(),
+        false,
+        false
+    )?;
+    Ok(install_pack(state, PackKind::Mouse, prepared))
 }
 
 /// Shared metadata-cache update used by both engine loaders (extracted from
@@ -772,6 +761,7 @@ fn capture_soundpack_loading_error(soundpack_id: &str, error: &str) {
             validation_status: "loading_error".to_string(),
             can_be_converted: false,
             last_error: Some(error.to_string()),
+            mapped_keys: None,
         };
 
         cache.soundpacks.insert(soundpack_id.to_string(), error_metadata);
@@ -780,3 +770,194 @@ fn capture_soundpack_loading_error(soundpack_id: &str, error: &str) {
     cache.save();
     crate::always_print!("💾 Updated cache with error information for {}", soundpack_id);
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::state::soundpack::{ KeyDefinition, SoundpackOptions, SoundpackType };
+    use super::super::test_support::ScratchDir;
+    use std::collections::HashMap;
+
+    /// Writes `ms` milliseconds of mono constant-`level` audio at 1 kHz, so
+    /// a file's length in samples equals its length in milliseconds.
+    fn write_wav(dir: &std::path::Path, name: &str, ms: usize, level: f32) {
+        let spec = hound::WavSpec {
+            channels: 1,
+            sample_rate: 1000,
+            bits_per_sample: 16,
+            sample_format: hound::SampleFormat::Int,
+        };
+        let mut writer = hound::WavWriter::create(dir.join(name), spec).expect("wav");
+        for _ in 0..ms {
+            writer.write_sample((level * (i16::MAX as f32)) as i16).expect("sample");
+        }
+        writer.finalize().expect("finalize");
+    }
+
+    fn multi_pack(definitions: HashMap<String, KeyDefinition>) -> SoundPack {
+        SoundPack {
+            id: "multi".to_string(),
+            name: "Multi".to_string(),
+            description: None,
+            author: None,
+            version: None,
+            config_version: None,
+            icon: None,
+            audio_file: None,
+            license: None,
+            tags: None,
+            created_at: None,
+            definition_method: "multi".to_string(),
+            options: SoundpackOptions::default(),
+            soundpack_type: SoundpackType::Keyboard,
+            config_version_num: 2,
+            definitions,
+        }
+    }
+
+    fn key(timing: Vec<[f32; 2]>, audio_file: &str) -> KeyDefinition {
+        KeyDefinition { timing, audio_file: Some(audio_file.to_string()), variations: Vec::new(), repeat: None }
+    }
+
+    #[test]
+    fn each_multi_pack_key_plays_from_its_own_file() {
+        let dir = ScratchDir::new("loader-multi");
+        write_wav(&dir, "a.wav", 50, 0.25);
+        write_wav(&dir, "space.wav", 80, 0.5);
+        let pack = multi_pack(
+            HashMap::from([
+                ("KeyA".to_string(), key(vec![[0.0, 50.0]], "a.wav")),
+                ("KeyB".to_string(), key(vec![[0.0, 50.0]], "a.wav")),
+                ("Space".to_string(), key(vec![[0.0, 80.0]], "space.wav")),
+            ])
+        );
+
+        let mut mappings = create_key_mappings(&pack);
+        let audio = load_pack_buffers(dir.to_str().unwrap(), &pack, &mut mappings, None, &LoadToken::detached(), None).expect(
+            "a multi pack must load without a top-level audio_file"
+        );
+
+        assert_eq!(audio.buffers.len(), 2, "a file shared by two keys is decoded once");
+        assert!(Arc::ptr_eq(&audio.buffer_for("KeyA").unwrap().0, &audio.buffer_for("KeyB").unwrap().0));
+        assert_eq!(audio.buffer_for("Space").unwrap().0.len(), 80);
+        assert!((audio.buffer_for("Space").unwrap().0[0] - 0.5).abs() < 0.01);
+        assert!(audio.buffer_for("KeyZ").is_none(), "unmapped keys have no buffer");
+    }
+
+    #[test]
+    fn a_multi_pack_key_without_timing_plays_its_whole_file() {
+        let dir = ScratchDir::new("loader-multi-untimed");
+        write_wav(&dir, "enter.wav", 120, 0.5);
+        let pack = multi_pack(HashMap::from([("Enter".to_string(), key(vec![], "enter.wav"))]));
+
+        let mut mappings = create_key_mappings(&pack);
+        load_pack_buffers(dir.to_str().unwrap(), &pack, &mut mappings, None, &LoadToken::detached(), None).expect("load");
+
+        assert_eq!(mappings["Enter"], vec![vec![[0.0, 120.0]]]);
+    }
+
+    #[test]
+    fn variations_are_mapped_after_the_primary_timing() {
+        let dir = ScratchDir::new("loader-variations");
+        write_wav(&dir, "a.wav", 300, 0.5);
+        let mut definition = key(vec![[0.0, 50.0], [50.0, 100.0]], "a.wav");
+        definition.variations = vec![vec![[100.0, 150.0], [150.0, 200.0]], vec![]];
+        let pack = multi_pack(HashMap::from([("KeyA".to_string(), definition)]));
+
+        let mut mappings = create_key_mappings(&pack);
+        load_pack_buffers(dir.to_str().unwrap(), &pack, &mut mappings, None, &LoadToken::detached(), None).expect("load");
+
+        assert_eq!(
+            mappings["KeyA"],
+            vec![
+                vec![[0.0, 50.0], [50.0, 100.0]],
+                vec![[100.0, 150.0], [150.0, 200.0]],
+                vec![[0.0, 300.0]]
+            ],
+            "an empty variation plays the whole file like an empty timing"
+        );
+    }
+
+    #[test]
+    fn a_missing_key_file_fails_the_load_with_its_path() {
+        let dir = ScratchDir::new("loader-multi-missing");
+        let pack = multi_pack(HashMap::from([("KeyA".to_string(), key(vec![], "gone.wav"))]));
+
+        let mut mappings = create_key_mappings(&pack);
+        let err = load_pack_buffers(dir.to_str().unwrap(), &pack, &mut mappings, None, &LoadToken::detached(), None)
+            .err()
+            .expect("a missing file must not load silently");
+
+        assert!(err.contains("gone.wav"), "error should name the file: {}", err);
+    }
+
+    /// Two frames of three-channel `S` audio: full positive on the first
+    /// channel, zero on the second, full negative on the third.
+    fn synthetic<S: Sample>(values: [S; 3]) -> AudioBuffer<S> {
+        use symphonia::core::audio::{ Channels, SignalSpec };
+
+        let channels = Channels::FRONT_LEFT | Channels::FRONT_RIGHT | Channels::FRONT_CENTRE;
+        let mut buf = AudioBuffer::new(2, SignalSpec::new(1000, channels));
+        buf.render_reserved(Some(2));
+        for (channel, value) in values.into_iter().enumerate() {
+            buf.chan_mut(channel).fill(value);
+        }
+        buf
+    }
+
+    fn assert_full_scale(decoded: AudioBufferRef, variant: &str) {
+        let mut out = Vec::new();
+        interleave(&decoded, &mut out);
+        assert_eq!(out.len(), 6, "{}: every channel of every frame is kept", variant);
+        for frame in out.chunks(3) {
+            for (sample, expected) in frame.iter().zip([1.0, 0.0, -1.0]) {
+                assert!((sample - expected).abs() < 0.01, "{}: {:?}", variant, frame);
+            }
+        }
+    }
+
+    #[test]
+    fn every_sample_format_interleaves_to_full_scale_f32() {
+        use symphonia::core::audio::AsAudioBufferRef;
+        use symphonia::core::sample::{ i24, u24 };
+
+        assert_full_scale(synthetic([u8::MAX, 128, 0]).as_audio_buffer_ref(), "U8");
+        assert_full_scale(synthetic([u16::MAX, 1 << 15, 0]).as_audio_buffer_ref(), "U16");
+        assert_full_scale(synthetic([u24::MAX, u24(1 << 23), u24(0)]).as_audio_buffer_ref(), "U24");
+        assert_full_scale(synthetic([u32::MAX, 1 << 31, 0]).as_audio_buffer_ref(), "U32");
+        assert_full_scale(synthetic([i8::MAX, 0, i8::MIN]).as_audio_buffer_ref(), "S8");
+        assert_full_scale(synthetic([i16::MAX, 0, i16::MIN]).as_audio_buffer_ref(), "S16");
+        assert_full_scale(synthetic([i24::MAX, i24(0), i24::MIN]).as_audio_buffer_ref(), "S24");
+        assert_full_scale(synthetic([i32::MAX, 0, i32::MIN]).as_audio_buffer_ref(), "S32");
+        assert_full_scale(synthetic([1.0f32, 0.0, -1.0]).as_audio_buffer_ref(), "F32");
+        assert_full_scale(synthetic([1.0f64, 0.0, -1.0]).as_audio_buffer_ref(), "F64");
+    }
+
+    #[test]
+    fn a_surround_file_decodes_to_stereo_at_its_real_length() {
+        let dir = ScratchDir::new("loader-surround");
+        let spec = hound::WavSpec {
+            channels: 6,
+            sample_rate: 1000,
+            bits_per_sample: 16,
+            sample_format: hound::SampleFormat::Int,
+        };
+        let mut writer = hound::WavWriter::create(dir.join("5.1.wav"), spec).expect("wav");
+        for _ in 0..100 {
+            // Front left and LFE only.
+            for level in [0.5f32, 0.0, 0.0, 0.9, 0.0, 0.0] {
+                writer.write_sample((level * (i16::MAX as f32)) as i16).expect("sample");
+            }
+        }
+        writer.finalize().expect("finalize");
+
+        let path = dir.join("5.1.wav");
+        let (samples, channels, _) = load_audio_with_symphonia(path.to_str().unwrap(), &LoadToken::detached())
+            .expect("decode");
+
+        assert_eq!(channels, 2);
+        assert_eq!(samples.len(), 200, "100 frames, not the 300 a stereo reading of 6 channels gives");
+        assert!(samples[0] > 0.0, "front left stays left");
+        assert_eq!(samples[1], 0.0, "nothing reaches the right - LFE is dropped");
+    }
+}
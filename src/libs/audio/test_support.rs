@@ -0,0 +1,75 @@
+//! Fixtures shared by the tests across `libs`: scratch directories, and
+//! packs built in memory the way the loader would hand them over.
+
+use super::engine::{ KeyTimings, PackBuffers };
+use super::soundpack_loader::PreparedPack;
+use crate::state::soundpack::SoundpackOptions;
+use std::collections::HashMap;
+use std::path::{ Path, PathBuf };
+use std::sync::atomic::{ AtomicUsize, Ordering };
+use std::sync::Arc;
+
+static NEXT_SCRATCH_DIR: AtomicUsize = AtomicUsize::new(0);
+
+/// An empty directory under the system temp dir, removed with everything in
+/// it when dropped. Every call gets its own, so tests running in parallel
+/// never share one, even with the same `tag`.
+pub(crate) struct ScratchDir {
+    path: PathBuf,
+}
+
+impl ScratchDir {
+    pub(crate) fn new(tag: &str) -> Self {
+        let n = NEXT_SCRATCH_DIR.fetch_add(1, Ordering::Relaxed);
+        let path = std::env
+            ::temp_dir()
+            .join(format!("mechvibes-{}-{}-{}", tag, std::process::id(), n));
+        // Left over from an earlier run that was killed before cleaning up
+        let _ = std::fs::remove_dir_all(&path);
+        std::fs::create_dir_all(&path).expect("scratch dir");
+        Self { path }
+    }
+}
+
+impl std::ops::Deref for ScratchDir {
+    type Target = Path;
+
+    fn deref(&self) -> &Path {
+        &self.path
+    }
+}
+
+impl Drop for ScratchDir {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_dir_all(&self.path);
+    }
+}
+
+/// A "single" pack of one mono file, `samples` at `rate`, with each key in
+/// `keys` timed into it. Options are the defaults; change them on the result
+/// before installing it.
+pub(super) fn prepared_pack(
+    soundpack_id: &str,
+    samples: Vec<f32>,
+    rate: u32,
+    keys: &[(&str, KeyTimings)]
+) -> PreparedPack {
+    let decoded = (Arc::new(samples), 1, rate);
+    let mut audio = PackBuffers::default();
+    audio.originals.insert("pack.wav".to_string(), decoded.clone());
+    audio.buffers.insert("pack.wav".to_string(), decoded);
+    audio.default_file = Some("pack.wav".to_string());
+    PreparedPack {
+        soundpack_id: soundpack_id.to_string(),
+        name: soundpack_id.to_string(),
+        audio,
+        map: keys
+            .iter()
+            .map(|(code, timings)| (code.to_string(), timings.clone()))
+            .collect(),
+        repeats: HashMap::new(),
+        loudness: None,
+        options: SoundpackOptions::default(),
+        device_rate: Some(rate),
+    }
+}
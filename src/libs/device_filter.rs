@@ -0,0 +1,118 @@
+//! `AppConfig::enabled_keyboards` / `enabled_mice` as the input listeners
+//! see them: the Windows worker host and the Linux evdev listener both drop
+//! presses from devices the user switched off in Settings.
+//!
+//! Both check every event from a thread that must never stall, so neither
+//! reads config per event. Settings publishes the lists here when it changes
+//! them (`notify_config_changed`), and each listener keeps a `DeviceFilter`
+//! that re-reads them only when the generation has moved.
+
+use std::sync::atomic::{ AtomicU64, Ordering };
+use std::sync::Mutex;
+
+/// Bumped whenever Settings changes `enabled_keyboards`/`enabled_mice`, so
+/// the listener threads notice their cached filter is stale.
+static CONFIG_GENERATION: AtomicU64 = AtomicU64::new(0);
+
+/// The lists themselves, published alongside the generation so the listener
+/// threads never have to go looking for them. Reading them from the config
+/// authority costs a clone; the `AppConfig::load()` this replaced parsed JSON,
+/// read the registry for the auto-start state and could rewrite the file - none
+/// of which belongs on a thread draining input, because stalling the Windows
+/// reader back-pressures the pipe and, through it, the worker's WndProc.
+static ENABLED_DEVICES: Mutex<Option<(Vec<String>, Vec<String>)>> = Mutex::new(None);
+
+/// Call after `AppConfig`'s enabled-device lists change (from
+/// `device_selector.rs`) to make filtering take effect immediately.
+pub fn notify_config_changed() {
+    let config = crate::state::config_writer::current();
+    *ENABLED_DEVICES.lock().unwrap() = Some((config.enabled_keyboards, config.enabled_mice));
+    // Published after the lists, so a reader that sees the new generation is
+    // guaranteed to find the new lists behind it.
+    CONFIG_GENERATION.fetch_add(1, Ordering::Release);
+}
+
+/// Cached copy of the enabled-device filter, refreshed only when
+/// `CONFIG_GENERATION` moves - re-reading config per keystroke is what the
+/// audio path was fixed away from in Phase 1/2.
+pub struct DeviceFilter {
+    pub(crate) generation: u64,
+    pub(crate) enabled_keyboards: Vec<String>,
+    pub(crate) enabled_mice: Vec<String>,
+}
+
+impl DeviceFilter {
+    /// Reads config from disk. Called once when a listener starts, before
+    /// its read loop begins - never from inside it.
+    pub fn load() -> Self {
+        let generation = CONFIG_GENERATION.load(Ordering::Acquire);
+        let (enabled_keyboards, enabled_mice) = ENABLED_DEVICES.lock()
+            .unwrap()
+            .clone()
+            .unwrap_or_else(|| {
+                let config = crate::state::config_writer::current();
+                (config.enabled_keyboards, config.enabled_mice)
+            });
+        Self { generation, enabled_keyboards, enabled_mice }
+    }
+
+    pub fn refresh_if_stale(&mut self) {
+        let generation = CONFIG_GENERATION.load(Ordering::Acquire);
+        if generation == self.generation {
+            return;
+        }
+        if let Some((keyboards, mice)) = ENABLED_DEVICES.lock().unwrap().clone() {
+            self.enabled_keyboards = keyboards;
+            self.enabled_mice = mice;
+        }
+        self.generation = generation;
+    }
+
+    /// `kind` is `'K'` for a keyboard, anything else for a mouse - the
+    /// worker's wire letters.
+    ///
+    /// Empty list = all devices allowed, matching
+    /// `InputDeviceManager::should_process_device`'s convention and what
+    /// Settings persists by default (`config.rs` defaults both to empty).
+    /// A device whose id could not be resolved (`-`) is allowed through:
+    /// going silent on a lookup failure would be a worse failure mode than
+    /// an unfilterable device making sound.
+    pub fn allows(&self, kind: char, device_id: &str) -> bool {
+        let enabled = match kind {
+            'K' => &self.enabled_keyboards,
+            _ => &self.enabled_mice,
+        };
+        enabled.is_empty() || device_id == "-" || enabled.iter().any(|id| id == device_id)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn empty_filter_allows_everything() {
+        let filter = DeviceFilter {
+            generation: 0,
+            enabled_keyboards: Vec::new(),
+            enabled_mice: Vec::new(),
+        };
+        assert!(filter.allows('K', "anything"));
+        assert!(filter.allows('M', "anything"));
+    }
+
+    #[test]
+    fn populated_filter_allows_only_listed_devices_and_unknown_ids() {
+        let filter = DeviceFilter {
+            generation: 0,
+            enabled_keyboards: vec!["kb1".to_string()],
+            enabled_mice: vec!["m1".to_string()],
+        };
+        assert!(filter.allows('K', "kb1"));
+        assert!(!filter.allows('K', "kb2"));
+        assert!(filter.allows('M', "m1"));
+        assert!(!filter.allows('M', "m2"));
+        // Unresolvable device id fails open rather than going silent.
+        assert!(filter.allows('K', "-"));
+    }
+}
@@ -326,6 +326,12 @@ impl DeviceManager {
     /// make rodio's realtime resampler run on top of ours, which is worse
     /// than not resampling at all.
     pub fn get_current_output_sample_rate(&self) -> Option<u32> {
+        self.get_current_output_format().map(|(rate, _)| rate)
+    }
+
+    /// `(sample_rate, channels)` of the selected output device's default
+    /// config - one device lookup for both.
+    pub fn get_current_output_format(&self) -> Option<(u32, u16)> {
         let config = crate::state::config_writer::current();
 
         let device = match &config.selected_audio_device {
@@ -337,7 +343,9 @@ impl DeviceManager {
             None => self.host.default_output_device(),
         };
 
-        device.and_then(|d| d.default_output_config().ok()).map(|c| c.sample_rate().0)
+        device
+            .and_then(|d| d.default_output_config().ok())
+            .map(|c| (c.sample_rate().0, c.channels()))
     }
 }
 
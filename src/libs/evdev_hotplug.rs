@@ -0,0 +1,141 @@
+//! Watches `/dev/input` for event nodes coming and going, so the evdev
+//! listeners pick up a keyboard plugged in after startup, a Bluetooth
+//! keyboard reconnecting or a KVM switching over, and let go of the ones
+//! that vanish.
+//!
+//! udev creates a node before it has set its permissions and ACLs, so a
+//! node that cannot be opened yet on `IN_CREATE` is reported again on the
+//! `IN_ATTRIB` that follows; callers treat a repeat `Added` for a node they
+//! already hold as a no-op.
+
+use std::os::fd::{ AsRawFd, FromRawFd, OwnedFd, RawFd };
+use std::path::{ Path, PathBuf };
+
+const INPUT_DIR: &str = "/dev/input";
+
+/// Size of `struct inotify_event` before its name.
+const EVENT_HEADER: usize = 16;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum NodeChange {
+    Added(PathBuf),
+    Removed(PathBuf),
+    /// The kernel dropped events; whatever was added or removed meanwhile
+    /// has to be found by enumerating again.
+    Overflowed,
+}
+
+pub struct InputDirWatcher {
+    fd: OwnedFd,
+}
+
+impl InputDirWatcher {
+    pub fn new() -> Result<Self, String> {
+        let fd = unsafe { libc::inotify_init1(libc::IN_NONBLOCK | libc::IN_CLOEXEC) };
+        if fd < 0 {
+            return Err(format!("inotify_init1 failed: {}", std::io::Error::last_os_error()));
+        }
+        let fd = unsafe { OwnedFd::from_raw_fd(fd) };
+
+        let dir = std::ffi::CString::new(INPUT_DIR).unwrap();
+        let mask = libc::IN_CREATE | libc::IN_DELETE | libc::IN_ATTRIB;
+        if unsafe { libc::inotify_add_watch(fd.as_raw_fd(), dir.as_ptr(), mask) } < 0 {
+            return Err(
+                format!("Failed to watch {}: {}", INPUT_DIR, std::io::Error::last_os_error())
+            );
+        }
+        Ok(Self { fd })
+    }
+
+    /// Everything that changed since the last call. Never blocks.
+    pub fn changes(&self) -> Vec<NodeChange> {
+        let mut changes = Vec::new();
+        let mut buffer = [0u8; 4096];
+        loop {
+            let read = unsafe {
+                libc::read(self.fd.as_raw_fd(), buffer.as_mut_ptr() as *mut _, buffer.len())
+            };
+            if read <= 0 {
+                // EAGAIN: drained
+                return changes;
+            }
+            changes.extend(parse_events(Path::new(INPUT_DIR), &buffer[..read as usize]));
+        }
+    }
+}
+
+impl AsRawFd for InputDirWatcher {
+    fn as_raw_fd(&self) -> RawFd {
+        self.fd.as_raw_fd()
+    }
+}
+
+/// Decodes a buffer of `struct inotify_event`s for `dir`, keeping only the
+/// `eventN` nodes - `/dev/input` also holds the legacy `mouseN`/`js*` nodes
+/// and the `by-id`/`by-path` directories.
+fn parse_events(dir: &Path, buffer: &[u8]) -> Vec<NodeChange> {
+    let field = |at: usize| u32::from_ne_bytes(buffer[at..at + 4].try_into().unwrap());
+
+    let mut changes = Vec::new();
+    let mut offset = 0;
+    while offset + EVENT_HEADER <= buffer.len() {
+        let mask = field(offset + 4);
+        let name_len = field(offset + 12) as usize;
+        let name_end = (offset + EVENT_HEADER + name_len).min(buffer.len());
+        let name = &buffer[offset + EVENT_HEADER..name_end];
+        let name = String::from_utf8_lossy(name.split(|byte| *byte == 0).next().unwrap_or_default());
+        offset = name_end;
+
+        if mask & libc::IN_Q_OVERFLOW != 0 {
+            changes.push(NodeChange::Overflowed);
+        } else if !name.starts_with("event") || mask & libc::IN_ISDIR != 0 {
+            continue;
+        } else if mask & libc::IN_DELETE != 0 {
+            changes.push(NodeChange::Removed(dir.join(name.as_ref())));
+        } else if mask & (libc::IN_CREATE | libc::IN_ATTRIB) != 0 {
+            changes.push(NodeChange::Added(dir.join(name.as_ref())));
+        }
+    }
+    changes
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn event(mask: u32, name: &str) -> Vec<u8> {
+        // Names are NUL-padded to a multiple of the header alignment
+        let padded = (name.len() + 1).div_ceil(4) * 4;
+        let mut bytes = Vec::new();
+        bytes.extend(1i32.to_ne_bytes());
+        bytes.extend(mask.to_ne_bytes());
+        bytes.extend(0u32.to_ne_bytes());
+        bytes.extend((padded as u32).to_ne_bytes());
+        bytes.extend(name.as_bytes());
+        bytes.extend(std::iter::repeat_n(0, padded - name.len()));
+        bytes
+    }
+
+    #[test]
+    fn event_nodes_are_reported_and_everything_else_ignored() {
+        let buffer = [
+            event(libc::IN_CREATE, "event7"),
+            event(libc::IN_CREATE, "mouse2"),
+            event(libc::IN_CREATE | libc::IN_ISDIR, "by-id"),
+            event(libc::IN_ATTRIB, "event7"),
+            event(libc::IN_DELETE, "event12"),
+        ].concat();
+
+        assert_eq!(parse_events(Path::new("/dev/input"), &buffer), vec![
+            NodeChange::Added(PathBuf::from("/dev/input/event7")),
+            NodeChange::Added(PathBuf::from("/dev/input/event7")),
+            NodeChange::Removed(PathBuf::from("/dev/input/event12"))
+        ]);
+    }
+
+    #[test]
+    fn a_queue_overflow_asks_for_a_rescan() {
+        let buffer = event(libc::IN_Q_OVERFLOW, "");
+        assert_eq!(parse_events(Path::new("/dev/input"), &buffer), vec![NodeChange::Overflowed]);
+    }
+}
@@ -1,30 +1,66 @@
 use std::sync::{Arc, Mutex};
 use std::thread;
-use std::time::Duration;
 
 #[cfg(target_os = "linux")]
 use crossbeam_channel::Sender;
 
 #[cfg(target_os = "linux")]
-pub fn start_evdev_keyboard_listener(
+use std::os::fd::AsRawFd;
+#[cfg(target_os = "linux")]
+use std::path::PathBuf;
+
+#[cfg(target_os = "linux")]
+use crate::libs::device_filter::DeviceFilter;
+#[cfg(target_os = "linux")]
+use crate::libs::evdev_hotplug::{InputDirWatcher, NodeChange};
+#[cfg(target_os = "linux")]
+use crate::libs::evdev_poll::Poller;
+
+/// Reads keyboards and mice straight from `/dev/input`, which works the same
+/// under Wayland, where no global hook sees other clients' input, and X11.
+/// Keys go out on `keyboard_tx` (hotkeys on `hotkey_tx`), buttons and wheel
+/// notches on `mouse_tx`, in the wire format the rdev listener uses.
+#[cfg(target_os = "linux")]
+pub fn start_evdev_input_listener(
     keyboard_tx: Sender<String>,
+    mouse_tx: Sender<String>,
     hotkey_tx: Sender<String>,
     _is_focused: Arc<Mutex<bool>>,
 ) {
-    crate::always_print!("🔍 [evdev] start_evdev_keyboard_listener() called - spawning thread");
+    crate::always_print!("🔍 [evdev] start_evdev_input_listener() called - spawning thread");
     thread::spawn(move || {
-        use evdev::{Device, EventType, KeyCode};
+        use evdev::{EventType, KeyCode, RelativeAxisCode};
 
-        crate::always_print!("🔍 [evdev] Thread started - initializing keyboard listener");
+        crate::always_print!("🔍 [evdev] Thread started - initializing input listener");
         crate::always_print!("🔍 [evdev] Current user: {:?}", std::env::var("USER"));
-        crate::always_print!("🔍 [evdev] Starting Linux keyboard listener (Wayland/X11 compatible)");
+        crate::always_print!("🔍 [evdev] Starting Linux keyboard and mouse listener (Wayland/X11 compatible)");
 
-        // Track modifier keys for hotkey detection
-        let mut ctrl_pressed = false;
-        let mut alt_pressed = false;
+        // Tracks held modifiers and recognizes the configured hotkeys
+        let mut hotkeys = crate::libs::hotkeys::HotkeyMatcher::new();
+        // Settings' enabled keyboards and mice, refreshed when Settings changes them
+        let mut filter = DeviceFilter::load();
+        let mut held = Vec::new();
 
-        // Find all keyboard devices
-        let mut keyboards = Vec::new();
+        // The thread sleeps here until a device or /dev/input has something
+        let poller = match Poller::new() {
+            Ok(poller) => poller,
+            Err(e) => {
+                crate::always_eprint!("❌ [evdev] Cannot wait for input: {}", e);
+                return;
+            }
+        };
+
+        // Devices connected later are attached as they appear
+        let watcher = match InputDirWatcher::new().and_then(|watcher| poller.add(&watcher).map(|_| watcher)) {
+            Ok(watcher) => Some(watcher),
+            Err(e) => {
+                crate::always_eprint!("⚠️ [evdev] Hotplug unavailable, devices connected later need a restart: {}", e);
+                None
+            }
+        };
+
+        // Find all keyboards and mice
+        let mut nodes = Vec::new();
 
         crate::always_print!("🔍 [evdev] Enumerating input devices...");
         let devices: Vec<_> = evdev::enumerate().collect();
@@ -38,122 +74,386 @@ pub fn start_evdev_keyboard_listener(
             crate::always_eprint!("   2. Add yourself to input group: sudo usermod -a -G input $USER");
             crate::always_eprint!("   3. Log out and log back in for group changes to take effect");
             crate::always_eprint!("   4. Check /dev/input permissions: ls -la /dev/input/event*");
-            return;
+            if watcher.is_none() {
+                return;
+            }
         }
 
-        for (path, mut device) in devices {
-            // Check if device has keyboard capabilities
-            if device.supported_keys().is_some() {
-                crate::always_print!("🔍 [evdev] Found keyboard device: {:?} - {}", path.display(), device.name().unwrap_or("Unknown"));
-
-                // Set device to non-blocking mode to prevent blocking on idle devices
-                if let Err(e) = device.set_nonblocking(true) {
-                    crate::always_eprint!("⚠️ [evdev] Failed to set non-blocking mode for {:?}: {}", path.display(), e);
-                }
-
-                keyboards.push(device);
-            } else {
-                crate::always_print!("🔍 [evdev] Skipping non-keyboard device: {:?}", path.display());
-            }
+        for (path, device) in devices {
+            nodes.extend(attach_node(&poller, path, device));
         }
 
-        if keyboards.is_empty() {
-            crate::always_eprint!("❌ [evdev] No keyboard devices found among the {} input devices!", device_count);
+        if nodes.is_empty() {
+            crate::always_eprint!("❌ [evdev] No keyboards or mice found among the {} input devices!", device_count);
             crate::always_eprint!("💡 [evdev] This might indicate a permission issue or unusual hardware setup");
-            return;
+            if watcher.is_none() {
+                return;
+            }
+            crate::always_print!("🔍 [evdev] Waiting for a keyboard or mouse to be connected...");
+        } else {
+            crate::always_print!(
+                "✅ [evdev] Successfully initialized {} keyboard(s) and {} mouse device(s)",
+                nodes.iter().filter(|node| node.is_keyboard).count(),
+                nodes.iter().filter(|node| node.is_mouse).count()
+            );
         }
-
-        crate::always_print!("✅ [evdev] Successfully initialized {} keyboard device(s)", keyboards.len());
         crate::always_print!("🔍 [evdev] Starting event monitoring loop...");
 
         let mut event_count = 0;
         let mut first_event_logged = false;
 
-        // Monitor all keyboards in a loop
+        let mut ready = Vec::new();
+
+        // Monitor all devices in a loop
         loop {
-            for device in &mut keyboards {
+            if let Err(e) = poller.wait(&mut ready) {
+                crate::always_eprint!("❌ [evdev] Stopped waiting for input: {}", e);
+                return;
+            }
+            filter.refresh_if_stale();
+
+            for &fd in &ready {
+                if let Some(watcher) = watcher.as_ref().filter(|watcher| watcher.as_raw_fd() == fd) {
+                    for change in watcher.changes() {
+                        match change {
+                            NodeChange::Added(path) => {
+                                if nodes.iter().any(|node| node.path == path) {
+                                    continue;
+                                }
+                                // Not openable yet is normal right after IN_CREATE;
+                                // the IN_ATTRIB that follows retries it
+                                if let Ok(device) = evdev::Device::open(&path) {
+                                    nodes.extend(attach_node(&poller, path, device));
+                                }
+                            }
+                            NodeChange::Removed(path) => {
+                                if let Some(index) = nodes.iter().position(|node| node.path == path) {
+                                    let node = nodes.remove(index);
+                                    detach_node(&node, &mut held, &keyboard_tx, &mouse_tx);
+                                }
+                            }
+                            NodeChange::Overflowed => {
+                                for (path, device) in evdev::enumerate() {
+                                    if !nodes.iter().any(|node| node.path == path) {
+                                        nodes.extend(attach_node(&poller, path, device));
+                                    }
+                                }
+                            }
+                        }
+                    }
+                    continue;
+                }
+
+                // A device removed earlier in this batch has nothing to read
+                let Some(index) = nodes.iter().position(|node| node.device.as_raw_fd() == fd) else {
+                    continue;
+                };
+                let node = &mut nodes[index];
+
                 // Fetch events (non-blocking)
-                match device.fetch_events() {
+                let unplugged = match node.device.fetch_events() {
                     Ok(events) => {
                         for event in events {
-                            if event.event_type() == EventType::KEY {
-                                event_count += 1;
-                                if !first_event_logged {
-                                    crate::always_print!("✅ [evdev] First keyboard event detected!");
-                                    first_event_logged = true;
-                                }
+                            match event.event_type() {
+                                EventType::KEY => {
+                                    // Convert event code to KeyCode
+                                    let key = KeyCode(event.code());
+                                    let key_value = event.value();
 
-                                let key_value = event.value();
+                                    if let Some(button) = map_evdev_button(key).filter(|_| node.is_mouse) {
+                                        if let Some(wire) = wire_for_event(&filter, &mut held, 'M', &node.id, button, key_value) {
+                                            let _ = mouse_tx.send(wire);
+                                        }
+                                        continue;
+                                    }
+                                    if !node.is_keyboard {
+                                        continue;
+                                    }
+
+                                    event_count += 1;
+                                    if !first_event_logged {
+                                        crate::always_print!("✅ [evdev] First keyboard event detected!");
+                                        first_event_logged = true;
+                                    }
 
-                                // Convert event code to KeyCode
-                                let key = KeyCode(event.code());
-                                {
                                     let key_code = map_evdev_keycode(key);
-                                    if !key_code.is_empty() {
-                                        // Handle key press (value == 1)
-                                        if key_value == 1 {
-                                            // Track modifier keys for hotkey detection
-                                            match key_code {
-                                                "ControlLeft" | "ControlRight" => {
-                                                    ctrl_pressed = true;
-                                                }
-                                                "AltLeft" | "AltRight" => {
-                                                    alt_pressed = true;
-                                                }
-                                                "KeyM" => {
-                                                    // Check for Ctrl+Alt+M hotkey combination
-                                                    if ctrl_pressed && alt_pressed {
-                                                        crate::always_print!("🔥 [evdev] Hotkey detected: Ctrl+Alt+M - Toggling global sound");
-                                                        let _ = hotkey_tx.send("TOGGLE_SOUND".to_string());
-                                                        continue; // Don't process this as a regular key event
-                                                    }
-                                                }
-                                                _ => {}
-                                            }
-
-                                            // Send key press event
-                                            if event_count <= 5 {
-                                                crate::always_print!("🔍 [evdev] Sending key press: {}", key_code);
-                                            }
-                                            let _ = keyboard_tx.send(key_code.to_string());
-                                        }
-                                        // Handle key release (value == 0)
-                                        else if key_value == 0 {
-                                            // Track modifier key releases for hotkey detection
-                                            match key_code {
-                                                "ControlLeft" | "ControlRight" => {
-                                                    ctrl_pressed = false;
-                                                }
-                                                "AltLeft" | "AltRight" => {
-                                                    alt_pressed = false;
-                                                }
-                                                _ => {}
-                                            }
-
-                                            // Send key release event
-                                            let _ = keyboard_tx.send(format!("UP:{}", key_code));
+                                    if key_code.is_empty() {
+                                        continue;
+                                    }
+
+                                    // Hotkeys work from any keyboard, even one
+                                    // disabled for soundpack playback; auto-repeat
+                                    // (value == 2) is not a new press
+                                    let hotkey = match key_value {
+                                        2 => None,
+                                        _ => hotkeys.observe(key_code, key_value == 1),
+                                    };
+                                    if let Some(action) = hotkey {
+                                        crate::always_print!("🔥 [evdev] Hotkey detected: {}", action.label());
+                                        let _ = hotkey_tx.send(action.wire_name().to_string());
+                                        continue; // Don't process this as a regular key event
+                                    }
+
+                                    if let Some(wire) = wire_for_event(&filter, &mut held, 'K', &node.id, key_code, key_value) {
+                                        if event_count <= 5 {
+                                            crate::always_print!("🔍 [evdev] Sending key event: {}", wire);
                                         }
-                                        // Ignore key repeat (value == 2)
+                                        trace_send(key_code, event.timestamp());
+                                        let _ = keyboard_tx.send(wire);
+                                    }
+                                }
+                                EventType::RELATIVE if node.is_mouse && event.code() == RelativeAxisCode::REL_WHEEL.0 => {
+                                    if let Some(notch) = wheel_notch(&filter, &node.id, event.value()) {
+                                        // A notch is a click of the wheel: press and release at once
+                                        let _ = mouse_tx.send(notch.to_string());
+                                        let _ = mouse_tx.send(format!("UP:{}", notch));
                                     }
                                 }
+                                _ => {}
                             }
                         }
+                        false
                     }
                     Err(e) if e.kind() == std::io::ErrorKind::WouldBlock => {
-                        // No events available, this is normal
+                        // Another reader got there first, or only a SYN was pending
+                        false
                     }
+                    // Unplugged; the watcher's IN_DELETE may not have arrived yet
+                    Err(e) if e.raw_os_error() == Some(libc::ENODEV) => true,
                     Err(e) => {
                         crate::always_eprint!("⚠️ [evdev] Error fetching events: {}", e);
+                        false
                     }
+                };
+
+                if unplugged {
+                    // Dropping the device closes its fd, which takes it out of the poller
+                    let node = nodes.remove(index);
+                    detach_node(&node, &mut held, &keyboard_tx, &mouse_tx);
                 }
             }
-            
-            // Small sleep to prevent busy-waiting
-            thread::sleep(Duration::from_millis(10));
         }
     });
 }
 
+/// The Linux counterpart of the worker host's `trace::Point::WorkerSend`:
+/// the listener is about to hand a key event to the engine. Its duration is
+/// how long the event waited since the kernel stamped it, which is where
+/// polling used to add up to a tick of latency.
+#[cfg(target_os = "linux")]
+fn trace_send(key_code: &str, stamped: std::time::SystemTime) {
+    if !crate::libs::trace::enabled() {
+        return;
+    }
+    let waited_ms = std::time::SystemTime
+        ::now()
+        .duration_since(stamped)
+        .map(|waited| waited.as_secs_f64() * 1000.0)
+        .unwrap_or(0.0);
+    crate::libs::trace::record(crate::libs::trace::Point::WorkerSend, key_code, waited_ms);
+}
+
+/// A keyboard or mouse being read, with the node it was opened from - the
+/// node is how a removal is matched, the id how it is filtered. One node
+/// can be both, like a wireless receiver that reports keys and a pointer
+/// through the same interface.
+#[cfg(target_os = "linux")]
+struct InputNode {
+    path: PathBuf,
+    id: String,
+    device: evdev::Device,
+    is_keyboard: bool,
+    is_mouse: bool,
+}
+
+/// Takes `device` on if it is a keyboard or a mouse. Devices disabled in
+/// Settings are attached too: the filter drops their presses, and enabling
+/// one takes effect without replugging it.
+#[cfg(target_os = "linux")]
+fn attach_node(poller: &Poller, path: PathBuf, device: evdev::Device) -> Option<InputNode> {
+    let is_keyboard = is_typing_device(&device);
+    let is_mouse = is_pointing_device(&device);
+    let kind = match (is_keyboard, is_mouse) {
+        (true, true) => "keyboard and mouse",
+        (true, false) => "keyboard",
+        (false, true) => "mouse",
+        (false, false) => {
+            crate::always_print!("🔍 [evdev] Skipping device that is neither keyboard nor mouse: {:?}", path.display());
+            return None;
+        }
+    };
+
+    let id = device_id(&device);
+    crate::always_print!("🔍 [evdev] Found {} device: {:?} - {} ({})", kind, path.display(), device.name().unwrap_or("Unknown"), id);
+
+    // Set device to non-blocking mode to prevent blocking on idle devices
+    if let Err(e) = device.set_nonblocking(true) {
+        crate::always_eprint!("⚠️ [evdev] Failed to set non-blocking mode for {:?}: {}", path.display(), e);
+    }
+
+    if let Err(e) = poller.add(&device) {
+        crate::always_eprint!("⚠️ [evdev] Cannot listen to {:?}: {}", path.display(), e);
+        return None;
+    }
+
+    Some(InputNode { path, id, device, is_keyboard, is_mouse })
+}
+
+#[cfg(target_os = "linux")]
+fn detach_node(
+    node: &InputNode,
+    held: &mut Vec<(char, String, &'static str)>,
+    keyboard_tx: &Sender<String>,
+    mouse_tx: &Sender<String>,
+) {
+    crate::always_print!("🔌 [evdev] Device disconnected: {:?} ({})", node.path.display(), node.id);
+    for (kind, release) in release_held(held, &node.id) {
+        let _ = match kind {
+            'K' => keyboard_tx.send(release),
+            _ => mouse_tx.send(release),
+        };
+    }
+}
+
+/// Releases for the keys and buttons still held on a device that went away,
+/// so the engine does not keep them down until they are pressed elsewhere.
+#[cfg(target_os = "linux")]
+fn release_held(held: &mut Vec<(char, String, &'static str)>, device_id: &str) -> Vec<(char, String)> {
+    let mut releases = Vec::new();
+    held.retain(|(kind, id, code)| {
+        if id != device_id {
+            return true;
+        }
+        releases.push((*kind, format!("UP:{}", code)));
+        false
+    });
+    releases
+}
+
+/// A stable id for an evdev device, as stored in `enabled_keyboards` and
+/// `enabled_mice`. `/dev/input/eventN` numbers are handed out afresh on every
+/// boot and replug, so the id is built from what the device reports about
+/// itself: vendor and product, then its serial if it has one, otherwise its
+/// name. Two identical keyboards without a serial share an id.
+pub fn stable_device_id(vendor: u16, product: u16, serial: Option<&str>, name: Option<&str>) -> String {
+    let tail = serial
+        .filter(|serial| !serial.is_empty())
+        .or(name)
+        .unwrap_or("unknown");
+    format!("evdev:{:04x}:{:04x}:{}", vendor, product, tail)
+}
+
+#[cfg(target_os = "linux")]
+pub fn device_id(device: &evdev::Device) -> String {
+    let input_id = device.input_id();
+    stable_device_id(input_id.vendor(), input_id.product(), device.unique_name(), device.name())
+}
+
+/// A keyboard has at least one of these. evdev reports keys for far more
+/// than keyboards - power buttons, volume and lid switches, and every
+/// mouse, whose buttons are keys - so a keyboard is a device with the
+/// letter block, or a numpad's digits.
+#[cfg(target_os = "linux")]
+pub const TYPING_KEYS: [evdev::KeyCode; 3] = [
+    evdev::KeyCode::KEY_A,
+    evdev::KeyCode::KEY_SPACE,
+    evdev::KeyCode::KEY_KP0,
+];
+
+/// Whether `device` is something a person types on; see `TYPING_KEYS`.
+#[cfg(target_os = "linux")]
+pub fn is_typing_device(device: &evdev::Device) -> bool {
+    device.supported_keys().is_some_and(|keys| TYPING_KEYS.into_iter().any(|key| keys.contains(key)))
+}
+
+/// Whether `device` is a mouse or touchpad: it has a left button and moves,
+/// relatively or - a touchpad - in absolute coordinates. The same rule
+/// Settings uses to list mice.
+#[cfg(target_os = "linux")]
+pub fn is_pointing_device(device: &evdev::Device) -> bool {
+    use evdev::{AbsoluteAxisCode, KeyCode, RelativeAxisCode};
+
+    let has_left = device.supported_keys().is_some_and(|keys| keys.contains(KeyCode::BTN_LEFT));
+    let moves = device
+        .supported_relative_axes()
+        .is_some_and(|axes| axes.contains(RelativeAxisCode::REL_X) && axes.contains(RelativeAxisCode::REL_Y));
+    let points = device
+        .supported_absolute_axes()
+        .is_some_and(|axes| axes.contains(AbsoluteAxisCode::ABS_X) && axes.contains(AbsoluteAxisCode::ABS_Y));
+    has_left && (moves || points)
+}
+
+/// What one key or button event from device `device_id` sends the engine,
+/// after the device filter - the same rules as the Windows worker host.
+/// `kind` is `'K'` or `'M'`. A disabled device's presses are dropped, and a
+/// release or auto-repeat only goes out while a press that was sent is
+/// held, so keys held across a Settings change still get their release.
+#[cfg(target_os = "linux")]
+fn wire_for_event(
+    filter: &DeviceFilter,
+    held: &mut Vec<(char, String, &'static str)>,
+    kind: char,
+    device_id: &str,
+    code: &'static str,
+    value: i32,
+) -> Option<String> {
+    let is_held = |(held_kind, id, held_code): &(char, String, &'static str)| {
+        *held_kind == kind && id == device_id && *held_code == code
+    };
+
+    match value {
+        1 => {
+            if !filter.allows(kind, device_id) {
+                return None;
+            }
+            held.push((kind, device_id.to_string(), code));
+            Some(code.to_string())
+        }
+        0 => {
+            let index = held.iter().position(is_held)?;
+            held.remove(index);
+            Some(format!("UP:{}", code))
+        }
+        // Auto-repeat: the engine decides whether it makes a sound
+        2 => held.iter().any(is_held).then(|| format!("REPEAT:{}", code)),
+        _ => None,
+    }
+}
+
+/// The wheel code for one `REL_WHEEL` report from an allowed mouse. A report
+/// of several notches at once plays one, as rdev's wheel events did.
+#[cfg(target_os = "linux")]
+fn wheel_notch(filter: &DeviceFilter, device_id: &str, value: i32) -> Option<&'static str> {
+    if !filter.allows('M', device_id) {
+        return None;
+    }
+    match value.signum() {
+        1 => Some("MouseWheelUp"),
+        -1 => Some("MouseWheelDown"),
+        _ => None,
+    }
+}
+
+/// Maps a mouse button to the codes `input_listener.rs::map_button_to_code`
+/// produces, so mouse packs play the same under either listener. Back and
+/// forward are `BTN_SIDE`/`BTN_EXTRA` on nearly every mouse.
+#[cfg(target_os = "linux")]
+fn map_evdev_button(button: evdev::KeyCode) -> Option<&'static str> {
+    use evdev::KeyCode;
+
+    match button {
+        KeyCode::BTN_LEFT => Some("MouseLeft"),
+        KeyCode::BTN_RIGHT => Some("MouseRight"),
+        KeyCode::BTN_MIDDLE => Some("MouseMiddle"),
+        KeyCode::BTN_SIDE => Some("Mouse4"),
+        KeyCode::BTN_EXTRA => Some("Mouse5"),
+        KeyCode::BTN_FORWARD => Some("Mouse6"),
+        KeyCode::BTN_BACK => Some("Mouse7"),
+        KeyCode::BTN_TASK => Some("Mouse8"),
+        _ => None,
+    }
+}
+
 #[cfg(target_os = "linux")]
 fn map_evdev_keycode(key: evdev::KeyCode) -> &'static str {
     use evdev::KeyCode;
@@ -225,3 +525,93 @@ fn map_evdev_keycode(key: evdev::KeyCode) -> &'static str {
     }
 }
 
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn filter_for(keyboards: &[&str], mice: &[&str]) -> DeviceFilter {
+        DeviceFilter {
+            generation: 0,
+            enabled_keyboards: keyboards.iter().map(|id| id.to_string()).collect(),
+            enabled_mice: mice.iter().map(|id| id.to_string()).collect(),
+        }
+    }
+
+    #[test]
+    fn device_ids_prefer_the_serial_and_never_the_event_node() {
+        assert_eq!(
+            stable_device_id(0x046d, 0xc52b, Some("A1B2"), Some("Logitech USB Receiver")),
+            "evdev:046d:c52b:A1B2"
+        );
+        assert_eq!(
+            stable_device_id(0x1050, 0x0407, Some(""), Some("Yubico YubiKey OTP+FIDO+CCID")),
+            "evdev:1050:0407:Yubico YubiKey OTP+FIDO+CCID"
+        );
+        assert_eq!(stable_device_id(0, 0, None, None), "evdev:0000:0000:unknown");
+    }
+
+    #[test]
+    fn a_disabled_keyboard_makes_no_sound() {
+        let filter = filter_for(&["kb1"], &[]);
+        let mut held = Vec::new();
+
+        assert_eq!(wire_for_event(&filter, &mut held, 'K', "yubikey", "KeyC", 1), None);
+        assert_eq!(wire_for_event(&filter, &mut held, 'K', "yubikey", "KeyC", 2), None);
+        assert_eq!(wire_for_event(&filter, &mut held, 'K', "yubikey", "KeyC", 0), None);
+
+        assert_eq!(wire_for_event(&filter, &mut held, 'K', "kb1", "KeyC", 1).as_deref(), Some("KeyC"));
+        assert_eq!(wire_for_event(&filter, &mut held, 'K', "kb1", "KeyC", 2).as_deref(), Some("REPEAT:KeyC"));
+        assert_eq!(wire_for_event(&filter, &mut held, 'K', "kb1", "KeyC", 0).as_deref(), Some("UP:KeyC"));
+    }
+
+    #[test]
+    fn a_key_held_while_its_keyboard_is_disabled_still_gets_its_release() {
+        let mut held = Vec::new();
+        wire_for_event(&filter_for(&[], &[]), &mut held, 'K', "kb1", "KeyA", 1);
+
+        let disabled = filter_for(&["kb2"], &[]);
+        assert_eq!(wire_for_event(&disabled, &mut held, 'K', "kb1", "KeyA", 0).as_deref(), Some("UP:KeyA"));
+        assert!(held.is_empty());
+    }
+
+    #[test]
+    fn an_unplugged_device_releases_only_its_own_keys() {
+        let filter = filter_for(&[], &[]);
+        let mut held = Vec::new();
+        wire_for_event(&filter, &mut held, 'K', "kb1", "ShiftLeft", 1);
+        wire_for_event(&filter, &mut held, 'K', "kb2", "KeyA", 1);
+        wire_for_event(&filter, &mut held, 'M', "kb1", "MouseLeft", 1);
+
+        assert_eq!(release_held(&mut held, "kb1"), vec![
+            ('K', "UP:ShiftLeft".to_string()),
+            ('M', "UP:MouseLeft".to_string())
+        ]);
+        assert_eq!(held, vec![('K', "kb2".to_string(), "KeyA")]);
+    }
+
+    #[test]
+    fn mice_are_filtered_by_their_own_list() {
+        let filter = filter_for(&["kb1"], &["mouse1"]);
+        let mut held = Vec::new();
+
+        assert_eq!(wire_for_event(&filter, &mut held, 'M', "kb1", "MouseLeft", 1), None);
+        assert_eq!(wheel_notch(&filter, "kb1", 1), None);
+
+        assert_eq!(wire_for_event(&filter, &mut held, 'M', "mouse1", "MouseLeft", 1).as_deref(), Some("MouseLeft"));
+        assert_eq!(wire_for_event(&filter, &mut held, 'M', "mouse1", "MouseLeft", 0).as_deref(), Some("UP:MouseLeft"));
+        assert_eq!(wheel_notch(&filter, "mouse1", 3), Some("MouseWheelUp"));
+        assert_eq!(wheel_notch(&filter, "mouse1", -1), Some("MouseWheelDown"));
+    }
+
+    #[test]
+    fn buttons_map_to_the_rdev_listener_codes() {
+        use evdev::KeyCode;
+
+        assert_eq!(map_evdev_button(KeyCode::BTN_LEFT), Some("MouseLeft"));
+        assert_eq!(map_evdev_button(KeyCode::BTN_SIDE), Some("Mouse4"));
+        assert_eq!(map_evdev_button(KeyCode::BTN_EXTRA), Some("Mouse5"));
+        assert_eq!(map_evdev_button(KeyCode::KEY_A), None);
+        assert_eq!(map_evdev_keycode(KeyCode::BTN_LEFT), "", "buttons are never keys");
+    }
+}
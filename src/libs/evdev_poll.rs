@@ -0,0 +1,88 @@
+//! One epoll set over every evdev node a listener reads, plus the hotplug
+//! watcher, so the listener thread sleeps in the kernel until one of them
+//! has input instead of waking on a timer to ask each device in turn.
+//!
+//! Each fd is registered with itself as its token. The set is
+//! level-triggered: events a read leaves behind report the fd ready again
+//! on the next `wait`. Closing a device's fd takes it out of the set, so a
+//! dropped `evdev::Device` needs no separate removal.
+
+use std::os::fd::{ AsRawFd, FromRawFd, OwnedFd, RawFd };
+
+/// Ready fds handled per wake; more simply wait for the next one.
+const MAX_READY: usize = 16;
+
+pub struct Poller {
+    fd: OwnedFd,
+}
+
+impl Poller {
+    pub fn new() -> Result<Self, String> {
+        let fd = unsafe { libc::epoll_create1(libc::EPOLL_CLOEXEC) };
+        if fd < 0 {
+            return Err(format!("epoll_create1 failed: {}", std::io::Error::last_os_error()));
+        }
+        Ok(Self { fd: unsafe { OwnedFd::from_raw_fd(fd) } })
+    }
+
+    pub fn add(&self, fd: &impl AsRawFd) -> Result<(), String> {
+        let fd = fd.as_raw_fd();
+        let mut event = libc::epoll_event {
+            events: libc::EPOLLIN as u32,
+            u64: fd as u64,
+        };
+        if unsafe { libc::epoll_ctl(self.fd.as_raw_fd(), libc::EPOLL_CTL_ADD, fd, &mut event) } < 0 {
+            return Err(format!("epoll_ctl failed: {}", std::io::Error::last_os_error()));
+        }
+        Ok(())
+    }
+
+    /// Blocks until at least one registered fd is readable, hung up or in
+    /// error, and fills `ready` with them. A signal interrupting the wait
+    /// returns with `ready` empty.
+    pub fn wait(&self, ready: &mut Vec<RawFd>) -> Result<(), String> {
+        ready.clear();
+        let mut events = [libc::epoll_event { events: 0, u64: 0 }; MAX_READY];
+        let count = unsafe {
+            libc::epoll_wait(self.fd.as_raw_fd(), events.as_mut_ptr(), MAX_READY as i32, -1)
+        };
+        if count < 0 {
+            let error = std::io::Error::last_os_error();
+            if error.kind() == std::io::ErrorKind::Interrupted {
+                return Ok(());
+            }
+            return Err(format!("epoll_wait failed: {}", error));
+        }
+        ready.extend(events[..count as usize].iter().map(|event| event.u64 as RawFd));
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+    use std::os::unix::net::UnixStream;
+
+    #[test]
+    fn wait_returns_only_the_fds_with_input() {
+        let poller = Poller::new().unwrap();
+        let (mut quiet_writer, quiet) = UnixStream::pair().unwrap();
+        let (mut writer, reader) = UnixStream::pair().unwrap();
+        poller.add(&quiet).unwrap();
+        poller.add(&reader).unwrap();
+
+        writer.write_all(b"x").unwrap();
+        let mut ready = Vec::new();
+        poller.wait(&mut ready).unwrap();
+        assert_eq!(ready, vec![reader.as_raw_fd()]);
+
+        // Level-triggered: still unread, still ready
+        quiet_writer.write_all(b"y").unwrap();
+        poller.wait(&mut ready).unwrap();
+        ready.sort();
+        let mut expected = vec![quiet.as_raw_fd(), reader.as_raw_fd()];
+        expected.sort();
+        assert_eq!(ready, expected);
+    }
+}
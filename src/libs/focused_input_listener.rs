@@ -1,9 +1,14 @@
 use crossbeam_channel::Sender;
 use device_query::{ DeviceQuery, DeviceState, Keycode };
-use std::collections::HashSet;
+use std::collections::{ HashMap, HashSet };
 use std::sync::{ Arc, Mutex };
 use std::thread;
-use std::time::Duration;
+use std::time::{ Duration, Instant };
+
+/// Polled state has no OS auto-repeat to pass on, so a held key repeats on
+/// its own timer instead, after the usual desktop delay and at ~30Hz
+const REPEAT_DELAY: Duration = Duration::from_millis(500);
+const REPEAT_INTERVAL: Duration = Duration::from_millis(33);
 
 /// Maps device_query Keycode to our standardized key code format (same as rdev)
 fn map_device_query_keycode(key: Keycode) -> &'static str {
@@ -122,6 +127,54 @@ fn map_device_query_keycode(key: Keycode) -> &'static str {
     }
 }
 
+/// The keys down on the last poll, each with when it next repeats
+#[derive(Default)]
+struct HeldKeys {
+    next_repeat: HashMap<Keycode, Instant>,
+}
+
+impl HeldKeys {
+    /// Compares a poll with the last one and returns its wire events: a press
+    /// for each new key, a release for each key let go, and a `REPEAT:` for
+    /// each key still held whose repeat is due. Like the other listeners, a
+    /// key only repeats while it is held
+    fn update(&mut self, current: &HashSet<Keycode>, now: Instant) -> Vec<String> {
+        let mut events = Vec::new();
+
+        for key in current {
+            let key_code = map_device_query_keycode(*key);
+            if key_code.is_empty() {
+                continue;
+            }
+            match self.next_repeat.get_mut(key) {
+                None => {
+                    self.next_repeat.insert(*key, now + REPEAT_DELAY);
+                    events.push(key_code.to_string());
+                }
+                Some(due) if now >= *due => {
+                    *due = now + REPEAT_INTERVAL;
+                    events.push(format!("REPEAT:{}", key_code));
+                }
+                Some(_) => {}
+            }
+        }
+
+        self.next_repeat.retain(|key, _| {
+            let held = current.contains(key);
+            if !held {
+                events.push(format!("UP:{}", map_device_query_keycode(*key)));
+            }
+            held
+        });
+
+        events
+    }
+
+    fn clear(&mut self) {
+        self.next_repeat.clear();
+    }
+}
+
 /// Start the focused keyboard listener (uses device_query polling)
 /// This listener is ONLY active when the window is focused
 pub fn start_focused_keyboard_listener(
@@ -132,7 +185,7 @@ pub fn start_focused_keyboard_listener(
         crate::always_print!("🎮 Starting focused keyboard listener (device_query polling)...");
         
         let device_state = DeviceState::new();
-        let mut prev_keys: HashSet<Keycode> = HashSet::new();
+        let mut held = HeldKeys::default();
 
         let mut last_focus_log = std::time::Instant::now();
 
@@ -153,27 +206,13 @@ pub fn start_focused_keyboard_listener(
                 let keys = device_state.get_keys();
                 let current_keys: HashSet<Keycode> = keys.into_iter().collect();
 
-                // Detect newly pressed keys
-                for key in current_keys.difference(&prev_keys) {
-                    let key_code = map_device_query_keycode(*key);
-                    if !key_code.is_empty() {
-                        // Send key event without logging sensitive keystrokes
-                        let _ = keyboard_tx.send(key_code.to_string());
-                    }
-                }
-
-                // Detect released keys
-                for key in prev_keys.difference(&current_keys) {
-                    let key_code = map_device_query_keycode(*key);
-                    if !key_code.is_empty() {
-                        let _ = keyboard_tx.send(format!("UP:{}", key_code));
-                    }
+                // Send presses, releases and repeats without logging sensitive keystrokes
+                for event in held.update(&current_keys, Instant::now()) {
+                    let _ = keyboard_tx.send(event);
                 }
-
-                prev_keys = current_keys;
             } else {
                 // Window not focused - clear state and sleep longer
-                prev_keys.clear();
+                held.clear();
                 thread::sleep(Duration::from_millis(100));
                 continue;
             }
@@ -184,3 +223,44 @@ pub fn start_focused_keyboard_listener(
     });
 }
 
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn keys(list: &[Keycode]) -> HashSet<Keycode> {
+        list.iter().copied().collect()
+    }
+
+    #[test]
+    fn a_held_key_repeats_after_the_delay_and_only_while_held() {
+        let mut held = HeldKeys::default();
+        let start = Instant::now();
+        let down = keys(&[Keycode::A]);
+
+        assert_eq!(held.update(&down, start), vec!["KeyA".to_string()]);
+        assert!(held.update(&down, start + REPEAT_DELAY / 2).is_empty());
+        assert_eq!(held.update(&down, start + REPEAT_DELAY), vec!["REPEAT:KeyA".to_string()]);
+        assert!(held.update(&down, start + REPEAT_DELAY + REPEAT_INTERVAL / 2).is_empty());
+        assert_eq!(
+            held.update(&down, start + REPEAT_DELAY + REPEAT_INTERVAL),
+            vec!["REPEAT:KeyA".to_string()]
+        );
+
+        let later = start + REPEAT_DELAY * 4;
+        assert_eq!(held.update(&keys(&[]), later), vec!["UP:KeyA".to_string()]);
+        assert!(held.update(&keys(&[]), later + REPEAT_DELAY).is_empty());
+    }
+
+    #[test]
+    fn pressing_again_after_a_release_restarts_the_delay() {
+        let mut held = HeldKeys::default();
+        let start = Instant::now();
+        let down = keys(&[Keycode::A]);
+
+        held.update(&down, start);
+        held.update(&keys(&[]), start + REPEAT_DELAY / 2);
+        let again = start + REPEAT_DELAY;
+        assert_eq!(held.update(&down, again), vec!["KeyA".to_string()]);
+        assert!(held.update(&down, again + REPEAT_DELAY / 2).is_empty());
+    }
+}
@@ -0,0 +1,417 @@
+//! Global hotkeys: the actions a combo can be bound to, how a binding is
+//! written in `AppConfig`, and the one matcher every input listener (rdev,
+//! evdev and the Windows worker host) runs keystrokes through.
+//!
+//! A binding is stored as a string such as `"Ctrl+Alt+KeyM"`: any of the
+//! modifiers `Ctrl`, `Alt`, `Shift` and `Meta`, then one key code in the
+//! same `"KeyA"` form the listeners send. Left and right modifiers are not
+//! told apart, and modifiers match exactly - Ctrl+Alt+M does not fire while
+//! Shift is also held, so Ctrl+Alt+Shift+M can be bound to something else.
+//!
+//! A matched press is sent on the hotkey channel as the action's wire name
+//! (`"TOGGLE_SOUND"`, ...) and is not played as a normal keystroke.
+
+use serde::{ Deserialize, Serialize };
+use std::collections::HashMap;
+use std::sync::atomic::{ AtomicBool, AtomicU64, Ordering };
+use std::sync::Mutex;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum HotkeyAction {
+    ToggleSound,
+    VolumeUp,
+    VolumeDown,
+    NextKeyboardPack,
+    PreviousKeyboardPack,
+    ToggleAmbiance,
+    ShowWindow,
+}
+
+impl HotkeyAction {
+    pub const ALL: [HotkeyAction; 7] = [
+        HotkeyAction::ToggleSound,
+        HotkeyAction::VolumeUp,
+        HotkeyAction::VolumeDown,
+        HotkeyAction::NextKeyboardPack,
+        HotkeyAction::PreviousKeyboardPack,
+        HotkeyAction::ToggleAmbiance,
+        HotkeyAction::ShowWindow,
+    ];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            HotkeyAction::ToggleSound => "Mute / unmute",
+            HotkeyAction::VolumeUp => "Volume up",
+            HotkeyAction::VolumeDown => "Volume down",
+            HotkeyAction::NextKeyboardPack => "Next keyboard soundpack",
+            HotkeyAction::PreviousKeyboardPack => "Previous keyboard soundpack",
+            HotkeyAction::ToggleAmbiance => "Play / pause ambiance",
+            HotkeyAction::ShowWindow => "Show window",
+        }
+    }
+
+    /// The string sent on the hotkey channel. `"TOGGLE_SOUND"` predates the
+    /// registry and is kept as is.
+    pub fn wire_name(self) -> &'static str {
+        match self {
+            HotkeyAction::ToggleSound => "TOGGLE_SOUND",
+            HotkeyAction::VolumeUp => "VOLUME_UP",
+            HotkeyAction::VolumeDown => "VOLUME_DOWN",
+            HotkeyAction::NextKeyboardPack => "NEXT_KEYBOARD_PACK",
+            HotkeyAction::PreviousKeyboardPack => "PREVIOUS_KEYBOARD_PACK",
+            HotkeyAction::ToggleAmbiance => "TOGGLE_AMBIANCE",
+            HotkeyAction::ShowWindow => "SHOW_WINDOW",
+        }
+    }
+
+    pub fn from_wire_name(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|action| action.wire_name() == name)
+    }
+}
+
+/// One key combo. Serialized as its `Display` form, e.g. `"Ctrl+Alt+KeyM"`.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub struct Hotkey {
+    pub ctrl: bool,
+    pub alt: bool,
+    pub shift: bool,
+    pub meta: bool,
+    /// Key code of the non-modifier key, e.g. `"KeyM"`.
+    pub key: String,
+}
+
+impl Hotkey {
+    pub fn new(ctrl: bool, alt: bool, shift: bool, meta: bool, key: &str) -> Self {
+        Self { ctrl, alt, shift, meta, key: key.to_string() }
+    }
+
+    pub fn parse(text: &str) -> Result<Self, String> {
+        let mut hotkey = Hotkey::new(false, false, false, false, "");
+        for part in text.split('+').map(str::trim) {
+            if !hotkey.key.is_empty() {
+                return Err(format!("`{}`: the key must come last", text));
+            }
+            match part.to_ascii_lowercase().as_str() {
+                "ctrl" | "control" => {
+                    hotkey.ctrl = true;
+                }
+                "alt" => {
+                    hotkey.alt = true;
+                }
+                "shift" => {
+                    hotkey.shift = true;
+                }
+                "meta" | "super" | "win" | "cmd" => {
+                    hotkey.meta = true;
+                }
+                "" => {
+                    return Err(format!("`{}` has an empty part", text));
+                }
+                _ if is_modifier(part) => {
+                    return Err(format!("`{}`: {} is a modifier, not a key", text, part));
+                }
+                _ => {
+                    hotkey.key = part.to_string();
+                }
+            }
+        }
+
+        if hotkey.key.is_empty() {
+            return Err(format!("`{}` has no key", text));
+        }
+        if !hotkey.has_modifier() {
+            return Err(format!("`{}` needs at least one modifier", text));
+        }
+        Ok(hotkey)
+    }
+
+    /// A bare key would swallow that key from normal typing, so every
+    /// binding needs at least one modifier.
+    pub fn has_modifier(&self) -> bool {
+        self.ctrl || self.alt || self.shift || self.meta
+    }
+
+    /// Human-readable form for the UI: `"Ctrl+Alt+M"` rather than `"KeyM"`.
+    pub fn label(&self) -> String {
+        let key = self.key
+            .strip_prefix("Key")
+            .or_else(|| self.key.strip_prefix("Digit"))
+            .unwrap_or(&self.key);
+        let mut parts: Vec<&str> = self.modifier_names().collect();
+        parts.push(key);
+        parts.join("+")
+    }
+
+    fn modifier_names(&self) -> impl Iterator<Item = &'static str> {
+        [(self.ctrl, "Ctrl"), (self.alt, "Alt"), (self.shift, "Shift"), (self.meta, "Meta")]
+            .into_iter()
+            .filter(|(held, _)| *held)
+            .map(|(_, name)| name)
+    }
+}
+
+impl std::fmt::Display for Hotkey {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for name in self.modifier_names() {
+            write!(f, "{}+", name)?;
+        }
+        write!(f, "{}", self.key)
+    }
+}
+
+impl TryFrom<String> for Hotkey {
+    type Error = String;
+
+    fn try_from(text: String) -> Result<Self, Self::Error> {
+        Hotkey::parse(&text)
+    }
+}
+
+impl From<Hotkey> for String {
+    fn from(hotkey: Hotkey) -> Self {
+        hotkey.to_string()
+    }
+}
+
+pub type HotkeyBindings = HashMap<HotkeyAction, Hotkey>;
+
+/// Out of the box only mute is bound, to the Ctrl+Alt+M it has always had.
+pub fn default_bindings() -> HotkeyBindings {
+    HashMap::from([(HotkeyAction::ToggleSound, Hotkey::new(true, true, false, false, "KeyM"))])
+}
+
+/// The action other than `action` already bound to `hotkey`, if any.
+pub fn find_conflict(
+    bindings: &HotkeyBindings,
+    action: HotkeyAction,
+    hotkey: &Hotkey
+) -> Option<HotkeyAction> {
+    HotkeyAction::ALL.into_iter().find(
+        |other| *other != action && bindings.get(other) == Some(hotkey)
+    )
+}
+
+/// Binds `hotkey` to `action`, taking it away from any action that had it,
+/// so two actions never share a combo.
+pub fn bind(bindings: &mut HotkeyBindings, action: HotkeyAction, hotkey: Hotkey) {
+    if let Some(other) = find_conflict(bindings, action, &hotkey) {
+        bindings.remove(&other);
+    }
+    bindings.insert(action, hotkey);
+}
+
+/// Whether `code` is one of the modifier keys a binding is built from.
+pub fn is_modifier(code: &str) -> bool {
+    modifier_bit(code).is_some()
+}
+
+fn modifier_bit(code: &str) -> Option<u8> {
+    match code {
+        "ControlLeft" => Some(1 << 0),
+        "ControlRight" => Some(1 << 1),
+        "AltLeft" => Some(1 << 2),
+        "AltRight" => Some(1 << 3),
+        "ShiftLeft" => Some(1 << 4),
+        "ShiftRight" => Some(1 << 5),
+        "MetaLeft" => Some(1 << 6),
+        "MetaRight" => Some(1 << 7),
+        _ => None,
+    }
+}
+
+/// Bumped whenever Settings changes `AppConfig::hotkeys`, so each listener's
+/// matcher notices its cached bindings are stale.
+static BINDINGS_GENERATION: AtomicU64 = AtomicU64::new(0);
+
+/// The bindings themselves, published alongside the generation so listener
+/// threads never read the config authority per keystroke.
+static PUBLISHED_BINDINGS: Mutex<Option<HotkeyBindings>> = Mutex::new(None);
+
+/// Set while Settings is recording a new combo, so pressing the current
+/// mute combo to rebind it records it instead of muting.
+static CAPTURING: AtomicBool = AtomicBool::new(false);
+
+/// Call after `AppConfig::hotkeys` changes to make the new bindings take
+/// effect in every listener.
+pub fn notify_hotkeys_changed() {
+    let config = crate::state::config_writer::current();
+    *PUBLISHED_BINDINGS.lock().unwrap() = Some(config.hotkeys);
+    // Published after the bindings, so a matcher that sees the new generation
+    // is guaranteed to find the new bindings behind it.
+    BINDINGS_GENERATION.fetch_add(1, Ordering::Release);
+}
+
+pub fn set_capturing(capturing: bool) {
+    CAPTURING.store(capturing, Ordering::Relaxed);
+}
+
+/// Tracks which modifiers are held on one input stream and recognizes bound
+/// combos. Each listener owns one, fed with every key event in order.
+pub struct HotkeyMatcher {
+    /// `None` for fixed bindings that never follow config (tests).
+    generation: Option<u64>,
+    bindings: Vec<(Hotkey, HotkeyAction)>,
+    held_modifiers: u8,
+}
+
+impl HotkeyMatcher {
+    /// A matcher over the configured bindings, following later changes.
+    pub fn new() -> Self {
+        let generation = BINDINGS_GENERATION.load(Ordering::Acquire);
+        let mut matcher = Self::with_bindings(&published_bindings());
+        matcher.generation = Some(generation);
+        matcher
+    }
+
+    pub fn with_bindings(bindings: &HotkeyBindings) -> Self {
+        Self {
+            generation: None,
+            bindings: bindings
+                .iter()
+                .map(|(action, hotkey)| (hotkey.clone(), *action))
+                .collect(),
+            held_modifiers: 0,
+        }
+    }
+
+    fn refresh_if_stale(&mut self) {
+        let Some(seen) = self.generation else {
+            return;
+        };
+        let current = BINDINGS_GENERATION.load(Ordering::Acquire);
+        if current != seen {
+            let held_modifiers = self.held_modifiers;
+            *self = Self::with_bindings(&published_bindings());
+            self.generation = Some(current);
+            self.held_modifiers = held_modifiers;
+        }
+    }
+
+    /// Feeds one key event through. Returns the action when this press
+    /// completes a bound combo; the caller sends it and does not play the
+    /// key as a normal keystroke.
+    pub fn observe(&mut self, code: &str, is_down: bool) -> Option<HotkeyAction> {
+        if let Some(bit) = modifier_bit(code) {
+            if is_down {
+                self.held_modifiers |= bit;
+            } else {
+                self.held_modifiers &= !bit;
+            }
+            return None;
+        }
+        if !is_down || CAPTURING.load(Ordering::Relaxed) {
+            return None;
+        }
+
+        self.refresh_if_stale();
+        let held = Hotkey::new(
+            self.held_modifiers & 0b0000_0011 != 0,
+            self.held_modifiers & 0b0000_1100 != 0,
+            self.held_modifiers & 0b0011_0000 != 0,
+            self.held_modifiers & 0b1100_0000 != 0,
+            code
+        );
+        if !held.has_modifier() {
+            return None;
+        }
+        self.bindings
+            .iter()
+            .find(|(hotkey, _)| *hotkey == held)
+            .map(|(_, action)| *action)
+    }
+}
+
+fn published_bindings() -> HotkeyBindings {
+    PUBLISHED_BINDINGS.lock()
+        .unwrap()
+        .clone()
+        .unwrap_or_else(|| crate::state::config_writer::current().hotkeys)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn hotkeys_round_trip_through_their_config_string() {
+        let hotkey = Hotkey::parse("Ctrl+Alt+KeyM").unwrap();
+        assert_eq!(hotkey, Hotkey::new(true, true, false, false, "KeyM"));
+        assert_eq!(hotkey.to_string(), "Ctrl+Alt+KeyM");
+        assert_eq!(hotkey.label(), "Ctrl+Alt+M");
+
+        let json = serde_json::to_string(&default_bindings()).unwrap();
+        assert_eq!(json, r#"{"toggle_sound":"Ctrl+Alt+KeyM"}"#);
+        assert_eq!(serde_json::from_str::<HotkeyBindings>(&json).unwrap(), default_bindings());
+    }
+
+    #[test]
+    fn malformed_hotkeys_are_rejected() {
+        assert!(Hotkey::parse("KeyM").is_err(), "needs a modifier");
+        assert!(Hotkey::parse("Ctrl+Alt").is_err(), "needs a key");
+        assert!(Hotkey::parse("Ctrl+KeyM+Alt").is_err(), "key must come last");
+        assert!(Hotkey::parse("Ctrl++KeyM").is_err());
+        assert!(Hotkey::parse("Ctrl+ShiftLeft").is_err());
+    }
+
+    #[test]
+    fn a_combo_fires_only_with_exactly_its_modifiers_held() {
+        let mut matcher = HotkeyMatcher::with_bindings(&default_bindings());
+        assert_eq!(matcher.observe("KeyM", true), None);
+
+        matcher.observe("ControlLeft", true);
+        assert_eq!(matcher.observe("KeyM", true), None);
+
+        matcher.observe("AltRight", true);
+        assert_eq!(matcher.observe("KeyM", true), Some(HotkeyAction::ToggleSound));
+        assert_eq!(matcher.observe("KeyM", false), None, "releases never fire");
+
+        matcher.observe("ShiftLeft", true);
+        assert_eq!(matcher.observe("KeyM", true), None, "an extra modifier is a different combo");
+        matcher.observe("ShiftLeft", false);
+
+        // Releasing a modifier disarms it again.
+        matcher.observe("ControlLeft", false);
+        assert_eq!(matcher.observe("KeyM", true), None);
+    }
+
+    #[test]
+    fn either_side_of_a_modifier_holds_it_until_both_are_released() {
+        let mut matcher = HotkeyMatcher::with_bindings(&default_bindings());
+        matcher.observe("AltLeft", true);
+        matcher.observe("ControlLeft", true);
+        matcher.observe("ControlRight", true);
+        matcher.observe("ControlLeft", false);
+        assert_eq!(matcher.observe("KeyM", true), Some(HotkeyAction::ToggleSound));
+    }
+
+    #[test]
+    fn a_conflict_names_the_other_action_using_the_combo() {
+        let mut bindings = default_bindings();
+        let combo = Hotkey::parse("Ctrl+Alt+KeyM").unwrap();
+        assert_eq!(find_conflict(&bindings, HotkeyAction::VolumeUp, &combo), Some(HotkeyAction::ToggleSound));
+        assert_eq!(find_conflict(&bindings, HotkeyAction::ToggleSound, &combo), None, "rebinding to itself");
+
+        bindings.insert(HotkeyAction::VolumeUp, Hotkey::parse("Ctrl+Alt+ArrowUp").unwrap());
+        let other = Hotkey::parse("Ctrl+Alt+ArrowDown").unwrap();
+        assert_eq!(find_conflict(&bindings, HotkeyAction::VolumeDown, &other), None);
+    }
+
+    #[test]
+    fn binding_a_taken_combo_moves_it() {
+        let mut bindings = default_bindings();
+        bind(&mut bindings, HotkeyAction::ShowWindow, Hotkey::parse("Ctrl+Alt+KeyM").unwrap());
+
+        assert!(!bindings.contains_key(&HotkeyAction::ToggleSound));
+        assert_eq!(bindings[&HotkeyAction::ShowWindow].to_string(), "Ctrl+Alt+KeyM");
+    }
+
+    #[test]
+    fn wire_names_map_back_to_their_action() {
+        for action in HotkeyAction::ALL {
+            assert_eq!(HotkeyAction::from_wire_name(action.wire_name()), Some(action));
+        }
+        assert_eq!(HotkeyAction::from_wire_name("KeyA"), None);
+    }
+}
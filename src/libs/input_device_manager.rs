@@ -17,10 +17,18 @@ pub struct InputDeviceInfo {
     pub device_type: InputDeviceType,
     pub vendor_id: u16,
     pub product_id: u16,
+    /// How the device is attached ("USB", "Bluetooth", "i8042" for a laptop's
+    /// built-in keyboard...), where the platform reports it.
+    pub bus_type: Option<String>,
+    /// Where the device sits on its bus, e.g. `usb-0000:00:14.0-2/input0`.
+    /// Changes when it moves to another port, so it is never part of `id`.
+    pub phys: Option<String>,
+    /// Serial number or Bluetooth address, if the device reports one.
+    pub uniq: Option<String>,
     pub is_enabled: bool,
 }
 
-#[derive(Debug, Clone, PartialEq)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
 pub enum InputDeviceType {
     Keyboard,
     Mouse,
@@ -29,7 +37,9 @@ pub enum InputDeviceType {
 
 #[allow(dead_code)]
 pub struct InputDeviceManager {
-    devices: HashMap<String, InputDeviceInfo>,
+    /// Keyed by id and type, since a keyboard with a built-in touchpad is
+    /// listed both as a keyboard and as a mouse under the one id.
+    devices: HashMap<(String, InputDeviceType), InputDeviceInfo>,
     enabled_keyboards: Vec<String>,
     enabled_mice: Vec<String>,
 }
@@ -50,7 +60,11 @@ impl InputDeviceManager {
         {
             self.enumerate_windows_devices()
         }
-        #[cfg(not(windows))]
+        #[cfg(target_os = "linux")]
+        {
+            self.enumerate_linux_devices()
+        }
+        #[cfg(not(any(windows, target_os = "linux")))]
         {
             // For non-Windows platforms, we'll use a simplified approach
             // that just returns the default device
@@ -107,7 +121,10 @@ impl InputDeviceManager {
                             InputDeviceType::Keyboard | InputDeviceType::Mouse
                         )
                     {
-                        self.devices.insert(device_info.id.clone(), device_info);
+                        self.devices.insert(
+                            (device_info.id.clone(), device_info.device_type.clone()),
+                            device_info
+                        );
                     }
                 }
             }
@@ -197,28 +214,64 @@ impl InputDeviceManager {
             device_type,
             vendor_id,
             product_id: product_id as u16,
+            bus_type: None,
+            phys: None,
+            uniq: None,
             is_enabled: true, // Default to enabled
         })
     }
 
-    #[cfg(not(windows))]
+    /// Lists keyboards and mice from sysfs rather than by opening
+    /// `/dev/input/event*`: the nodes need the `input` group, their sysfs
+    /// attributes are world-readable, so Settings can show every device
+    /// even before evdev input works. Each id is the one the evdev listener
+    /// computes for the same device (`evdev_input_listener::device_id`).
+    ///
+    /// Event nodes that share an id and role - one physical device exposing
+    /// several, or two identical ones without a serial - are listed once.
+    #[cfg(target_os = "linux")]
+    fn enumerate_linux_devices(&mut self) -> Result<(), String> {
+        let entries = std::fs
+            ::read_dir(SYSFS_INPUT)
+            .map_err(|e| format!("Failed to read {}: {}", SYSFS_INPUT, e))?;
+
+        for entry in entries.flatten() {
+            if !entry.file_name().to_string_lossy().starts_with("event") {
+                continue;
+            }
+            for device_info in linux_device_info(&entry.path().join("device")) {
+                self.devices
+                    .entry((device_info.id.clone(), device_info.device_type.clone()))
+                    .or_insert(device_info);
+            }
+        }
+        Ok(())
+    }
+
+    #[cfg(not(any(windows, target_os = "linux")))]
     fn add_default_devices(&mut self) {
         // Add default keyboard and mouse for non-Windows platforms
-        self.devices.insert("default_keyboard".to_string(), InputDeviceInfo {
+        self.devices.insert(("default_keyboard".to_string(), InputDeviceType::Keyboard), InputDeviceInfo {
             id: "default_keyboard".to_string(),
             name: "Default Keyboard".to_string(),
             device_type: InputDeviceType::Keyboard,
             vendor_id: 0,
             product_id: 0,
+            bus_type: None,
+            phys: None,
+            uniq: None,
             is_enabled: true,
         });
 
-        self.devices.insert("default_mouse".to_string(), InputDeviceInfo {
+        self.devices.insert(("default_mouse".to_string(), InputDeviceType::Mouse), InputDeviceInfo {
             id: "default_mouse".to_string(),
             name: "Default Mouse".to_string(),
             device_type: InputDeviceType::Mouse,
             vendor_id: 0,
             product_id: 0,
+            bus_type: None,
+            phys: None,
+            uniq: None,
             is_enabled: true,
         });
     }
@@ -478,7 +531,7 @@ impl InputDeviceManager {
 
     /// Enable/disable a specific device
     pub fn set_device_enabled(&mut self, device_id: &str, enabled: bool) {
-        if let Some(device) = self.devices.get_mut(device_id) {
+        for device in self.devices.values_mut().filter(|device| device.id == device_id) {
             device.is_enabled = enabled;
 
             match device.device_type {
@@ -535,3 +588,200 @@ impl Default for InputDeviceManager {
         Self::new()
     }
 }
+
+#[cfg(target_os = "linux")]
+const SYSFS_INPUT: &str = "/sys/class/input";
+
+/// An evdev capability bitmap as sysfs prints it (`capabilities/key`,
+/// `rel`, `abs`): hex words of a kernel `long`, most significant first,
+/// with bit `n` set when the device reports code `n`.
+#[cfg(target_os = "linux")]
+struct CapabilityBits(Vec<u64>);
+
+#[cfg(target_os = "linux")]
+impl CapabilityBits {
+    const WORD_BITS: usize = usize::BITS as usize;
+
+    fn parse(text: &str) -> Self {
+        Self(
+            text
+                .split_whitespace()
+                .rev()
+                .map(|word| u64::from_str_radix(word, 16).unwrap_or(0))
+                .collect()
+        )
+    }
+
+    fn has(&self, code: u16) -> bool {
+        let code = code as usize;
+        self.0
+            .get(code / Self::WORD_BITS)
+            .is_some_and(|word| (word >> (code % Self::WORD_BITS)) & 1 == 1)
+    }
+}
+
+/// Keyboard, mouse or both, from what the device reports it can send - the
+/// same rules the evdev listener attaches by. A keyboard has typing keys
+/// (`TYPING_KEYS`); a mouse has a left button and moves, relatively or - for
+/// a touchpad - in absolute coordinates. A keyboard with a built-in touchpad
+/// is both, and empty means neither.
+#[cfg(target_os = "linux")]
+fn classify(keys: &CapabilityBits, rel: &CapabilityBits, abs: &CapabilityBits) -> Vec<InputDeviceType> {
+    use crate::libs::evdev_input_listener::TYPING_KEYS;
+    use evdev::{ AbsoluteAxisCode, KeyCode, RelativeAxisCode };
+
+    let moves =
+        (rel.has(RelativeAxisCode::REL_X.0) && rel.has(RelativeAxisCode::REL_Y.0)) ||
+        (abs.has(AbsoluteAxisCode::ABS_X.0) && abs.has(AbsoluteAxisCode::ABS_Y.0));
+
+    let mut roles = Vec::new();
+    if TYPING_KEYS.iter().any(|key| keys.has(key.code())) {
+        roles.push(InputDeviceType::Keyboard);
+    }
+    if keys.has(KeyCode::BTN_LEFT.code()) && moves {
+        roles.push(InputDeviceType::Mouse);
+    }
+    roles
+}
+
+/// Reads one input device's sysfs directory (`/sys/class/input/eventN/device`),
+/// listing it once per role it has; nothing for a device that is neither a
+/// keyboard nor a mouse.
+#[cfg(target_os = "linux")]
+fn linux_device_info(dir: &std::path::Path) -> Vec<InputDeviceInfo> {
+    use crate::libs::evdev_input_listener::stable_device_id;
+
+    let read = |attribute: &str| {
+        std::fs
+            ::read_to_string(dir.join(attribute))
+            .ok()
+            .map(|value| value.trim().to_string())
+            .filter(|value| !value.is_empty())
+    };
+    let read_hex = |attribute: &str| {
+        read(attribute)
+            .and_then(|value| u16::from_str_radix(&value, 16).ok())
+            .unwrap_or(0)
+    };
+    let capabilities = |kind: &str| {
+        CapabilityBits::parse(&read(&format!("capabilities/{}", kind)).unwrap_or_default())
+    };
+
+    let roles = classify(&capabilities("key"), &capabilities("rel"), &capabilities("abs"));
+    if roles.is_empty() {
+        return Vec::new();
+    }
+
+    let name = read("name");
+    let uniq = read("uniq");
+    let vendor_id = read_hex("id/vendor");
+    let product_id = read_hex("id/product");
+    let device = InputDeviceInfo {
+        id: stable_device_id(vendor_id, product_id, uniq.as_deref(), name.as_deref()),
+        name: name.unwrap_or_else(|| "Input Device".to_string()),
+        device_type: InputDeviceType::Unknown,
+        vendor_id,
+        product_id,
+        bus_type: Some(evdev::BusType(read_hex("id/bustype")).to_string()),
+        phys: read("phys"),
+        uniq,
+        is_enabled: true,
+    };
+
+    roles
+        .into_iter()
+        .map(|device_type| InputDeviceInfo { device_type, ..device.clone() })
+        .collect()
+}
+
+#[cfg(all(test, target_os = "linux"))]
+mod tests {
+    use super::*;
+    use crate::libs::audio::test_support::ScratchDir;
+
+    fn sysfs_device(tag: &str, attributes: &[(&str, &str)]) -> ScratchDir {
+        let dir = ScratchDir::new(&format!("sysfs-{}", tag));
+        for (attribute, value) in attributes {
+            let path = dir.join(attribute);
+            std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+            std::fs::write(path, format!("{}\n", value)).unwrap();
+        }
+        dir
+    }
+
+    #[test]
+    fn capability_words_are_read_least_significant_last() {
+        // KEY_ESC (1) in the low word, BTN_LEFT (0x110) in the fifth
+        let bits = CapabilityBits::parse("10000 0 0 0 2");
+        assert!(bits.has(1));
+        assert!(bits.has(0x110));
+        assert!(!bits.has(0x111));
+        assert!(!bits.has(0x2ff), "past the end is unset");
+    }
+
+    #[test]
+    fn a_usb_keyboard_is_listed_with_the_id_the_listener_uses() {
+        let dir = sysfs_device("keyboard", &[
+            ("name", "Keychron K2"),
+            ("phys", "usb-0000:00:14.0-2/input0"),
+            ("uniq", ""),
+            ("id/bustype", "0003"),
+            ("id/vendor", "05ac"),
+            ("id/product", "024f"),
+            // Typing keys, but no pointer
+            ("capabilities/key", "1000000000007 ff800000000007ff febeffdff3cfffff fffffffffffffffe"),
+            ("capabilities/rel", "0"),
+        ]);
+
+        let [device] = linux_device_info(&dir).try_into().unwrap();
+        assert_eq!(device.device_type, InputDeviceType::Keyboard);
+        assert_eq!(device.id, "evdev:05ac:024f:Keychron K2");
+        assert_eq!(device.bus_type.as_deref(), Some("USB"));
+        assert_eq!(device.phys.as_deref(), Some("usb-0000:00:14.0-2/input0"));
+        assert_eq!(device.uniq, None);
+    }
+
+    #[test]
+    fn mice_are_told_apart_from_power_buttons() {
+        let mouse = sysfs_device("mouse", &[
+            ("name", "Logitech G203"),
+            ("uniq", "1A2B3C"),
+            ("id/bustype", "0005"),
+            ("id/vendor", "046d"),
+            ("id/product", "c092"),
+            ("capabilities/key", "1f0000 0 0 0 0"),
+            ("capabilities/rel", "1943"),
+        ]);
+        let power_button = sysfs_device("power", &[
+            ("name", "Power Button"),
+            ("id/bustype", "0019"),
+            ("capabilities/key", "10000000000000 0"),
+        ]);
+
+        let [device] = linux_device_info(&mouse).try_into().unwrap();
+        assert_eq!(device.device_type, InputDeviceType::Mouse);
+        assert_eq!(device.id, "evdev:046d:c092:1A2B3C", "the serial identifies it");
+        assert_eq!(device.bus_type.as_deref(), Some("Bluetooth"));
+        assert!(linux_device_info(&power_button).is_empty());
+
+    }
+
+    #[test]
+    fn a_keyboard_with_a_touchpad_is_listed_as_both() {
+        let dir = sysfs_device("combo", &[
+            ("name", "Logitech K400 Plus"),
+            ("id/bustype", "0003"),
+            ("id/vendor", "046d"),
+            ("id/product", "404d"),
+            // Typing keys and BTN_LEFT, moving by REL_X/REL_Y
+            ("capabilities/key", "1f0000 1000000000007 ff800000000007ff febeffdff3cfffff fffffffffffffffe"),
+            ("capabilities/rel", "1943"),
+        ]);
+
+        let roles: Vec<_> = linux_device_info(&dir).into_iter().map(|device| (device.id, device.device_type)).collect();
+        assert_eq!(roles, vec![
+            ("evdev:046d:404d:Logitech K400 Plus".to_string(), InputDeviceType::Keyboard),
+            ("evdev:046d:404d:Logitech K400 Plus".to_string(), InputDeviceType::Mouse)
+        ]);
+    }
+}
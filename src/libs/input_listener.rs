@@ -178,9 +178,8 @@ pub fn start_unified_input_listener(
         let pressed_keys = Arc::new(Mutex::new(HashSet::<String>::new()));
         let pressed_buttons = Arc::new(Mutex::new(HashSet::<String>::new()));
 
-        // Track pressed modifier keys for hotkey detection
-        let mut ctrl_pressed = false;
-        let mut alt_pressed = false;
+        // Tracks held modifiers and recognizes the configured hotkeys
+        let mut hotkeys = crate::libs::hotkeys::HotkeyMatcher::new();
 
         crate::always_print!("🎮 Starting rdev::listen() - listening to keyboard/mouse events");
         let result = listen(move |event: Event| {
@@ -189,25 +188,18 @@ pub fn start_unified_input_listener(
                 EventType::KeyPress(key) => {
                     let key_code = map_key_to_code(key);
                     if !key_code.is_empty() {
-                        // Track modifier keys for hotkey detection
-                        match key_code {
-                            "ControlLeft" | "ControlRight" => {
-                                ctrl_pressed = true;
-                            }
-                            "AltLeft" | "AltRight" => {
-                                alt_pressed = true;
-                            }
-                            "KeyM" => {
-                                // Check for Ctrl+Alt+M hotkey combination
-                                if ctrl_pressed && alt_pressed {
-                                    crate::always_print!(
-                                        "🔥 Hotkey detected: Ctrl+Alt+M - Toggling global sound"
-                                    );
-                                    let _ = hotkey_tx.send("TOGGLE_SOUND".to_string());
-                                    return; // Don't process this as a regular key event
-                                }
-                            }
-                            _ => {}
+                        // A press for a key that is already down is the OS
+                        // auto-repeating it. Recorded before anything else, so
+                        // a held hotkey and a key held while focused are known
+                        // to be down too
+                        let is_repeat = !pressed_keys.lock().unwrap().insert(key_code.to_string());
+
+                        // Repeats are not new presses, so holding a hotkey fires it once
+                        let hotkey = if is_repeat { None } else { hotkeys.observe(key_code, true) };
+                        if let Some(action) = hotkey {
+                            crate::always_print!("🔥 Hotkey detected: {}", action.label());
+                            let _ = hotkey_tx.send(action.wire_name().to_string());
+                            return; // Don't process this as a regular key event
                         }
 
                         // If focus state is provided, only send keyboard events when UNFOCUSED
@@ -218,13 +210,13 @@ pub fn start_unified_input_listener(
                             }
                         }
 
-                        // Check if key is already pressed
-                        let mut pressed = pressed_keys.lock().unwrap();
-                        if pressed.contains(&key_code.to_string()) {
-                            return; // Key already pressed, ignore
+                        // Pass a repeat on as a repeat, not a press
+                        if is_repeat {
+                            let _ = keyboard_tx.send(format!("REPEAT:{}", key_code));
+                            return;
                         }
-                        pressed.insert(key_code.to_string());
-                        drop(pressed); // Apply debounce and detect rapid key events
+
+                        // Apply debounce and detect rapid key events
                         let now = Instant::now();
                         let mut last = keyboard_last_press.lock().unwrap();
                         let time_since_last = now.duration_since(*last);
@@ -244,15 +236,11 @@ pub fn start_unified_input_listener(
                     let key_code = map_key_to_code(key);
                     if !key_code.is_empty() {
                         // Track modifier key releases for hotkey detection
-                        match key_code {
-                            "ControlLeft" | "ControlRight" => {
-                                ctrl_pressed = false;
-                            }
-                            "AltLeft" | "AltRight" => {
-                                alt_pressed = false;
-                            }
-                            _ => {}
-                        }
+                        hotkeys.observe(key_code, false);
+
+                        // Remove key from pressed set, focused or not, since
+                        // presses are recorded either way
+                        pressed_keys.lock().unwrap().remove(&key_code.to_string());
 
                         // If focus state is provided, only send keyboard events when UNFOCUSED
                         if let Some(ref focus_state) = is_focused {
@@ -261,11 +249,6 @@ pub fn start_unified_input_listener(
                             }
                         }
 
-                        // Remove key from pressed set
-                        let mut pressed = pressed_keys.lock().unwrap();
-                        pressed.remove(&key_code.to_string());
-                        drop(pressed);
-
                         let _ = keyboard_tx.send(format!("UP:{}", key_code));
                     }
                 }
@@ -13,8 +13,8 @@
 //! ## Wire format (worker -> host, one line per event)
 //!
 //! ```text
-//! K\t{device_id}\t{code}\t{down|up}    keyboard
-//! M\t{device_id}\t{code}\t{down|up}    mouse
+//! K\t{device_id}\t{code}\t{down|up|repeat}    keyboard
+//! M\t{device_id}\t{code}\t{down|up}           mouse
 //! ```
 //!
 //! `device_id` is the hash `InputDeviceManager` computes for that physical
@@ -64,7 +64,13 @@ pub fn run() {
                 EventKind::Mouse => 'M',
             };
             let device_id = event.device_id.as_deref().unwrap_or("-");
-            let direction = if event.is_down { "down" } else { "up" };
+            let direction = if event.is_repeat {
+                "repeat"
+            } else if event.is_down {
+                "down"
+            } else {
+                "up"
+            };
 
             // Flushed per event on purpose: a BufWriter that only flushes
             // when full would hold keystrokes back until the next burst,
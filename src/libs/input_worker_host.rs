@@ -4,7 +4,7 @@
 //! when rdev produced those events.
 //!
 //! Everything that needs `AppConfig` lives here rather than in the worker:
-//! per-device filtering and the Ctrl+Alt+M hotkey. That keeps config in one
+//! per-device filtering and the global hotkeys. That keeps config in one
 //! process and the pipe one-directional.
 //!
 //! If the worker cannot be kept alive (spawn failure, or it dies
@@ -16,10 +16,10 @@ use crossbeam_channel::Sender;
 use std::io::{ BufRead, BufReader };
 use std::os::windows::process::CommandExt;
 use std::process::{ Child, Command, Stdio };
-use std::sync::atomic::{ AtomicU64, Ordering };
-use std::sync::Mutex;
 use std::time::{ Duration, Instant };
 
+use crate::libs::device_filter::DeviceFilter;
+use crate::libs::hotkeys::HotkeyMatcher;
 use crate::libs::input_worker::WORKER_ARG;
 
 /// `CREATE_NO_WINDOW` - keeps the worker from flashing a console window.
@@ -35,107 +35,6 @@ const MAX_RESTARTS: u32 = 5;
 /// and then dies once would be treated as the 2nd of 5 strikes forever.
 const HEALTHY_UPTIME: Duration = Duration::from_secs(30);
 
-/// Bumped whenever Settings changes `enabled_keyboards`/`enabled_mice`, so
-/// the reader thread notices its cached filter is stale.
-static CONFIG_GENERATION: AtomicU64 = AtomicU64::new(0);
-
-/// The lists themselves, published alongside the generation so the reader
-/// thread never has to go looking for them. Reading them from the config
-/// authority costs a clone; the `AppConfig::load()` this replaced parsed JSON,
-/// read the registry for the auto-start state and could rewrite the file - none
-/// of which belongs on the thread draining the worker's pipe, because stalling
-/// that reader back-pressures the pipe and, through it, the worker's WndProc.
-static ENABLED_DEVICES: Mutex<Option<(Vec<String>, Vec<String>)>> = Mutex::new(None);
-
-/// Call after `AppConfig`'s enabled-device lists change (from
-/// `device_selector.rs`) to make filtering take effect immediately.
-pub fn notify_config_changed() {
-    let config = crate::state::config_writer::current();
-    *ENABLED_DEVICES.lock().unwrap() = Some((config.enabled_keyboards, config.enabled_mice));
-    // Published after the lists, so a reader that sees the new generation is
-    // guaranteed to find the new lists behind it.
-    CONFIG_GENERATION.fetch_add(1, Ordering::Release);
-}
-
-/// Cached copy of the enabled-device filter, refreshed only when
-/// `CONFIG_GENERATION` moves - re-reading config per keystroke is what the
-/// audio path was fixed away from in Phase 1/2.
-struct DeviceFilter {
-    generation: u64,
-    enabled_keyboards: Vec<String>,
-    enabled_mice: Vec<String>,
-}
-
-impl DeviceFilter {
-    /// Reads config from disk. Called once when a worker starts, from the
-    /// supervisor thread before the read loop begins - never from inside it.
-    fn load() -> Self {
-        let generation = CONFIG_GENERATION.load(Ordering::Acquire);
-        let (enabled_keyboards, enabled_mice) = ENABLED_DEVICES.lock()
-            .unwrap()
-            .clone()
-            .unwrap_or_else(|| {
-                let config = crate::state::config_writer::current();
-                (config.enabled_keyboards, config.enabled_mice)
-            });
-        Self { generation, enabled_keyboards, enabled_mice }
-    }
-
-    fn refresh_if_stale(&mut self) {
-        let generation = CONFIG_GENERATION.load(Ordering::Acquire);
-        if generation == self.generation {
-            return;
-        }
-        if let Some((keyboards, mice)) = ENABLED_DEVICES.lock().unwrap().clone() {
-            self.enabled_keyboards = keyboards;
-            self.enabled_mice = mice;
-        }
-        self.generation = generation;
-    }
-
-    /// Empty list = all devices allowed, matching
-    /// `InputDeviceManager::should_process_device`'s convention and what
-    /// Settings persists by default (`config.rs` defaults both to empty).
-    /// A device whose id the worker could not resolve (`-`) is allowed
-    /// through: going silent on a lookup failure would be a worse failure
-    /// mode than an unfilterable device making sound.
-    fn allows(&self, kind: char, device_id: &str) -> bool {
-        let enabled = match kind {
-            'K' => &self.enabled_keyboards,
-            _ => &self.enabled_mice,
-        };
-        enabled.is_empty() || device_id == "-" || enabled.iter().any(|id| id == device_id)
-    }
-}
-
-/// Tracks Ctrl/Alt so Ctrl+Alt+M can be recognized, mirroring
-/// `input_listener.rs`'s rdev hotkey logic.
-#[derive(Default)]
-struct HotkeyState {
-    ctrl_pressed: bool,
-    alt_pressed: bool,
-}
-
-impl HotkeyState {
-    /// Returns true when this event was the hotkey itself and should not
-    /// also be played as a normal key.
-    fn observe(&mut self, code: &str, is_down: bool) -> bool {
-        match code {
-            "ControlLeft" | "ControlRight" => {
-                self.ctrl_pressed = is_down;
-            }
-            "AltLeft" | "AltRight" => {
-                self.alt_pressed = is_down;
-            }
-            "KeyM" if is_down && self.ctrl_pressed && self.alt_pressed => {
-                return true;
-            }
-            _ => {}
-        }
-        false
-    }
-}
-
 /// Updates the consecutive-failure count after one worker attempt.
 ///
 /// A worker that stayed up for `HEALTHY_UPTIME` was clearly working, so its
@@ -284,7 +183,7 @@ fn pump_worker(
     };
 
     let mut filter = DeviceFilter::load();
-    let mut hotkey = HotkeyState::default();
+    let mut hotkeys = HotkeyMatcher::new();
     let mut exit = PumpExit::WorkerGone;
     // Keys/buttons the engine currently believes are held. If the worker
     // dies while one is down, its release never arrives, and the engine's
@@ -305,12 +204,13 @@ fn pump_worker(
 
         filter.refresh_if_stale();
 
-        if event.kind == 'K' {
+        // Repeats are not new presses, so holding a hotkey fires it once.
+        if event.kind == 'K' && !event.is_repeat {
             // Hotkey tracking runs before the device filter on purpose: the
-            // global mute toggle should work from any keyboard, even one the
+            // global hotkeys should work from any keyboard, even one the
             // user disabled for soundpack playback.
-            if hotkey.observe(event.code, event.is_down) {
-                let _ = hotkey_tx.send("TOGGLE_SOUND".to_string());
+            if let Some(action) = hotkeys.observe(event.code, event.is_down) {
+                let _ = hotkey_tx.send(action.wire_name().to_string());
                 continue;
             }
         }
@@ -361,6 +261,7 @@ struct WorkerEvent<'a> {
     device_id: &'a str,
     code: &'a str,
     is_down: bool,
+    is_repeat: bool,
 }
 
 /// Decides what to put on the wire for one event, updating `held` to match.
@@ -373,11 +274,24 @@ struct WorkerEvent<'a> {
 /// stay silent. Keying the decision off `held` rather than off the filter also
 /// stops a disabled device from leaking a lone keyup sound, which testing the
 /// filter on the down alone would not.
+///
+/// Repeats follow the same rule as releases: one goes out as `REPEAT:` only
+/// while its press is held, so a disabled device stays silent however long
+/// a key is held on it.
 fn wire_for_event(
     event: &WorkerEvent<'_>,
     filter: &DeviceFilter,
     held: &mut Vec<(char, String)>
 ) -> Option<String> {
+    let is_held = |(kind, code): &(char, String)| *kind == event.kind && code == event.code;
+
+    if event.is_repeat {
+        return held
+            .iter()
+            .any(is_held)
+            .then(|| format!("REPEAT:{}", event.code));
+    }
+
     if event.is_down {
         if !filter.allows(event.kind, event.device_id) {
             return None;
@@ -386,7 +300,6 @@ fn wire_for_event(
         return Some(event.code.to_string());
     }
 
-    let is_held = |(kind, code): &(char, String)| *kind == event.kind && code == event.code;
     if !held.iter().any(is_held) {
         return None;
     }
@@ -394,7 +307,7 @@ fn wire_for_event(
     Some(format!("UP:{}", event.code))
 }
 
-/// Parses one `K\t{device_id}\t{code}\t{down|up}` line. Returns `None` for
+/// Parses one `K\t{device_id}\t{code}\t{down|up|repeat}` line. Returns `None` for
 /// anything malformed so a garbled line can never be mistaken for input.
 fn parse_worker_line(line: &str) -> Option<WorkerEvent<'_>> {
     let mut parts = line.split('\t');
@@ -407,9 +320,10 @@ fn parse_worker_line(line: &str) -> Option<WorkerEvent<'_>> {
     };
     let device_id = parts.next()?;
     let code = parts.next()?;
-    let is_down = match parts.next()? {
-        "down" => true,
-        "up" => false,
+    let (is_down, is_repeat) = match parts.next()? {
+        "down" => (true, false),
+        "up" => (false, false),
+        "repeat" => (true, true),
         _ => {
             return None;
         }
@@ -418,7 +332,7 @@ fn parse_worker_line(line: &str) -> Option<WorkerEvent<'_>> {
         return None;
     }
 
-    Some(WorkerEvent { kind, device_id, code, is_down })
+    Some(WorkerEvent { kind, device_id, code, is_down, is_repeat })
 }
 
 #[cfg(test)]
@@ -437,6 +351,9 @@ mod tests {
         assert_eq!(e.kind, 'M');
         assert_eq!(e.device_id, "-");
         assert!(!e.is_down);
+
+        let e = parse_worker_line("K\tabc123\tKeyA\trepeat").unwrap();
+        assert!(e.is_down && e.is_repeat);
     }
 
     #[test]
@@ -454,32 +371,6 @@ mod tests {
         }
     }
 
-    #[test]
-    fn empty_filter_allows_everything() {
-        let filter = DeviceFilter {
-            generation: 0,
-            enabled_keyboards: Vec::new(),
-            enabled_mice: Vec::new(),
-        };
-        assert!(filter.allows('K', "anything"));
-        assert!(filter.allows('M', "anything"));
-    }
-
-    #[test]
-    fn populated_filter_allows_only_listed_devices_and_unknown_ids() {
-        let filter = DeviceFilter {
-            generation: 0,
-            enabled_keyboards: vec!["kb1".to_string()],
-            enabled_mice: vec!["m1".to_string()],
-        };
-        assert!(filter.allows('K', "kb1"));
-        assert!(!filter.allows('K', "kb2"));
-        assert!(filter.allows('M', "m1"));
-        assert!(!filter.allows('M', "m2"));
-        // Unresolvable device id fails open rather than going silent.
-        assert!(filter.allows('K', "-"));
-    }
-
     #[test]
     fn healthy_uptime_clears_accumulated_failures() {
         assert_eq!(next_failure_count(3, Some(HEALTHY_UPTIME)), 0);
@@ -535,7 +426,7 @@ mod tests {
     }
 
     fn event<'a>(device_id: &'a str, code: &'a str, is_down: bool) -> WorkerEvent<'a> {
-        WorkerEvent { kind: 'K', device_id, code, is_down }
+        WorkerEvent { kind: 'K', device_id, code, is_down, is_repeat: false }
     }
 
     #[test]
@@ -575,35 +466,50 @@ mod tests {
         assert!(wire_for_event(&event("kb1", "KeyX", false), &filter, &mut held).is_none());
     }
 
+    #[test]
+    fn repeats_go_out_only_while_their_press_is_held() {
+        let filter = filter_for(vec!["kb1"]);
+        let mut held = Vec::new();
+        let repeat = |device_id| WorkerEvent { kind: 'K', device_id, code: "KeyX", is_down: true, is_repeat: true };
+
+        assert!(wire_for_event(&repeat("kb1"), &filter, &mut held).is_none(), "no press yet");
+        wire_for_event(&event("kb1", "KeyX", true), &filter, &mut held);
+        assert_eq!(wire_for_event(&repeat("kb1"), &filter, &mut held).as_deref(), Some("REPEAT:KeyX"));
+        assert_eq!(held.len(), 1, "a repeat is not held twice");
+
+        wire_for_event(&event("kb1", "KeyX", false), &filter, &mut held);
+        assert!(wire_for_event(&repeat("kb2"), &filter, &mut held).is_none(), "disabled device");
+    }
+
     #[test]
     fn held_tracks_kind_and_code_independently() {
         let filter = filter_for(vec![]);
         let mut held = Vec::new();
 
         wire_for_event(&event("kb1", "KeyX", true), &filter, &mut held);
-        let mouse_down = WorkerEvent { kind: 'M', device_id: "m1", code: "KeyX", is_down: true };
+        let mouse_down = WorkerEvent { kind: 'M', device_id: "m1", code: "KeyX", is_down: true, is_repeat: false };
         wire_for_event(&mouse_down, &filter, &mut held);
         assert_eq!(held.len(), 2);
 
         // Releasing the mouse "KeyX" must not clear the keyboard's entry.
-        let mouse_up = WorkerEvent { kind: 'M', device_id: "m1", code: "KeyX", is_down: false };
+        let mouse_up = WorkerEvent { kind: 'M', device_id: "m1", code: "KeyX", is_down: false, is_repeat: false };
         assert_eq!(wire_for_event(&mouse_up, &filter, &mut held).as_deref(), Some("UP:KeyX"));
         assert_eq!(held, vec![('K', "KeyX".to_string())]);
     }
 
     #[test]
     fn hotkey_needs_both_modifiers_held() {
-        let mut state = HotkeyState::default();
-        assert!(!state.observe("KeyM", true));
+        let mut state = HotkeyMatcher::with_bindings(&crate::libs::hotkeys::default_bindings());
+        assert!(state.observe("KeyM", true).is_none());
 
         state.observe("ControlLeft", true);
-        assert!(!state.observe("KeyM", true));
+        assert!(state.observe("KeyM", true).is_none());
 
         state.observe("AltLeft", true);
-        assert!(state.observe("KeyM", true));
+        assert!(state.observe("KeyM", true).is_some());
 
         // Releasing a modifier disarms it again.
         state.observe("ControlLeft", false);
-        assert!(!state.observe("KeyM", true));
+        assert!(state.observe("KeyM", true).is_none());
     }
 }
@@ -1,6 +1,8 @@
 pub mod audio;
+pub mod device_filter;
 pub mod device_manager;
 pub mod focused_input_listener;
+pub mod hotkeys;
 pub mod input_device_manager;
 pub mod input_listener;
 pub mod input_manager;
@@ -13,8 +15,12 @@ pub mod tray_service;
 pub mod ui;
 pub mod window_manager;
 
+#[cfg(target_os = "linux")]
+pub mod evdev_hotplug;
 #[cfg(target_os = "linux")]
 pub mod evdev_input_listener;
+#[cfg(target_os = "linux")]
+pub mod evdev_poll;
 
 // Windows input worker: Raw Input capture runs in a separate process
 // (`input_worker`, driven by `rawinput_listener`) because tao/wry claims the
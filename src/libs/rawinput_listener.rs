@@ -48,6 +48,8 @@ pub struct RawInputEvent<'a> {
     pub device_id: Option<String>,
     pub code: &'a str,
     pub is_down: bool,
+    /// A down the OS sent again for a key that is still held.
+    pub is_repeat: bool,
 }
 
 #[derive(Clone, Copy, PartialEq)]
@@ -93,8 +95,8 @@ thread_local! {
 ///
 /// Device filtering and hotkey detection are deliberately NOT done here:
 /// the worker process has no config, the UI process owns both (see
-/// `input_worker_host.rs`). Key-repeat suppression IS done here, to keep
-/// held-key traffic off the IPC pipe.
+/// `input_worker_host.rs`). Key repeats are told apart from presses here,
+/// where every raw down is seen, and reach the sink with `is_repeat` set.
 pub fn run_rawinput_loop(sink: Box<dyn FnMut(RawInputEvent)>) -> Result<(), String> {
     STATE.with(|s| {
         *s.borrow_mut() = Some(ListenerState {
@@ -319,9 +321,7 @@ fn handle_keyboard(raw: &RAWINPUT) {
             return;
         };
 
-        let Some(transition) = classify_key_transition(&mut state.pressed_keys, &code, is_up) else {
-            return;
-        };
+        let transition = classify_key_transition(&mut state.pressed_keys, &code, is_up);
 
         let device_id = state.device_id(raw.header.hDevice);
 
@@ -337,6 +337,7 @@ fn handle_keyboard(raw: &RAWINPUT) {
                 device_id: device_id.clone(),
                 code: &code,
                 is_down: true,
+                is_repeat: false,
             });
         }
 
@@ -345,6 +346,7 @@ fn handle_keyboard(raw: &RAWINPUT) {
             device_id,
             code: &code,
             is_down: !is_up,
+            is_repeat: transition == KeyTransition::Repeat,
         });
     });
 }
@@ -354,6 +356,9 @@ fn handle_keyboard(raw: &RAWINPUT) {
 enum KeyTransition {
     /// First down of a press. Emit a down.
     Down,
+    /// Another down for a key already held: the OS auto-repeat. Emit it
+    /// marked as a repeat.
+    Repeat,
     /// Release of a key whose down we saw. Emit an up.
     Up,
     /// Release of a key whose down we never saw, because another process
@@ -362,7 +367,6 @@ enum KeyTransition {
 }
 
 /// Applies one raw key transition to `pressed_keys` and says what to emit.
-/// `None` means the event is a key-repeat and should be dropped.
 ///
 /// ## Why an orphan up implies a swallowed down
 ///
@@ -401,16 +405,12 @@ fn classify_key_transition(
     pressed_keys: &mut HashSet<String>,
     code: &str,
     is_up: bool
-) -> Option<KeyTransition> {
+) -> KeyTransition {
     if is_up {
-        return Some(if pressed_keys.remove(code) {
-            KeyTransition::Up
-        } else {
-            KeyTransition::OrphanUp
-        });
+        return if pressed_keys.remove(code) { KeyTransition::Up } else { KeyTransition::OrphanUp };
     }
 
-    if pressed_keys.insert(code.to_string()) { Some(KeyTransition::Down) } else { None }
+    if pressed_keys.insert(code.to_string()) { KeyTransition::Down } else { KeyTransition::Repeat }
 }
 
 fn handle_mouse(raw: &RAWINPUT) {
@@ -445,6 +445,7 @@ fn handle_mouse(raw: &RAWINPUT) {
                 device_id,
                 code,
                 is_down,
+                is_repeat: false,
             });
         }
     });
@@ -651,16 +652,18 @@ fn map_vkey_to_code(vkey: u16, scancode: u16, is_e0: bool) -> Option<String> {
 mod tests {
     use super::*;
 
-    /// Replays a sequence of `(code, is_up)` transitions and returns what the
-    /// sink would emit, as `(code, is_down)` pairs - the same order and
-    /// pairing `handle_keyboard` produces.
+    /// Replays a sequence of `(code, is_up)` transitions and returns the
+    /// presses and releases the sink would emit, as `(code, is_down)` pairs -
+    /// the same order and pairing `handle_keyboard` produces. Repeats are
+    /// left out; they never count as a press.
     fn emitted(events: &[(&str, bool)]) -> Vec<(String, bool)> {
         let mut pressed = HashSet::new();
         let mut out = Vec::new();
         for (code, is_up) in events {
-            let Some(transition) = classify_key_transition(&mut pressed, code, *is_up) else {
+            let transition = classify_key_transition(&mut pressed, code, *is_up);
+            if transition == KeyTransition::Repeat {
                 continue;
-            };
+            }
             if transition == KeyTransition::OrphanUp {
                 out.push(((*code).to_string(), true));
             }
@@ -692,12 +695,18 @@ mod tests {
     }
 
     #[test]
-    fn auto_repeat_downs_still_collapse_to_one_sound() {
-        // Raw Input resends the down for a held key; only the first crosses.
+    fn auto_repeat_downs_still_collapse_to_one_press() {
+        // Raw Input resends the down for a held key; only the first is a
+        // press, the rest cross as repeats.
         assert_eq!(
             emitted(&[("KeyA", false), ("KeyA", false), ("KeyA", false), ("KeyA", true)]),
             vec![("KeyA".to_string(), true), ("KeyA".to_string(), false)]
         );
+
+        let mut pressed = HashSet::new();
+        classify_key_transition(&mut pressed, "KeyA", false);
+        assert_eq!(classify_key_transition(&mut pressed, "KeyA", false), KeyTransition::Repeat);
+        assert_eq!(classify_key_transition(&mut pressed, "KeyA", true), KeyTransition::Up);
     }
 
     #[test]
@@ -723,10 +732,10 @@ mod tests {
         // pressed_keys, its up would look like an orphan and every keystroke
         // would fire twice.
         let mut pressed = HashSet::new();
-        assert_eq!(classify_key_transition(&mut pressed, "KeyA", false), Some(KeyTransition::Down));
+        assert_eq!(classify_key_transition(&mut pressed, "KeyA", false), KeyTransition::Down);
         assert_eq!(
             classify_key_transition(&mut pressed, "KeyA", true),
-            Some(KeyTransition::Up),
+            KeyTransition::Up,
             "a down we recorded must classify its up as a plain Up"
         );
     }
@@ -746,7 +755,7 @@ mod tests {
 
         assert_eq!(
             classify_key_transition(&mut pressed, "KeyD", true),
-            Some(KeyTransition::Up),
+            KeyTransition::Up,
             "a filtered source must stay silent - synthesizing here would \
              resurrect a down that a downstream filter deliberately dropped"
         );
@@ -759,11 +768,11 @@ mod tests {
         classify_key_transition(&mut pressed, "KeyA", false);
         assert_eq!(
             classify_key_transition(&mut pressed, "F1", true),
-            Some(KeyTransition::OrphanUp)
+            KeyTransition::OrphanUp
         );
         assert_eq!(
             classify_key_transition(&mut pressed, "KeyA", true),
-            Some(KeyTransition::Up),
+            KeyTransition::Up,
             "the still-held key must be unaffected by the other key's orphan up"
         );
     }
@@ -51,7 +51,9 @@ static ORIGIN: OnceLock<Instant> = OnceLock::new();
 #[derive(Clone, Copy, PartialEq, Eq, Debug)]
 pub enum Point {
     /// The worker host read a line off the worker's stdout and is about to put
-    /// it on the channel the engine reads.
+    /// it on the channel the engine reads. On Linux, the evdev listener is
+    /// about to send a key event, and the duration is how long the event
+    /// waited since the kernel stamped it.
     WorkerSend,
     /// The engine's `select!` handed this event to the engine loop.
     EngineDequeue,
@@ -277,6 +279,9 @@ const SLOW_HOP_MS: f64 = 50.0;
 struct Pending {
     key: InlineKey,
     worker_at: f64,
+    /// How long the event waited before the listener sent it, where the
+    /// listener knows (`WorkerSend`'s duration on Linux).
+    input_wait: f64,
     engine_at: Option<f64>,
     sound_at: Option<f64>,
     sound_dur: f64,
@@ -291,16 +296,20 @@ impl Pending {
         let sound = self.sound_at.unwrap_or(engine);
         let ui_event = self.ui_event_at.unwrap_or(sound);
 
-        let hops = [
-            ("worker->engine", engine - self.worker_at),
-            ("handle_key", self.sound_dur.max(sound - engine)),
-            ("->ui_event", ui_event - sound),
-            ("->ui_write", ui_write_at - ui_event),
-        ];
+        let input = (self.input_wait > 0.0).then_some(("input->worker", self.input_wait));
+        let hops: Vec<(&str, f64)> = input
+            .into_iter()
+            .chain([
+                ("worker->engine", engine - self.worker_at),
+                ("handle_key", self.sound_dur.max(sound - engine)),
+                ("->ui_event", ui_event - sound),
+                ("->ui_write", ui_write_at - ui_event),
+            ])
+            .collect();
         let total = ui_write_at - self.worker_at;
 
         let mut line = format!("🔬 TRACE key={:<12} total={:>8.1}ms", self.key.as_str(), total);
-        for (name, ms) in hops {
+        for &(name, ms) in &hops {
             line.push_str(&format!("  {}={:.1}ms", name, ms));
         }
         if let Some((name, ms)) = hops.iter().copied().find(|(_, ms)| *ms >= SLOW_HOP_MS) {
@@ -352,6 +361,7 @@ fn writer_loop(
                 pending.push(Pending {
                     key: rec.key,
                     worker_at: rec.at_ms,
+                    input_wait: rec.dur_ms,
                     engine_at: None,
                     sound_at: None,
                     sound_dur: 0.0,
@@ -556,6 +566,7 @@ mod tests {
         let pending = Pending {
             key: InlineKey::new("KeyA"),
             worker_at: 0.0,
+            input_wait: 0.0,
             engine_at: Some(1.0),
             sound_at: Some(801.0),
             sound_dur: 0.0,
@@ -572,6 +583,7 @@ mod tests {
         let pending = Pending {
             key: InlineKey::new("KeyB"),
             worker_at: 100.0,
+            input_wait: 0.0,
             engine_at: Some(100.1),
             sound_at: Some(100.4),
             sound_dur: 0.3,
@@ -579,5 +591,21 @@ mod tests {
         };
         let line = pending.summarize(109.0);
         assert!(!line.contains("SLOW"), "{}", line);
+        assert!(!line.contains("input->worker"), "the worker host does not know the wait: {}", line);
+    }
+
+    #[test]
+    fn the_evdev_listener_wait_is_its_own_hop() {
+        let pending = Pending {
+            key: InlineKey::new("KeyC"),
+            worker_at: 100.0,
+            input_wait: 0.2,
+            engine_at: Some(100.1),
+            sound_at: Some(100.4),
+            sound_dur: 0.3,
+            ui_event_at: Some(100.5),
+        };
+        let line = pending.summarize(109.0);
+        assert!(line.contains("input->worker=0.2ms  worker->engine=0.1ms"), "{}", line);
     }
 }
@@ -4,6 +4,7 @@ use crate::libs::routes::Route;
 use crate::libs::input_manager::set_window_focus;
 use crate::libs::AudioContext;
 use crate::state::keyboard::KeyboardState;
+use crate::state::output::OutputState;
 use crate::state::paths;
 use crate::utils::delay;
 use crate::{ debug_print, always_eprint };
@@ -46,6 +47,11 @@ pub fn app() -> Element {
     // Provide the keyboard state context to all child components
     use_context_provider(|| keyboard_state);
 
+    // Whether the output limiter is engaged, for the volume sliders' clip
+    // indicator
+    let output_state = use_signal(OutputState::default);
+    use_context_provider(|| output_state);
+
     // Initialize the audio system for mechvibes sounds - moved here to be accessible by both keyboard processing and UI
     let audio_context = use_hook(|| Arc::new(AudioContext::new()));
 
@@ -114,6 +120,7 @@ pub fn app() -> Element {
     // works from anywhere and costs one atomic load per tick.
     {
         let mut keyboard_state = keyboard_state;
+        let mut output_state = output_state;
         let mut global_config = global_config;
         let mut seen_config_generation = crate::state::config_writer::generation();
 
@@ -174,6 +181,16 @@ pub fn app() -> Element {
                                     ),
                             }
                         }
+                        crate::libs::audio::UiEvent::Hotkey(action) => {
+                            handle_ui_hotkey(action);
+                        }
+                        crate::libs::audio::UiEvent::Clipping(clipping) => {
+                            // Only start and stop are reported, so this
+                            // writes at most twice per saturated stretch.
+                            if output_state.peek().clipping != clipping {
+                                output_state.write().clipping = clipping;
+                            }
+                        }
                     }
                 }
 
@@ -446,6 +463,24 @@ pub fn app() -> Element {
     }
 }
 
+/// Runs a hotkey action the engine handed over because it acts on UI-side
+/// state (the ambiance player, the window). Called from the poll loop above.
+fn handle_ui_hotkey(action: crate::libs::hotkeys::HotkeyAction) {
+    use crate::libs::hotkeys::HotkeyAction;
+
+    match action {
+        HotkeyAction::ToggleAmbiance => {
+            crate::state::ambiance::update_global_ambiance_player_state(|player| {
+                player.toggle_play_pause();
+            });
+        }
+        HotkeyAction::ShowWindow => {
+            crate::libs::window_manager::WINDOW_MANAGER.request_show();
+        }
+        _ => {}
+    }
+}
+
 /// Reduces one tick's worth of key events to the state that should be
 /// rendered, matching the poll loop above: the newest event wins and nothing
 /// is queued behind a previous keystroke.
@@ -17,10 +17,7 @@ use libs::input_manager::{ init_window_focus_state_with_value, get_window_focus_
 use std::sync::mpsc;
 
 #[cfg(target_os = "linux")]
-use libs::evdev_input_listener::start_evdev_keyboard_listener;
-
-#[cfg(target_os = "linux")]
-use std::sync::{Arc, Mutex};
+use libs::evdev_input_listener::start_evdev_input_listener;
 
 // Platform-specific icon files for optimal quality
 #[cfg(target_os = "windows")]
@@ -96,6 +93,27 @@ fn main() {
         return;
     }
 
+    // Render mode: a command-line tool run, not an app launch, so it skips
+    // the instance lock, window and tray below entirely. See offline_render.rs.
+    if std::env::args_os().nth(1).is_some_and(|arg| arg == libs::audio::offline_render::RENDER_ARG) {
+        // A GUI-subsystem binary starts with no console, so its output would
+        // go nowhere; borrow the one of the shell that ran it, if any.
+        #[cfg(target_os = "windows")]
+        unsafe {
+            winapi::um::wincon::AttachConsole(winapi::um::wincon::ATTACH_PARENT_PROCESS);
+        }
+        let args: Vec<String> = std::env
+            ::args_os()
+            .skip(2)
+            .map(|arg| arg.to_string_lossy().into_owned())
+            .collect();
+        if let Err(e) = libs::audio::offline_render::run(&args) {
+            always_eprint!("❌ {}", e);
+            std::process::exit(1);
+        }
+        return;
+    }
+
     // Refuse to start a second copy: two instances means two input listeners
     // and two audio engines, so every keystroke would play twice. Claimed
     // after the worker branch above on purpose - the worker is a child of an
@@ -203,6 +221,7 @@ fn main() {
     // blocking select!/recv() loop instead of the UI polling it every ~1ms.
     libs::audio::spawn_engine(keyboard_rx, mouse_rx, hotkey_rx);
     debug_print!("🎧 Audio engine thread started");
+    state::app::publish_keyboard_pack_order();
 
     // Initialize window focus state
     // If window starts visible (not minimized), it will be focused
@@ -221,17 +240,12 @@ fn main() {
     #[cfg(target_os = "linux")]
     {
         if display_server == "wayland" {
-            // On Wayland, use evdev for keyboard input (works both focused and unfocused)
-            // evdev also handles hotkey detection (Ctrl+Alt+M)
-            debug_print!("🎮 Starting evdev keyboard listener (Wayland mode)...");
+            // On Wayland, use evdev for keyboard and mouse input (works both focused and unfocused);
+            // rdev cannot see other clients' input there. evdev also handles the global
+            // hotkeys (see libs/hotkeys.rs)
+            debug_print!("🎮 Starting evdev input listener (Wayland mode)...");
             let focus_state = get_window_focus_state();
-            start_evdev_keyboard_listener(keyboard_tx.clone(), hotkey_tx.clone(), focus_state);
-
-            // Use rdev for mouse events only (no keyboard/hotkeys on Wayland)
-            // Pass "always focused" state to prevent rdev from sending keyboard events
-            debug_print!("🎮 Starting unified input listener for mouse events (Wayland mode)...");
-            let always_focused = Arc::new(Mutex::new(true));
-            start_unified_input_listener(keyboard_tx, mouse_tx, hotkey_tx, Some(always_focused));
+            start_evdev_input_listener(keyboard_tx, mouse_tx, hotkey_tx, focus_state);
         } else {
             // On X11, use the hybrid approach (rdev + device_query)
             // rdev handles keyboard when unfocused, device_query when focused
@@ -1,228 +1,28 @@
 use serde::{ Deserialize, Serialize };
 use std::collections::HashMap;
 use std::sync::{ Arc, Mutex };
-use std::sync::mpsc;
-use std::thread;
-use rodio::{ Decoder, OutputStream, OutputStreamHandle, Sink, Source };
-use std::fs::File;
-use std::io::BufReader;
-
-use crate::libs::device_manager::DeviceManager;
-
-/// Commands for the single background thread that owns the shared ambiance
-/// `OutputStream`. All sounds share one stream/handle (rather than each
-/// `play_ambiance_sound` call opening its own, as before Phase 3) so a
-/// device switch has exactly one stream to rebuild.
-enum AmbianceEngineCommand {
-    Play {
-        sound_id: String,
-        audio_url: String,
-        volume: f32,
-    },
-    Stop(String),
-    SetVolume {
-        sound_id: String,
-        volume: f32,
-    },
-    SetAllVolume(f32),
-    PauseAll,
-    ResumeAll,
-    /// `resume` carries the sounds that were actually audible before the
-    /// switch (empty if paused/muted) - see `switch_ambiance_player_device`,
-    /// which reads `AmbiancePlayerState` to build this since the engine
-    /// thread only knows about `Sink`s, not playback intent.
-    SwitchDevice {
-        device_id: Option<String>,
-        resume: Vec<(String, String, f32)>, // (sound_id, audio_url, effective_volume)
-    },
-}
 
-static AMBIANCE_ENGINE_TX: std::sync::OnceLock<
-    Mutex<Option<mpsc::Sender<AmbianceEngineCommand>>>
-> = std::sync::OnceLock::new();
+use crate::libs::audio::engine::engine_handle;
+use crate::libs::audio::{ AudioCommand, Bus };
 
 // Global ambiance player state
 static GLOBAL_AMBIANCE_PLAYER_STATE: std::sync::OnceLock<Arc<Mutex<AmbiancePlayerState>>> = std::sync::OnceLock::new();
 
-fn send_engine_command(command: AmbianceEngineCommand) {
-    if let Some(tx_slot) = AMBIANCE_ENGINE_TX.get() {
-        if let Some(tx) = tx_slot.lock().unwrap().as_ref() {
-            let _ = tx.send(command);
-        }
-    }
-}
-
-/// Switches the ambiance player's shared output device at runtime (`None` =
-/// system default). Sounds that were actually audible (playing, not paused,
-/// not muted) before the switch are re-started on the new stream - existing
-/// `Sink`s are bound to the old `OutputStreamHandle` and can't just move
-/// over. No-ops if the engine hasn't started yet.
-pub fn switch_ambiance_player_device(device_id: Option<String>) {
-    let resume = get_global_ambiance_player_state_copy()
-        .map(|state| {
-            if !state.is_playing || state.is_muted {
-                return Vec::new();
-            }
-            state.active_sounds
-                .iter()
-                .filter_map(|(sound_id, &volume)| {
-                    state
-                        .get_sound_by_id(sound_id)
-                        .map(|sound| {
-                            (sound_id.clone(), sound.audio_url.clone(), volume * state.global_volume)
-                        })
-                })
-                .collect()
-        })
-        .unwrap_or_default();
-
-    send_engine_command(AmbianceEngineCommand::SwitchDevice { device_id, resume });
-}
-
-/// Opens, decodes, and starts a looping `Sink` for `audio_url` on
-/// `stream_handle`. Shared by `Play` and the `SwitchDevice` resume step so
-/// both build sinks the same way.
-fn build_ambiance_sink(
-    stream_handle: &OutputStreamHandle,
-    audio_url: &str,
-    volume: f32
-) -> Result<Sink, String> {
-    let audio_path = audio_url.replace("assets/", "");
-    let full_path = format!("assets/{}", audio_path);
-    let file = File::open(&full_path).map_err(|e|
-        format!("Failed to open audio file {}: {}", full_path, e)
-    )?;
-    let decoder = Decoder::new(BufReader::new(file)).map_err(|e|
-        format!("Failed to decode audio: {}", e)
-    )?;
-    let sink = Sink::try_new(stream_handle).map_err(|e|
-        format!("Failed to create audio sink: {}", e)
-    )?;
-    sink.set_volume(volume.clamp(0.0, 1.0));
-    sink.append(decoder.repeat_infinite());
-    Ok(sink)
-}
-
-fn open_stream(device_id: Option<&str>) -> Result<(OutputStream, OutputStreamHandle), String> {
-    if let Some(id) = device_id {
-        let device_manager = DeviceManager::new();
-        if let Ok(Some(device)) = device_manager.get_output_device_by_id(id) {
-            if let Ok(pair) = rodio::OutputStream::try_from_device(&device) {
-                return Ok(pair);
-            }
-        }
-        crate::always_eprint!("❌ [Ambiance] Failed to open device {}, falling back to default", id);
-    }
-    rodio::OutputStream
-        ::try_default()
-        .map_err(|e| format!("Failed to create audio output stream: {}", e))
+/// Ambiance sounds play as streams on the audio engine's `Bus::Ambiance`, in
+/// the same mix and on the same device as the keystrokes. The global volume
+/// is that bus's gain and mute its mute, so a sound's own volume is all a
+/// stream carries, and a device switch moves ambiance along with everything
+/// else.
+fn stream_id(sound_id: &str) -> String {
+    format!("ambiance:{}", sound_id)
 }
 
 // Initialize global ambiance player
 pub fn initialize_global_ambiance_player() {
-    let (tx, rx) = mpsc::channel::<AmbianceEngineCommand>();
-    AMBIANCE_ENGINE_TX.get_or_init(|| Mutex::new(Some(tx)));
-
-    // Initialize global state
-    let _state_ref = GLOBAL_AMBIANCE_PLAYER_STATE.get_or_init(|| {
+    GLOBAL_AMBIANCE_PLAYER_STATE.get_or_init(|| {
         Arc::new(Mutex::new(AmbiancePlayerState::initialize()))
     });
 
-    // One thread owns the shared OutputStream for its whole lifetime -
-    // OutputStream (via cpal::Stream) is not Send, so it must be created on
-    // and never leave this thread; SwitchDevice rebuilds it in place here.
-    thread::spawn(move || {
-        let initial_device = crate::state::config_writer::current().selected_audio_device;
-        let (mut stream, mut stream_handle) = match open_stream(initial_device.as_deref()) {
-            Ok(pair) => pair,
-            Err(e) => {
-                crate::always_eprint!("❌ [Ambiance] Failed to open audio stream: {}", e);
-                return;
-            }
-        };
-        let mut sinks: HashMap<String, Sink> = HashMap::new();
-
-        while let Ok(command) = rx.recv() {
-            match command {
-                AmbianceEngineCommand::Play { sound_id, audio_url, volume } => {
-                    if let Some(old) = sinks.remove(&sound_id) {
-                        old.stop();
-                    }
-
-                    match build_ambiance_sink(&stream_handle, &audio_url, volume) {
-                        Ok(sink) => {
-                            sinks.insert(sound_id.clone(), sink);
-                            crate::always_print!("🎵 [Ambiance] Started playing sound: {}", sound_id);
-                        }
-                        Err(e) => crate::always_eprint!("❌ [Ambiance] Failed to play sound {}: {}", sound_id, e),
-                    }
-                }
-                AmbianceEngineCommand::Stop(sound_id) => {
-                    if let Some(sink) = sinks.remove(&sound_id) {
-                        sink.stop();
-                        crate::always_print!("🔇 [Ambiance] Stopped sound: {}", sound_id);
-                    }
-                }
-                AmbianceEngineCommand::SetVolume { sound_id, volume } => {
-                    if let Some(sink) = sinks.get(&sound_id) {
-                        sink.set_volume(volume.clamp(0.0, 1.0));
-                    }
-                }
-                AmbianceEngineCommand::SetAllVolume(volume) => {
-                    let clamped = volume.clamp(0.0, 1.0);
-                    for sink in sinks.values() {
-                        sink.set_volume(clamped);
-                    }
-                }
-                AmbianceEngineCommand::PauseAll => {
-                    for sink in sinks.values() {
-                        sink.pause();
-                    }
-                }
-                AmbianceEngineCommand::ResumeAll => {
-                    for sink in sinks.values() {
-                        sink.play();
-                    }
-                }
-                AmbianceEngineCommand::SwitchDevice { device_id, resume } => {
-                    match open_stream(device_id.as_deref()) {
-                        Ok((new_stream, new_handle)) => {
-                            // Sinks are bound to the stream_handle they were
-                            // created from and can't move to the new stream,
-                            // so drop them and rebuild fresh ones on the new
-                            // handle for whatever was actually audible.
-                            for sink in sinks.values() {
-                                sink.stop();
-                            }
-                            sinks.clear();
-                            stream = new_stream;
-                            stream_handle = new_handle;
-
-                            for (sound_id, audio_url, volume) in resume {
-                                match build_ambiance_sink(&stream_handle, &audio_url, volume) {
-                                    Ok(sink) => {
-                                        sinks.insert(sound_id, sink);
-                                    }
-                                    Err(e) =>
-                                        crate::always_eprint!(
-                                            "❌ [Ambiance] Failed to resume sound {} after device switch: {}",
-                                            sound_id,
-                                            e
-                                        ),
-                                }
-                            }
-                            crate::always_print!("🎵 [Ambiance] Switched output device");
-                        }
-                        Err(e) => crate::always_eprint!("❌ [Ambiance] Failed to switch device: {}", e),
-                    }
-                }
-            }
-        }
-
-        // Keep `stream` alive for the thread's lifetime.
-        let _ = &stream;
-    });
-
     crate::always_print!("🎵 Global ambiance player initialized");
 }
 
@@ -257,52 +57,54 @@ pub fn get_global_ambiance_player_state_copy() -> Option<AmbiancePlayerState> {
 
 // Play a sound
 pub fn play_ambiance_sound(sound_id: String, audio_url: String, volume: f32) -> Result<(), String> {
-    send_engine_command(AmbianceEngineCommand::Play { sound_id, audio_url, volume });
+    let audio_path = audio_url.replace("assets/", "");
+    engine_handle().send(AudioCommand::PlayStream {
+        id: stream_id(&sound_id),
+        bus: Bus::Ambiance,
+        path: format!("assets/{}", audio_path),
+        gain: volume.clamp(0.0, 1.0),
+    });
     Ok(())
 }
 
 // Stop a sound
 pub fn stop_ambiance_sound(sound_id: &str) -> Result<(), String> {
-    send_engine_command(AmbianceEngineCommand::Stop(sound_id.to_string()));
+    engine_handle().send(AudioCommand::StopStream(stream_id(sound_id)));
     Ok(())
 }
 
 // Pause all sounds
 pub fn pause_all_ambiance_sounds() -> Result<(), String> {
-    send_engine_command(AmbianceEngineCommand::PauseAll);
+    engine_handle().send(AudioCommand::SetBusPaused(Bus::Ambiance, true));
     crate::always_print!("⏸️ Paused all ambiance sounds");
     Ok(())
 }
 
 // Resume all sounds
 pub fn resume_all_ambiance_sounds() -> Result<(), String> {
-    send_engine_command(AmbianceEngineCommand::ResumeAll);
+    engine_handle().send(AudioCommand::SetBusPaused(Bus::Ambiance, false));
     crate::always_print!("▶️ Resumed all ambiance sounds");
     Ok(())
 }
 
 // Set sound volume
 pub fn set_ambiance_sound_volume(sound_id: &str, volume: f32) -> Result<(), String> {
-    send_engine_command(AmbianceEngineCommand::SetVolume {
-        sound_id: sound_id.to_string(),
-        volume,
+    engine_handle().send(AudioCommand::SetStreamGain {
+        id: stream_id(sound_id),
+        gain: volume.clamp(0.0, 1.0),
     });
     Ok(())
 }
 
 // Set global volume for all sounds
 pub fn set_global_ambiance_volume(volume: f32) -> Result<(), String> {
-    send_engine_command(AmbianceEngineCommand::SetAllVolume(volume));
+    engine_handle().send(AudioCommand::SetBusGain(Bus::Ambiance, volume.clamp(0.0, 1.0)));
     Ok(())
 }
 
 // Set global mute for all sounds
 pub fn set_global_ambiance_mute(muted: bool) -> Result<(), String> {
-    if muted {
-        send_engine_command(AmbianceEngineCommand::PauseAll);
-    } else {
-        send_engine_command(AmbianceEngineCommand::ResumeAll);
-    }
+    engine_handle().send(AudioCommand::SetBusMuted(Bus::Ambiance, muted));
     Ok(())
 }
 
@@ -471,14 +273,9 @@ impl AmbiancePlayerState {
         } else {
             // Start the sound
             self.active_sounds.insert(sound_id.clone(), 0.5); // Default volume 50%
-            if self.is_playing && !self.is_muted {
+            if self.is_playing {
                 if let Some(sound) = self.get_sound_by_id(&sound_id) {
-                    let effective_volume = 0.5 * self.global_volume;
-                    let _ = play_ambiance_sound(
-                        sound_id.clone(),
-                        sound.audio_url.clone(),
-                        effective_volume
-                    );
+                    let _ = play_ambiance_sound(sound_id.clone(), sound.audio_url.clone(), 0.5);
                 }
             }
         }
@@ -491,11 +288,8 @@ impl AmbiancePlayerState {
         if self.active_sounds.contains_key(&sound_id) {
             self.active_sounds.insert(sound_id.clone(), clamped_volume);
 
-            // Update audio player volume if sound is playing
-            if self.is_playing && !self.is_muted {
-                let effective_volume = clamped_volume * self.global_volume;
-                let _ = set_ambiance_sound_volume(&sound_id, effective_volume);
-            }
+            // Global volume and mute apply on the ambiance bus, on top of this
+            let _ = set_ambiance_sound_volume(&sound_id, clamped_volume);
 
             self.save_config();
         }
@@ -538,18 +332,13 @@ impl AmbiancePlayerState {
 
     /// Start all active sounds when play is pressed
     fn start_all_active_sounds(&self) {
-        if !self.is_playing || self.is_muted {
+        if !self.is_playing {
             return;
         }
 
         for (sound_id, &volume) in &self.active_sounds {
             if let Some(sound) = self.get_sound_by_id(sound_id) {
-                let effective_volume = volume * self.global_volume;
-                let _ = play_ambiance_sound(
-                    sound_id.clone(),
-                    sound.audio_url.clone(),
-                    effective_volume
-                );
+                let _ = play_ambiance_sound(sound_id.clone(), sound.audio_url.clone(), volume);
             }
         }
     }
@@ -36,6 +36,7 @@ impl AppState {
         let mut fresh_cache = SoundpackCache::load();
         fresh_cache.refresh_from_directory();
         fresh_cache.save();
+        send_keyboard_pack_order(&fresh_cache);
         self.optimized_cache = Arc::new(fresh_cache);
         self.last_updated = std::time::Instant::now();
     }
@@ -140,6 +141,23 @@ pub fn reload_current_soundpacks(audio_ctx: &crate::libs::audio::AudioContext) {
     }
 }
 
+/// Hands the engine the keyboard packs in selector order, for the
+/// next/previous-pack hotkeys, so it never has to read the cache from disk.
+fn send_keyboard_pack_order(cache: &SoundpackCache) {
+    crate::libs::audio::engine::engine_handle().send(
+        crate::libs::audio::AudioCommand::SetKeyboardPackOrder(cache.keyboard_pack_order())
+    );
+}
+
+/// Sends the engine the pack order from the cache `init_app_state` loaded;
+/// call once the engine is running.
+pub fn publish_keyboard_pack_order() {
+    let Some(Ok(state)) = GLOBAL_APP_STATE.get().map(|global_state| global_state.lock()) else {
+        return;
+    };
+    send_keyboard_pack_order(&state.optimized_cache);
+}
+
 // Initialize the app state - call this once at startup
 pub fn init_app_state() {
     if GLOBAL_APP_STATE.get().is_none() {
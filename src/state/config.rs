@@ -1,8 +1,10 @@
 use crate::debug_print;
+use crate::libs::hotkeys::{ self, HotkeyBindings };
 use crate::libs::theme::{ BuiltInTheme, Theme };
 use crate::state::paths;
 use crate::utils::{ data, path };
 use crate::utils::auto_updater::AutoUpdateConfig;
+use crate::utils::keyboard_layout::KeyboardLayout;
 use chrono::{ DateTime, Utc };
 use serde::{ Deserialize, Serialize };
 use std::collections::HashMap;
@@ -28,6 +30,203 @@ impl Default for MusicPlayerConfig {
     }
 }
 
+/// Loudest a single key can be pushed by a per-key override.
+pub const MAX_KEY_GAIN: f32 = 2.0;
+
+/// A user's adjustment to one key of one soundpack, see
+/// `AppConfig::key_overrides`.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
+#[serde(default)]
+pub struct KeyOverride {
+    /// Multiplies the key's level on top of the volume; 1.0 leaves it as the
+    /// pack recorded it.
+    pub gain: f32,
+    pub muted: bool,
+}
+
+impl Default for KeyOverride {
+    fn default() -> Self {
+        Self { gain: 1.0, muted: false }
+    }
+}
+
+impl KeyOverride {
+    pub fn is_default(&self) -> bool {
+        *self == Self::default()
+    }
+}
+
+/// Key code -> override, for one soundpack.
+pub type KeyOverrides = HashMap<String, KeyOverride>;
+
+/// Which sounding voice gives way to a new one once `MAX_VOICES` are
+/// playing, see `libs::audio::mixer`.
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum VoiceStealing {
+    /// The one that started first.
+    #[default]
+    Oldest,
+    /// The one currently playing the softest, usually a tail near its end.
+    Quietest,
+    /// An earlier sound of the same key if one is still playing, otherwise
+    /// the oldest.
+    SameKeyFirst,
+}
+
+impl VoiceStealing {
+    pub const ALL: [VoiceStealing; 3] = [VoiceStealing::Oldest, VoiceStealing::Quietest, VoiceStealing::SameKeyFirst];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            VoiceStealing::Oldest => "Oldest",
+            VoiceStealing::Quietest => "Quietest",
+            VoiceStealing::SameKeyFirst => "Same key first",
+        }
+    }
+}
+
+/// What a held key's OS auto-repeat plays.
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum KeyRepeat {
+    /// Nothing: a held key sounds once.
+    #[default]
+    Silent,
+    /// The key's press sound again, at `AppConfig::repeat_gain`.
+    Replay,
+    /// The key's own `repeat` segment from the pack; keys without one
+    /// replay as `Replay` does.
+    Segment,
+}
+
+impl KeyRepeat {
+    pub const ALL: [KeyRepeat; 3] = [KeyRepeat::Silent, KeyRepeat::Replay, KeyRepeat::Segment];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            KeyRepeat::Silent => "Silent",
+            KeyRepeat::Replay => "Replay quieter",
+            KeyRepeat::Segment => "Pack's repeat sound",
+        }
+    }
+}
+
+/// Largest boost or cut each EQ band offers, in dB.
+pub const MAX_EQ_DB: f32 = 12.0;
+/// Lowest cutoff the low-pass offers - about as dull as a keystroke gets.
+pub const MIN_LOWPASS_HZ: f32 = 500.0;
+/// The low-pass cutoff that means "off".
+pub const MAX_LOWPASS_HZ: f32 = 20_000.0;
+
+/// The effects chain keyboard and mouse sounds go through, see
+/// `libs::audio::effects`. A stage at its neutral value is skipped.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
+#[serde(default)]
+pub struct EffectsSettings {
+    pub enabled: bool,
+    /// Low shelf, mid peak and high shelf gains, each within +/-`MAX_EQ_DB`.
+    pub eq_low_db: f32,
+    pub eq_mid_db: f32,
+    pub eq_high_db: f32,
+    /// Low-pass cutoff, `MIN_LOWPASS_HZ` to `MAX_LOWPASS_HZ` (off).
+    pub lowpass_hz: f32,
+    /// Reverb level over the dry sound, 0.0 (off) to 1.0.
+    pub reverb_mix: f32,
+    /// From a small booth (0.0) to a hall (1.0).
+    pub reverb_size: f32,
+}
+
+impl Default for EffectsSettings {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            eq_low_db: 0.0,
+            eq_mid_db: 0.0,
+            eq_high_db: 0.0,
+            lowpass_hz: MAX_LOWPASS_HZ,
+            reverb_mix: 0.0,
+            reverb_size: 0.5,
+        }
+    }
+}
+
+/// Which bus sidechain ducking turns down, and on what cue; see
+/// `libs::audio::ducking`.
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum DuckingMode {
+    #[default]
+    Off,
+    /// Typing turns the ambiance down.
+    Ambiance,
+    /// Loud ambiance turns the keys down.
+    Keys,
+}
+
+impl DuckingMode {
+    pub const ALL: [DuckingMode; 3] = [DuckingMode::Off, DuckingMode::Ambiance, DuckingMode::Keys];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            DuckingMode::Off => "Off",
+            DuckingMode::Ambiance => "Typing lowers ambiance",
+            DuckingMode::Keys => "Loud ambiance lowers keys",
+        }
+    }
+}
+
+/// Deepest cut ducking offers, in dB.
+pub const MAX_DUCK_DB: f32 = 24.0;
+
+/// Sidechain ducking between the keys and the ambiance.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
+#[serde(default)]
+pub struct Ducking {
+    pub mode: DuckingMode,
+    /// How far the ducked bus is turned down, 0 to `MAX_DUCK_DB`.
+    pub amount_db: f32,
+    /// How quickly the duck pulls in once its cue starts.
+    pub attack_ms: f32,
+    /// How slowly the ducked bus comes back once its cue stops.
+    pub release_ms: f32,
+}
+
+impl Default for Ducking {
+    fn default() -> Self {
+        Self {
+            mode: DuckingMode::Off,
+            amount_db: 9.0,
+            attack_ms: 30.0,
+            release_ms: 800.0,
+        }
+    }
+}
+
+/// Which steps of the fallback chain a key missing from the keyboard pack
+/// goes through, see `utils::key_fallback`. With every step off the key is
+/// silent, as it always was.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(default)]
+pub struct KeyFallback {
+    /// Borrow the sound of a mapped key from the same family.
+    pub family: bool,
+    /// Then of any mapped letter key.
+    pub alpha: bool,
+    /// Then of this key, if the pack maps it.
+    pub default_key: Option<String>,
+}
+
+impl Default for KeyFallback {
+    fn default() -> Self {
+        Self {
+            family: true,
+            alpha: true,
+            default_key: None,
+        }
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
 pub struct LogoCustomization {
     pub border_color: String,
@@ -101,6 +300,43 @@ pub struct AppConfig {
     pub enable_sound: bool,
     pub enable_keyboard_sound: bool, // Enable/disable keyboard sounds specifically
     pub enable_mouse_sound: bool, // Enable/disable mouse sounds specifically
+    /// Largest +/- playback-rate change applied to each voice of a pack whose
+    /// `options.random_pitch` is set, as a fraction (0.05 = up to 5% faster or
+    /// slower). Packs without the option always play at their recorded pitch.
+    pub random_pitch_range: f32,
+    /// Level every pack by its measured loudness instead of the author's
+    /// `options.recommended_volume`, so switching packs keeps the same
+    /// perceived volume.
+    pub auto_normalize_loudness: bool,
+    /// Pan each keystroke left or right by where its key sits on
+    /// `keyboard_layout`. Mouse sounds stay centred.
+    pub stereo_panning: bool,
+    pub keyboard_layout: KeyboardLayout,
+    /// How far apart the outermost keys are panned, from 0.0 (all centred)
+    /// to 1.0 (hard left and right).
+    pub stereo_width: f32,
+    /// Global key combos and the action each triggers. See `libs::hotkeys`.
+    pub hotkeys: HotkeyBindings,
+    /// Per-key gain and mute, by keyboard soundpack id and then key code.
+    /// Kept per pack because a key that is too loud in one pack is usually
+    /// fine in the next.
+    pub key_overrides: HashMap<String, KeyOverrides>,
+    pub effects: EffectsSettings,
+    /// Which voice is cut when too many sounds overlap.
+    pub voice_stealing: VoiceStealing,
+    /// Every key and button cuts off its own previous sound - a keyup ends
+    /// its keydown's tail, a repeated press restarts cleanly - whatever the
+    /// pack's `options.self_choke` says.
+    pub choke_same_key: bool,
+    /// Groups of key codes that cut each other off, for every pack, on top
+    /// of a pack's own `options.choke_groups`.
+    pub choke_groups: Vec<Vec<String>>,
+    /// What holding a key down plays once the OS starts repeating it.
+    pub key_repeat: KeyRepeat,
+    /// Level of a replayed repeat relative to the press, 0.0 to 1.0.
+    pub repeat_gain: f32,
+    /// What plays for a key the keyboard pack has no sound for.
+    pub key_fallback: KeyFallback,
     // Device settings
     pub selected_audio_device: Option<String>, // Selected audio output device
     pub enabled_keyboards: Vec<String>, // Enabled physical keyboards (by device instance ID)
@@ -117,6 +353,7 @@ pub struct AppConfig {
     pub ambiance_active_sounds: HashMap<String, f32>, // sound_id -> volume (0.0 to 1.0)
     pub ambiance_global_volume: f32, // 0.0 to 1.0 - global multiplier
     pub ambiance_is_muted: bool,
+    pub ambiance_ducking: Ducking,
     // Note: ambiance play state is not persistent - always starts paused
     // System settings
     pub auto_start: bool,
@@ -252,6 +489,20 @@ impl AppConfig {
             && self.enable_sound == other.enable_sound
             && self.enable_keyboard_sound == other.enable_keyboard_sound
             && self.enable_mouse_sound == other.enable_mouse_sound
+            && self.random_pitch_range == other.random_pitch_range
+            && self.auto_normalize_loudness == other.auto_normalize_loudness
+            && self.stereo_panning == other.stereo_panning
+            && self.keyboard_layout == other.keyboard_layout
+            && self.stereo_width == other.stereo_width
+            && self.hotkeys == other.hotkeys
+            && self.key_overrides == other.key_overrides
+            && self.effects == other.effects
+            && self.voice_stealing == other.voice_stealing
+            && self.choke_same_key == other.choke_same_key
+            && self.choke_groups == other.choke_groups
+            && self.key_repeat == other.key_repeat
+            && self.repeat_gain == other.repeat_gain
+            && self.key_fallback == other.key_fallback
             && self.selected_audio_device == other.selected_audio_device
             && self.enabled_keyboards == other.enabled_keyboards
             && self.enabled_mice == other.enabled_mice
@@ -265,6 +516,7 @@ impl AppConfig {
             && self.ambiance_active_sounds == other.ambiance_active_sounds
             && self.ambiance_global_volume == other.ambiance_global_volume
             && self.ambiance_is_muted == other.ambiance_is_muted
+            && self.ambiance_ducking == other.ambiance_ducking
             && self.auto_start == other.auto_start
             && self.start_minimized == other.start_minimized
             && self.landscape_mode == other.landscape_mode
@@ -272,6 +524,25 @@ impl AppConfig {
             && self.enable_telemetry == other.enable_telemetry
     }
 
+    /// Sets `code`'s override in `soundpack_id`. An override back at the
+    /// defaults is removed, and so is a pack left with none, so the file only
+    /// lists keys the user actually changed.
+    pub fn set_key_override(&mut self, soundpack_id: &str, code: &str, key_override: KeyOverride) {
+        if key_override.is_default() {
+            if let Some(overrides) = self.key_overrides.get_mut(soundpack_id) {
+                overrides.remove(code);
+                if overrides.is_empty() {
+                    self.key_overrides.remove(soundpack_id);
+                }
+            }
+        } else {
+            self.key_overrides
+                .entry(soundpack_id.to_string())
+                .or_default()
+                .insert(code.to_string(), key_override);
+        }
+    }
+
     pub fn load() -> Self {
         let config_path = paths::data::config_json();
 
@@ -308,6 +579,21 @@ impl AppConfig {
                     config_updated = true;
                 }
 
+                // Linux listed placeholder devices before it enumerated real
+                // ones; the evdev listener now filters by this list, where a
+                // placeholder would match nothing and silence every keyboard
+                #[cfg(target_os = "linux")]
+                {
+                    let placeholders = ["default_keyboard", "default_mouse"];
+                    let before = config.enabled_keyboards.len() + config.enabled_mice.len();
+                    config.enabled_keyboards.retain(|id| !placeholders.contains(&id.as_str()));
+                    config.enabled_mice.retain(|id| !placeholders.contains(&id.as_str()));
+                    if config.enabled_keyboards.len() + config.enabled_mice.len() != before {
+                        crate::always_print!("🔄 Removing placeholder input devices from enabled devices");
+                        config_updated = true;
+                    }
+                }
+
                 // Sync auto_start with actual registry state
                 let actual_auto_start = crate::utils::auto_startup::get_auto_startup_state();
                 if config.auto_start != actual_auto_start {
@@ -401,6 +687,20 @@ impl Default for AppConfig {
             enable_sound: true,
             enable_keyboard_sound: true, // Default keyboard sounds enabled
             enable_mouse_sound: true, // Default mouse sounds enabled
+            random_pitch_range: 0.05, // Subtle enough to keep a pack's character
+            auto_normalize_loudness: false,
+            stereo_panning: false,
+            keyboard_layout: KeyboardLayout::default(),
+            stereo_width: 0.5,
+            hotkeys: hotkeys::default_bindings(),
+            key_overrides: HashMap::new(),
+            effects: EffectsSettings::default(),
+            voice_stealing: VoiceStealing::default(),
+            choke_same_key: false,
+            choke_groups: Vec::new(),
+            key_repeat: KeyRepeat::default(),
+            repeat_gain: 0.5,
+            key_fallback: KeyFallback::default(),
             selected_audio_device: None, // Default to system default audio device
             enabled_keyboards: Vec::new(), // Default to no keyboards enabled (all keyboards will work)
             enabled_mice: Vec::new(), // Default to no mice enabled (all mice will work)
@@ -414,6 +714,7 @@ impl Default for AppConfig {
             ambiance_active_sounds: HashMap::new(),
             ambiance_global_volume: 0.5, // Default global ambiance volume to 50%
             ambiance_is_muted: false,
+            ambiance_ducking: Ducking::default(),
             // Note: ambiance play state is not persistent - always starts paused
             auto_start: false,
             start_minimized: false, // Default to not starting minimized
@@ -719,4 +1020,19 @@ mod tests {
         same.volume = config.volume + 0.25;
         assert!(!same.data_equals(&config), "a real change must still be detected");
     }
+
+    #[test]
+    fn a_key_override_back_at_defaults_leaves_no_trace() {
+        let mut config = AppConfig::default();
+        let quiet = KeyOverride { gain: 0.5, muted: false };
+        config.set_key_override("keyboard/a", "Space", quiet);
+        config.set_key_override("keyboard/a", "Enter", KeyOverride { gain: 1.0, muted: true });
+        assert_eq!(config.key_overrides["keyboard/a"]["Space"], quiet);
+
+        config.set_key_override("keyboard/a", "Space", KeyOverride::default());
+        assert!(!config.key_overrides["keyboard/a"].contains_key("Space"));
+
+        config.set_key_override("keyboard/a", "Enter", KeyOverride::default());
+        assert!(config.key_overrides.is_empty(), "a pack with no overrides is dropped");
+    }
 }
@@ -4,6 +4,7 @@ pub mod config;
 pub mod config_writer;
 pub mod keyboard;
 pub mod manifest;
+pub mod output;
 pub mod paths;
 pub mod soundpack;
 pub mod themes;
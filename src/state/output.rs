@@ -0,0 +1,9 @@
+//! Output state mirrored from the audio engine for the UI
+
+/// What the UI knows about the mixed output
+/// - `clipping`: Whether the limiter is holding the mix down, i.e. the sound
+///   would clip without it. Follows `UiEvent::Clipping`.
+#[derive(Debug, PartialEq, Clone, Copy, Default)]
+pub struct OutputState {
+    pub clipping: bool,
+}
@@ -454,6 +454,12 @@ pub mod data {
         get_writable_data_dir().join("soundpack_cache.json")
     }
 
+    /// Decoded soundpack audio, so a pack loaded before skips decoding
+    /// (see `libs::audio::pcm_cache`)
+    pub fn pcm_cache_dir() -> PathBuf {
+        get_system_app_data_dir().join("pcm_cache")
+    }
+
     /// Custom images directory for user-uploaded images
     /// Uses system app data directory (e.g., %APPDATA%/Mechvibes/custom_images on Windows)
     pub fn custom_images_dir() -> PathBuf {
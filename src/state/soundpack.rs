@@ -29,6 +29,27 @@ pub struct SoundpackOptions {
     pub recommended_volume: f32,
     #[serde(default = "default_random_pitch")]
     pub random_pitch: bool,
+    #[serde(default)]
+    pub variation_mode: VariationMode,
+    /// Each key cuts off its own previous sound: its keyup ends the keydown's
+    /// tail and a repeated press restarts cleanly.
+    #[serde(default)]
+    pub self_choke: bool,
+    /// Groups of key codes that cut each other off: a new sound from any key
+    /// in a group ends whatever the group is still playing.
+    #[serde(default)]
+    pub choke_groups: Vec<Vec<String>>,
+}
+
+/// How a key that lists `variations` picks which one plays on each press.
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum VariationMode {
+    /// Each press takes the next variation in order, wrapping around.
+    #[default]
+    RoundRobin,
+    /// Each press takes a random variation, never the same one twice running.
+    Random,
 }
 
 fn default_recommended_volume() -> f32 {
@@ -44,6 +65,9 @@ impl Default for SoundpackOptions {
         Self {
             recommended_volume: 1.0,
             random_pitch: false,
+            variation_mode: VariationMode::default(),
+            self_choke: false,
+            choke_groups: Vec::new(),
         }
     }
 }
@@ -54,6 +78,22 @@ pub struct KeyDefinition {
     pub timing: Vec<[f32; 2]>, // Array of [start_ms, end_ms] pairs
     #[serde(default)]
     pub audio_file: Option<String>, // For "multi" definition method
+    /// Alternative `timing` sets for the same key, same shape as `timing`.
+    /// With any present, each press plays one of `timing` and these, picked
+    /// by `options.variation_mode`, and its keyup plays from the same set.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub variations: Vec<Vec<[f32; 2]>>,
+    /// What the key plays for each OS auto-repeat while held, when the user
+    /// picked the pack's repeat sound. A single `[start_ms, end_ms]`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub repeat: Option<[f32; 2]>,
+}
+
+impl KeyDefinition {
+    /// `timing` followed by every entry of `variations`.
+    pub fn timing_sets(&self) -> impl Iterator<Item = &Vec<[f32; 2]>> {
+        std::iter::once(&self.timing).chain(self.variations.iter())
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
@@ -117,10 +157,20 @@ pub struct SoundpackMetadata {
     // Error tracking
     #[serde(default)]
     pub last_error: Option<String>,
+    /// Key codes the pack defines, for the coverage report. `None` for
+    /// entries cached before it was recorded, and for packs that failed to
+    /// load.
+    #[serde(default)]
+    pub mapped_keys: Option<Vec<String>>,
 }
 
 // ===== SOUNDPACK CACHE =====
 
+/// Bumped whenever `SoundpackMetadata` gains a field that older caches lack;
+/// a cache written by an older version is rescanned on load.
+/// 5: `mapped_keys`, for the coverage report.
+const CACHE_VERSION: u32 = 5;
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct SoundpackCache {
     pub soundpacks: HashMap<String, SoundpackMetadata>,
@@ -160,9 +210,9 @@ impl SoundpackCache {
             }
         };
 
-        // Auto-refresh if cache is empty or missing
-        if cache.soundpacks.is_empty() {
-            crate::always_print!("🔄 Cache is empty, refreshing from soundpack directories...");
+        // Auto-refresh if cache is empty, missing or from an older version
+        if cache.soundpacks.is_empty() || cache.cache_version < CACHE_VERSION {
+            crate::always_print!("🔄 Cache is empty or outdated, refreshing from soundpack directories...");
             cache.refresh_from_directory();
             cache.save();
         }
@@ -173,11 +223,25 @@ impl SoundpackCache {
         Self {
             soundpacks: HashMap::new(),
             last_scan: 0,
-            cache_version: 4, // Current version with error tracking support
+            cache_version: CACHE_VERSION,
             count: SoundpackCount::default(),
         }
     }
 
+    /// Ids of the keyboard packs in the order the soundpack selector lists
+    /// them, most recently modified first.
+    pub fn keyboard_pack_order(&self) -> Vec<String> {
+        let mut packs: Vec<_> = self.soundpacks
+            .values()
+            .filter(|pack| pack.soundpack_type == SoundpackType::Keyboard)
+            .collect();
+        packs.sort_by_key(|pack| std::cmp::Reverse(pack.last_modified));
+        packs
+            .into_iter()
+            .map(|pack| pack.folder_path.clone())
+            .collect()
+    }
+
     pub fn save(&self) {
         let cache_file = Self::cache_file();
 
@@ -207,6 +271,7 @@ impl SoundpackCache {
         crate::always_print!("📂 Scanning soundpacks directories...");
 
         self.soundpacks.clear(); // Clear all existing entries
+        self.cache_version = CACHE_VERSION;
 
         // Scan built-in soundpacks (app root)
         let builtin_soundpacks_dir = paths::soundpacks::get_builtin_soundpacks_dir()
@@ -335,6 +400,7 @@ impl SoundpackCache {
             validation_status: "error".to_string(),
             can_be_converted: false,
             last_error: Some(error),
+            mapped_keys: None,
         };
         self.soundpacks.insert(full_soundpack_id.to_string(), error_metadata);
     }
@@ -158,12 +158,8 @@ pub fn convert_v1_to_v2(
 
             for key in sorted_keys {
                 if let Some(value) = defines.get(key) {
-                    if let Some(filename) = value.as_str() {
-                        if
-                            !filename.is_empty() &&
-                            filename != "null" &&
-                            !seen_files.contains(filename)
-                        {
+                    for filename in v1_define_files(value) {
+                        if !seen_files.contains(filename) {
                             // Just collect the files, we'll get timing from concatenation
                             audio_files_ordered.push(filename.to_string());
                             seen_files.insert(filename.to_string());
@@ -259,75 +255,67 @@ pub fn convert_v1_to_v2(
             for (iohook_code, value) in defines {
                 if let Ok(iohook_num) = iohook_code.parse::<u32>() {
                     if let Some(key_name) = key_mappings.get(&iohook_num) {
-                        if let Some(audio_filename) = value.as_str() {
-                            if !audio_filename.is_empty() && audio_filename != "null" {
-                                let mut key_def = Map::new();
-
-                                // Get offset and duration for this audio file
-                                if
-                                    let Some(&(offset, duration)) =
-                                        audio_file_info.get(audio_filename)
-                                {
-                                    let end_time = offset + duration;
-
-                                    // Special debug for Enter key
-                                    if key_name == "Enter" {
-                                        crate::always_print!("🔍 [ENTER DEBUG] Key: {}", key_name);
-                                        crate::always_print!("🔍 [ENTER DEBUG] IOHook code: {}", iohook_num);
-                                        crate::always_print!("🔍 [ENTER DEBUG] Audio file: {}", audio_filename);
-                                        crate::always_print!("🔍 [ENTER DEBUG] Offset: {}ms", offset);
-                                        crate::always_print!("🔍 [ENTER DEBUG] Duration: {}ms", duration);
-                                        crate::always_print!("🔍 [ENTER DEBUG] End time: {}ms", end_time);
-
-                                        // Check concatenated audio file duration
-                                        let concat_path =
-                                            format!("{}/concatenated_audio.wav", soundpack_dir);
-                                        if
-                                            let Ok(concat_duration) = get_audio_duration_ms(
-                                                &concat_path
-                                            )
-                                        {
-                                            crate::always_print!("🔍 [ENTER DEBUG] Concatenated audio duration: {}ms", concat_duration);
-                                            if end_time > concat_duration {
-                                                crate::always_print!(
-                                                    "❌ [ENTER DEBUG] ERROR: End time ({}) > Concat duration ({})",
-                                                    end_time,
-                                                    concat_duration
-                                                );
-                                            }
-                                        }
-                                    }
+                        let audio_filenames = v1_define_files(value);
+                        if audio_filenames.is_empty() {
+                            crate::always_print!("   ⚠️ Key IOHook {} has empty/null audio file", iohook_code);
+                            continue;
+                        }
 
-                                    // Create timing based on offset in concatenated file
-                                    let timing = vec![
-                                        Value::Array(
-                                            vec![
-                                                Value::Number(
-                                                    serde_json::Number::from_f64(offset).unwrap()
-                                                ),
-                                                Value::Number(
-                                                    serde_json::Number::from_f64(end_time).unwrap()
-                                                )
-                                            ]
+                        // A key listing several files keeps each one, as a
+                        // variation of the first.
+                        let mut segments = Vec::new();
+                        for audio_filename in audio_filenames {
+                            // Get offset and duration for this audio file
+                            if
+                                let Some(&(offset, duration)) =
+                                    audio_file_info.get(audio_filename)
+                            {
+                                let end_time = offset + duration;
+
+                                // Special debug for Enter key
+                                if key_name == "Enter" {
+                                    crate::always_print!("🔍 [ENTER DEBUG] Key: {}", key_name);
+                                    crate::always_print!("🔍 [ENTER DEBUG] IOHook code: {}", iohook_num);
+                                    crate::always_print!("🔍 [ENTER DEBUG] Audio file: {}", audio_filename);
+                                    crate::always_print!("🔍 [ENTER DEBUG] Offset: {}ms", offset);
+                                    crate::always_print!("🔍 [ENTER DEBUG] Duration: {}ms", duration);
+                                    crate::always_print!("🔍 [ENTER DEBUG] End time: {}ms", end_time);
+
+                                    // Check concatenated audio file duration
+                                    let concat_path =
+                                        format!("{}/concatenated_audio.wav", soundpack_dir);
+                                    if
+                                        let Ok(concat_duration) = get_audio_duration_ms(
+                                            &concat_path
                                         )
-                                    ];
-                                    key_def.insert("timing".to_string(), Value::Array(timing));
-
-                                    definitions.insert(key_name.clone(), Value::Object(key_def));
-                                    crate::always_print!(
-                                        "   ✅ Key '{}' -> {} [offset: {}ms, end: {}ms]",
-                                        key_name,
-                                        audio_filename,
-                                        offset,
-                                        end_time
-                                    );
-                                } else {
-                                    crate::always_print!("   ⚠️ No offset found for audio file: {}", audio_filename);
+                                    {
+                                        crate::always_print!("🔍 [ENTER DEBUG] Concatenated audio duration: {}ms", concat_duration);
+                                        if end_time > concat_duration {
+                                            crate::always_print!(
+                                                "❌ [ENTER DEBUG] ERROR: End time ({}) > Concat duration ({})",
+                                                end_time,
+                                                concat_duration
+                                            );
+                                        }
+                                    }
                                 }
+
+                                segments.push((offset, end_time));
+                                crate::always_print!(
+                                    "   ✅ Key '{}' -> {} [offset: {}ms, end: {}ms]",
+                                    key_name,
+                                    audio_filename,
+                                    offset,
+                                    end_time
+                                );
                             } else {
-                                crate::always_print!("   ⚠️ Key IOHook {} has empty/null audio file", iohook_code);
+                                crate::always_print!("   ⚠️ No offset found for audio file: {}", audio_filename);
                             }
                         }
+
+                        if let Some(key_def) = key_definition_from_segments(&segments) {
+                            definitions.insert(key_name.clone(), key_def);
+                        }
                     } else {
                         crate::always_print!("   ⚠️ No key mapping found for IOHook code: {}", iohook_code);
                     }
@@ -340,34 +328,14 @@ pub fn convert_v1_to_v2(
             for (iohook_code, value) in defines {
                 if let Ok(iohook_num) = iohook_code.parse::<u32>() {
                     if let Some(key_name) = key_mappings.get(&iohook_num) {
-                        let mut key_def = Map::new();
-
                         // For single method, use timing from defines
-                        if let Some(timing_array) = value.as_array() {
-                            if timing_array.len() >= 2 {
-                                let start = timing_array[0].as_f64().unwrap_or(0.0) as f32;
-                                let duration = timing_array[1].as_f64().unwrap_or(100.0) as f32;
-                                let end = start + duration;
-
-                                // Create timing array with keydown and keyup
-                                let timing = vec![
-                                    Value::Array(
-                                        vec![
-                                            Value::Number(
-                                                serde_json::Number::from_f64(start as f64).unwrap()
-                                            ),
-                                            Value::Number(
-                                                serde_json::Number::from_f64(end as f64).unwrap()
-                                            )
-                                        ]
-                                    )
-                                ];
-                                key_def.insert("timing".to_string(), Value::Array(timing));
-
-                                definitions.insert(key_name.clone(), Value::Object(key_def));
+                        let segments = v1_define_segments(value);
+                        if let Some(key_def) = key_definition_from_segments(&segments) {
+                            definitions.insert(key_name.clone(), key_def);
+                            for (start, end) in &segments {
                                 crate::always_print!("   ✅ Key '{}' -> timing [{}, {}]", key_name, start, end);
                             }
-                        } else {
+                        } else if value.as_array().is_none() {
                             crate::always_print!("   ⚠️ Key '{}' has invalid timing format", key_name);
                         }
                     }
@@ -388,165 +356,62 @@ pub fn convert_v1_to_v2(
     Ok(())
 }
 
-/// Convert V2 config from multi method to single method
-/// This ensures all V2 configs use the single method format
-pub fn convert_v2_multi_to_single(
-    config_path: &str,
-    soundpack_dir: &str
-) -> Result<(), Box<dyn std::error::Error>> {
-    crate::always_print!("🔄 Converting V2 multi method to single method...");
-
-    // Read the existing V2 config
-    let content = std::fs::read_to_string(config_path)?;
-    let mut config: Value = serde_json::from_str(&content)?;
-
-    // Check if this is already single method
-    if let Some(definition_method) = config.get("definition_method").and_then(|v| v.as_str()) {
-        if definition_method == "single" {
-            crate::always_print!("✅ Already using single method, no conversion needed");
-            return Ok(());
-        }
-    }
-
-    crate::always_print!("🔧 Converting from multi method to single method");
+/// The audio files a V1 "multi" define names: a single filename, or a list
+/// of alternatives for the same key. Empty and `"null"` entries are skipped.
+fn v1_define_files(value: &Value) -> Vec<&str> {
+    let names: Vec<&Value> = match value {
+        Value::Array(items) => items.iter().collect(),
+        other => vec![other],
+    };
+    names
+        .into_iter()
+        .filter_map(|name| name.as_str())
+        .filter(|name| !name.is_empty() && *name != "null")
+        .collect()
+}
 
-    // Analyze audio files used in definitions to find the most common one
-    let mut audio_file_usage = std::collections::HashMap::new();
-    if let Some(definitions) = config.get("definitions").and_then(|d| d.as_object()) {
-        for (key_name, key_def) in definitions {
-            if let Some(key_obj) = key_def.as_object() {
-                if let Some(audio_file) = key_obj.get("audio_file").and_then(|v| v.as_str()) {
-                    *audio_file_usage.entry(audio_file.to_string()).or_insert(0) += 1;
-                    crate::always_print!("🔍 Key '{}' uses audio file: {}", key_name, audio_file);
-                }
-            }
-        }
-    }
-    // Find the most commonly used audio file
-    let main_audio_file = if
-        let Some((audio_file, count)) = audio_file_usage.iter().max_by_key(|(_, count)| *count)
-    {
-        crate::always_print!("🎵 Most used audio file: {} (used by {} keys)", audio_file, count);
-        audio_file.clone()
+/// The `(start, end)` ms segments a V1 "single" define names. V1 stores
+/// `[start, duration]`; a list of those gives the key alternatives.
+fn v1_define_segments(value: &Value) -> Vec<(f64, f64)> {
+    let Some(items) = value.as_array() else {
+        return Vec::new();
+    };
+    let pairs: Vec<&Vec<Value>> = if items.iter().all(|item| item.is_array()) {
+        items.iter().filter_map(|item| item.as_array()).collect()
     } else {
-        // Fallback: find any audio file in the directory
-        let audio_extensions = ["ogg", "mp3", "wav", "flac"];
-        let mut found_audio = None;
-
-        if let Ok(entries) = std::fs::read_dir(soundpack_dir) {
-            for entry in entries.filter_map(|e| e.ok()) {
-                if let Some(filename) = entry.file_name().to_str() {
-                    let filename_lower = filename.to_lowercase();
-                    for ext in &audio_extensions {
-                        if filename_lower.ends_with(&format!(".{}", ext)) {
-                            found_audio = Some(filename.to_string());
-                            break;
-                        }
-                    }
-                    if found_audio.is_some() {
-                        break;
-                    }
-                }
-            }
-        }
-
-        found_audio.ok_or("No audio file found in soundpack directory")?
+        vec![items]
     };
 
-    crate::always_print!("🎵 Using main audio file for single method: {}", main_audio_file);
-
-    // Update config to single method
-    config
-        .as_object_mut()
-        .unwrap()
-        .insert("definition_method".to_string(), Value::String("single".to_string()));
-
-    config
-        .as_object_mut()
-        .unwrap()
-        .insert("audio_file".to_string(), Value::String(main_audio_file.clone()));
-
-    // Convert definitions from multi to single format
-    if
-        let Some(definitions) = config
-            .get("definitions")
-            .and_then(|d| d.as_object())
-            .cloned()
-    {
-        let mut new_definitions = serde_json::Map::new();
-
-        for (key_name, key_def) in definitions {
-            if let Some(key_obj) = key_def.as_object() {
-                let mut new_key_def = serde_json::Map::new();
-
-                // Check if this key was using the main audio file
-                let key_audio_file = key_obj
-                    .get("audio_file")
-                    .and_then(|v| v.as_str())
-                    .unwrap_or("");
-
-                if key_audio_file == main_audio_file {
-                    // This key uses the main audio file, keep its timing
-                    if let Some(timing) = key_obj.get("timing") {
-                        new_key_def.insert("timing".to_string(), timing.clone());
-                        crate::always_print!("✅ Key '{}' kept timing (uses main audio file)", key_name);
-                    } else {
-                        // Create default timing for the whole audio file
-                        let audio_path = format!("{}/{}", soundpack_dir, main_audio_file);
-                        let duration = get_audio_duration_ms(&audio_path).unwrap_or(100.0);
-
-                        let timing = vec![
-                            Value::Array(
-                                vec![
-                                    Value::Number(serde_json::Number::from_f64(0.0).unwrap()),
-                                    Value::Number(serde_json::Number::from_f64(duration).unwrap())
-                                ]
-                            )
-                        ];
-                        new_key_def.insert("timing".to_string(), Value::Array(timing));
-                        crate::always_print!("⚠️ Key '{}' got default timing (no timing specified)", key_name);
-                    }
+    pairs
+        .into_iter()
+        .filter(|pair| pair.len() >= 2)
+        .map(|pair| {
+            let start = pair[0].as_f64().unwrap_or(0.0);
+            let duration = pair[1].as_f64().unwrap_or(100.0);
+            (start, start + duration)
+        })
+        .collect()
+}
 
-                    new_definitions.insert(key_name, Value::Object(new_key_def));
-                } else if !key_audio_file.is_empty() {
-                    // This key uses a different audio file, we'll skip it in single method
-                    crate::always_print!(
-                        "⚠️ Key '{}' uses different audio file '{}', skipping in single method conversion",
-                        key_name,
-                        key_audio_file
-                    );
-                } else {
-                    // No audio_file specified, create default timing
-                    let audio_path = format!("{}/{}", soundpack_dir, main_audio_file);
-                    let duration = get_audio_duration_ms(&audio_path).unwrap_or(100.0);
-
-                    let timing = vec![
-                        Value::Array(
-                            vec![
-                                Value::Number(serde_json::Number::from_f64(0.0).unwrap()),
-                                Value::Number(serde_json::Number::from_f64(duration).unwrap())
-                            ]
-                        )
-                    ];
-                    new_key_def.insert("timing".to_string(), Value::Array(timing));
-                    crate::always_print!("⚠️ Key '{}' got default timing (no audio_file specified)", key_name);
-                    new_definitions.insert(key_name, Value::Object(new_key_def));
-                }
-            }
-        }
+/// A V2 key definition playing `segments[0]` on keydown, with any further
+/// segments as `variations` of it. V1 has no keyup sounds, so each set is a
+/// single keydown segment. `None` for no segments.
+fn key_definition_from_segments(segments: &[(f64, f64)]) -> Option<Value> {
+    let timing_set = |&(start, end): &(f64, f64)| {
+        let pair = vec![
+            Value::Number(serde_json::Number::from_f64(start).unwrap_or(0.into())),
+            Value::Number(serde_json::Number::from_f64(end).unwrap_or(0.into()))
+        ];
+        Value::Array(vec![Value::Array(pair)])
+    };
 
-        config
-            .as_object_mut()
-            .unwrap()
-            .insert("definitions".to_string(), Value::Object(new_definitions));
+    let (first, rest) = segments.split_first()?;
+    let mut key_def = Map::new();
+    key_def.insert("timing".to_string(), timing_set(first));
+    if !rest.is_empty() {
+        key_def.insert("variations".to_string(), Value::Array(rest.iter().map(timing_set).collect()));
     }
-
-    // Write the converted config back
-    let output_json = serde_json::to_string_pretty(&config)?;
-    std::fs::write(config_path, output_json)?;
-
-    crate::always_print!("✅ Successfully converted to single method");
-    Ok(())
+    Some(Value::Object(key_def))
 }
 
 /// Move an existing file aside before something overwrites it, returning
@@ -868,6 +733,19 @@ fn save_audio_file(
     Ok(())
 }
 
+/// The key codes a V1 `defines` map gives a sound, converted from the IOHook
+/// scancodes it is keyed by. Keys defined as `null` and scancodes with no
+/// key code are left out.
+pub fn v1_define_key_codes(defines: &Map<String, Value>) -> Vec<String> {
+    let key_mappings = create_iohook_to_web_key_mapping();
+    defines
+        .iter()
+        .filter(|(_, value)| !value.is_null())
+        .filter_map(|(iohook_code, _)| iohook_code.parse::<u32>().ok())
+        .filter_map(|iohook_num| key_mappings.get(&iohook_num).cloned())
+        .collect()
+}
+
 /// Create comprehensive IOHook to Web API key mapping
 /// Supports all platforms (Windows, Linux, macOS)
 fn create_iohook_to_web_key_mapping() -> HashMap<u32, String> {
@@ -1095,7 +973,14 @@ fn create_iohook_to_web_key_mapping() -> HashMap<u32, String> {
 
 #[cfg(test)]
 mod tests {
-    use super::back_up_existing_file;
+    use super::{
+        back_up_existing_file,
+        key_definition_from_segments,
+        v1_define_files,
+        v1_define_key_codes,
+        v1_define_segments,
+    };
+    use serde_json::json;
 
     fn temp_dir(tag: &str) -> std::path::PathBuf {
         let dir = std::env
@@ -1152,6 +1037,44 @@ mod tests {
         std::fs::remove_dir_all(&dir).ok();
     }
 
+    #[test]
+    fn v1_defines_with_several_sounds_keep_them_all() {
+        assert_eq!(v1_define_files(&json!("a.wav")), vec!["a.wav"]);
+        assert_eq!(v1_define_files(&json!(["a.wav", "", "b.wav", null])), vec!["a.wav", "b.wav"]);
+        assert!(v1_define_files(&json!("null")).is_empty());
+
+        assert_eq!(v1_define_segments(&json!([100, 50])), vec![(100.0, 150.0)]);
+        assert_eq!(
+            v1_define_segments(&json!([[100, 50], [300, 20]])),
+            vec![(100.0, 150.0), (300.0, 320.0)]
+        );
+        assert!(v1_define_segments(&json!("a.wav")).is_empty());
+    }
+
+    #[test]
+    fn v1_scancodes_become_key_codes() {
+        let defines = json!({ "1": [0, 50], "30": [100, 50], "57": null, "99999": [200, 50], "x": [0, 1] });
+        let mut codes = v1_define_key_codes(defines.as_object().unwrap());
+        codes.sort();
+        assert_eq!(codes, vec!["Escape", "KeyA"]);
+    }
+
+    #[test]
+    fn extra_segments_become_variations_the_loader_reads() {
+        let key_def = key_definition_from_segments(&[(0.0, 10.0), (20.0, 30.0), (40.0, 50.0)]).unwrap();
+        assert_eq!(
+            key_def,
+            json!({ "timing": [[0.0, 10.0]], "variations": [[[20.0, 30.0]], [[40.0, 50.0]]] })
+        );
+
+        let parsed: crate::state::soundpack::KeyDefinition = serde_json::from_value(key_def).unwrap();
+        assert_eq!(parsed.timing_sets().count(), 3);
+
+        let single = key_definition_from_segments(&[(0.0, 10.0)]).unwrap();
+        assert!(single.get("variations").is_none(), "one sound needs no variations");
+        assert_eq!(key_definition_from_segments(&[]), None);
+    }
+
     /// The common case - a pack with no such file yet - must not be reported
     /// as a backup, or callers would log a rescue that never happened.
     #[test]
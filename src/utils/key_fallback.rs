@@ -0,0 +1,288 @@
+//! Sounds for keys a keyboard pack does not define.
+//!
+//! Older packs stop at the main block, so F13 and up, media keys or the
+//! numpad were simply silent. A missing key now borrows the sound of a key
+//! the pack does map, tried in order: a key of its own family (the other
+//! Shift, another numpad key, another function key), then any letter key,
+//! then the key the user picked in `KeyFallback::default_key`. The engine
+//! walks this chain per keystroke; the soundpack table walks the same chain
+//! to report each pack's coverage.
+
+use crate::state::config::KeyFallback;
+use crate::utils::keyboard_layout::KeyboardLayout;
+
+/// Families of interchangeable keys, tightest first. A key is looked up in
+/// every family it belongs to, in this order, so `AltRight` tries `AltLeft`
+/// before falling back to the other modifiers.
+const FAMILIES: &[&[&str]] = &[
+    &["ShiftLeft", "ShiftRight"],
+    &["ControlLeft", "ControlRight"],
+    &["AltLeft", "AltRight"],
+    &["MetaLeft", "MetaRight", "ContextMenu"],
+    &[
+        "ShiftLeft",
+        "ShiftRight",
+        "ControlLeft",
+        "ControlRight",
+        "AltLeft",
+        "AltRight",
+        "MetaLeft",
+        "MetaRight",
+        "CapsLock",
+        "Fn",
+    ],
+    &["Enter", "NumpadEnter"],
+    &["Backspace", "Delete"],
+    &[
+        "Numpad0",
+        "Numpad1",
+        "Numpad2",
+        "Numpad3",
+        "Numpad4",
+        "Numpad5",
+        "Numpad6",
+        "Numpad7",
+        "Numpad8",
+        "Numpad9",
+        "NumpadDecimal",
+        "NumpadAdd",
+        "NumpadSubtract",
+        "NumpadMultiply",
+        "NumpadDivide",
+        "NumpadEnter",
+        "NumLock",
+    ],
+    &[
+        "Digit1",
+        "Digit2",
+        "Digit3",
+        "Digit4",
+        "Digit5",
+        "Digit6",
+        "Digit7",
+        "Digit8",
+        "Digit9",
+        "Digit0",
+        "Numpad1",
+        "Numpad2",
+        "Numpad3",
+        "Numpad4",
+        "Numpad5",
+        "Numpad6",
+        "Numpad7",
+        "Numpad8",
+        "Numpad9",
+        "Numpad0",
+    ],
+    &[
+        "F1",
+        "F2",
+        "F3",
+        "F4",
+        "F5",
+        "F6",
+        "F7",
+        "F8",
+        "F9",
+        "F10",
+        "F11",
+        "F12",
+        "F13",
+        "F14",
+        "F15",
+        "F16",
+        "F17",
+        "F18",
+        "F19",
+        "F20",
+        "F21",
+        "F22",
+        "F23",
+        "F24",
+    ],
+    &[
+        "ArrowUp",
+        "ArrowDown",
+        "ArrowLeft",
+        "ArrowRight",
+        "Home",
+        "End",
+        "PageUp",
+        "PageDown",
+        "Insert",
+        "Delete",
+    ],
+    &[
+        "AudioVolumeMute",
+        "AudioVolumeDown",
+        "AudioVolumeUp",
+        "MediaPlayPause",
+        "MediaStop",
+        "MediaTrackNext",
+        "MediaTrackPrevious",
+        "PrintScreen",
+        "ScrollLock",
+        "Pause",
+    ],
+    &[
+        "Minus",
+        "Equal",
+        "BracketLeft",
+        "BracketRight",
+        "Backslash",
+        "IntlBackslash",
+        "Semicolon",
+        "Quote",
+        "Backquote",
+        "Comma",
+        "Period",
+        "Slash",
+    ],
+    &ALPHA_KEYS,
+];
+
+/// Letter keys in the order the alpha step tries them: home row first, as
+/// the keys a pack author is least likely to have skipped.
+const ALPHA_KEYS: [&str; 26] = [
+    "KeyA",
+    "KeyS",
+    "KeyD",
+    "KeyF",
+    "KeyJ",
+    "KeyK",
+    "KeyL",
+    "KeyG",
+    "KeyH",
+    "KeyQ",
+    "KeyW",
+    "KeyE",
+    "KeyR",
+    "KeyT",
+    "KeyY",
+    "KeyU",
+    "KeyI",
+    "KeyO",
+    "KeyP",
+    "KeyZ",
+    "KeyX",
+    "KeyC",
+    "KeyV",
+    "KeyB",
+    "KeyN",
+    "KeyM",
+];
+
+/// The mapped key whose sound `code` borrows, or `None` to stay silent.
+/// Only called for keys the pack lacks; a key never falls back to itself.
+pub fn fallback_for<'a>(
+    code: &str,
+    is_mapped: impl Fn(&str) -> bool,
+    settings: &'a KeyFallback
+) -> Option<&'a str> {
+    let family = FAMILIES.iter()
+        .filter(|family| settings.family && family.contains(&code))
+        .flat_map(|family| family.iter());
+    let alpha = ALPHA_KEYS.iter().filter(|_| settings.alpha);
+
+    family
+        .chain(alpha)
+        .copied()
+        .chain(settings.default_key.as_deref())
+        .find(|key| *key != code && is_mapped(key))
+}
+
+/// How much of a layout one pack covers.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct KeyCoverage {
+    /// Keys the pack defines.
+    pub native: Vec<&'static str>,
+    /// Keys it lacks that borrow another key's sound.
+    pub fallback: Vec<&'static str>,
+    /// Keys that stay silent.
+    pub silent: Vec<&'static str>,
+}
+
+impl KeyCoverage {
+    pub fn total(&self) -> usize {
+        self.native.len() + self.fallback.len() + self.silent.len()
+    }
+}
+
+/// Sorts every key on `layout` by whether a pack mapping `mapped` plays it
+/// natively, through the fallback chain, or not at all.
+pub fn coverage(layout: KeyboardLayout, mapped: &[String], settings: &KeyFallback) -> KeyCoverage {
+    let is_mapped = |code: &str| mapped.iter().any(|key| key == code);
+    let mut coverage = KeyCoverage::default();
+    for key in layout.keys() {
+        if is_mapped(key.code) {
+            coverage.native.push(key.code);
+        } else if fallback_for(key.code, is_mapped, settings).is_some() {
+            coverage.fallback.push(key.code);
+        } else {
+            coverage.silent.push(key.code);
+        }
+    }
+    coverage
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn mapped(keys: &[&str]) -> impl Fn(&str) -> bool {
+        let keys: Vec<String> = keys.iter().map(|key| key.to_string()).collect();
+        move |code: &str| keys.iter().any(|key| key == code)
+    }
+
+    #[test]
+    fn the_tightest_family_is_tried_first() {
+        let settings = KeyFallback::default();
+        let pack = mapped(&["ShiftLeft", "AltLeft", "KeyA"]);
+
+        assert_eq!(fallback_for("AltRight", &pack, &settings), Some("AltLeft"));
+        assert_eq!(fallback_for("ControlRight", &pack, &settings), Some("ShiftLeft"));
+        assert_eq!(fallback_for("F13", mapped(&["F12", "KeyA"]), &settings), Some("F12"));
+        assert_eq!(fallback_for("Numpad5", mapped(&["Digit5", "KeyA"]), &settings), Some("Digit5"));
+    }
+
+    #[test]
+    fn each_step_of_the_chain_can_be_turned_off() {
+        let pack = mapped(&["F1", "KeyS", "Space"]);
+        let mut settings = KeyFallback {
+            family: true,
+            alpha: true,
+            default_key: Some("Space".to_string()),
+        };
+
+        assert_eq!(fallback_for("F13", &pack, &settings), Some("F1"));
+        settings.family = false;
+        assert_eq!(fallback_for("F13", &pack, &settings), Some("KeyS"));
+        settings.alpha = false;
+        assert_eq!(fallback_for("F13", &pack, &settings), Some("Space"));
+        settings.default_key = None;
+        assert_eq!(fallback_for("F13", &pack, &settings), None);
+    }
+
+    #[test]
+    fn a_default_key_the_pack_lacks_leaves_the_key_silent() {
+        let settings = KeyFallback {
+            family: false,
+            alpha: false,
+            default_key: Some("Space".to_string()),
+        };
+        assert_eq!(fallback_for("F13", mapped(&["KeyA"]), &settings), None);
+    }
+
+    #[test]
+    fn coverage_splits_a_layout_three_ways() {
+        let pack: Vec<String> = ["KeyA", "F1"].iter().map(|key| key.to_string()).collect();
+        let settings = KeyFallback { family: true, alpha: false, default_key: None };
+
+        let coverage = coverage(KeyboardLayout::Tkl, &pack, &settings);
+        assert_eq!(coverage.native, vec!["F1", "KeyA"]);
+        assert!(coverage.fallback.contains(&"F12"));
+        assert!(coverage.fallback.contains(&"KeyZ"), "letters are one family");
+        assert!(coverage.silent.contains(&"Space"));
+        assert_eq!(coverage.total(), KeyboardLayout::Tkl.keys().len());
+    }
+}
@@ -0,0 +1,363 @@
+//! Physical keyboard layouts: which key codes a board has and where each one
+//! sits, in key units (1u = one letter key). Stereo panning places keys by
+//! these positions; the codes are the same `"KeyA"` / `"Numpad5"` strings
+//! soundpack definitions are keyed by.
+
+use serde::{ Deserialize, Serialize };
+use std::collections::HashMap;
+
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum KeyboardLayout {
+    /// Full size, ANSI main block.
+    #[default]
+    Ansi,
+    /// Full size, ISO main block (tall Enter, extra key beside left Shift).
+    Iso,
+    /// ANSI without the numpad.
+    Tkl,
+    /// The ANSI main block alone, with Escape in place of Backquote.
+    Sixty,
+}
+
+/// One key on a layout. `x` is its left edge and `width` its size, both in
+/// key units; `row` counts down from the function row.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct KeyCap {
+    pub code: &'static str,
+    pub row: usize,
+    pub x: f32,
+    pub width: f32,
+}
+
+/// A row segment as `(code, width)`; an empty code is a gap.
+type Row = &'static [(&'static str, f32)];
+
+const FUNCTION_ROW: Row = &[
+    ("Escape", 1.0),
+    ("", 1.0),
+    ("F1", 1.0),
+    ("F2", 1.0),
+    ("F3", 1.0),
+    ("F4", 1.0),
+    ("", 0.5),
+    ("F5", 1.0),
+    ("F6", 1.0),
+    ("F7", 1.0),
+    ("F8", 1.0),
+    ("", 0.5),
+    ("F9", 1.0),
+    ("F10", 1.0),
+    ("F11", 1.0),
+    ("F12", 1.0),
+];
+
+const NUMBER_ROW: Row = &[
+    ("Backquote", 1.0),
+    ("Digit1", 1.0),
+    ("Digit2", 1.0),
+    ("Digit3", 1.0),
+    ("Digit4", 1.0),
+    ("Digit5", 1.0),
+    ("Digit6", 1.0),
+    ("Digit7", 1.0),
+    ("Digit8", 1.0),
+    ("Digit9", 1.0),
+    ("Digit0", 1.0),
+    ("Minus", 1.0),
+    ("Equal", 1.0),
+    ("Backspace", 2.0),
+];
+
+const ANSI_MAIN: [Row; 4] = [
+    &[
+        ("Tab", 1.5),
+        ("KeyQ", 1.0),
+        ("KeyW", 1.0),
+        ("KeyE", 1.0),
+        ("KeyR", 1.0),
+        ("KeyT", 1.0),
+        ("KeyY", 1.0),
+        ("KeyU", 1.0),
+        ("KeyI", 1.0),
+        ("KeyO", 1.0),
+        ("KeyP", 1.0),
+        ("BracketLeft", 1.0),
+        ("BracketRight", 1.0),
+        ("Backslash", 1.5),
+    ],
+    &[
+        ("CapsLock", 1.75),
+        ("KeyA", 1.0),
+        ("KeyS", 1.0),
+        ("KeyD", 1.0),
+        ("KeyF", 1.0),
+        ("KeyG", 1.0),
+        ("KeyH", 1.0),
+        ("KeyJ", 1.0),
+        ("KeyK", 1.0),
+        ("KeyL", 1.0),
+        ("Semicolon", 1.0),
+        ("Quote", 1.0),
+        ("Enter", 2.25),
+    ],
+    &[
+        ("ShiftLeft", 2.25),
+        ("KeyZ", 1.0),
+        ("KeyX", 1.0),
+        ("KeyC", 1.0),
+        ("KeyV", 1.0),
+        ("KeyB", 1.0),
+        ("KeyN", 1.0),
+        ("KeyM", 1.0),
+        ("Comma", 1.0),
+        ("Period", 1.0),
+        ("Slash", 1.0),
+        ("ShiftRight", 2.75),
+    ],
+    BOTTOM_ROW,
+];
+
+/// ISO moves Backslash down beside a tall Enter, whose upper half sits where
+/// ANSI's Backslash is, and splits left Shift for IntlBackslash.
+const ISO_MAIN: [Row; 4] = [
+    &[
+        ("Tab", 1.5),
+        ("KeyQ", 1.0),
+        ("KeyW", 1.0),
+        ("KeyE", 1.0),
+        ("KeyR", 1.0),
+        ("KeyT", 1.0),
+        ("KeyY", 1.0),
+        ("KeyU", 1.0),
+        ("KeyI", 1.0),
+        ("KeyO", 1.0),
+        ("KeyP", 1.0),
+        ("BracketLeft", 1.0),
+        ("BracketRight", 1.0),
+        ("Enter", 1.5),
+    ],
+    &[
+        ("CapsLock", 1.75),
+        ("KeyA", 1.0),
+        ("KeyS", 1.0),
+        ("KeyD", 1.0),
+        ("KeyF", 1.0),
+        ("KeyG", 1.0),
+        ("KeyH", 1.0),
+        ("KeyJ", 1.0),
+        ("KeyK", 1.0),
+        ("KeyL", 1.0),
+        ("Semicolon", 1.0),
+        ("Quote", 1.0),
+        ("Backslash", 1.0),
+        ("", 1.25),
+    ],
+    &[
+        ("ShiftLeft", 1.25),
+        ("IntlBackslash", 1.0),
+        ("KeyZ", 1.0),
+        ("KeyX", 1.0),
+        ("KeyC", 1.0),
+        ("KeyV", 1.0),
+        ("KeyB", 1.0),
+        ("KeyN", 1.0),
+        ("KeyM", 1.0),
+        ("Comma", 1.0),
+        ("Period", 1.0),
+        ("Slash", 1.0),
+        ("ShiftRight", 2.75),
+    ],
+    BOTTOM_ROW,
+];
+
+const BOTTOM_ROW: Row = &[
+    ("ControlLeft", 1.25),
+    ("MetaLeft", 1.25),
+    ("AltLeft", 1.25),
+    ("Space", 6.25),
+    ("AltRight", 1.25),
+    ("MetaRight", 1.25),
+    ("ContextMenu", 1.25),
+    ("ControlRight", 1.25),
+];
+
+/// Width of the main block, which every row of it fills exactly.
+const MAIN_WIDTH: f32 = 15.0;
+
+/// Navigation cluster and arrows, one segment per row from the function row.
+const NAVIGATION: [Row; 6] = [
+    &[("PrintScreen", 1.0), ("ScrollLock", 1.0), ("Pause", 1.0)],
+    &[("Insert", 1.0), ("Home", 1.0), ("PageUp", 1.0)],
+    &[("Delete", 1.0), ("End", 1.0), ("PageDown", 1.0)],
+    &[],
+    &[("", 1.0), ("ArrowUp", 1.0)],
+    &[("ArrowLeft", 1.0), ("ArrowDown", 1.0), ("ArrowRight", 1.0)],
+];
+
+const NUMPAD: [Row; 6] = [
+    &[],
+    &[("NumLock", 1.0), ("NumpadDivide", 1.0), ("NumpadMultiply", 1.0), ("NumpadSubtract", 1.0)],
+    &[("Numpad7", 1.0), ("Numpad8", 1.0), ("Numpad9", 1.0), ("NumpadAdd", 1.0)],
+    &[("Numpad4", 1.0), ("Numpad5", 1.0), ("Numpad6", 1.0)],
+    &[("Numpad1", 1.0), ("Numpad2", 1.0), ("Numpad3", 1.0), ("NumpadEnter", 1.0)],
+    &[("Numpad0", 2.0), ("NumpadDecimal", 1.0)],
+];
+
+/// Where the navigation cluster and numpad start, after a quarter-key gap.
+const NAVIGATION_X: f32 = MAIN_WIDTH + 0.25;
+const NUMPAD_X: f32 = NAVIGATION_X + 3.25;
+
+impl KeyboardLayout {
+    pub const ALL: [KeyboardLayout; 4] = [
+        KeyboardLayout::Ansi,
+        KeyboardLayout::Iso,
+        KeyboardLayout::Tkl,
+        KeyboardLayout::Sixty,
+    ];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            KeyboardLayout::Ansi => "ANSI (full size)",
+            KeyboardLayout::Iso => "ISO (full size)",
+            KeyboardLayout::Tkl => "TKL",
+            KeyboardLayout::Sixty => "60%",
+        }
+    }
+
+    /// Overall width in key units.
+    pub fn width(self) -> f32 {
+        match self {
+            KeyboardLayout::Ansi | KeyboardLayout::Iso => NUMPAD_X + 4.0,
+            KeyboardLayout::Tkl => NAVIGATION_X + 3.0,
+            KeyboardLayout::Sixty => MAIN_WIDTH,
+        }
+    }
+
+    /// Every key on the layout, row by row, left to right.
+    pub fn keys(self) -> Vec<KeyCap> {
+        let main = match self {
+            KeyboardLayout::Iso => &ISO_MAIN,
+            _ => &ANSI_MAIN,
+        };
+        let has_function_row = self != KeyboardLayout::Sixty;
+        let has_navigation = self != KeyboardLayout::Sixty;
+        let has_numpad = matches!(self, KeyboardLayout::Ansi | KeyboardLayout::Iso);
+
+        let mut keys = Vec::new();
+        let mut place = |row: usize, x: f32, segment: Row| {
+            let mut x = x;
+            for &(code, width) in segment {
+                if !code.is_empty() {
+                    keys.push(KeyCap { code, row, x, width });
+                }
+                x += width;
+            }
+        };
+
+        if has_function_row {
+            place(0, 0.0, FUNCTION_ROW);
+            place(1, 0.0, NUMBER_ROW);
+        } else {
+            // No function row: Escape takes Backquote's place.
+            place(1, 0.0, &[("Escape", 1.0)]);
+            place(1, 1.0, &NUMBER_ROW[1..]);
+        }
+        for (i, segment) in main.iter().enumerate() {
+            place(i + 2, 0.0, segment);
+        }
+        for row in 0..6 {
+            if has_navigation {
+                place(row, NAVIGATION_X, NAVIGATION[row]);
+            }
+            if has_numpad {
+                place(row, NUMPAD_X, NUMPAD[row]);
+            }
+        }
+
+        if !has_function_row {
+            for key in &mut keys {
+                key.row -= 1;
+            }
+        }
+        keys
+    }
+
+    /// Stereo position of every key on this layout, by code, from -1.0
+    /// (leftmost key centre at the left edge) to 1.0. Built once per layout
+    /// change and looked up per keystroke; keys the layout lacks are absent.
+    pub fn pans(self) -> HashMap<&'static str, f32> {
+        let width = self.width();
+        self.keys()
+            .into_iter()
+            .map(|key| (key.code, key.pan(width)))
+            .collect()
+    }
+}
+
+impl KeyCap {
+    /// Where the key's centre sits across a layout `width` key units wide.
+    fn pan(&self, width: f32) -> f32 {
+        ((self.x + self.width / 2.0) / width) * 2.0 - 1.0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn pan(layout: KeyboardLayout, code: &str) -> Option<f32> {
+        layout.pans().get(code).copied()
+    }
+
+    #[test]
+    fn every_main_block_row_fills_the_block_exactly() {
+        for row in ANSI_MAIN.iter().chain(ISO_MAIN.iter()).chain([FUNCTION_ROW, NUMBER_ROW].iter()) {
+            let width: f32 = row.iter().map(|(_, w)| w).sum();
+            assert_eq!(width, MAIN_WIDTH, "row starting {:?}", row[0]);
+        }
+    }
+
+    #[test]
+    fn keys_pan_by_where_they_sit() {
+        let layout = KeyboardLayout::Ansi;
+        let escape = pan(layout, "Escape").unwrap();
+        let space = pan(layout, "Space").unwrap();
+        let numpad = pan(layout, "Numpad5").unwrap();
+
+        assert!(escape < -0.9, "Escape is far left: {}", escape);
+        assert!(space < 0.0, "Space sits left of centre on a full-size board: {}", space);
+        assert!(numpad > 0.7, "the numpad is far right: {}", numpad);
+        assert!(pan(layout, "KeyA").unwrap() < pan(layout, "KeyL").unwrap());
+    }
+
+    #[test]
+    fn smaller_layouts_spread_their_keys_over_the_same_field() {
+        // Without a numpad, the main block takes more of the stereo width.
+        let full = pan(KeyboardLayout::Ansi, "Enter").unwrap();
+        let sixty = pan(KeyboardLayout::Sixty, "Enter").unwrap();
+        assert!(sixty > full);
+        assert!(sixty > 0.8, "Enter is at the right edge of a 60%: {}", sixty);
+    }
+
+    #[test]
+    fn keys_a_layout_lacks_have_no_position() {
+        assert_eq!(pan(KeyboardLayout::Tkl, "Numpad5"), None);
+        assert_eq!(pan(KeyboardLayout::Sixty, "ArrowUp"), None);
+        assert_eq!(pan(KeyboardLayout::Ansi, "IntlBackslash"), None);
+        assert!(pan(KeyboardLayout::Iso, "IntlBackslash").is_some());
+        assert!(pan(KeyboardLayout::Sixty, "Escape").unwrap() < -0.9);
+    }
+
+    #[test]
+    fn no_key_appears_twice_on_a_layout() {
+        for layout in KeyboardLayout::ALL {
+            let keys = layout.keys();
+            let mut codes: Vec<&str> = keys.iter().map(|k| k.code).collect();
+            codes.sort();
+            codes.dedup();
+            assert_eq!(codes.len(), keys.len(), "{:?}", layout);
+            assert!(keys.iter().all(|k| k.x + k.width <= layout.width()), "{:?}", layout);
+        }
+    }
+}
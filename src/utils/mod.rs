@@ -4,6 +4,8 @@ pub mod config;
 pub mod config_converter;
 pub mod constants;
 pub mod data;
+pub mod key_fallback;
+pub mod keyboard_layout;
 pub mod delay;
 pub mod log_buffer;
 pub mod logger;
@@ -69,37 +69,13 @@ pub fn load_soundpack_metadata(soundpack_id: &str) -> Result<SoundpackMetadata,
         ::read_to_string(&config_path)
         .map_err(|e| format!("Failed to read config: {}", e))?;
 
-    let mut config: serde_json::Value = serde_json
+    let config: serde_json::Value = serde_json
         ::from_str(&content)
         .map_err(|e| format!("Failed to parse config: {}", e))?;
 
-    // Check if this is V2 config with multi method and convert to single method
-    if let Some(definition_method) = config.get("definition_method").and_then(|v| v.as_str()) {
-        if definition_method == "multi" {
-            crate::always_print!("🔄 [CACHE DEBUG] Found V2 multi method config, converting to single method");
-            let soundpack_dir = paths::soundpacks::soundpack_dir(soundpack_id);
-
-            if
-                let Err(e) = config_converter::convert_v2_multi_to_single(
-                    &config_path,
-                    &soundpack_dir
-                )
-            {
-                crate::always_print!("❌ [CACHE DEBUG] Failed to convert multi to single: {}", e);
-                return Err(format!("Failed to convert multi to single method: {}", e));
-            }
-
-            // Re-read the converted config
-            let new_content = fs
-                ::read_to_string(&config_path)
-                .map_err(|e| format!("Failed to re-read converted config: {}", e))?;
-            config = serde_json
-                ::from_str(&new_content)
-                .map_err(|e| format!("Failed to parse converted config: {}", e))?;
-
-            crate::always_print!("✅ [CACHE DEBUG] Successfully converted to single method");
-        }
-    }
+    // "multi" packs are played as-is by the engine (one buffer per key file),
+    // so there is nothing to convert here - rewriting them as "single" used
+    // to drop every key that did not share the most common file.
 
     // Debug: Check if config has audio_file field
     let audio_file = config.get("audio_file").and_then(|v| v.as_str());
@@ -125,7 +101,7 @@ pub fn load_soundpack_metadata(soundpack_id: &str) -> Result<SoundpackMetadata,
         if !std::path::Path::new(&full_audio_path).exists() {
             crate::always_print!("⚠️ [CACHE DEBUG] Audio file not found during cache refresh: {}", full_audio_path);
         }
-    } else {
+    } else if config.get("definition_method").and_then(|v| v.as_str()) != Some("multi") {
         crate::always_print!("⚠️ [CACHE DEBUG] No audio_file field found in config");
     }
 
@@ -240,5 +216,19 @@ pub fn load_soundpack_metadata(soundpack_id: &str) -> Result<SoundpackMetadata,
         can_be_converted: final_validation.can_be_converted,
         // Error tracking - clear error if we successfully loaded metadata
         last_error: last_error,
+        mapped_keys: match
+            config
+                .get("definitions")
+                .or_else(|| config.get("defs"))
+                .and_then(|defs| defs.as_object())
+        {
+            Some(defs) => Some(defs.keys().cloned().collect()),
+            // A V1 pack keys its sounds by IOHook scancode
+            None =>
+                config
+                    .get("defines")
+                    .and_then(|defines| defines.as_object())
+                    .map(crate::utils::config_converter::v1_define_key_codes),
+        },
     })
 }
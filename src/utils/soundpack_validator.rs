@@ -163,6 +163,30 @@ pub fn validate_soundpack_config(config_path: &str) -> SoundpackValidationResult
     }
 }
 
+/// Checks one timing array - `[[start, end], ...]` - reporting each bad entry
+/// as `<label>[<index>]`.
+fn check_timing_set(timings: &Value, label: &str, issues: &mut Vec<String>) {
+    let Some(arr) = timings.as_array() else {
+        return;
+    };
+
+    for (i, timing) in arr.iter().enumerate() {
+        if let Some(timing_arr) = timing.as_array() {
+            if timing_arr.len() != 2 {
+                issues.push(format!(
+                    "Invalid timing array for '{}[{}]': expected [start, end]",
+                    label, i
+                ));
+            }
+        } else {
+            issues.push(format!(
+                "Invalid timing entry for '{}[{}]': expected array",
+                label, i
+            ));
+        }
+    }
+}
+
 /// Validate V2 soundpack structure
 fn validate_v2_structure(
     config: &Value,
@@ -199,30 +223,47 @@ fn validate_v2_structure(
                     None => value,
                 };
 
-                let arr = match timings.as_array() {
-                    Some(arr) => arr,
-                    None => {
+                if timings.as_array().is_none() {
+                    issues.push(format!(
+                        "Invalid definitions entry for '{}': expected timing array",
+                        key
+                    ));
+                    continue;
+                }
+                check_timing_set(timings, key, &mut issues);
+
+                // Optional alternatives to `timing`, each a timing array of
+                // the same shape.
+                match value.get("variations") {
+                    None => {}
+                    Some(Value::Array(variations)) => {
+                        for (v, variation) in variations.iter().enumerate() {
+                            let label = format!("{}.variations[{}]", key, v);
+                            if variation.as_array().is_some() {
+                                check_timing_set(variation, &label, &mut issues);
+                            } else {
+                                issues.push(format!(
+                                    "Invalid variation for '{}': expected timing array",
+                                    label
+                                ));
+                            }
+                        }
+                    }
+                    Some(_) => {
                         issues.push(format!(
-                            "Invalid definitions entry for '{}': expected timing array",
+                            "Invalid variations for '{}': expected an array of timing arrays",
                             key
                         ));
-                        continue;
                     }
-                };
+                }
 
-                for (i, timing) in arr.iter().enumerate() {
-                    if let Some(timing_arr) = timing.as_array() {
-                        if timing_arr.len() != 2 {
-                            issues.push(format!(
-                                "Invalid timing array for '{}[{}]': expected [start, end]",
-                                key, i
-                            ));
-                        }
-                    } else {
-                        issues.push(format!(
-                            "Invalid timing entry for '{}[{}]': expected array",
-                            key, i
-                        ));
+                // Optional segment played for the key's auto-repeat.
+                if let Some(repeat) = value.get("repeat") {
+                    let is_segment = repeat
+                        .as_array()
+                        .is_some_and(|pair| pair.len() == 2 && pair.iter().all(Value::is_number));
+                    if !is_segment {
+                        issues.push(format!("Invalid repeat for '{}': expected [start, end]", key));
                     }
                 }
             }
@@ -231,6 +272,28 @@ fn validate_v2_structure(
         }
     }
 
+    let variation_mode = config.get("options").and_then(|options| options.get("variation_mode"));
+    if variation_mode.is_some_and(|mode| !matches!(mode.as_str(), Some("round_robin" | "random"))) {
+        issues.push("options.variation_mode should be \"round_robin\" or \"random\"".to_string());
+    }
+
+    let self_choke = config.get("options").and_then(|options| options.get("self_choke"));
+    if self_choke.is_some_and(|value| !value.is_boolean()) {
+        issues.push("options.self_choke should be boolean".to_string());
+    }
+    let choke_groups = config.get("options").and_then(|options| options.get("choke_groups"));
+    if
+        choke_groups.is_some_and(|groups| {
+            !groups.as_array().is_some_and(|groups| {
+                groups.iter().all(|group| {
+                    group.as_array().is_some_and(|codes| codes.iter().all(|code| code.is_string()))
+                })
+            })
+        })
+    {
+        issues.push("options.choke_groups should be an array of arrays of key codes".to_string());
+    }
+
     // Check mouse field
     if let Some(mouse) = config.get("mouse") {
         if !mouse.is_boolean() {
@@ -581,6 +644,115 @@ mod tests {
         assert!(result.can_be_converted);
     }
 
+    #[test]
+    fn keys_with_sample_variations_validate() {
+        let result = validate_json(
+            r#"{
+                "config_version": "2",
+                "name": "varied pack",
+                "author": "someone",
+                "options": { "variation_mode": "random" },
+                "definitions": {
+                    "KeyA": {
+                        "timing": [[0.0, 80.0], [80.0, 160.0]],
+                        "variations": [[[200.0, 280.0], [280.0, 360.0]], [[400.0, 480.0]]]
+                    }
+                }
+            }"#
+        );
+
+        assert_eq!(result.status, SoundpackValidationStatus::Valid, "got: {}", result.message);
+    }
+
+    #[test]
+    fn a_malformed_variation_is_reported_by_its_index() {
+        for (variations, expected) in [
+            (r#"[[[200.0]]]"#, "KeyA.variations[0][0]"),
+            (r#"[[[0.0, 1.0]], 5]"#, "KeyA.variations[1]"),
+            (r#"{ "a": [] }"#, "Invalid variations for 'KeyA'"),
+        ] {
+            let result = validate_json(
+                &format!(
+                    r#"{{
+                        "config_version": "2",
+                        "name": "varied pack",
+                        "author": "someone",
+                        "definitions": {{
+                            "KeyA": {{ "timing": [[0.0, 80.0]], "variations": {} }}
+                        }}
+                    }}"#,
+                    variations
+                )
+            );
+
+            assert!(!result.is_valid_v2, "{} should not validate", variations);
+            assert!(result.message.contains(expected), "expected {:?} in: {}", expected, result.message);
+        }
+    }
+
+    #[test]
+    fn a_repeat_segment_must_be_one_start_end_pair() {
+        let pack = |repeat: &str| {
+            validate_json(
+                &format!(
+                    r#"{{
+                        "config_version": "2",
+                        "name": "repeating pack",
+                        "author": "someone",
+                        "definitions": {{
+                            "KeyA": {{ "timing": [[0.0, 80.0]], "repeat": {} }}
+                        }}
+                    }}"#,
+                    repeat
+                )
+            )
+        };
+
+        let valid = pack("[80.0, 120.0]");
+        assert_eq!(valid.status, SoundpackValidationStatus::Valid, "got: {}", valid.message);
+        for repeat in ["[[80.0, 120.0]]", "[80.0]", r#""80-120""#] {
+            assert!(pack(repeat).message.contains("Invalid repeat for 'KeyA'"), "{} should not validate", repeat);
+        }
+    }
+
+    #[test]
+    fn an_unknown_variation_mode_is_rejected() {
+        let result = validate_json(
+            r#"{
+                "config_version": "2",
+                "name": "varied pack",
+                "author": "someone",
+                "options": { "variation_mode": "shuffle" },
+                "definitions": { "KeyA": { "timing": [[0.0, 80.0]] } }
+            }"#
+        );
+
+        assert!(result.message.contains("variation_mode"), "got: {}", result.message);
+    }
+
+    #[test]
+    fn choke_options_are_checked_for_shape() {
+        let pack = |options: &str| {
+            validate_json(
+                &format!(
+                    r#"{{
+                        "config_version": "2",
+                        "name": "choked pack",
+                        "author": "someone",
+                        "options": {},
+                        "definitions": {{ "KeyA": {{ "timing": [[0.0, 80.0]] }} }}
+                    }}"#,
+                    options
+                )
+            )
+        };
+
+        let valid = pack(r#"{ "self_choke": true, "choke_groups": [["ShiftLeft", "ShiftRight"]] }"#);
+        assert_eq!(valid.status, SoundpackValidationStatus::Valid, "got: {}", valid.message);
+        assert!(pack(r#"{ "self_choke": "yes" }"#).message.contains("self_choke"));
+        assert!(pack(r#"{ "choke_groups": ["ShiftLeft"] }"#).message.contains("choke_groups"));
+    }
+
     /// A config that is neither shape still reports what it is missing, and
     /// names the field by the spelling packs actually use.
     #[test]